@@ -0,0 +1,35 @@
+use std::process::Command;
+
+/// Runs `git` with `args` in the crate's directory, returning its trimmed output.
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Generates the gRPC service from `proto/sapso.proto`, with a vendored
+/// `protoc` so the build needs no system one.
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    println!("cargo:rerun-if-changed=proto/sapso.proto");
+    let protoc = protoc_bin_vendored::protoc_bin_path().expect("vendored protoc");
+    std::env::set_var("PROTOC", protoc);
+    tonic_build::compile_protos("proto/sapso.proto").expect("failed to compile proto/sapso.proto");
+}
+
+fn main() {
+    #[cfg(feature = "grpc")]
+    compile_protos();
+
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+
+    if let Some(commit) = git(&["rev-parse", "HEAD"]) {
+        println!("cargo:rustc-env=SAPSO_GIT_COMMIT={}", commit);
+        if let Some(status) = git(&["status", "--porcelain", "--untracked-files=no"]) {
+            println!("cargo:rustc-env=SAPSO_GIT_DIRTY={}", !status.is_empty());
+        }
+    }
+}
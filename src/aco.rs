@@ -1,21 +1,138 @@
-use std::time::Instant;
+use std::{fmt, str::FromStr, sync::Arc};
 
+use anyhow::{bail, Result};
 use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 
-use crate::tsplib::{HeuristicAlgorithm, Route, TspLib};
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::{self, Checkpoint, Checkpointing, RunState},
+    config::AcoConfig,
+    construct::{self, Construction},
+    error::Error,
+    fitness::Evaluator,
+    local_search::{LocalSearch, LocalSearcher},
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::{self, Configurable, HeuristicAlgorithm, Route, Solution, StepResult, TspLib},
+};
 
+/// Chance MAX-MIN Ant System's bounds give an ant that follows the best tour
+/// of rebuilding it once the pheromone has converged, which sets how far
+/// τ_min sits below τ_max.
+const MMAS_P_BEST: f64 = 0.05;
+
+/// How the colony lays pheromone after each iteration, configured as
+/// `variant = "max_min"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcoVariant {
+    /// Every ant deposits on its own tour.
+    #[default]
+    AntSystem,
+    /// Every ant deposits, and the best tour so far deposits as much as
+    /// `elite` ants more.
+    Elitist,
+    /// Only the `elite - 1` shortest tours of the iteration deposit, the
+    /// `r`th shortest weighted `elite - r`, and the best tour so far weighted
+    /// `elite`.
+    RankBased,
+    /// MAX-MIN Ant System: only the iteration's shortest tour deposits, and
+    /// pheromone is kept between τ_min and τ_max, which follow the best tour
+    /// so far. The matrix starts at τ_max of a nearest-neighbour tour.
+    MaxMin,
+}
+
+impl AcoVariant {
+    pub fn is_ant_system(&self) -> bool {
+        *self == AcoVariant::AntSystem
+    }
+}
+
+impl FromStr for AcoVariant {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "ant_system" | "as" => AcoVariant::AntSystem,
+            "elitist" | "eas" => AcoVariant::Elitist,
+            "rank_based" | "rank" | "asrank" => AcoVariant::RankBased,
+            "max_min" | "mmas" => AcoVariant::MaxMin,
+            _ => bail!(
+                "unknown ACO variant `{}`, expected ant_system, elitist, rank_based or max_min",
+                name
+            ),
+        })
+    }
+}
+
+impl fmt::Display for AcoVariant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AcoVariant::AntSystem => "ant_system",
+            AcoVariant::Elitist => "elitist",
+            AcoVariant::RankBased => "rank_based",
+            AcoVariant::MaxMin => "max_min",
+        })
+    }
+}
+
+/// The pheromone matrix, carried between iterations.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct AcoState {
+    pheromone: Vec<Vec<f64>>,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct AntColonyOptimization {
     history: Vec<Route>,
+    history_times: Vec<u64>,
     best_route: Route,
     run_time: u64,
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    checkpointing: Option<Checkpointing>,
+    #[serde(skip)]
+    resumed: Option<RunState<AcoState>>,
+    #[serde(skip)]
+    initial_solution: Option<Vec<usize>>,
+    #[serde(skip)]
+    run: Option<RunState<AcoState>>,
+    #[serde(skip)]
+    pool: Option<SolutionPool>,
+    #[serde(skip)]
+    evaluator: Evaluator,
+    #[serde(skip)]
+    searcher: Option<LocalSearcher>,
+    /// Whether the instance's distances differ both ways, so pheromone on
+    /// an edge only marks it in the direction ants took.
+    #[serde(skip)]
+    directed: bool,
+    /// Every city's `candidates` nearest neighbours when that is more than
+    /// the instance's own candidate lists hold.
+    #[serde(skip)]
+    neighbors: Option<Vec<Vec<usize>>>,
+    archive: Option<SolutionArchive>,
+    seed: Option<u64>,
+    operator_counts: OperatorCounts,
+    pheromone_snapshots: Vec<(usize, Vec<Vec<f64>>)>,
 
     // Parameters
-    pub alpha: f64,        // pheromone importance
-    pub beta: f64,         // distance importance
-    pub decay: f64,        // pheromone evaporation rate
-    pub q: f64,            // pheromone deposit factor
-    pub ants: usize,       // number of ants
-    pub iterations: usize, // number of iterations
+    pub alpha: f64,                         // pheromone importance
+    pub beta: f64,                          // distance importance
+    pub decay: f64,                         // pheromone evaporation rate
+    pub q: f64,                             // pheromone deposit factor
+    pub ants: usize,                        // number of ants
+    pub iterations: usize,                  // number of iterations
+    pub snapshot_interval: Option<usize>,   // record the pheromone matrix every N iterations
+    pub local_search: LocalSearch,          // run on every ant's tour
+    pub variant: AcoVariant,                // pheromone update strategy
+    pub elite: usize,                       // elitist and rank-based weight of the best tour
+    pub candidates: usize,                  // nearest neighbours ants pick from, 0 for all
+    pub construction: Option<Construction>, // builds the initial best tour
 }
 
 impl AntColonyOptimization {
@@ -31,8 +148,23 @@ impl AntColonyOptimization {
     ) -> Self {
         AntColonyOptimization {
             history: Vec::new(),
-            best_route: Route::new(&tsp.cities.clone()),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
             run_time: 0,
+            observers: Observers::default(),
+            checkpointing: None,
+            resumed: None,
+            initial_solution: None,
+            run: None,
+            pool: None,
+            evaluator: Evaluator::default(),
+            searcher: None,
+            directed: false,
+            neighbors: None,
+            archive: None,
+            seed: None,
+            operator_counts: OperatorCounts::default(),
+            pheromone_snapshots: Vec::new(),
 
             alpha,
             beta,
@@ -40,43 +172,90 @@ impl AntColonyOptimization {
             q,
             ants,
             iterations,
+            snapshot_interval: None,
+            local_search: LocalSearch::None,
+            variant: AcoVariant::AntSystem,
+            elite: 6,
+            candidates: tsplib::CANDIDATES,
+            construction: None,
         }
     }
 
-    fn construct_solution(&self, pheromone: &[Vec<f64>], tsp: &TspLib) -> Route {
-        let mut rng = rand::thread_rng();
+    /// Pheromone matrices recorded every `snapshot_interval` iterations and
+    /// after the last one, tagged with their iteration.
+    pub fn get_pheromone_snapshots(&self) -> &[(usize, Vec<Vec<f64>>)] {
+        &self.pheromone_snapshots
+    }
+
+    fn construct_solution(
+        &self,
+        pheromone: &[Vec<f64>],
+        tsp: &TspLib,
+        rng: &mut impl Rng,
+    ) -> Vec<usize> {
         let n = tsp.dimension;
+        // Unvisited cities in no particular order, with each city's index in
+        // the list, so visiting one takes constant time.
         let mut unvisited: Vec<usize> = (0..n).collect();
+        let mut position: Vec<usize> = (0..n).collect();
+        let mut visited = vec![false; n];
+        let mut visit = |city: usize, unvisited: &mut Vec<usize>, visited: &mut [bool]| {
+            let index = position[city];
+            unvisited.swap_remove(index);
+            if let Some(&moved) = unvisited.get(index) {
+                position[moved] = index;
+            }
+            visited[city] = true;
+        };
+
         let start = rng.gen_range(0..n);
         let mut path = vec![start];
-        unvisited.remove(start);
+        visit(start, &mut unvisited, &mut visited);
 
         while !unvisited.is_empty() {
             let current = *path.last().unwrap();
-            let next = self.select_next_city(current, &unvisited, pheromone, tsp);
+            let next = self.select_next_city(current, &unvisited, &visited, pheromone, tsp, rng);
             path.push(next);
-            unvisited.retain(|&x| x != next);
+            visit(next, &mut unvisited, &mut visited);
         }
 
-        let route_cities: Vec<(f64, f64)> = path.iter().map(|&idx| tsp.cities[idx]).collect();
-
-        Route::new(&route_cities)
+        path
     }
 
+    /// Draws the next city from the unvisited ones among `current`'s
+    /// `self.candidates` nearest neighbours, or from every unvisited city
+    /// once those are all visited.
+    #[allow(clippy::too_many_arguments)]
     fn select_next_city(
         &self,
         current: usize,
-        unvisited: &Vec<usize>,
+        unvisited: &[usize],
+        visited: &[bool],
         pheromone: &[Vec<f64>],
         tsp: &TspLib,
+        rng: &mut impl Rng,
     ) -> usize {
-        let mut rng = rand::thread_rng();
-        let mut probabilities = Vec::new();
+        let neighbors = match &self.neighbors {
+            Some(neighbors) => &neighbors[current],
+            None => &tsp.neighbors()[current],
+        };
+        let candidates: Vec<usize> = neighbors[..self.candidates.min(neighbors.len())]
+            .iter()
+            .copied()
+            .filter(|&city| !visited[city])
+            .collect();
+        let cities = if candidates.is_empty() {
+            unvisited
+        } else {
+            &candidates
+        };
+
+        let mut probabilities = Vec::with_capacity(cities.len());
         let mut sum = 0.0;
 
-        for &next in unvisited {
+        for &next in cities {
             let tau = pheromone[current][next].powf(self.alpha);
-            let eta = (1.0 / tsp.distance_matrix[current][next] as f64).powf(self.beta);
+            let eta = (1.0 / tsp.distance_matrix.get(current, next) as f64).powf(self.beta);
             let probability = tau * eta;
             sum += probability;
             probabilities.push((next, probability));
@@ -91,81 +270,356 @@ impl AntColonyOptimization {
             }
         }
 
-        *unvisited.last().unwrap()
+        *cities.last().unwrap()
     }
 
-    fn update_pheromone(&self, pheromone: &mut [Vec<f64>], solutions: &Vec<Route>, tsp: &TspLib) {
+    fn update_pheromone(&self, pheromone: &mut [Vec<f64>], solutions: &[Route]) {
         pheromone.iter_mut().for_each(|row| {
             row.iter_mut().for_each(|value| {
                 *value *= 1.0 - self.decay;
             });
         });
 
-        for route in solutions {
-            let deposit = self.q / route.distance as f64;
-            let cities: Vec<usize> = route
-                .cities
-                .iter()
-                .map(|city| tsp.cities.iter().position(|&c| c == *city).unwrap())
-                .collect();
+        let elite = self.elite as f64;
+        match self.variant {
+            AcoVariant::AntSystem => {
+                for route in solutions {
+                    self.deposit_pheromone(pheromone, route, 1.0);
+                }
+            }
+            AcoVariant::Elitist => {
+                for route in solutions {
+                    self.deposit_pheromone(pheromone, route, 1.0);
+                }
+                self.deposit_pheromone(pheromone, &self.best_route, elite);
+            }
+            AcoVariant::RankBased => {
+                let mut ranked: Vec<&Route> = solutions.iter().collect();
+                ranked.sort_by_key(|route| route.distance);
+                for (rank, route) in ranked
+                    .into_iter()
+                    .take(self.elite.saturating_sub(1))
+                    .enumerate()
+                {
+                    self.deposit_pheromone(pheromone, route, elite - 1.0 - rank as f64);
+                }
+                self.deposit_pheromone(pheromone, &self.best_route, elite);
+            }
+            AcoVariant::MaxMin => {
+                if let Some(best) = solutions.iter().min_by_key(|route| route.distance) {
+                    self.deposit_pheromone(pheromone, best, 1.0);
+                }
+                let (min, max) = self.pheromone_bounds(self.best_route.distance, pheromone.len());
+                pheromone.iter_mut().flatten().for_each(|value| {
+                    *value = value.clamp(min, max);
+                });
+            }
+        }
+    }
+
+    /// Lays `weight` times the pheromone of one ant along every edge of
+    /// `route`, more for shorter routes, both ways unless the instance is
+    /// asymmetric.
+    fn deposit_pheromone(&self, pheromone: &mut [Vec<f64>], route: &Route, weight: f64) {
+        let deposit = weight * self.q / route.distance as f64;
+        let cities = &route.tour;
 
-            for i in 0..cities.len() - 1 {
-                let (city1, city2) = (cities[i], cities[i + 1]);
-                pheromone[city1][city2] += deposit;
+        for i in 0..cities.len() - 1 {
+            let (city1, city2) = (cities[i], cities[i + 1]);
+            pheromone[city1][city2] += deposit;
+            if !self.directed {
                 pheromone[city2][city1] += deposit;
             }
+        }
 
-            let (last, first) = (cities[cities.len() - 1], cities[0]);
-            pheromone[last][first] += deposit;
+        let (last, first) = (cities[cities.len() - 1], cities[0]);
+        pheromone[last][first] += deposit;
+        if !self.directed {
             pheromone[first][last] += deposit;
         }
     }
+
+    /// MAX-MIN Ant System's τ_min and τ_max on an instance of `n` cities
+    /// whose best tour so far is `distance` long: τ_max is where pheromone on
+    /// that tour's edges settles when only it deposits.
+    fn pheromone_bounds(&self, distance: u64, n: usize) -> (f64, f64) {
+        let max = self.q / (self.decay * distance.max(1) as f64);
+        let root = MMAS_P_BEST.powf(1.0 / n as f64);
+        let min = max * (1.0 - root) / ((n as f64 / 2.0 - 1.0).max(1.0) * root);
+        (min.min(max), max)
+    }
 }
 
 impl HeuristicAlgorithm for AntColonyOptimization {
-    fn solve(&mut self, tsp: &TspLib) {
-        let start_time = Instant::now();
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        tsplib::check_instance(tsp)?;
+        self.observers.start("ACO", tsp);
+        self.searcher = LocalSearcher::new(self.local_search, tsp);
+        self.directed = tsp.is_asymmetric();
+        self.neighbors = (self.candidates > tsplib::CANDIDATES)
+            .then(|| tsp.distance_matrix.nearest_neighbors(self.candidates));
+        let run = match self.resumed.take() {
+            Some(run) => run,
+            None => {
+                let mut rng = tsplib::solver_rng(self.seed);
+                self.best_route = Route::random_through(tsp, &mut rng);
+                let seed_route = self
+                    .initial_solution
+                    .as_ref()
+                    .map(|tour| Route::validated(tour, tsp))
+                    .transpose()?;
+                let constructed = self
+                    .construction
+                    .filter(|_| seed_route.is_none())
+                    .map(|construction| Route::from_tour(&construction.build(tsp, 0), tsp));
+                let initial = match self.variant {
+                    AcoVariant::MaxMin => {
+                        let length = seed_route.as_ref().or(constructed.as_ref()).map_or_else(
+                            || {
+                                let tour = construct::nearest_neighbor(&tsp.distance_matrix, 0);
+                                tsplib::tour_length(&tour, &tsp.distance_matrix)
+                            },
+                            |route| route.distance,
+                        );
+                        self.pheromone_bounds(length, tsp.dimension).1
+                    }
+                    _ => 1.0,
+                };
+                let mut pheromone = vec![vec![initial; tsp.dimension]; tsp.dimension];
+                if let Some(route) = seed_route {
+                    // As if every ant had walked the initial tour once.
+                    if self.variant != AcoVariant::MaxMin {
+                        self.deposit_pheromone(&mut pheromone, &route, self.ants as f64);
+                    }
+                    self.best_route = route;
+                }
+                if let Some(route) = constructed {
+                    self.best_route = route;
+                }
+                RunState::new(tsp, rng, AcoState { pheromone })
+            }
+        };
+        self.run = Some(run);
+        Ok(())
+    }
 
-        let mut pheromone = vec![vec![1.0; tsp.dimension]; tsp.dimension];
-        self.best_route = Route::new_random(&tsp.cities);
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        let _span = self.observers.enter_step(run.iterations);
+
+        if run.iterations < self.iterations {
+            if let Some(route) = self
+                .pool
+                .as_ref()
+                .and_then(|pool| pool.better_than(self.best_route.distance))
+            {
+                self.deposit_pheromone(&mut run.state.pheromone, &route, 1.0);
+                self.best_route = route;
+            }
 
-        for iteration in 0..self.iterations {
+            let iteration = run.iterations;
+            let pheromone = &run.state.pheromone;
+            let mut paths: Vec<Vec<usize>> = tsplib::task_rngs(&mut run.rng, self.ants)
+                .into_par_iter()
+                .map(|mut rng| self.construct_solution(pheromone, tsp, &mut rng))
+                .collect();
+            if let Some(searcher) = &self.searcher {
+                let improved: Vec<bool> = paths
+                    .par_iter_mut()
+                    .map(|path| {
+                        let before = tsplib::tour_length(path, &tsp.distance_matrix);
+                        searcher.improve(path, &tsp.distance_matrix) < before
+                    })
+                    .collect();
+                for improved in improved {
+                    self.operator_counts.record("local search", true, improved);
+                }
+            }
+            let tours: Vec<&[usize]> = paths.iter().map(Vec::as_slice).collect();
+            let distances = self.evaluator.evaluate(tsp, &tours);
             let mut solutions = Vec::new();
 
-            for _ in 0..self.ants {
-                let solution = self.construct_solution(&pheromone, tsp);
+            for (path, distance) in paths.iter().zip(distances) {
+                let solution = Route::from_measured_tour(path, distance, tsp);
 
+                // Every tour is counted as accepted, though depending on the
+                // variant only the best ones deposit pheromone.
+                self.operator_counts.record(
+                    "ant tour",
+                    true,
+                    solution.distance < self.best_route.distance,
+                );
                 if solution.distance < self.best_route.distance {
                     self.best_route = solution.clone();
                 }
+                if let Some(archive) = &mut self.archive {
+                    archive.offer(path, solution.distance);
+                }
 
                 solutions.push(solution);
             }
 
-            self.update_pheromone(&mut pheromone, &solutions, tsp);
+            let iteration_best = solutions.iter().map(|s| s.distance).min().unwrap_or(0);
+            let mean_distance = solutions.iter().map(|s| s.distance as f64).sum::<f64>()
+                / solutions.len().max(1) as f64;
+            self.update_pheromone(&mut run.state.pheromone, &solutions);
 
-            self.history.push(self.best_route.clone());
+            if let Some(interval) = self.snapshot_interval {
+                if iteration % interval.max(1) == 0 || iteration + 1 == self.iterations {
+                    self.pheromone_snapshots
+                        .push((iteration, run.state.pheromone.clone()));
+                }
+            }
 
-            if iteration % (self.iterations / 10) == 0 {
-                println!(
-                    "ACO Iteration: {}/{}, Best distance: {}",
-                    iteration, self.iterations, self.best_route.distance
-                );
+            self.history_times
+                .push(start_time.elapsed().as_millis() as u64);
+
+            self.history.push(self.best_route.clone());
+            self.observers.iteration(IterationInfo {
+                algorithm: "ACO",
+                iteration,
+                best: &self.best_route,
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+                stats: &[
+                    ("iteration_best", iteration_best as f64),
+                    ("mean_distance", mean_distance),
+                ],
+            });
+            if let Some(pool) = &self.pool {
+                pool.publish(&self.best_route);
             }
+
+            run.iterations = iteration + 1;
         }
 
+        let saved = checkpoint::save_if_due(
+            self.checkpointing.as_ref(),
+            &*self,
+            &mut run,
+            start_time,
+            false,
+        );
+        let step = StepResult {
+            iterations: run.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.best_route.distance,
+            done: run.iterations >= self.iterations || self.observers.stop_requested(),
+        };
+        self.run = Some(run);
+        saved?;
+        Ok(step)
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        if run.iterations < self.iterations {
+            checkpoint::save_if_due(
+                self.checkpointing.as_ref(),
+                &*self,
+                &mut run,
+                start_time,
+                true,
+            )?;
+        }
         self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: run.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("ACO", &solution, &self.operator_counts);
+        Ok(solution)
     }
 
-    fn get_history(&self) -> Vec<Route> {
-        self.history.clone()
+    fn get_history(&self) -> &[Route] {
+        &self.history
     }
 
-    fn get_best_route(&self) -> Route {
-        self.best_route.clone()
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
+    }
+
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
     }
 
     fn get_run_time(&self) -> u64 {
         self.run_time
     }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        self.archive.as_ref()
+    }
+
+    fn set_evaluator(&mut self, evaluator: Evaluator) {
+        self.evaluator = evaluator;
+    }
+}
+
+impl Checkpoint for AntColonyOptimization {
+    type State = AcoState;
+
+    fn resume(&mut self, run: RunState<AcoState>) {
+        self.resumed = Some(run);
+    }
+}
+
+impl Configurable for AntColonyOptimization {
+    type Config = AcoConfig;
+
+    fn from_config(tsp: &TspLib, config: &AcoConfig) -> Self {
+        let mut aco = AntColonyOptimization::new(
+            tsp,
+            config.alpha,
+            config.beta,
+            config.decay,
+            config.q,
+            config.ants,
+            config.iterations,
+        );
+        aco.snapshot_interval = config.snapshot_interval;
+        aco.seed = config.seed;
+        aco.local_search = config.local_search;
+        aco.variant = config.variant;
+        aco.elite = config.elite;
+        aco.candidates = config.candidates;
+        aco.construction = config.construction;
+        aco
+    }
 }
@@ -0,0 +1,107 @@
+use std::{collections::HashSet, fs, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{diversity, tsplib::Route};
+
+/// Share of edges above which two tours count as the same for an archive
+/// unless told otherwise.
+pub const DEFAULT_MAX_OVERLAP: f64 = 0.8;
+
+/// A tour kept by a [`SolutionArchive`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTour {
+    /// City indices in visiting order.
+    pub tour: Vec<usize>,
+    pub distance: u64,
+}
+
+/// The `capacity` shortest tours seen during a run that are structurally
+/// distinct: no two of them share more than `max_overlap` of their edges.
+/// Of two tours that are too alike only the shorter is kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SolutionArchive {
+    capacity: usize,
+    max_overlap: f64,
+    /// Sorted by distance, shortest first.
+    tours: Vec<ArchivedTour>,
+}
+
+impl SolutionArchive {
+    pub fn new(capacity: usize, max_overlap: f64) -> Self {
+        SolutionArchive {
+            capacity,
+            max_overlap,
+            tours: Vec::new(),
+        }
+    }
+
+    pub fn tours(&self) -> &[ArchivedTour] {
+        &self.tours
+    }
+
+    /// Whether a tour of `distance` could still make it into the archive,
+    /// a cheap check before building the tour to offer.
+    pub fn accepts(&self, distance: u64) -> bool {
+        self.capacity > 0
+            && (self.tours.len() < self.capacity
+                || self
+                    .tours
+                    .last()
+                    .is_some_and(|worst| distance < worst.distance))
+    }
+
+    /// Offers `tour` to the archive. It is kept unless a tour sharing more
+    /// than `max_overlap` of its edges is already there and no longer, or the
+    /// archive is full of shorter tours. Returns whether it was kept.
+    pub fn offer(&mut self, tour: &[usize], distance: u64) -> bool {
+        if !self.accepts(distance) {
+            return false;
+        }
+        let edges: HashSet<(usize, usize)> = diversity::edges(tour).collect();
+        let similar = |other: &ArchivedTour| {
+            1.0 - diversity::missing_share(&other.tour, &edges) > self.max_overlap
+        };
+        if self
+            .tours
+            .iter()
+            .any(|other| other.distance <= distance && similar(other))
+        {
+            return false;
+        }
+
+        self.tours.retain(|other| !similar(other));
+        let position = self
+            .tours
+            .partition_point(|other| other.distance <= distance);
+        self.tours.insert(
+            position,
+            ArchivedTour {
+                tour: tour.to_vec(),
+                distance,
+            },
+        );
+        self.tours.truncate(self.capacity);
+        true
+    }
+
+    /// Offers the tour of `route`.
+    pub fn offer_route(&mut self, route: &Route) -> bool {
+        self.accepts(route.distance) && self.offer(&route.tour, route.distance)
+    }
+
+    /// Writes the archive to `path` as JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Reads an archive written by [`SolutionArchive::save`].
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse archive {}", path.display()))
+    }
+}
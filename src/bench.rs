@@ -0,0 +1,619 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use prettytable::{row, Cell, Row, Table};
+
+use crate::{
+    config::{build_solver, Config, SolverConfig},
+    distributed::{Cluster, Task},
+    experiment::{AlgorithmRecord, Experiment, InstanceRecord},
+    fitness::Evaluator,
+    hyper::Algorithm,
+    manifest::Manifest,
+    memory::MemoryTracker,
+    plot::{self, PlotConfig},
+    progress::status,
+    stats::{self, RunStats},
+    store::{self, Source, StoredRun},
+    tsplib::{read_tsp_file, CancellationToken, Termination, TspLib},
+};
+
+/// Instances benchmarked when none are given: TSPLIB instances of growing
+/// size, all with known optima.
+pub const DEFAULT_INSTANCES: [&str; 4] = ["berlin52", "kroA100", "a280", "pcb442"];
+
+/// Settings of a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchOptions {
+    /// Paths of the instances to run on.
+    pub instances: Vec<String>,
+    pub algorithms: Vec<Algorithm>,
+    /// Runs per algorithm and instance; run `k` is seeded with `seed + k`.
+    pub runs: usize,
+    pub seed: u64,
+    /// Wall-clock cap on every run.
+    pub time_limit: Option<Duration>,
+    /// Gap to the optimum, in percent, within which a run counts as a
+    /// success.
+    pub success_gap: f64,
+    /// Where to write the whole benchmark as an experiment JSON document.
+    pub experiment_json: Option<PathBuf>,
+    /// Results store every run is appended to.
+    pub store: Option<PathBuf>,
+    /// Stops the run in progress, and every later one after its first
+    /// iteration, once cancelled. Runs already started on a worker only stop
+    /// at `time_limit`.
+    pub cancellation: CancellationToken,
+    /// Remote workers the runs of each instance are spread over instead of
+    /// running here one after another.
+    pub workers: Option<Arc<Cluster>>,
+    /// Where runs here evaluate their batches of tours.
+    pub evaluator: Evaluator,
+}
+
+/// Every run of one algorithm on one instance.
+struct AlgorithmRuns {
+    algorithm: Algorithm,
+    runs: Vec<RunStats>,
+}
+
+/// Everything measured on one instance.
+struct InstanceResults {
+    tsp: TspLib,
+    algorithms: Vec<AlgorithmRuns>,
+    /// Anytime curves of each algorithm's best run.
+    figure: Option<PathBuf>,
+    /// Convergence area, diversity and acceptance rate of each algorithm.
+    metrics_figure: Option<PathBuf>,
+}
+
+/// Runs `options.algorithms`, configured by `solvers`, `options.runs` times on
+/// every instance and writes `bench_report.md` and `bench_report.html`, with a
+/// results table and the anytime curves of each instance, to the output
+/// directory of `plot`.
+pub fn run(options: &BenchOptions, solvers: &SolverConfig, plot: &PlotConfig) -> Result<()> {
+    let config = Config {
+        instances: options.instances.clone(),
+        algorithms: options.algorithms.clone(),
+        constructions: Vec::new(),
+        seed: Some(options.seed),
+        runs: Some(options.runs),
+        solvers: solvers.clone(),
+        plot: plot.clone(),
+        pipeline: Vec::new(),
+    };
+    let seeds = (0..options.runs as u64)
+        .map(|k| options.seed.wrapping_add(k))
+        .collect();
+    let manifest = Manifest::new(&config, &options.instances, seeds)?
+        .write(&plot.output_dir, MANIFEST_FILE)?;
+    let colors = plot.series_colors().context("invalid palette")?;
+    let mut results = Vec::new();
+
+    for instance in &options.instances {
+        let tsp =
+            read_tsp_file(instance).with_context(|| format!("failed to parse {}", instance))?;
+        status!("Benchmarking {} ({} cities)", tsp.name, tsp.dimension);
+        let mut plot = plot.clone();
+        plot.instance = tsp.name.clone();
+
+        let termination = Termination {
+            time_limit: options.time_limit,
+            cancellation: Some(options.cancellation.clone()),
+            ..Termination::default()
+        };
+        let outcomes = match &options.workers {
+            Some(workers) => run_remotely(workers, &tsp, options, solvers)?,
+            None => run_locally(&tsp, options, solvers, &termination)?,
+        };
+        let mut algorithms = Vec::new();
+        let mut curves = Vec::new();
+        for (index, (&algorithm, outcomes)) in options.algorithms.iter().zip(outcomes).enumerate() {
+            let mut runs: Vec<RunStats> = Vec::with_capacity(options.runs);
+            let mut best_curve = Vec::new();
+            for (run, curve) in outcomes {
+                if runs.iter().all(|r| run.best_distance < r.best_distance) {
+                    best_curve = curve;
+                }
+                runs.push(run);
+            }
+
+            let summary = stats::aggregate(&runs);
+            status!(
+                "{} on {}: mean {:.1} ± {:.1}, best {}{}",
+                algorithm.name(),
+                tsp.name,
+                summary.mean,
+                summary.std,
+                summary.min,
+                stats::gap(summary.mean, &tsp).map_or(String::new(), |gap| format!(
+                    ", mean gap {}",
+                    stats::format_gap(gap)
+                ))
+            );
+            curves.push((algorithm.name(), best_curve, colors[index % colors.len()]));
+            algorithms.push(AlgorithmRuns { algorithm, runs });
+        }
+
+        let figure = plot::plot_anytime_curves(&curves, &tsp, &plot)?;
+        let metrics: Vec<(&str, stats::AggregateStats)> = algorithms
+            .iter()
+            .map(|AlgorithmRuns { algorithm, runs }| (algorithm.name(), stats::aggregate(runs)))
+            .collect();
+        let metrics_figure = plot::plot_run_metrics(&metrics, &plot)?;
+        results.push(InstanceResults {
+            tsp,
+            algorithms,
+            figure,
+            metrics_figure,
+        });
+    }
+
+    if options.experiment_json.is_some() || options.store.is_some() {
+        let experiment = experiment(&results, options, solvers, &manifest)?;
+        if let Some(path) = &options.experiment_json {
+            experiment.write(path)?;
+            status!("Experiment written to {}", path.display());
+        }
+        if let Some(path) = &options.store {
+            let runs: Vec<StoredRun> = experiment
+                .instances
+                .iter()
+                .flat_map(|record| StoredRun::from_record(record, Source::Bench, Some(&manifest)))
+                .collect();
+            store::append(path, &runs)?;
+        }
+    }
+    for instance in &results {
+        print_table(instance, options);
+    }
+    print_ranking(&results, options);
+    let profiles = plot_profiles(&results, options, plot)?;
+
+    fs::create_dir_all(&plot.output_dir)
+        .with_context(|| format!("failed to create {}", plot.output_dir.display()))?;
+    for (file_name, contents) in [
+        (
+            "bench_report.md",
+            markdown_report(&results, &profiles, options)?,
+        ),
+        (
+            "bench_report.html",
+            html_report(&results, &profiles, options)?,
+        ),
+    ] {
+        let path = plot.output_dir.join(file_name);
+        fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        status!("Benchmark report written to {}", path.display());
+    }
+
+    Ok(())
+}
+
+/// Statistics and anytime curve of every run, per algorithm of `options`.
+type Outcomes = Vec<Vec<(RunStats, Vec<(u64, u64)>)>>;
+
+/// Runs every algorithm `options.runs` times on `tsp`, one run at a time.
+fn run_locally(
+    tsp: &TspLib,
+    options: &BenchOptions,
+    solvers: &SolverConfig,
+    termination: &Termination,
+) -> Result<Outcomes> {
+    let mut outcomes = Vec::new();
+    for &algorithm in &options.algorithms {
+        let mut runs = Vec::with_capacity(options.runs);
+        for k in 0..options.runs {
+            let mut solver = build_solver(tsp, &solvers.spec(algorithm));
+            solver.set_seed(options.seed.wrapping_add(k as u64));
+            solver.set_evaluator(options.evaluator.clone());
+            let tracker = MemoryTracker::start();
+            solver.solve(tsp, termination)?;
+
+            let mut run = RunStats::from(solver.as_ref(), tsp);
+            run.memory = tracker.finish();
+            runs.push((run, stats::anytime_curve(solver.as_ref())));
+        }
+        outcomes.push(runs);
+    }
+    Ok(outcomes)
+}
+
+/// Spreads the runs of [`run_locally`] over `workers`, all at once.
+fn run_remotely(
+    workers: &Cluster,
+    tsp: &TspLib,
+    options: &BenchOptions,
+    solvers: &SolverConfig,
+) -> Result<Outcomes> {
+    let tasks = options
+        .algorithms
+        .iter()
+        .flat_map(|&algorithm| {
+            (0..options.runs).map(move |k| Task {
+                spec: solvers.spec(algorithm),
+                seed: options.seed.wrapping_add(k as u64),
+                time_limit: options.time_limit,
+                max_iterations: None,
+                stats: true,
+            })
+        })
+        .collect();
+    let mut reports = workers
+        .run_all(tsp, tasks, Some(&options.cancellation))?
+        .into_iter();
+
+    Ok(options
+        .algorithms
+        .iter()
+        .map(|_| {
+            reports
+                .by_ref()
+                .take(options.runs)
+                .map(|report| {
+                    let run = report.stats.expect("stats were asked for");
+                    (run, report.curve)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// What algorithms are compared by across instances.
+#[derive(Debug, Clone, Copy)]
+enum Criterion {
+    /// Mean best distance.
+    Distance,
+    /// Mean time to come within [`stats::SUCCESS_GAP`] of the optimum, on the
+    /// instances whose optimum is known.
+    TimeToTarget,
+}
+
+impl Criterion {
+    fn name(self) -> &'static str {
+        match self {
+            Criterion::Distance => "distance",
+            Criterion::TimeToTarget => "time to target",
+        }
+    }
+
+    /// `costs[instance][algorithm]` by this criterion, `None` where an
+    /// algorithm never met the target.
+    fn costs(self, results: &[InstanceResults]) -> Vec<Vec<Option<f64>>> {
+        let success = stats::TARGET_GAPS
+            .iter()
+            .position(|&gap| gap == stats::SUCCESS_GAP)
+            .expect("the success gap is a target gap");
+        results
+            .iter()
+            .filter(|instance| match self {
+                Criterion::Distance => true,
+                Criterion::TimeToTarget => instance.tsp.optimal_tour_length.is_some(),
+            })
+            .map(|instance| {
+                instance
+                    .algorithms
+                    .iter()
+                    .map(|AlgorithmRuns { runs, .. }| {
+                        let summary = stats::aggregate(runs);
+                        match self {
+                            Criterion::Distance => Some(summary.mean),
+                            Criterion::TimeToTarget => summary.targets[success].1,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Draws the performance profiles of the algorithms over all instances by
+/// every [`Criterion`] and returns the figures' paths.
+fn plot_profiles(
+    results: &[InstanceResults],
+    options: &BenchOptions,
+    plot: &PlotConfig,
+) -> Result<Vec<PathBuf>> {
+    let colors = plot.series_colors().context("invalid palette")?;
+    let mut figures = Vec::new();
+    for criterion in [Criterion::Distance, Criterion::TimeToTarget] {
+        let costs = criterion.costs(results);
+        if costs.is_empty() {
+            continue;
+        }
+        let curves: Vec<plot::ProfileCurve> = stats::performance_profiles(&costs)
+            .into_iter()
+            .zip(&options.algorithms)
+            .enumerate()
+            .map(|(index, (curve, algorithm))| {
+                (algorithm.name(), curve, colors[index % colors.len()])
+            })
+            .collect();
+        figures.extend(plot::plot_performance_profiles(
+            &curves,
+            criterion.name(),
+            plot,
+        )?);
+    }
+
+    Ok(figures)
+}
+
+/// Prints the results table of `instance`.
+fn print_table(instance: &InstanceResults, options: &BenchOptions) {
+    let mut table = Table::new();
+    table.add_row(row![bFg => instance_heading(&instance.tsp)]);
+    table.add_row(Row::new(
+        TABLE_HEADER
+            .iter()
+            .map(|title| Cell::new(title).style_spec("bFg"))
+            .collect(),
+    ));
+    for row in table_rows(instance, options.success_gap) {
+        table.add_row(Row::new(row.iter().map(|value| Cell::new(value)).collect()));
+    }
+    table.printstd();
+}
+
+/// Prints how the algorithms ranked across all instances: their average rank
+/// and wins/ties/losses by gap and by time to target.
+fn print_ranking(results: &[InstanceResults], options: &BenchOptions) {
+    // On a given instance, mean distance and mean gap rank alike, and only
+    // the former is defined without a known optimum.
+    let by_gap = stats::rank_summary(&Criterion::Distance.costs(results));
+    let by_time = stats::rank_summary(&Criterion::TimeToTarget.costs(results));
+
+    let mut table = Table::new();
+    table.add_row(row![bFg => format!("Ranking over {} instances", results.len())]);
+    table.add_row(
+        row![bFg => "Algorithm", "Rank by gap", "W/T/L", "Rank by time to target", "W/T/L"],
+    );
+    let describe = |summary: Option<&stats::RankSummary>| match summary {
+        Some(summary) => (
+            format!("{:.2}", summary.average_rank),
+            format!("{}/{}/{}", summary.wins, summary.ties, summary.losses),
+        ),
+        None => ("-".to_string(), "-".to_string()),
+    };
+    for (index, algorithm) in options.algorithms.iter().enumerate() {
+        let (gap_rank, gap_record) = describe(by_gap.get(index));
+        let (time_rank, time_record) = describe(by_time.get(index));
+        table.add_row(row![
+            algorithm.name(),
+            gap_rank,
+            gap_record,
+            time_rank,
+            time_record
+        ]);
+    }
+    table.printstd();
+}
+
+/// The benchmark's results as an experiment document.
+fn experiment(
+    results: &[InstanceResults],
+    options: &BenchOptions,
+    solvers: &SolverConfig,
+    manifest: &Path,
+) -> Result<Experiment> {
+    let mut instances = Vec::new();
+    for instance in results {
+        let mut record = InstanceRecord::new(&instance.tsp);
+        for AlgorithmRuns { algorithm, runs } in &instance.algorithms {
+            let name = algorithm.name();
+            let seed = Some(options.seed);
+            record.algorithms.push(AlgorithmRecord::new(
+                name,
+                &solvers.spec(*algorithm),
+                runs,
+                seed,
+            )?);
+        }
+        instances.push(record);
+    }
+
+    Ok(Experiment::new(instances, Some(manifest)))
+}
+
+/// File, in the output directory, recording how to reproduce the benchmark.
+const MANIFEST_FILE: &str = "bench_manifest.json";
+
+const TABLE_HEADER: [&str; 12] = [
+    "Algorithm",
+    "Best",
+    "Mean",
+    "Median",
+    "Std",
+    "Best gap",
+    "Mean gap",
+    "Success",
+    "Convergence area",
+    "Diversity",
+    "Acceptance",
+    "Mean runtime (ms)",
+];
+
+/// One results table row per algorithm run on `instance`, success counting
+/// runs within `success_gap` percent of the optimum.
+fn table_rows(instance: &InstanceResults, success_gap: f64) -> Vec<Vec<String>> {
+    instance
+        .algorithms
+        .iter()
+        .map(|AlgorithmRuns { algorithm, runs }| {
+            let summary = stats::aggregate(runs);
+            let gap = |distance: f64| {
+                stats::gap(distance, &instance.tsp).map_or("-".to_string(), stats::format_gap)
+            };
+            let runtime =
+                runs.iter().map(|run| run.run_time_ms).sum::<u64>() as f64 / runs.len() as f64;
+            let percent = |share: Option<f64>| {
+                share.map_or("-".to_string(), |s| format!("{:.1}%", s * 100.0))
+            };
+            vec![
+                algorithm.name().to_string(),
+                summary.min.to_string(),
+                format!("{:.1}", summary.mean),
+                format!("{:.1}", summary.median),
+                format!("{:.1}", summary.std),
+                gap(summary.min as f64),
+                gap(summary.mean),
+                stats::success_rate(runs, success_gap)
+                    .map_or("-".to_string(), |rate| format!("{:.0}%", rate * 100.0)),
+                stats::format_gap(summary.convergence_area),
+                percent(summary.population_diversity),
+                percent(summary.acceptance_rate),
+                format!("{:.0}", runtime),
+            ]
+        })
+        .collect()
+}
+
+/// Heading of an instance's section, with its size and optimum.
+fn instance_heading(tsp: &TspLib) -> String {
+    match tsp.optimal_tour_length {
+        Some(optimum) => format!(
+            "{} ({} cities, optimum {})",
+            tsp.name, tsp.dimension, optimum
+        ),
+        None => format!("{} ({} cities)", tsp.name, tsp.dimension),
+    }
+}
+
+fn settings_line(options: &BenchOptions) -> String {
+    let mut line = format!(
+        "{} runs per algorithm and instance, seeds {} to {}",
+        options.runs,
+        options.seed,
+        options.seed.wrapping_add(options.runs as u64 - 1)
+    );
+    if let Some(limit) = options.time_limit {
+        line += &format!(", at most {:.1}s per run", limit.as_secs_f64());
+    }
+    line + &format!(
+        ". Success counts runs within {}% of the optimum. Convergence area is the gap of \
+         the best tour so far averaged over a run's time, diversity the share of edges two \
+         members of a GA or PSO population do not share at the end, and acceptance the \
+         share of moves SA kept. Everything needed to reproduce these results is \
+         recorded in {}.",
+        options.success_gap, MANIFEST_FILE
+    )
+}
+
+fn figure_name(figure: Option<&PathBuf>) -> Option<String> {
+    figure
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().into_owned())
+}
+
+const PROFILES_HEADING: &str = "Performance profiles";
+
+const PROFILES_TEXT: &str = "Share of instances on which each algorithm was within a \
+     factor τ of the best one.";
+
+fn markdown_report(
+    results: &[InstanceResults],
+    profiles: &[PathBuf],
+    options: &BenchOptions,
+) -> Result<String> {
+    let mut report = String::from("# Benchmark report\n\n");
+    writeln!(report, "{}\n", settings_line(options))?;
+
+    for instance in results {
+        writeln!(report, "## {}\n", instance_heading(&instance.tsp))?;
+        writeln!(report, "| {} |", TABLE_HEADER.join(" | "))?;
+        writeln!(report, "|{}", "---|".repeat(TABLE_HEADER.len()))?;
+        for row in table_rows(instance, options.success_gap) {
+            writeln!(report, "| {} |", row.join(" | "))?;
+        }
+        if let Some(figure) = figure_name(instance.figure.as_ref()) {
+            writeln!(
+                report,
+                "\n![{} anytime curves]({})",
+                instance.tsp.name, figure
+            )?;
+        }
+        if let Some(figure) = figure_name(instance.metrics_figure.as_ref()) {
+            writeln!(report, "\n![{} run metrics]({})", instance.tsp.name, figure)?;
+        }
+        report.push('\n');
+    }
+
+    if !profiles.is_empty() {
+        writeln!(report, "## {}\n\n{}\n", PROFILES_HEADING, PROFILES_TEXT)?;
+        for figure in profiles.iter().filter_map(|path| figure_name(Some(path))) {
+            writeln!(report, "![{}]({})\n", PROFILES_HEADING, figure)?;
+        }
+    }
+
+    Ok(report)
+}
+
+fn html_report(
+    results: &[InstanceResults],
+    profiles: &[PathBuf],
+    options: &BenchOptions,
+) -> Result<String> {
+    let mut report = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Benchmark report</title>\n\
+         <style>body { font-family: sans-serif; } table { border-collapse: collapse; } \
+         th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: right; }</style>\n\
+         </head>\n<body>\n<h1>Benchmark report</h1>\n",
+    );
+    writeln!(report, "<p>{}</p>", settings_line(options))?;
+
+    for instance in results {
+        writeln!(
+            report,
+            "<h2>{}</h2>\n<table>",
+            instance_heading(&instance.tsp)
+        )?;
+        writeln!(
+            report,
+            "<tr><th>{}</th></tr>",
+            TABLE_HEADER.join("</th><th>")
+        )?;
+        for row in table_rows(instance, options.success_gap) {
+            writeln!(report, "<tr><td>{}</td></tr>", row.join("</td><td>"))?;
+        }
+        report += "</table>\n";
+        if let Some(figure) = figure_name(instance.figure.as_ref()) {
+            writeln!(
+                report,
+                "<p><img src=\"{}\" alt=\"{} anytime curves\"></p>",
+                figure, instance.tsp.name
+            )?;
+        }
+        if let Some(figure) = figure_name(instance.metrics_figure.as_ref()) {
+            writeln!(
+                report,
+                "<p><img src=\"{}\" alt=\"{} run metrics\"></p>",
+                figure, instance.tsp.name
+            )?;
+        }
+    }
+
+    if !profiles.is_empty() {
+        writeln!(
+            report,
+            "<h2>{}</h2>\n<p>{}</p>",
+            PROFILES_HEADING, PROFILES_TEXT
+        )?;
+        for figure in profiles.iter().filter_map(|path| figure_name(Some(path))) {
+            writeln!(
+                report,
+                "<p><img src=\"{}\" alt=\"{}\"></p>",
+                figure, PROFILES_HEADING
+            )?;
+        }
+    }
+    report += "</body>\n</html>\n";
+
+    Ok(report)
+}
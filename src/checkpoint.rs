@@ -0,0 +1,143 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::tsplib::{HeuristicAlgorithm, SolverRng, TspLib};
+
+/// Where a solver saves its run while it goes, and how often.
+#[derive(Debug, Clone)]
+pub struct Checkpointing {
+    pub path: PathBuf,
+    /// Iterations between two checkpoints.
+    pub every: usize,
+}
+
+/// A run in progress: the instance it is on, its random number generator, the
+/// iterations done, the time spent so far and whatever else the algorithm
+/// carries from one iteration to the next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunState<S> {
+    /// [`TspLib::fingerprint`] and dimension of the instance, so the run is
+    /// not resumed on another one. 0 in checkpoints that predate them.
+    #[serde(default)]
+    pub instance: u64,
+    #[serde(default)]
+    pub dimension: usize,
+    pub rng: SolverRng,
+    pub iterations: usize,
+    pub elapsed_ms: u64,
+    pub state: S,
+    /// When the run started or resumed in this process; see `start_time`.
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl<S> RunState<S> {
+    /// A new run on `tsp`.
+    pub fn new(tsp: &TspLib, rng: SolverRng, state: S) -> Self {
+        RunState {
+            instance: tsp.fingerprint(),
+            dimension: tsp.dimension,
+            rng,
+            iterations: 0,
+            elapsed_ms: 0,
+            state,
+            started: None,
+        }
+    }
+
+    /// An instant as long ago as the run had already taken when it was first
+    /// asked for, so elapsed times carry on from where a checkpoint left off.
+    pub fn start_time(&mut self) -> Instant {
+        *self.started.get_or_insert_with(|| {
+            let now = Instant::now();
+            now.checked_sub(Duration::from_millis(self.elapsed_ms))
+                .unwrap_or(now)
+        })
+    }
+}
+
+/// A solver that can save its run part-way through and pick it up again later,
+/// ending exactly as if it had never stopped.
+pub trait Checkpoint: HeuristicAlgorithm + Serialize + DeserializeOwned {
+    /// What the algorithm carries from one iteration to the next besides the
+    /// solver's own fields.
+    type State: Serialize + DeserializeOwned;
+
+    /// Makes the next `solve` or `init` continue `run` rather than start a new
+    /// one.
+    fn resume(&mut self, run: RunState<Self::State>);
+}
+
+#[derive(Serialize)]
+struct SavedRun<'a, T, S> {
+    solver: &'a T,
+    run: &'a RunState<S>,
+}
+
+#[derive(Deserialize)]
+struct LoadedRun<T, S> {
+    solver: T,
+    run: RunState<S>,
+}
+
+/// Writes `solver` and its run in progress to `path` as JSON. The previous
+/// checkpoint is only replaced once the new one is complete.
+pub fn save<T: Serialize, S: Serialize>(path: &Path, solver: &T, run: &RunState<S>) -> Result<()> {
+    let partial = path.with_extension("partial");
+    let contents = serde_json::to_string(&SavedRun { solver, run })?;
+    fs::write(&partial, contents)
+        .with_context(|| format!("failed to write {}", partial.display()))?;
+    fs::rename(&partial, path).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Saves the run if `checkpointing` is set and a checkpoint is due after its
+/// latest iteration or the run is `stopping` early.
+pub fn save_if_due<T: Serialize, S: Serialize>(
+    checkpointing: Option<&Checkpointing>,
+    solver: &T,
+    run: &mut RunState<S>,
+    start_time: Instant,
+    stopping: bool,
+) -> Result<()> {
+    let Some(checkpointing) = checkpointing else {
+        return Ok(());
+    };
+    if stopping || run.iterations.is_multiple_of(checkpointing.every.max(1)) {
+        run.elapsed_ms = start_time.elapsed().as_millis() as u64;
+        save(&checkpointing.path, solver, run)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a solver saved by a checkpoint, ready for `solve` to continue its run
+/// on `tsp`. Fails if the run was on another instance.
+pub fn load<T: Checkpoint>(path: &Path, tsp: &TspLib) -> Result<T> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let loaded: LoadedRun<T, T::State> = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse checkpoint {}", path.display()))?;
+    if loaded.run.instance != tsp.fingerprint() || loaded.run.dimension != tsp.dimension {
+        bail!(
+            "checkpoint {} was saved on a different instance than {} ({} cities)",
+            path.display(),
+            tsp.name,
+            tsp.dimension
+        );
+    }
+    let mut solver = loaded.solver;
+    solver.resume(loaded.run);
+
+    Ok(solver)
+}
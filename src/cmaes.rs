@@ -0,0 +1,242 @@
+use rand::Rng;
+
+/// Covariance matrix adaptation evolution strategy over the unit hypercube,
+/// following Hansen's "The CMA Evolution Strategy: A Tutorial".
+///
+/// Each generation, [`CmaEs::ask`] draws [`CmaEs::population_size`] points
+/// from a multivariate normal distribution, and [`CmaEs::tell`] moves its mean
+/// towards the best of them and adapts its step size and covariance to the
+/// directions they succeeded in. Points are clamped into the hypercube, so
+/// integer and categorical dimensions are simply rounded by whoever maps the
+/// points back to values.
+pub struct CmaEs {
+    dimensions: usize,
+    population_size: usize,
+    weights: Vec<f64>,
+    mu_eff: f64,
+    c_sigma: f64,
+    d_sigma: f64,
+    c_c: f64,
+    c_1: f64,
+    c_mu: f64,
+    chi_n: f64,
+
+    mean: Vec<f64>,
+    sigma: f64,
+    covariance: Vec<Vec<f64>>,
+    p_sigma: Vec<f64>,
+    p_c: Vec<f64>,
+    // The covariance is B * diag(D^2) * B^T, with B's columns its eigenvectors.
+    b: Vec<Vec<f64>>,
+    d: Vec<f64>,
+    generation: usize,
+}
+
+impl CmaEs {
+    /// Starts the search around `mean` with step size `sigma`, both in
+    /// hypercube units.
+    pub fn new(mean: Vec<f64>, sigma: f64) -> Self {
+        let n = mean.len().max(1) as f64;
+        let population_size = 4 + (3.0 * n.ln()).floor() as usize;
+        let mu = population_size / 2;
+
+        let raw: Vec<f64> = (1..=mu)
+            .map(|i| ((population_size as f64 + 1.0) / 2.0).ln() - (i as f64).ln())
+            .collect();
+        let total: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / total).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let d_sigma = 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma;
+        let c_c = (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        let c_mu =
+            (1.0 - c_1).min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff));
+        let chi_n = n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n));
+
+        let dimensions = mean.len();
+        CmaEs {
+            dimensions,
+            population_size,
+            weights,
+            mu_eff,
+            c_sigma,
+            d_sigma,
+            c_c,
+            c_1,
+            c_mu,
+            chi_n,
+            mean,
+            sigma,
+            covariance: identity(dimensions),
+            p_sigma: vec![0.0; dimensions],
+            p_c: vec![0.0; dimensions],
+            b: identity(dimensions),
+            d: vec![1.0; dimensions],
+            generation: 0,
+        }
+    }
+
+    /// Points drawn per generation.
+    pub fn population_size(&self) -> usize {
+        self.population_size
+    }
+
+    /// Draws a generation of points.
+    pub fn ask(&self, rng: &mut impl Rng) -> Vec<Vec<f64>> {
+        (0..self.population_size)
+            .map(|_| {
+                let scaled: Vec<f64> = self.d.iter().map(|d| d * standard_normal(rng)).collect();
+                (0..self.dimensions)
+                    .map(|i| {
+                        let step: f64 =
+                            (0..self.dimensions).map(|j| self.b[i][j] * scaled[j]).sum();
+                        (self.mean[i] + self.sigma * step).clamp(0.0, 1.0 - f64::EPSILON)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Updates the distribution from a generation of `points` and their
+    /// `fitness`, lower being better.
+    pub fn tell(&mut self, points: &[Vec<f64>], fitness: &[f64]) {
+        let n = self.dimensions;
+        let mut order: Vec<usize> = (0..points.len()).collect();
+        order.sort_by(|&a, &b| fitness[a].total_cmp(&fitness[b]));
+
+        // Steps of the selected points from the old mean, in units of sigma.
+        let steps: Vec<Vec<f64>> = order
+            .iter()
+            .take(self.weights.len())
+            .map(|&k| {
+                points[k]
+                    .iter()
+                    .zip(&self.mean)
+                    .map(|(x, m)| (x - m) / self.sigma)
+                    .collect()
+            })
+            .collect();
+        let weights = &self.weights[..steps.len()];
+        let step_w: Vec<f64> = (0..n)
+            .map(|i| weights.iter().zip(&steps).map(|(w, y)| w * y[i]).sum())
+            .collect();
+        for (m, y) in self.mean.iter_mut().zip(&step_w) {
+            *m += self.sigma * y;
+        }
+
+        // C^(-1/2) * step_w = B * diag(1/D) * B^T * step_w
+        let rotated: Vec<f64> = (0..n)
+            .map(|j| (0..n).map(|i| self.b[i][j] * step_w[i]).sum::<f64>() / self.d[j])
+            .collect();
+        let whitened: Vec<f64> = (0..n)
+            .map(|i| (0..n).map(|j| self.b[i][j] * rotated[j]).sum())
+            .collect();
+
+        let sigma_rate = (self.c_sigma * (2.0 - self.c_sigma) * self.mu_eff).sqrt();
+        for (p, z) in self.p_sigma.iter_mut().zip(&whitened) {
+            *p = (1.0 - self.c_sigma) * *p + sigma_rate * z;
+        }
+        let p_sigma_norm = norm(&self.p_sigma);
+
+        self.generation += 1;
+        let correction = (1.0 - (1.0 - self.c_sigma).powi(2 * self.generation as i32)).sqrt();
+        let stalled = p_sigma_norm / correction >= (1.4 + 2.0 / (n as f64 + 1.0)) * self.chi_n;
+        let h_sigma = if stalled { 0.0 } else { 1.0 };
+
+        let c_rate = (self.c_c * (2.0 - self.c_c) * self.mu_eff).sqrt();
+        for (p, y) in self.p_c.iter_mut().zip(&step_w) {
+            *p = (1.0 - self.c_c) * *p + h_sigma * c_rate * y;
+        }
+
+        let delta_h = (1.0 - h_sigma) * self.c_c * (2.0 - self.c_c);
+        for i in 0..n {
+            for j in 0..n {
+                let rank_mu: f64 = weights
+                    .iter()
+                    .zip(&steps)
+                    .map(|(w, y)| w * y[i] * y[j])
+                    .sum();
+                self.covariance[i][j] = (1.0 - self.c_1 - self.c_mu) * self.covariance[i][j]
+                    + self.c_1 * (self.p_c[i] * self.p_c[j] + delta_h * self.covariance[i][j])
+                    + self.c_mu * rank_mu;
+            }
+        }
+
+        self.sigma *= ((self.c_sigma / self.d_sigma) * (p_sigma_norm / self.chi_n - 1.0)).exp();
+        // Past this the search spans the whole hypercube anyway.
+        self.sigma = self.sigma.min(1.0);
+
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&self.covariance);
+        self.d = eigenvalues.iter().map(|&e| e.max(1e-20).sqrt()).collect();
+        self.b = eigenvectors;
+    }
+}
+
+fn identity(n: usize) -> Vec<Vec<f64>> {
+    (0..n)
+        .map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect()
+}
+
+fn norm(v: &[f64]) -> f64 {
+    v.iter().map(|x| x * x).sum::<f64>().sqrt()
+}
+
+/// Samples the standard normal distribution with the Box-Muller transform.
+fn standard_normal(rng: &mut impl Rng) -> f64 {
+    let u: f64 = 1.0 - rng.gen::<f64>();
+    let v: f64 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+}
+
+/// Eigenvalues and eigenvectors (as columns) of the symmetric `matrix`, by
+/// cyclic Jacobi rotations; search spaces are small enough for it to be exact
+/// to rounding within a few sweeps.
+fn symmetric_eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut v = identity(n);
+
+    for _ in 0..50 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal < 1e-30 {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (upper, lower) = a.split_at_mut(q);
+                for (apk, aqk) in upper[p].iter_mut().zip(lower[0].iter_mut()) {
+                    let (x, y) = (*apk, *aqk);
+                    *apk = c * x - s * y;
+                    *aqk = s * x + c * y;
+                }
+                for row in v.iter_mut() {
+                    let (vkp, vkq) = (row[p], row[q]);
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    ((0..n).map(|i| a[i][i]).collect(), v)
+}
@@ -0,0 +1,585 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aco::{AcoVariant, AntColonyOptimization},
+    construct::Construction,
+    ga::{Crossover, GeneticAlgorithm, Selection},
+    hyper::Algorithm,
+    local_search::LocalSearch,
+    pso::ParticleSwarmOptimization,
+    sa::{Cooling, SimulatedAnnealing},
+    tabu::TabuSearch,
+    tsplib::{self, Configurable, HeuristicAlgorithm, TspLib},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AcoConfig {
+    pub alpha: f64,                       // pheromone importance
+    pub beta: f64,                        // distance importance
+    pub decay: f64,                       // pheromone evaporation rate
+    pub q: f64,                           // pheromone deposit factor
+    pub ants: usize,                      // number of ants
+    pub iterations: usize,                // number of iterations
+    pub snapshot_interval: Option<usize>, // pheromone snapshot period for heatmaps
+    pub seed: Option<u64>,                // fixed seed for reproducible runs
+    #[serde(skip_serializing_if = "LocalSearch::is_none")]
+    pub local_search: LocalSearch, // run on every ant's tour
+    #[serde(skip_serializing_if = "AcoVariant::is_ant_system")]
+    pub variant: AcoVariant, // pheromone update strategy
+    pub elite: usize,                     // elitist and rank-based weight of the best tour
+    pub candidates: usize,                // nearest neighbours ants pick from, 0 for all
+    pub construction: Option<Construction>, // builds the initial best tour
+}
+
+impl Default for AcoConfig {
+    fn default() -> Self {
+        AcoConfig {
+            alpha: 1.0,
+            beta: 2.0,
+            decay: 0.5,
+            q: 50.0,
+            ants: 100,
+            iterations: 100,
+            snapshot_interval: None,
+            seed: None,
+            local_search: LocalSearch::None,
+            variant: AcoVariant::AntSystem,
+            elite: 6,
+            candidates: tsplib::CANDIDATES,
+            construction: None,
+        }
+    }
+}
+
+impl AcoConfig {
+    fn set(&mut self, field: &str, value: &str) -> Result<()> {
+        match field {
+            "alpha" => self.alpha = value.parse()?,
+            "beta" => self.beta = value.parse()?,
+            "decay" => self.decay = value.parse()?,
+            "q" => self.q = value.parse()?,
+            "ants" => self.ants = value.parse()?,
+            "iterations" => self.iterations = value.parse()?,
+            "snapshot_interval" => self.snapshot_interval = Some(value.parse()?),
+            "seed" => self.seed = Some(value.parse()?),
+            "local_search" => self.local_search = value.parse()?,
+            "variant" => self.variant = value.parse()?,
+            "elite" => self.elite = value.parse()?,
+            "candidates" => self.candidates = value.parse()?,
+            "construction" => self.construction = Some(value.parse()?),
+            _ => bail!("unknown ACO parameter `{}`", field),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaConfig {
+    pub temperature: f64,     // initial temperature
+    pub cooling_rate: f64,    // cooling step per epoch, as `cooling` uses it
+    pub min_temperature: f64, // stopping temperature
+    pub seed: Option<u64>,    // fixed seed for reproducible runs
+    #[serde(skip_serializing_if = "LocalSearch::is_none")]
+    pub local_search: LocalSearch, // run on each epoch's new best route
+    #[serde(skip_serializing_if = "Cooling::is_geometric")]
+    pub cooling: Cooling, // temperature schedule
+    pub reheats: usize,       // most reheats when acceptance collapses, 0 for none
+    pub reheat_below: f64,    // acceptance rate that triggers a reheat
+    pub swap_weight: f64,     // relative frequency of swap moves
+    pub two_opt_weight: f64,  // relative frequency of 2-opt moves
+    pub or_opt_weight: f64,   // relative frequency of Or-opt moves
+}
+
+impl Default for SaConfig {
+    fn default() -> Self {
+        SaConfig {
+            temperature: 1000.0,
+            cooling_rate: 0.001,
+            min_temperature: 0.1,
+            seed: None,
+            local_search: LocalSearch::None,
+            cooling: Cooling::Geometric,
+            reheats: 0,
+            reheat_below: 0.01,
+            swap_weight: 0.8,
+            two_opt_weight: 0.2,
+            or_opt_weight: 0.0,
+        }
+    }
+}
+
+impl SaConfig {
+    fn set(&mut self, field: &str, value: &str) -> Result<()> {
+        match field {
+            "temperature" => self.temperature = value.parse()?,
+            "cooling_rate" => self.cooling_rate = value.parse()?,
+            "min_temperature" => self.min_temperature = value.parse()?,
+            "seed" => self.seed = Some(value.parse()?),
+            "local_search" => self.local_search = value.parse()?,
+            "cooling" => self.cooling = value.parse()?,
+            "reheats" => self.reheats = value.parse()?,
+            "reheat_below" => self.reheat_below = value.parse()?,
+            "swap_weight" => self.swap_weight = value.parse()?,
+            "two_opt_weight" => self.two_opt_weight = value.parse()?,
+            "or_opt_weight" => self.or_opt_weight = value.parse()?,
+            _ => bail!("unknown SA parameter `{}`", field),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GaConfig {
+    pub population_size: usize,
+    pub generations: usize,
+    pub mutation_rate: f64,
+    /// Fixed seed for reproducible runs.
+    pub seed: Option<u64>,
+    /// Run on every offspring once mutated.
+    #[serde(skip_serializing_if = "LocalSearch::is_none")]
+    pub local_search: LocalSearch,
+    #[serde(skip_serializing_if = "Crossover::is_order")]
+    pub crossover: Crossover,
+    #[serde(skip_serializing_if = "Selection::is_roulette")]
+    pub selection: Selection,
+    /// Chromosomes drawn for each tournament of tournament selection.
+    pub tournament_size: usize,
+    /// Sub-populations evolved apart on their own threads, 1 for a single
+    /// population.
+    pub islands: usize,
+    /// Generations between migrations of elites to the next island.
+    pub migration_interval: usize,
+    /// Chromosomes each island sends at a migration.
+    pub migration_size: usize,
+    /// Builds the first chromosome, the others being nearest-neighbour
+    /// tours from random cities.
+    pub construction: Option<Construction>,
+}
+
+impl Default for GaConfig {
+    fn default() -> Self {
+        GaConfig {
+            population_size: 400,
+            generations: 2000,
+            mutation_rate: 0.01,
+            seed: None,
+            local_search: LocalSearch::None,
+            crossover: Crossover::Order,
+            selection: Selection::Roulette,
+            tournament_size: 5,
+            islands: 1,
+            migration_interval: 10,
+            migration_size: 2,
+            construction: None,
+        }
+    }
+}
+
+impl GaConfig {
+    fn set(&mut self, field: &str, value: &str) -> Result<()> {
+        match field {
+            "population_size" => self.population_size = value.parse()?,
+            "generations" => self.generations = value.parse()?,
+            "mutation_rate" => self.mutation_rate = value.parse()?,
+            "seed" => self.seed = Some(value.parse()?),
+            "local_search" => self.local_search = value.parse()?,
+            "crossover" => self.crossover = value.parse()?,
+            "selection" => self.selection = value.parse()?,
+            "tournament_size" => self.tournament_size = value.parse()?,
+            "islands" => self.islands = value.parse()?,
+            "migration_interval" => self.migration_interval = value.parse()?,
+            "migration_size" => self.migration_size = value.parse()?,
+            "construction" => self.construction = Some(value.parse()?),
+            _ => bail!("unknown GA parameter `{}`", field),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PsoConfig {
+    pub particles: usize,
+    pub iterations: usize,
+    pub cognitive_weight: f64,
+    pub social_weight: f64,
+    pub inertia_weight: f64,
+    /// Fixed seed for reproducible runs.
+    pub seed: Option<u64>,
+    /// Run on every particle's position once moved.
+    #[serde(skip_serializing_if = "LocalSearch::is_none")]
+    pub local_search: LocalSearch,
+    /// Builds the first particle's position, the others starting from
+    /// nearest-neighbour tours from random cities.
+    pub construction: Option<Construction>,
+}
+
+impl Default for PsoConfig {
+    fn default() -> Self {
+        PsoConfig {
+            particles: 300,
+            iterations: 4000,
+            cognitive_weight: 1.5,
+            social_weight: 1.5,
+            inertia_weight: 0.8,
+            seed: None,
+            local_search: LocalSearch::None,
+            construction: None,
+        }
+    }
+}
+
+impl PsoConfig {
+    fn set(&mut self, field: &str, value: &str) -> Result<()> {
+        match field {
+            "particles" => self.particles = value.parse()?,
+            "iterations" => self.iterations = value.parse()?,
+            "cognitive_weight" => self.cognitive_weight = value.parse()?,
+            "social_weight" => self.social_weight = value.parse()?,
+            "inertia_weight" => self.inertia_weight = value.parse()?,
+            "seed" => self.seed = Some(value.parse()?),
+            "local_search" => self.local_search = value.parse()?,
+            "construction" => self.construction = Some(value.parse()?),
+            _ => bail!("unknown PSO parameter `{}`", field),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TabuConfig {
+    /// Iterations during which the edges a move removed may not be added
+    /// back.
+    pub tenure: usize,
+    pub iterations: usize,
+    /// Random moves sampled each iteration, the best admissible one taken.
+    pub candidates: usize,
+    /// Fixed seed for reproducible runs.
+    pub seed: Option<u64>,
+}
+
+impl Default for TabuConfig {
+    fn default() -> Self {
+        TabuConfig {
+            tenure: 20,
+            iterations: 5000,
+            candidates: 400,
+            seed: None,
+        }
+    }
+}
+
+impl TabuConfig {
+    fn set(&mut self, field: &str, value: &str) -> Result<()> {
+        match field {
+            "tenure" => self.tenure = value.parse()?,
+            "iterations" => self.iterations = value.parse()?,
+            "candidates" => self.candidates = value.parse()?,
+            "seed" => self.seed = Some(value.parse()?),
+            _ => bail!("unknown Tabu parameter `{}`", field),
+        }
+        Ok(())
+    }
+}
+
+/// Chainable setters for every setting of a solver config and a `build` that
+/// makes the solver, so solvers can be set up without the positional `new`
+/// constructors, e.g. `AcoConfig::default().ants(50).seed(7).build(&tsp)`.
+macro_rules! builder {
+    (
+        $config:ident => $solver:ty {
+            $($field:ident: $ty:ty),* $(,)?
+        }
+        optional { $($optional:ident: $optional_ty:ty),* $(,)? }
+    ) => {
+        impl $config {
+            $(
+                pub fn $field(mut self, $field: $ty) -> Self {
+                    self.$field = $field;
+                    self
+                }
+            )*
+            $(
+                pub fn $optional(mut self, $optional: $optional_ty) -> Self {
+                    self.$optional = Some($optional);
+                    self
+                }
+            )*
+
+            /// The solver for `tsp` with these settings.
+            pub fn build(&self, tsp: &TspLib) -> $solver {
+                <$solver>::from_config(tsp, self)
+            }
+        }
+    };
+}
+
+builder!(AcoConfig => AntColonyOptimization {
+    alpha: f64,
+    beta: f64,
+    decay: f64,
+    q: f64,
+    ants: usize,
+    iterations: usize,
+    local_search: LocalSearch,
+    variant: AcoVariant,
+    elite: usize,
+    candidates: usize,
+} optional { snapshot_interval: usize, seed: u64, construction: Construction });
+
+builder!(SaConfig => SimulatedAnnealing {
+    temperature: f64,
+    cooling_rate: f64,
+    min_temperature: f64,
+    local_search: LocalSearch,
+    cooling: Cooling,
+    reheats: usize,
+    reheat_below: f64,
+    swap_weight: f64,
+    two_opt_weight: f64,
+    or_opt_weight: f64,
+} optional { seed: u64 });
+
+builder!(GaConfig => GeneticAlgorithm {
+    population_size: usize,
+    generations: usize,
+    mutation_rate: f64,
+    local_search: LocalSearch,
+    crossover: Crossover,
+    selection: Selection,
+    tournament_size: usize,
+    islands: usize,
+    migration_interval: usize,
+    migration_size: usize,
+} optional { seed: u64, construction: Construction });
+
+builder!(PsoConfig => ParticleSwarmOptimization {
+    particles: usize,
+    iterations: usize,
+    cognitive_weight: f64,
+    social_weight: f64,
+    inertia_weight: f64,
+    local_search: LocalSearch,
+} optional { seed: u64, construction: Construction });
+
+builder!(TabuConfig => TabuSearch {
+    tenure: usize,
+    iterations: usize,
+    candidates: usize,
+} optional { seed: u64 });
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SolverConfig {
+    pub aco: AcoConfig,
+    pub sa: SaConfig,
+    pub ga: GaConfig,
+    pub pso: PsoConfig,
+    pub tabu: TabuConfig,
+}
+
+impl SolverConfig {
+    /// Every `algorithm.parameter` [`SolverConfig::apply_param`] takes but the
+    /// seeds.
+    pub const PARAMETERS: &'static [&'static str] = &[
+        "aco.alpha",
+        "aco.beta",
+        "aco.decay",
+        "aco.q",
+        "aco.ants",
+        "aco.iterations",
+        "aco.snapshot_interval",
+        "aco.local_search",
+        "aco.variant",
+        "aco.elite",
+        "aco.candidates",
+        "aco.construction",
+        "sa.temperature",
+        "sa.cooling_rate",
+        "sa.min_temperature",
+        "sa.local_search",
+        "sa.cooling",
+        "sa.reheats",
+        "sa.reheat_below",
+        "sa.swap_weight",
+        "sa.two_opt_weight",
+        "sa.or_opt_weight",
+        "ga.population_size",
+        "ga.generations",
+        "ga.mutation_rate",
+        "ga.local_search",
+        "ga.crossover",
+        "ga.selection",
+        "ga.tournament_size",
+        "ga.islands",
+        "ga.migration_interval",
+        "ga.migration_size",
+        "ga.construction",
+        "pso.particles",
+        "pso.iterations",
+        "pso.cognitive_weight",
+        "pso.social_weight",
+        "pso.inertia_weight",
+        "pso.local_search",
+        "pso.construction",
+        "tabu.tenure",
+        "tabu.iterations",
+        "tabu.candidates",
+    ];
+
+    /// Applies a single `algorithm.parameter=value` override, e.g. `aco.ants=200`.
+    pub fn apply_param(&mut self, param: &str) -> Result<()> {
+        let (key, value) = param
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected KEY=VALUE, got `{}`", param))?;
+        let (algorithm, field) = key
+            .trim()
+            .split_once('.')
+            .ok_or_else(|| anyhow!("expected ALGORITHM.PARAMETER, got `{}`", key))?;
+        let value = value.trim();
+
+        match algorithm {
+            "aco" => self.aco.set(field, value),
+            "sa" => self.sa.set(field, value),
+            "ga" => self.ga.set(field, value),
+            "pso" => self.pso.set(field, value),
+            "tabu" => self.tabu.set(field, value),
+            _ => bail!("unknown algorithm `{}`", algorithm),
+        }
+        .with_context(|| format!("invalid parameter override `{}`", param))
+    }
+
+    /// Whether any algorithm has a fixed seed.
+    pub fn is_seeded(&self) -> bool {
+        self.aco.seed.is_some()
+            || self.sa.seed.is_some()
+            || self.ga.seed.is_some()
+            || self.pso.seed.is_some()
+            || self.tabu.seed.is_some()
+    }
+
+    /// The settings of `algorithm`.
+    pub fn spec(&self, algorithm: Algorithm) -> SolverSpec {
+        match algorithm {
+            Algorithm::Aco => SolverSpec::Aco(self.aco.clone()),
+            Algorithm::Sa => SolverSpec::Sa(self.sa.clone()),
+            Algorithm::Ga => SolverSpec::Ga(self.ga.clone()),
+            Algorithm::Pso => SolverSpec::Pso(self.pso.clone()),
+            Algorithm::Tabu => SolverSpec::Tabu(self.tabu.clone()),
+        }
+    }
+}
+
+/// One solver and its settings, written with the algorithm next to them, e.g.
+/// `{ algorithm = "sa", cooling_rate = 0.01 }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum SolverSpec {
+    Aco(AcoConfig),
+    Sa(SaConfig),
+    Ga(GaConfig),
+    Pso(PsoConfig),
+    Tabu(TabuConfig),
+}
+
+impl SolverSpec {
+    pub fn algorithm(&self) -> Algorithm {
+        match self {
+            SolverSpec::Aco(_) => Algorithm::Aco,
+            SolverSpec::Sa(_) => Algorithm::Sa,
+            SolverSpec::Ga(_) => Algorithm::Ga,
+            SolverSpec::Pso(_) => Algorithm::Pso,
+            SolverSpec::Tabu(_) => Algorithm::Tabu,
+        }
+    }
+
+    /// The settings alone, as the `--config` table of the algorithm named by
+    /// the returned key.
+    pub fn to_table(&self) -> Result<(&'static str, toml::Value)> {
+        Ok(match self {
+            SolverSpec::Aco(config) => ("aco", toml::Value::try_from(config)?),
+            SolverSpec::Sa(config) => ("sa", toml::Value::try_from(config)?),
+            SolverSpec::Ga(config) => ("ga", toml::Value::try_from(config)?),
+            SolverSpec::Pso(config) => ("pso", toml::Value::try_from(config)?),
+            SolverSpec::Tabu(config) => ("tabu", toml::Value::try_from(config)?),
+        })
+    }
+}
+
+/// A solver for `tsp` configured as `spec` says.
+pub fn build_solver(tsp: &TspLib, spec: &SolverSpec) -> Box<dyn HeuristicAlgorithm> {
+    match spec {
+        SolverSpec::Aco(config) => Box::new(AntColonyOptimization::from_config(tsp, config)),
+        SolverSpec::Sa(config) => Box::new(SimulatedAnnealing::from_config(tsp, config)),
+        SolverSpec::Ga(config) => Box::new(GeneticAlgorithm::from_config(tsp, config)),
+        SolverSpec::Pso(config) => Box::new(ParticleSwarmOptimization::from_config(tsp, config)),
+        SolverSpec::Tabu(config) => Box::new(TabuSearch::from_config(tsp, config)),
+    }
+}
+
+/// Everything a run can be configured with, as read from a `--config` TOML
+/// (or, with the `yaml` feature, YAML) file with `[aco]`, `[sa]`, `[ga]`,
+/// `[pso]`, `[tabu]` and `[plot]` tables and the `[[pipeline]]` stages run by
+/// `--pipeline`. The top-level `instances`, `algorithms`, `seed` and `runs`
+/// describe the experiment `sapso run` repeats; command-line flags override
+/// them.
+#[cfg(feature = "plot")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Instance names or `.tsp` paths, solved one after the other.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub instances: Vec<String>,
+    /// Solvers to run, all of them if empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub algorithms: Vec<Algorithm>,
+    /// Construction heuristics whose tours are reported before the solvers
+    /// run, e.g. `greedy_edge`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub constructions: Vec<Construction>,
+    /// Seed of the first run, later runs counting up from it.
+    pub seed: Option<u64>,
+    /// Independent runs of every solver.
+    pub runs: Option<usize>,
+    #[serde(flatten)]
+    pub solvers: SolverConfig,
+    pub plot: crate::plot::PlotConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pipeline: Vec<crate::pipeline::Stage>,
+}
+
+#[cfg(feature = "plot")]
+impl Config {
+    /// Reads `path` as YAML if it ends in `.yaml` or `.yml` and as TOML
+    /// otherwise.
+    pub fn load(path: &str) -> Result<Self> {
+        let contents =
+            std::fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        if path.ends_with(".yaml") || path.ends_with(".yml") {
+            #[cfg(feature = "yaml")]
+            return serde_yaml::from_str(&contents)
+                .with_context(|| format!("failed to parse {}", path));
+            #[cfg(not(feature = "yaml"))]
+            bail!("reading {} requires a build with `--features yaml`", path);
+        }
+        toml::from_str(&contents).with_context(|| format!("failed to parse {}", path))
+    }
+}
+
+/// One-line `key=value, ...` summary of a solver's settings, as shown in plot
+/// annotations. Unset optional settings are left out.
+pub fn parameter_summary<T: Serialize>(config: &T) -> String {
+    match toml::Value::try_from(config) {
+        Ok(toml::Value::Table(table)) => table
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => String::new(),
+    }
+}
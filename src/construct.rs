@@ -0,0 +1,402 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{distance::DistanceMatrix, tsplib::TspLib};
+
+/// A classical heuristic that builds a tour in one pass, configured as
+/// `construction = "greedy_edge"`. On its own it gives a quick baseline;
+/// solvers use it for their first tours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Construction {
+    /// Always moves on to the nearest unvisited city.
+    #[default]
+    NearestNeighbor,
+    /// Adds the shortest edges first, skipping any that would give a city a
+    /// third edge or close a cycle too early.
+    GreedyEdge,
+    /// Grows a small cycle by inserting the city that lengthens it least.
+    CheapestInsertion,
+    /// Grows a small cycle by inserting the city farthest from it where it
+    /// lengthens the cycle least.
+    FarthestInsertion,
+    /// Shortcuts an Euler tour of a minimum spanning tree and a matching of
+    /// its odd-degree cities. The matching is greedy rather than minimum, so
+    /// the tour need not be within 1.5 times the optimum.
+    Christofides,
+}
+
+impl Construction {
+    pub fn is_nearest_neighbor(&self) -> bool {
+        *self == Construction::NearestNeighbor
+    }
+
+    /// Name for reports and plots, e.g. `Greedy Edge`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Construction::NearestNeighbor => "Nearest Neighbor",
+            Construction::GreedyEdge => "Greedy Edge",
+            Construction::CheapestInsertion => "Cheapest Insertion",
+            Construction::FarthestInsertion => "Farthest Insertion",
+            Construction::Christofides => "Christofides",
+        }
+    }
+
+    /// The tour of `tsp` this heuristic builds from city `start`. Greedy edge
+    /// builds the same cycle from every city, only starting it at `start`.
+    pub fn build(self, tsp: &TspLib, start: usize) -> Vec<usize> {
+        if tsp.dimension < 3 {
+            return (0..tsp.dimension).collect();
+        }
+        let matrix = &tsp.distance_matrix;
+        match self {
+            Construction::NearestNeighbor => nearest_neighbor(matrix, start),
+            Construction::GreedyEdge => greedy_edge(tsp, start),
+            Construction::CheapestInsertion => cheapest_insertion(matrix, start),
+            Construction::FarthestInsertion => farthest_insertion(matrix, start),
+            Construction::Christofides => christofides(matrix, start),
+        }
+    }
+}
+
+impl FromStr for Construction {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "nearest_neighbor" | "nn" => Construction::NearestNeighbor,
+            "greedy_edge" | "greedy" => Construction::GreedyEdge,
+            "cheapest_insertion" | "cheapest" => Construction::CheapestInsertion,
+            "farthest_insertion" | "farthest" => Construction::FarthestInsertion,
+            "christofides" => Construction::Christofides,
+            _ => bail!(
+                "unknown construction `{}`, expected nearest_neighbor, greedy_edge, cheapest_insertion, farthest_insertion or christofides",
+                name
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Construction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Construction::NearestNeighbor => "nearest_neighbor",
+            Construction::GreedyEdge => "greedy_edge",
+            Construction::CheapestInsertion => "cheapest_insertion",
+            Construction::FarthestInsertion => "farthest_insertion",
+            Construction::Christofides => "christofides",
+        })
+    }
+}
+
+/// The tour that starts at `start` and always moves on to the nearest
+/// unvisited city, the first of them on ties.
+pub fn nearest_neighbor(matrix: &DistanceMatrix, start: usize) -> Vec<usize> {
+    let n = matrix.len();
+    let mut visited = vec![false; n];
+    visited[start] = true;
+    let mut tour = Vec::with_capacity(n);
+    tour.push(start);
+    for _ in 1..n {
+        let current = *tour.last().unwrap();
+        let next = (0..n)
+            .filter(|&city| !visited[city])
+            .min_by_key(|&city| matrix.get(current, city))
+            .unwrap();
+        visited[next] = true;
+        tour.push(next);
+    }
+    tour
+}
+
+/// Greedy edge matching over the candidate edges of `tsp`, its cities'
+/// nearest neighbours; fragments left over are joined end to nearest end.
+fn greedy_edge(tsp: &TspLib, start: usize) -> Vec<usize> {
+    let n = tsp.dimension;
+    let matrix = &tsp.distance_matrix;
+    let mut edges: Vec<(u64, usize, usize)> = tsp
+        .neighbors()
+        .iter()
+        .enumerate()
+        .flat_map(|(a, neighbors)| {
+            neighbors
+                .iter()
+                .filter(move |&&b| a < b)
+                .map(move |&b| (matrix.get(a, b), a, b))
+        })
+        .collect();
+    edges.sort_unstable();
+
+    let mut links: Vec<Vec<usize>> = vec![Vec::with_capacity(2); n];
+    let mut fragments = Fragments::new(n);
+    let mut added = 0;
+    for (_, a, b) in edges {
+        if links[a].len() < 2 && links[b].len() < 2 && fragments.join(a, b) {
+            links[a].push(b);
+            links[b].push(a);
+            added += 1;
+        }
+    }
+
+    // Chain the fragments into one path, each from the end of the last to
+    // the nearest end of one not yet in the chain.
+    let ends: Vec<usize> = (0..n).filter(|&city| links[city].len() < 2).collect();
+    let roots: Vec<usize> = ends.iter().map(|&end| fragments.find(end)).collect();
+    let mut chained = vec![false; n];
+    chained[roots[0]] = true;
+    let mut current = other_end(&links, ends[0]);
+    for _ in added + 1..n {
+        let (index, &next) = ends
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !chained[roots[i]])
+            .min_by_key(|&(_, &end)| matrix.get(current, end))
+            .unwrap();
+        chained[roots[index]] = true;
+        let end = other_end(&links, next);
+        links[current].push(next);
+        links[next].push(current);
+        current = end;
+    }
+
+    // Walk the path from its first end; closing it back there makes the
+    // cycle.
+    let mut tour = Vec::with_capacity(n);
+    let (mut previous, mut city) = (usize::MAX, ends[0]);
+    loop {
+        tour.push(city);
+        match links[city].iter().find(|&&next| next != previous) {
+            Some(&next) if tour.len() < n => (previous, city) = (city, next),
+            _ => break,
+        }
+    }
+    rotate_to(&mut tour, start);
+    tour
+}
+
+/// The other end of the path fragment that ends at `end`.
+fn other_end(links: &[Vec<usize>], end: usize) -> usize {
+    let (mut previous, mut city) = (usize::MAX, end);
+    while let Some(&next) = links[city].iter().find(|&&next| next != previous) {
+        (previous, city) = (city, next);
+    }
+    city
+}
+
+/// Union-find over the cities, telling apart the path fragments greedy edge
+/// has built.
+struct Fragments {
+    parent: Vec<usize>,
+}
+
+impl Fragments {
+    fn new(n: usize) -> Self {
+        Fragments {
+            parent: (0..n).collect(),
+        }
+    }
+
+    fn find(&mut self, mut city: usize) -> usize {
+        while self.parent[city] != city {
+            self.parent[city] = self.parent[self.parent[city]];
+            city = self.parent[city];
+        }
+        city
+    }
+
+    /// Joins the fragments of `a` and `b`, unless they are the same one.
+    fn join(&mut self, a: usize, b: usize) -> bool {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a] = b;
+        a != b
+    }
+}
+
+/// Cost of inserting `city` between `a` and `b`.
+fn insertion_cost(matrix: &DistanceMatrix, a: usize, city: usize, b: usize) -> i64 {
+    (matrix.get(a, city) + matrix.get(city, b)) as i64 - matrix.get(a, b) as i64
+}
+
+/// Cheapest position to insert `city` in the cycle of `next`, which starts
+/// at `start`: the city it goes after, and what it costs.
+fn cheapest_position(
+    matrix: &DistanceMatrix,
+    next: &[usize],
+    start: usize,
+    city: usize,
+) -> (i64, usize) {
+    let mut best = (i64::MAX, start);
+    let mut a = start;
+    loop {
+        let cost = insertion_cost(matrix, a, city, next[a]);
+        if cost < best.0 {
+            best = (cost, a);
+        }
+        a = next[a];
+        if a == start {
+            return best;
+        }
+    }
+}
+
+/// The cycle of `next` from `start`, as a tour.
+fn cycle_tour(next: &[usize], start: usize) -> Vec<usize> {
+    let mut tour = vec![start];
+    let mut city = next[start];
+    while city != start {
+        tour.push(city);
+        city = next[city];
+    }
+    tour
+}
+
+/// Cheapest insertion from the cycle of `start` and its nearest city.
+fn cheapest_insertion(matrix: &DistanceMatrix, start: usize) -> Vec<usize> {
+    let n = matrix.len();
+    let mut next = vec![usize::MAX; n];
+    let nearest = (0..n)
+        .filter(|&city| city != start)
+        .min_by_key(|&city| matrix.get(start, city))
+        .unwrap();
+    next[start] = nearest;
+    next[nearest] = start;
+
+    // Every city outside the cycle with its cheapest insertion, kept up to
+    // date as the cycle grows.
+    let mut outside: Vec<(usize, (i64, usize))> = (0..n)
+        .filter(|&city| next[city] == usize::MAX)
+        .map(|city| (city, cheapest_position(matrix, &next, start, city)))
+        .collect();
+    while !outside.is_empty() {
+        let index = (0..outside.len())
+            .min_by_key(|&i| (outside[i].1, outside[i].0))
+            .unwrap();
+        let (city, (_, after)) = outside.swap_remove(index);
+        let before = next[after];
+        next[after] = city;
+        next[city] = before;
+        for (other, best) in &mut outside {
+            if best.1 == after {
+                // Its cheapest edge is gone.
+                *best = cheapest_position(matrix, &next, start, *other);
+            } else {
+                for a in [after, city] {
+                    let cost = insertion_cost(matrix, a, *other, next[a]);
+                    if cost < best.0 {
+                        *best = (cost, a);
+                    }
+                }
+            }
+        }
+    }
+    cycle_tour(&next, start)
+}
+
+/// Farthest insertion from the cycle of `start` and its farthest city.
+fn farthest_insertion(matrix: &DistanceMatrix, start: usize) -> Vec<usize> {
+    let n = matrix.len();
+    let mut next = vec![usize::MAX; n];
+    let farthest = (0..n)
+        .filter(|&city| city != start)
+        .max_by_key(|&city| matrix.get(start, city))
+        .unwrap();
+    next[start] = farthest;
+    next[farthest] = start;
+
+    // Distance from each city to the nearest city of the cycle.
+    let mut gap: Vec<u64> = (0..n)
+        .map(|city| matrix.get(start, city).min(matrix.get(farthest, city)))
+        .collect();
+    for _ in 2..n {
+        let city = (0..n)
+            .filter(|&city| next[city] == usize::MAX)
+            .max_by_key(|&city| gap[city])
+            .unwrap();
+        let (_, after) = cheapest_position(matrix, &next, start, city);
+        next[city] = next[after];
+        next[after] = city;
+        for (other, gap) in gap.iter_mut().enumerate() {
+            *gap = (*gap).min(matrix.get(city, other));
+        }
+    }
+    cycle_tour(&next, start)
+}
+
+/// Christofides with a greedy matching of the odd-degree cities.
+fn christofides(matrix: &DistanceMatrix, start: usize) -> Vec<usize> {
+    let n = matrix.len();
+
+    // Prim's minimum spanning tree, as a multigraph of adjacency lists.
+    let mut graph: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut in_tree = vec![false; n];
+    let mut link: Vec<(u64, usize)> = (0..n)
+        .map(|city| (matrix.get(start, city), start))
+        .collect();
+    in_tree[start] = true;
+    for _ in 1..n {
+        let city = (0..n)
+            .filter(|&city| !in_tree[city])
+            .min_by_key(|&city| link[city])
+            .unwrap();
+        in_tree[city] = true;
+        let parent = link[city].1;
+        graph[city].push(parent);
+        graph[parent].push(city);
+        for other in (0..n).filter(|&other| !in_tree[other]) {
+            let distance = matrix.get(city, other);
+            if distance < link[other].0 {
+                link[other] = (distance, city);
+            }
+        }
+    }
+
+    // Match the odd-degree cities, shortest pairs first.
+    let odd: Vec<usize> = (0..n).filter(|&city| graph[city].len() % 2 == 1).collect();
+    let mut pairs: Vec<(u64, usize, usize)> = odd
+        .iter()
+        .enumerate()
+        .flat_map(|(i, &a)| odd[i + 1..].iter().map(move |&b| (matrix.get(a, b), a, b)))
+        .collect();
+    pairs.sort_unstable();
+    let mut matched = vec![false; n];
+    for (_, a, b) in pairs {
+        if !matched[a] && !matched[b] {
+            matched[a] = true;
+            matched[b] = true;
+            graph[a].push(b);
+            graph[b].push(a);
+        }
+    }
+
+    // Hierholzer's Euler tour, shortcut past the cities already on the tour.
+    let mut visited = vec![false; n];
+    let mut tour = Vec::with_capacity(n);
+    let mut stack = vec![start];
+    while let Some(&city) = stack.last() {
+        match graph[city].pop() {
+            Some(other) => {
+                let back = graph[other].iter().position(|&c| c == city).unwrap();
+                graph[other].swap_remove(back);
+                stack.push(other);
+            }
+            None => {
+                stack.pop();
+                if !std::mem::replace(&mut visited[city], true) {
+                    tour.push(city);
+                }
+            }
+        }
+    }
+    tour.reverse();
+    rotate_to(&mut tour, start);
+    tour
+}
+
+/// Rotates `tour` to start at `city`.
+fn rotate_to(tour: &mut [usize], city: usize) {
+    if let Some(position) = tour.iter().position(|&c| c == city) {
+        tour.rotate_left(position);
+    }
+}
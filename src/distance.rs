@@ -0,0 +1,549 @@
+use std::{borrow::Cow, fmt, str::FromStr, sync::Arc};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use anyhow::Context;
+use anyhow::{bail, Result};
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::tsplib::{self, City};
+
+/// How the distances of an instance are defined, its TSPLIB
+/// `EDGE_WEIGHT_TYPE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum DistanceKind {
+    /// Euclidean distance rounded to the nearest integer.
+    #[default]
+    #[serde(rename = "EUC_2D")]
+    Euc2d,
+    /// Euclidean distance rounded up.
+    #[serde(rename = "CEIL_2D")]
+    Ceil2d,
+    /// Pseudo-Euclidean distance of the `att` instances.
+    Att,
+    /// Great-circle distance in kilometres, with coordinates given as
+    /// `DDD.MM` degrees and minutes of latitude and longitude.
+    Geo,
+    /// Given as a matrix in the instance's `EDGE_WEIGHT_SECTION`.
+    Explicit,
+}
+
+impl DistanceKind {
+    /// Whether routes measured by the Euclidean distance between their
+    /// coordinates have the lengths this kind gives them.
+    pub fn is_euclidean(self) -> bool {
+        self == DistanceKind::Euc2d
+    }
+
+    /// Whether the distance grows with the Euclidean distance between the
+    /// coordinates, so the nearest cities are nearest in the plane too.
+    fn is_planar(self) -> bool {
+        matches!(
+            self,
+            DistanceKind::Euc2d | DistanceKind::Ceil2d | DistanceKind::Att
+        )
+    }
+
+    /// Distance from `a` to `b`. Explicit distances have no formula, so they
+    /// are 0 here and come from the instance's matrix instead.
+    pub fn distance(self, a: City, b: City) -> u64 {
+        match self {
+            DistanceKind::Euc2d => {
+                let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+                (dx * dx + dy * dy).sqrt().round() as u64
+            }
+            DistanceKind::Ceil2d => {
+                let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+                (dx * dx + dy * dy).sqrt().ceil() as u64
+            }
+            DistanceKind::Att => {
+                let (dx, dy) = (a.0 - b.0, a.1 - b.1);
+                let r = ((dx * dx + dy * dy) / 10.0).sqrt();
+                let t = r.round();
+                if t < r {
+                    t as u64 + 1
+                } else {
+                    t as u64
+                }
+            }
+            DistanceKind::Geo => {
+                const RADIUS: f64 = 6378.388;
+                let (latitude_a, longitude_a) = (geo_radians(a.0), geo_radians(a.1));
+                let (latitude_b, longitude_b) = (geo_radians(b.0), geo_radians(b.1));
+                let q1 = (longitude_a - longitude_b).cos();
+                let q2 = (latitude_a - latitude_b).cos();
+                let q3 = (latitude_a + latitude_b).cos();
+                (RADIUS * (0.5 * ((1.0 + q1) * q2 - (1.0 - q1) * q3)).acos() + 1.0) as u64
+            }
+            DistanceKind::Explicit => 0,
+        }
+    }
+
+    /// [`DistanceKind::distance`] from `origin` to each of `cities`, written
+    /// to `row`.
+    fn distances_from(self, origin: City, cities: &[City], row: &mut [u64]) {
+        match self {
+            DistanceKind::Euc2d => tsplib::distances_from(origin, cities, row),
+            _ => {
+                for (out, &city) in row.iter_mut().zip(cities) {
+                    *out = self.distance(origin, city);
+                }
+            }
+        }
+    }
+}
+
+/// A `DDD.MM` coordinate of a `GEO` instance in radians, with TSPLIB's
+/// value of pi so distances match the published optima.
+fn geo_radians(coordinate: f64) -> f64 {
+    #[allow(clippy::approx_constant)]
+    const PI: f64 = 3.141592;
+    let degrees = coordinate.trunc();
+    let minutes = coordinate - degrees;
+    PI * (degrees + 5.0 * minutes / 3.0) / 180.0
+}
+
+impl FromStr for DistanceKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ok(match s {
+            "EUC_2D" => DistanceKind::Euc2d,
+            "CEIL_2D" => DistanceKind::Ceil2d,
+            "ATT" => DistanceKind::Att,
+            "GEO" => DistanceKind::Geo,
+            "EXPLICIT" => DistanceKind::Explicit,
+            _ => bail!(
+                "unsupported EDGE_WEIGHT_TYPE `{}`, expected EUC_2D, CEIL_2D, ATT, GEO or EXPLICIT",
+                s
+            ),
+        })
+    }
+}
+
+impl fmt::Display for DistanceKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            DistanceKind::Euc2d => "EUC_2D",
+            DistanceKind::Ceil2d => "CEIL_2D",
+            DistanceKind::Att => "ATT",
+            DistanceKind::Geo => "GEO",
+            DistanceKind::Explicit => "EXPLICIT",
+        })
+    }
+}
+
+/// Where an instance's distance matrix is kept.
+#[derive(Debug, Clone, Default)]
+pub enum DistanceProvider {
+    /// Picked by the number of cities: [`DistanceProvider::Dense`] up to
+    /// [`DENSE_MAX_CITIES`], [`DistanceProvider::Triangular`] up to
+    /// [`TRIANGULAR_MAX_CITIES`] and [`DistanceProvider::Computed`] beyond.
+    #[default]
+    Auto,
+    /// In memory, every distance both ways.
+    Dense,
+    /// In memory, each distance once, which halves the memory of a dense
+    /// matrix at the cost of a little arithmetic per lookup.
+    Triangular,
+    /// Not kept at all, but computed from the coordinates on every lookup,
+    /// with a grid of the cities finding their nearest neighbours. Needs
+    /// memory linear in the number of cities, for instances far too large for
+    /// any matrix.
+    Computed,
+    /// In the file at the path, memory-mapped read-only and shared by every
+    /// thread, for instances whose matrix does not fit in memory. The file is
+    /// built on first use and reused while it matches the instance; it must
+    /// not be changed while mapped.
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(PathBuf),
+}
+
+/// Most cities [`DistanceProvider::Auto`] keeps a dense matrix for, about
+/// 200 MB of distances.
+pub const DENSE_MAX_CITIES: usize = 5_000;
+
+/// Most cities [`DistanceProvider::Auto`] keeps a triangular matrix for,
+/// about 900 MB of distances.
+pub const TRIANGULAR_MAX_CITIES: usize = 15_000;
+
+/// Start of a mapped matrix file, followed by the instance's city count and
+/// fingerprint and then the distances row by row, all as native-endian `u64`.
+#[cfg(not(target_arch = "wasm32"))]
+const MAGIC: &[u8; 8] = b"SAPSODM1";
+
+#[cfg(not(target_arch = "wasm32"))]
+const HEADER_LEN: usize = 24;
+
+#[derive(Clone)]
+enum Storage {
+    Dense(Arc<[u64]>),
+    /// The distances from each city `a` to cities `0..=a`, row by row.
+    Triangular(Arc<[u64]>),
+    Computed {
+        cities: Arc<[City]>,
+        kind: DistanceKind,
+    },
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(Arc<Mmap>),
+}
+
+/// Distances between every two cities of an instance; `matrix.get(a, b)` is
+/// the distance from city `a` to city `b`. Clones share the distances.
+#[derive(Clone)]
+pub struct DistanceMatrix {
+    cities: usize,
+    storage: Storage,
+}
+
+impl DistanceMatrix {
+    /// The rounded Euclidean distances of `cities`, kept in memory.
+    pub fn new(cities: &[City]) -> Self {
+        Self::of_kind(cities, DistanceKind::Euc2d)
+    }
+
+    /// The `kind` distances of `cities`, kept in memory.
+    pub fn of_kind(cities: &[City], kind: DistanceKind) -> Self {
+        let n = cities.len();
+        let mut distances = vec![0; n * n];
+        for (i, row) in distances.chunks_exact_mut(n.max(1)).enumerate() {
+            kind.distances_from(cities[i], cities, row);
+        }
+        DistanceMatrix {
+            cities: n,
+            storage: Storage::Dense(distances.into()),
+        }
+    }
+
+    /// The `kind` distances of `cities`, each kept once in memory.
+    pub fn triangular(cities: &[City], kind: DistanceKind) -> Self {
+        let n = cities.len();
+        let mut distances = vec![0; n * (n + 1) / 2];
+        let mut rest = distances.as_mut_slice();
+        for (i, &city) in cities.iter().enumerate() {
+            let (row, tail) = rest.split_at_mut(i + 1);
+            kind.distances_from(city, &cities[..=i], row);
+            rest = tail;
+        }
+        DistanceMatrix {
+            cities: n,
+            storage: Storage::Triangular(distances.into()),
+        }
+    }
+
+    /// The `kind` distances of `cities`, computed on every lookup.
+    pub fn computed(cities: &[City], kind: DistanceKind) -> Self {
+        DistanceMatrix {
+            cities: cities.len(),
+            storage: Storage::Computed {
+                cities: cities.into(),
+                kind,
+            },
+        }
+    }
+
+    /// The `kind` distances of `cities`, kept as [`DistanceProvider::Auto`]
+    /// picks for their number.
+    pub fn auto(cities: &[City], kind: DistanceKind) -> Self {
+        match cities.len() {
+            n if n <= DENSE_MAX_CITIES => Self::of_kind(cities, kind),
+            n if n <= TRIANGULAR_MAX_CITIES => Self::triangular(cities, kind),
+            _ => Self::computed(cities, kind),
+        }
+    }
+
+    /// The `n` × `n` matrix with `distance(a, b)` from `a` to `b`, kept in
+    /// memory.
+    pub fn from_fn(n: usize, mut distance: impl FnMut(usize, usize) -> u64) -> Self {
+        DistanceMatrix {
+            cities: n,
+            storage: Storage::Dense(
+                (0..n)
+                    .flat_map(|a| (0..n).map(move |b| (a, b)))
+                    .map(|(a, b)| distance(a, b))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The `kind` distances of `cities`, kept where `provider` says.
+    /// `fingerprint` identifies the instance, so a mapped file built for
+    /// another one is rebuilt rather than reused.
+    pub fn provide(
+        cities: &[City],
+        kind: DistanceKind,
+        fingerprint: u64,
+        provider: &DistanceProvider,
+    ) -> Result<Self> {
+        match provider {
+            DistanceProvider::Auto => Ok(Self::auto(cities, kind)),
+            DistanceProvider::Dense => Ok(Self::of_kind(cities, kind)),
+            DistanceProvider::Triangular => Ok(Self::triangular(cities, kind)),
+            DistanceProvider::Computed => Ok(Self::computed(cities, kind)),
+            #[cfg(not(target_arch = "wasm32"))]
+            DistanceProvider::Mapped(path) => Self::mapped(cities, kind, fingerprint, path),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn mapped(cities: &[City], kind: DistanceKind, fingerprint: u64, path: &Path) -> Result<Self> {
+        let n = cities.len();
+        let header = |file: &Mmap| {
+            file.len() == HEADER_LEN + n * n * 8
+                && &file[..8] == MAGIC
+                && file[8..16] == (n as u64).to_ne_bytes()
+                && file[16..24] == fingerprint.to_ne_bytes()
+        };
+
+        let mut map = Self::map(path)?;
+        if !map.as_ref().is_some_and(header) {
+            drop(map);
+            Self::build(cities, kind, fingerprint, path)?;
+            map = Self::map(path)?;
+        }
+        let map = map
+            .filter(header)
+            .with_context(|| format!("{} does not hold the expected matrix", path.display()))?;
+
+        Ok(DistanceMatrix {
+            cities: n,
+            storage: Storage::Mapped(Arc::new(map)),
+        })
+    }
+
+    /// Maps the file at `path`, if there is one.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn map(path: &Path) -> Result<Option<Mmap>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err).with_context(|| format!("failed to open {}", path.display()))
+            }
+        };
+        // SAFETY: the file is only read, and callers are told not to change
+        // it while it is mapped.
+        let map = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("failed to map {}", path.display()))?;
+        Ok(Some(map))
+    }
+
+    /// Writes the matrix file, a row at a time so the whole matrix is never
+    /// in memory.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn build(cities: &[City], kind: DistanceKind, fingerprint: u64, path: &Path) -> Result<()> {
+        let n = cities.len();
+        let write = || -> std::io::Result<()> {
+            let mut file = BufWriter::new(File::create(path)?);
+            file.write_all(MAGIC)?;
+            file.write_all(&(n as u64).to_ne_bytes())?;
+            file.write_all(&fingerprint.to_ne_bytes())?;
+            let mut row = vec![0; n];
+            for &city in cities {
+                kind.distances_from(city, cities, &mut row);
+                for distance in &row {
+                    file.write_all(&distance.to_ne_bytes())?;
+                }
+            }
+            file.flush()
+        };
+        write().with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Number of cities, and of rows.
+    pub fn len(&self) -> usize {
+        self.cities
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cities == 0
+    }
+
+    /// Every distance of a dense or mapped matrix, row by row.
+    fn full(&self) -> Option<&[u64]> {
+        match &self.storage {
+            Storage::Dense(distances) => Some(distances),
+            #[cfg(not(target_arch = "wasm32"))]
+            Storage::Mapped(map) => {
+                let bytes = &map[HEADER_LEN..];
+                // SAFETY: the map is page-aligned and the header a multiple of
+                // 8 bytes long, so the distances are aligned `u64`s, and
+                // `mapped` checked that there are `cities`² of them.
+                Some(unsafe {
+                    std::slice::from_raw_parts(
+                        bytes.as_ptr().cast::<u64>(),
+                        self.cities * self.cities,
+                    )
+                })
+            }
+            Storage::Triangular(_) | Storage::Computed { .. } => None,
+        }
+    }
+
+    /// Distance from city `a` to city `b`.
+    #[inline]
+    pub fn get(&self, a: usize, b: usize) -> u64 {
+        match &self.storage {
+            Storage::Dense(distances) => distances[a * self.cities + b],
+            Storage::Triangular(distances) => {
+                let (a, b) = if a >= b { (a, b) } else { (b, a) };
+                distances[a * (a + 1) / 2 + b]
+            }
+            Storage::Computed { cities, kind } => kind.distance(cities[a], cities[b]),
+            #[cfg(not(target_arch = "wasm32"))]
+            Storage::Mapped(_) => self.full().unwrap()[a * self.cities + b],
+        }
+    }
+
+    /// Distances from city `city` to every city, borrowed from the matrix
+    /// where it keeps them as a row.
+    pub fn row(&self, city: usize) -> Cow<'_, [u64]> {
+        if let Some(distances) = self.full() {
+            return Cow::Borrowed(&distances[city * self.cities..(city + 1) * self.cities]);
+        }
+        let mut row = vec![0; self.cities];
+        match &self.storage {
+            Storage::Computed { cities, kind } => {
+                kind.distances_from(cities[city], cities, &mut row)
+            }
+            _ => {
+                for (other, distance) in row.iter_mut().enumerate() {
+                    *distance = self.get(city, other);
+                }
+            }
+        }
+        Cow::Owned(row)
+    }
+
+    /// The rows of the matrix, in city order.
+    pub fn rows(&self) -> impl Iterator<Item = Cow<'_, [u64]>> {
+        (0..self.cities).map(|city| self.row(city))
+    }
+
+    /// Whether every distance is the same both ways.
+    pub fn is_symmetric(&self) -> bool {
+        match self.storage {
+            Storage::Triangular(_) | Storage::Computed { .. } => true,
+            _ => (0..self.cities)
+                .all(|a| (a + 1..self.cities).all(|b| self.get(a, b) == self.get(b, a))),
+        }
+    }
+
+    /// Every city's `k` nearest other cities, nearest first and those as
+    /// near in index order.
+    pub fn nearest_neighbors(&self, k: usize) -> Vec<Vec<usize>> {
+        let n = self.cities;
+        let k = k.min(n.saturating_sub(1));
+        if let Storage::Computed { cities, kind } = &self.storage {
+            if kind.is_planar() {
+                return grid_neighbors(cities, *kind, k);
+            }
+        }
+        (0..n)
+            .into_par_iter()
+            .map(|city| {
+                let row = self.row(city);
+                let mut others: Vec<usize> = (0..n).filter(|&other| other != city).collect();
+                if k < others.len() {
+                    others.select_nth_unstable_by_key(k, |&other| (row[other], other));
+                    others.truncate(k);
+                }
+                others.sort_unstable_by_key(|&other| (row[other], other));
+                others
+            })
+            .collect()
+    }
+}
+
+/// Every city's `k` nearest other cities under `kind`, a distance that grows
+/// with the Euclidean one, found by searching a grid of the cities outwards
+/// from each city's cell rather than measuring every other city.
+fn grid_neighbors(cities: &[City], kind: DistanceKind, k: usize) -> Vec<Vec<usize>> {
+    let n = cities.len();
+    let (min_x, max_x) = cities
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+            (lo.min(c.0), hi.max(c.0))
+        });
+    let (min_y, max_y) = cities
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), c| {
+            (lo.min(c.1), hi.max(c.1))
+        });
+    // About two cities per cell.
+    let side = ((n / 2) as f64).sqrt().ceil().max(1.0) as usize;
+    let width = ((max_x - min_x) / side as f64).max(f64::MIN_POSITIVE);
+    let height = ((max_y - min_y) / side as f64).max(f64::MIN_POSITIVE);
+    let cell_of = |city: City| {
+        let column = (((city.0 - min_x) / width) as usize).min(side - 1);
+        let row = (((city.1 - min_y) / height) as usize).min(side - 1);
+        (column, row)
+    };
+    let mut cells = vec![Vec::new(); side * side];
+    for (index, &city) in cities.iter().enumerate() {
+        let (column, row) = cell_of(city);
+        cells[row * side + column].push(index);
+    }
+
+    (0..n)
+        .into_par_iter()
+        .map(|city| {
+            let origin = cities[city];
+            let (column, row) = cell_of(origin);
+            // The nearest cities found so far with their distances, at most
+            // `k`, in the order `nearest_neighbors` gives them.
+            let mut nearest: Vec<(u64, usize)> = Vec::with_capacity(k + 1);
+            for ring in 0..side {
+                // Every city in a cell `ring` cells away is at least
+                // `ring - 1` cells' width or height from `origin`.
+                let bound = (ring.saturating_sub(1) as f64) * width.min(height);
+                let bound = kind.distance((0.0, 0.0), (bound, 0.0));
+                if nearest.len() == k && nearest.last().is_none_or(|&(d, _)| d < bound) {
+                    break;
+                }
+                let (low_column, high_column) =
+                    (column.saturating_sub(ring), (column + ring).min(side - 1));
+                let (low_row, high_row) = (row.saturating_sub(ring), (row + ring).min(side - 1));
+                for cell_row in low_row..=high_row {
+                    for cell_column in low_column..=high_column {
+                        // Only the cells on the ring, the inner ones were
+                        // searched before.
+                        if cell_row.abs_diff(row).max(cell_column.abs_diff(column)) != ring {
+                            continue;
+                        }
+                        for &other in &cells[cell_row * side + cell_column] {
+                            if other == city {
+                                continue;
+                            }
+                            let candidate = (kind.distance(origin, cities[other]), other);
+                            if nearest.len() < k || candidate < nearest[k - 1] {
+                                let at = nearest.partition_point(|&near| near < candidate);
+                                nearest.insert(at, candidate);
+                                nearest.truncate(k);
+                            }
+                        }
+                    }
+                }
+            }
+            nearest.into_iter().map(|(_, other)| other).collect()
+        })
+        .collect()
+}
+
+impl Default for DistanceMatrix {
+    fn default() -> Self {
+        DistanceMatrix {
+            cities: 0,
+            storage: Storage::Dense(Arc::new([])),
+        }
+    }
+}
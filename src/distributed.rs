@@ -0,0 +1,386 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Condvar, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    config::{self, SolverSpec},
+    fitness::Evaluator,
+    memory::MemoryTracker,
+    progress::status,
+    stats::{self, RunStats},
+    tsplib::{self, CancellationToken, Termination, TspLib},
+};
+
+/// How long a worker may take to answer beyond a run's time limit, or to take
+/// in an instance, before it is taken for hung and dropped.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A single solver run for a worker.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Task {
+    pub spec: SolverSpec,
+    pub seed: u64,
+    pub time_limit: Option<Duration>,
+    pub max_iterations: Option<usize>,
+    /// Send back the run's [`RunStats`], history included, rather than only
+    /// its distance and runtime.
+    pub stats: bool,
+}
+
+/// What a worker sends back for a [`Task`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    /// Distance of the route the solver returned.
+    pub distance: u64,
+    pub run_time_ms: u64,
+    /// Only when the task asked for it.
+    pub stats: Option<RunStats>,
+    /// The run's anytime curve, along with `stats`.
+    pub curve: Vec<(u64, u64)>,
+}
+
+/// Lines the coordinator sends on a connection: the instance whenever it
+/// changes, then the tasks to run on it.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// The distance matrix is rebuilt on the worker, so workers need no
+    /// instance files and subsampled instances work too.
+    Instance(TspLib),
+    Run(Task),
+}
+
+/// A worker's answer to each [`Message`].
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Reply {
+    Ready,
+    Done(Box<RunReport>),
+    Failed { error: String },
+}
+
+/// Writes `value` as a line of JSON.
+fn send(stream: &mut TcpStream, value: &impl Serialize) -> Result<()> {
+    let mut line = serde_json::to_string(value)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Reads a line of JSON, or `None` once the other side has closed.
+fn receive<T: DeserializeOwned>(reader: &mut BufReader<TcpStream>) -> Result<Option<T>> {
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_str(&line)?))
+}
+
+/// Serves solver runs to coordinators on `address` until the process is
+/// killed, each connection on its own thread, evaluating tours with
+/// `evaluator`. Workers trust their coordinators, so only listen on networks
+/// you trust.
+pub fn serve(address: &str, evaluator: Evaluator) -> Result<()> {
+    let listener =
+        TcpListener::bind(address).with_context(|| format!("failed to listen on {}", address))?;
+    status!("Worker listening on {}", address);
+
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                eprintln!("Warning: failed to accept a connection: {}", err);
+                continue;
+            }
+        };
+        let evaluator = evaluator.clone();
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "unknown".to_string(), |peer| peer.to_string());
+            status!("Coordinator {} connected", peer);
+            if let Err(err) = handle(stream, &evaluator) {
+                eprintln!("Warning: connection to {} failed: {:#}", peer, err);
+            }
+            status!("Coordinator {} disconnected", peer);
+        });
+    }
+    Ok(())
+}
+
+/// Answers the messages of one coordinator until it disconnects.
+fn handle(stream: TcpStream, evaluator: &Evaluator) -> Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut tsp: Option<TspLib> = None;
+
+    while let Some(message) = receive::<Message>(&mut reader)? {
+        let reply = match message {
+            Message::Instance(instance) => match tsplib::check_instance(&instance) {
+                Ok(()) => {
+                    tsp = Some(instance);
+                    Reply::Ready
+                }
+                Err(err) => {
+                    tsp = None;
+                    Reply::Failed {
+                        error: format!("{:#}", err),
+                    }
+                }
+            },
+            Message::Run(task) => match &tsp {
+                Some(tsp) => match run(tsp, &task, evaluator) {
+                    Ok(report) => Reply::Done(Box::new(report)),
+                    Err(err) => Reply::Failed {
+                        error: format!("{:#}", err),
+                    },
+                },
+                None => Reply::Failed {
+                    error: "no instance to run on".to_string(),
+                },
+            },
+        };
+        send(&mut writer, &reply)?;
+    }
+    Ok(())
+}
+
+/// Runs `task` on `tsp`. Memory is measured process-wide, so with several
+/// coordinators connected it covers their runs together.
+fn run(tsp: &TspLib, task: &Task, evaluator: &Evaluator) -> Result<RunReport> {
+    let mut solver = config::build_solver(tsp, &task.spec);
+    solver.set_seed(task.seed);
+    solver.set_evaluator(evaluator.clone());
+    let termination = Termination {
+        time_limit: task.time_limit,
+        max_iterations: task.max_iterations,
+        ..Termination::default()
+    };
+    let tracker = task.stats.then(MemoryTracker::start);
+    let solution = solver.solve(tsp, &termination)?;
+
+    let (stats, curve) = match tracker {
+        Some(tracker) => {
+            let mut run = RunStats::from(solver.as_ref(), tsp);
+            run.memory = tracker.finish();
+            (Some(run), stats::anytime_curve(solver.as_ref()))
+        }
+        None => (None, Vec::new()),
+    };
+    Ok(RunReport {
+        distance: solution.route.distance,
+        run_time_ms: solution.run_time,
+        stats,
+        curve,
+    })
+}
+
+/// A coordinator's connection to one worker.
+#[derive(Debug)]
+struct Connection {
+    address: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    /// Fingerprint of the instance the worker holds.
+    instance: Option<u64>,
+}
+
+impl Connection {
+    fn open(address: &str) -> Result<Self> {
+        let stream = TcpStream::connect(address)
+            .with_context(|| format!("failed to connect to worker {}", address))?;
+        Ok(Connection {
+            address: address.to_string(),
+            writer: stream.try_clone()?,
+            reader: BufReader::new(stream),
+            instance: None,
+        })
+    }
+
+    /// Sends `message` and waits up to `timeout` for the reply, or for as long
+    /// as it takes without one. The outer error means the connection broke,
+    /// the inner one that the worker turned the message down.
+    fn call(
+        &mut self,
+        message: &Message,
+        timeout: Option<Duration>,
+    ) -> Result<Result<Option<RunReport>, String>> {
+        self.reader.get_ref().set_read_timeout(timeout)?;
+        send(&mut self.writer, message)?;
+        match receive(&mut self.reader)? {
+            Some(Reply::Ready) => Ok(Ok(None)),
+            Some(Reply::Done(report)) => Ok(Ok(Some(*report))),
+            Some(Reply::Failed { error }) => Ok(Err(error)),
+            None => bail!("the worker closed the connection"),
+        }
+    }
+
+    fn run(&mut self, tsp: &TspLib, task: &Task) -> Result<Result<RunReport, String>> {
+        let fingerprint = tsp.fingerprint();
+        if self.instance != Some(fingerprint) {
+            self.instance = None;
+            if let Err(error) = self.call(&Message::Instance(tsp.clone()), Some(REPLY_TIMEOUT))? {
+                return Ok(Err(error));
+            }
+            self.instance = Some(fingerprint);
+        }
+        // Runs without a time limit may take arbitrarily long.
+        let timeout = task.time_limit.map(|limit| limit + REPLY_TIMEOUT);
+        match self.call(&Message::Run(task.clone()), timeout)? {
+            Ok(Some(report)) => Ok(Ok(report)),
+            Ok(None) => bail!("worker {} answered a run without a report", self.address),
+            Err(error) => Ok(Err(error)),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Workers {
+    idle: Vec<Connection>,
+    /// Connections that have not failed, idle or not.
+    alive: usize,
+}
+
+/// Workers a coordinator hands solver runs to, one run per worker at a time.
+/// A worker whose connection breaks is dropped and its run retried on
+/// another; list an address twice to run two tasks on it at once.
+#[derive(Debug)]
+pub struct Cluster {
+    workers: Mutex<Workers>,
+    available: Condvar,
+    size: usize,
+}
+
+impl Cluster {
+    /// Connects to the worker at every `host:port` of `addresses`.
+    pub fn connect(addresses: &[String]) -> Result<Self> {
+        if addresses.is_empty() {
+            bail!("no workers given");
+        }
+        let idle = addresses
+            .iter()
+            .map(|address| Connection::open(address))
+            .collect::<Result<Vec<_>>>()?;
+        status!("Connected to {} workers", idle.len());
+        Ok(Cluster {
+            size: idle.len(),
+            workers: Mutex::new(Workers {
+                alive: idle.len(),
+                idle,
+            }),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Number of workers connected to at the start.
+    pub fn workers(&self) -> usize {
+        self.size
+    }
+
+    /// Waits for an idle worker.
+    fn acquire(&self) -> Result<Connection> {
+        let mut workers = self.workers.lock().unwrap();
+        loop {
+            if let Some(connection) = workers.idle.pop() {
+                return Ok(connection);
+            }
+            if workers.alive == 0 {
+                bail!("no workers left");
+            }
+            workers = self.available.wait(workers).unwrap();
+        }
+    }
+
+    fn release(&self, connection: Connection) {
+        self.workers.lock().unwrap().idle.push(connection);
+        self.available.notify_one();
+    }
+
+    fn fail(&self) {
+        self.workers.lock().unwrap().alive -= 1;
+        self.available.notify_all();
+    }
+
+    /// Runs `task` on `tsp` on the next idle worker.
+    pub fn run(&self, tsp: &TspLib, task: &Task) -> Result<RunReport> {
+        self.dispatch(tsp, task.clone(), None)
+    }
+
+    fn dispatch(
+        &self,
+        tsp: &TspLib,
+        mut task: Task,
+        cancellation: Option<&CancellationToken>,
+    ) -> Result<RunReport> {
+        loop {
+            let mut connection = self.acquire()?;
+            if cancellation.is_some_and(CancellationToken::is_cancelled) {
+                task.max_iterations = Some(1);
+            }
+            match connection.run(tsp, &task) {
+                Ok(result) => {
+                    let address = connection.address.clone();
+                    self.release(connection);
+                    return result.map_err(|error| anyhow!("worker {}: {}", address, error));
+                }
+                Err(err) => {
+                    eprintln!("Warning: dropping worker {}: {:#}", connection.address, err);
+                    self.fail();
+                }
+            }
+        }
+    }
+
+    /// Runs every task of `tasks` on `tsp`, spread over the workers, and
+    /// returns their reports in order. Once `cancellation` is cancelled, tasks
+    /// not yet started stop after their first iteration.
+    pub fn run_all(
+        &self,
+        tsp: &TspLib,
+        tasks: Vec<Task>,
+        cancellation: Option<&CancellationToken>,
+    ) -> Result<Vec<RunReport>> {
+        // One coordinator thread per worker, each taking the next task once
+        // its last one is done.
+        let count = tasks.len();
+        let queue = Mutex::new(tasks.into_iter().enumerate());
+        let mut reports: Vec<Option<Result<RunReport>>> = (0..count).map(|_| None).collect();
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..self.size.min(count))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        loop {
+                            let next = queue.lock().unwrap().next();
+                            let Some((index, task)) = next else {
+                                return done;
+                            };
+                            done.push((index, self.dispatch(tsp, task, cancellation)));
+                        }
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let done = handle
+                    .join()
+                    .map_err(|_| anyhow!("a coordinator thread panicked"))?;
+                for (index, report) in done {
+                    reports[index] = Some(report);
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        reports
+            .into_iter()
+            .map(|report| report.expect("every task is dispatched"))
+            .collect()
+    }
+}
@@ -0,0 +1,107 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Undirected edges of a closed tour over city indices, each as `(low, high)`.
+pub fn edges(tour: &[usize]) -> impl Iterator<Item = (usize, usize)> + '_ {
+    tour.iter()
+        .zip(tour.iter().cycle().skip(1))
+        .map(|(&a, &b)| (a.min(b), a.max(b)))
+}
+
+/// Share of the edges of `tour` missing from `reference`: 0 when it is the
+/// reference tour, 1 when they have no edge in common.
+pub fn missing_share(tour: &[usize], reference: &HashSet<(usize, usize)>) -> f64 {
+    let missing = edges(tour).filter(|edge| !reference.contains(edge)).count();
+    missing as f64 / tour.len().max(1) as f64
+}
+
+/// How varied a population of tours is, as measured in several ways.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Diversity {
+    /// Tours in the population.
+    pub tours: usize,
+    /// Tours that differ as cycles, i.e. regardless of start city and direction.
+    pub distinct_tours: usize,
+    /// Mean share of edges two distinct members of the population do not
+    /// have in common.
+    pub pairwise_distance: f64,
+    /// Entropy of how often each edge occurs across the population, scaled to
+    /// 0 when every tour is the same and 1 when no two tours share an edge.
+    pub edge_entropy: f64,
+}
+
+impl Diversity {
+    pub fn of<'a>(tours: impl IntoIterator<Item = &'a [usize]>) -> Self {
+        let mut distinct = HashSet::new();
+        let mut frequencies: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut count = 0;
+        let mut cities = 0;
+        for tour in tours {
+            count += 1;
+            cities = tour.len();
+            distinct.insert(canonical(tour));
+            for edge in edges(tour) {
+                *frequencies.entry(edge).or_default() += 1;
+            }
+        }
+        if count == 0 || cities == 0 {
+            return Diversity::default();
+        }
+
+        // Pairs of tours sharing an edge, summed over all edges, give the mean
+        // number of shared edges per pair without comparing every pair.
+        let pairs = (count * (count - 1)) as f64;
+        let shared_pairs: usize = frequencies.values().map(|&f| f * (f - 1)).sum();
+        let pairwise_distance = if count > 1 {
+            1.0 - shared_pairs as f64 / pairs / cities as f64
+        } else {
+            0.0
+        };
+
+        let occurrences = (count * cities) as f64;
+        let entropy: f64 = frequencies
+            .values()
+            .map(|&f| {
+                let p = f as f64 / occurrences;
+                -p * p.ln()
+            })
+            .sum();
+        let lowest = (cities as f64).ln();
+        let highest = occurrences.min((cities * (cities - 1) / 2) as f64).ln();
+        let edge_entropy = if highest > lowest {
+            ((entropy - lowest) / (highest - lowest)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        Diversity {
+            tours: count,
+            distinct_tours: distinct.len(),
+            pairwise_distance,
+            edge_entropy,
+        }
+    }
+
+    /// Share of the population's tours that are distinct.
+    pub fn distinct_share(&self) -> f64 {
+        self.distinct_tours as f64 / self.tours.max(1) as f64
+    }
+}
+
+/// `tour` rotated to start at its smallest city and oriented towards the
+/// smaller of that city's neighbours, so equal cycles compare equal.
+fn canonical(tour: &[usize]) -> Vec<usize> {
+    let Some(start) = (0..tour.len()).min_by_key(|&i| tour[i]) else {
+        return Vec::new();
+    };
+    let n = tour.len();
+    let forward: Vec<usize> = (0..n).map(|k| tour[(start + k) % n]).collect();
+    if n > 2 && forward[n - 1] < forward[1] {
+        std::iter::once(forward[0])
+            .chain(forward[1..].iter().rev().copied())
+            .collect()
+    } else {
+        forward
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt;
+
+/// What the library rejects as invalid input, as opposed to failing I/O.
+/// Functions still return `anyhow::Result`, so callers that need to tell these
+/// apart use `error.downcast_ref::<sapso::error::Error>()`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    /// The instance has too few cities to form a tour.
+    TooFewCities { instance: String, cities: usize },
+    /// A solver setting is outside the range its algorithm works with.
+    InvalidConfig {
+        algorithm: &'static str,
+        reason: String,
+    },
+    /// A tour is not a permutation of the instance's cities.
+    InvalidTour(String),
+    /// `step` or `finish` was called on a solver before `init`.
+    NotStarted,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::TooFewCities { instance, cities } => write!(
+                f,
+                "{} has {} cities, at least 2 are needed",
+                instance, cities
+            ),
+            Error::InvalidConfig { algorithm, reason } => {
+                write!(f, "invalid {} settings: {}", algorithm, reason)
+            }
+            Error::InvalidTour(reason) => write!(f, "{}", reason),
+            Error::NotStarted => write!(f, "the solver has no run, call `init` first"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
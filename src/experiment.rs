@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{progress::status, stats::RunStats, tsplib::TspLib};
+
+/// Version of the experiment document layout, bumped whenever a field is
+/// renamed or removed so archived experiments can still be told apart.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Everything recorded about an experiment: every instance it ran on, the
+/// configuration of every algorithm and the statistics and history of every
+/// run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Experiment {
+    pub version: u32,
+    /// Seconds since the Unix epoch at which the experiment was recorded.
+    pub created: u64,
+    /// Path of the manifest describing how to reproduce the experiment.
+    pub manifest: Option<PathBuf>,
+    pub instances: Vec<InstanceRecord>,
+}
+
+impl Experiment {
+    pub fn new(instances: Vec<InstanceRecord>, manifest: Option<&Path>) -> Self {
+        Experiment {
+            version: FORMAT_VERSION,
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            manifest: manifest.map(Path::to_path_buf),
+            instances,
+        }
+    }
+
+    /// Writes the experiment to `path` as pretty-printed JSON.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+}
+
+/// An instance and the runs of every algorithm on it.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceRecord {
+    pub name: String,
+    pub comment: String,
+    pub dimension: usize,
+    pub optimal_tour_length: Option<u64>,
+    pub algorithms: Vec<AlgorithmRecord>,
+}
+
+impl InstanceRecord {
+    pub fn new(tsp: &TspLib) -> Self {
+        InstanceRecord {
+            name: tsp.name.clone(),
+            comment: tsp.comment.clone(),
+            dimension: tsp.dimension,
+            optimal_tour_length: tsp.optimal_tour_length,
+            algorithms: Vec::new(),
+        }
+    }
+}
+
+/// An algorithm's configuration and its runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlgorithmRecord {
+    pub name: String,
+    pub config: serde_json::Value,
+    pub runs: Vec<RunRecord>,
+}
+
+impl AlgorithmRecord {
+    /// Records `runs` of the algorithm `name` configured by `config`, run `k`
+    /// having been seeded with `seed + k` if a seed was given.
+    pub fn new<T: Serialize>(
+        name: &str,
+        config: &T,
+        runs: &[RunStats],
+        seed: Option<u64>,
+    ) -> Result<Self> {
+        Ok(AlgorithmRecord {
+            name: name.to_string(),
+            config: serde_json::to_value(config)?,
+            runs: runs
+                .iter()
+                .enumerate()
+                .map(|(k, stats)| RunRecord {
+                    seed: seed.map(|seed| seed.wrapping_add(k as u64)),
+                    stats: stats.clone(),
+                })
+                .collect(),
+        })
+    }
+}
+
+/// The best run of an algorithm on an instance, a row of `results.json` and
+/// `results.csv`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResultRow {
+    pub instance: String,
+    pub algorithm: String,
+    /// Shortest tour any run found.
+    pub distance: u64,
+    /// Run time of the run that found it.
+    pub runtime_ms: u64,
+    /// Percentage by which `distance` exceeds the known optimum.
+    pub gap: Option<f64>,
+    pub optimal_tour_length: Option<u64>,
+    pub runs: usize,
+    /// Seed of the run that found it.
+    pub seed: Option<u64>,
+    pub parameters: serde_json::Value,
+    /// TSPLIB `.tour` file the tour was written to.
+    pub tour: Option<PathBuf>,
+}
+
+impl ResultRow {
+    /// The best of the runs of `algorithm` on `instance`, or `None` without
+    /// runs.
+    pub fn best(
+        instance: &InstanceRecord,
+        algorithm: &AlgorithmRecord,
+        tour: Option<PathBuf>,
+    ) -> Option<Self> {
+        let best = algorithm
+            .runs
+            .iter()
+            .min_by_key(|run| run.stats.best_distance)?;
+        Some(ResultRow {
+            instance: instance.name.clone(),
+            algorithm: algorithm.name.clone(),
+            distance: best.stats.best_distance,
+            runtime_ms: best.stats.run_time_ms,
+            gap: best.stats.gap,
+            optimal_tour_length: instance.optimal_tour_length,
+            runs: algorithm.runs.len(),
+            seed: best.seed,
+            parameters: algorithm.config.clone(),
+            tour,
+        })
+    }
+}
+
+/// Writes `rows` to `results.json` and `results.csv` in `dir`, the CSV with
+/// the parameters summarized in one quoted column.
+pub fn write_results(rows: &[ResultRow], dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let json = dir.join("results.json");
+    fs::write(&json, serde_json::to_string_pretty(rows)?)
+        .with_context(|| format!("failed to write {}", json.display()))?;
+
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let mut contents = String::from(
+        "instance,algorithm,distance,runtime_ms,gap_percent,optimal_tour_length,runs,seed,parameters,tour\n",
+    );
+    for row in rows {
+        let fields = [
+            csv_field(&row.instance),
+            csv_field(&row.algorithm),
+            row.distance.to_string(),
+            row.runtime_ms.to_string(),
+            optional(row.gap.map(|gap| gap.to_string())),
+            optional(row.optimal_tour_length.map(|length| length.to_string())),
+            row.runs.to_string(),
+            optional(row.seed.map(|seed| seed.to_string())),
+            csv_field(&parameter_summary(&row.parameters)),
+            optional(
+                row.tour
+                    .as_ref()
+                    .map(|tour| csv_field(&tour.to_string_lossy())),
+            ),
+        ];
+        contents += &fields.join(",");
+        contents.push('\n');
+    }
+    let csv = dir.join("results.csv");
+    fs::write(&csv, contents).with_context(|| format!("failed to write {}", csv.display()))?;
+    status!(
+        "Results written to {} and {}",
+        json.display(),
+        csv.display()
+    );
+
+    Ok(())
+}
+
+/// `key=value, ...` of the settings in `parameters` that are set, without
+/// the algorithm's name.
+fn parameter_summary(parameters: &serde_json::Value) -> String {
+    let serde_json::Value::Object(parameters) = parameters else {
+        return String::new();
+    };
+    parameters
+        .iter()
+        .filter(|(key, value)| *key != "algorithm" && !value.is_null())
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `field` quoted for CSV if it holds a comma, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// One run: its seed, if any, and its statistics and history.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunRecord {
+    pub seed: Option<u64>,
+    #[serde(flatten)]
+    pub stats: RunStats,
+}
@@ -0,0 +1,65 @@
+use std::fmt;
+#[cfg(feature = "gpu")]
+use std::sync::Arc;
+
+#[cfg(not(feature = "gpu"))]
+use anyhow::bail;
+use anyhow::Result;
+use rayon::prelude::*;
+
+#[cfg(feature = "gpu")]
+use crate::gpu::Gpu;
+use crate::tsplib::{self, TspLib};
+
+/// Where solvers evaluate batches of candidate tours: GA offspring, the PSO
+/// swarm and the tours of ACO's ants. SA evaluates one tour at a time and
+/// does not use it.
+#[derive(Clone, Default)]
+pub enum Evaluator {
+    /// On the threads of the current rayon pool.
+    #[default]
+    Cpu,
+    /// On a GPU, for batches large enough to pay for the transfer; anything
+    /// the GPU cannot take is evaluated on the CPU instead.
+    #[cfg(feature = "gpu")]
+    Gpu(Arc<Gpu>),
+}
+
+impl Evaluator {
+    /// An evaluator on the first GPU found. Fails without one, or in a build
+    /// without the `gpu` feature.
+    pub fn gpu() -> Result<Evaluator> {
+        #[cfg(feature = "gpu")]
+        return Ok(Evaluator::Gpu(Arc::new(Gpu::new()?)));
+        #[cfg(not(feature = "gpu"))]
+        bail!("GPU evaluation requires a build with `--features gpu`")
+    }
+
+    /// Lengths of the closed `tours` of city indices of `tsp`, in order.
+    pub fn evaluate(&self, tsp: &TspLib, tours: &[&[usize]]) -> Vec<u64> {
+        match self {
+            Evaluator::Cpu => evaluate_on_cpu(tsp, tours),
+            #[cfg(feature = "gpu")]
+            Evaluator::Gpu(gpu) => gpu
+                .evaluate(tsp, tours)
+                .unwrap_or_else(|| evaluate_on_cpu(tsp, tours)),
+        }
+    }
+}
+
+impl fmt::Debug for Evaluator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Evaluator::Cpu => write!(f, "CPU"),
+            #[cfg(feature = "gpu")]
+            Evaluator::Gpu(gpu) => write!(f, "GPU ({})", gpu.name()),
+        }
+    }
+}
+
+fn evaluate_on_cpu(tsp: &TspLib, tours: &[&[usize]]) -> Vec<u64> {
+    tours
+        .par_iter()
+        .map(|tour| tsplib::tour_length(tour, &tsp.distance_matrix))
+        .collect()
+}
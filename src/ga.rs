@@ -1,41 +1,195 @@
-use std::{collections::HashSet, time::Instant};
+use std::{collections::HashSet, fmt, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Result};
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::{self, Checkpoint, Checkpointing, RunState},
+    config::GaConfig,
+    construct::{self, Construction},
+    distance::DistanceMatrix,
+    diversity::{self, Diversity},
+    error::Error,
+    fitness::Evaluator,
+    local_search::{LocalSearch, LocalSearcher},
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::{self, Configurable, HeuristicAlgorithm, Route, Solution, StepResult, TspLib},
+};
+
+/// Chromosomes carried over unchanged into every next generation.
+pub(crate) const ELITE_SIZE: usize = 2;
+
+/// How two parents are recombined into an offspring, configured as
+/// `crossover = "edge_recombination"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Crossover {
+    /// Order crossover (OX): a stretch of the first parent, with the other
+    /// cities in the order the second parent visits them.
+    #[default]
+    Order,
+    /// Partially mapped crossover (PMX): a stretch of the first parent, with
+    /// the second parent's cities elsewhere, those the stretch took mapped
+    /// through it to the ones it displaced.
+    PartiallyMapped,
+    /// Edge recombination crossover (ERX): a tour built from the parents'
+    /// edges, moving on to the neighbour with the fewest neighbours left.
+    EdgeRecombination,
+    /// Cycle crossover (CX): every city at the position it has in one of
+    /// the parents, alternating parents cycle by cycle.
+    Cycle,
+}
+
+impl Crossover {
+    pub fn is_order(&self) -> bool {
+        *self == Crossover::Order
+    }
+
+    /// Name the crossover's offspring are counted under.
+    fn name(self) -> &'static str {
+        match self {
+            Crossover::Order => "order crossover",
+            Crossover::PartiallyMapped => "PMX crossover",
+            Crossover::EdgeRecombination => "edge recombination",
+            Crossover::Cycle => "cycle crossover",
+        }
+    }
+}
+
+impl FromStr for Crossover {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "order" | "ox" => Crossover::Order,
+            "partially_mapped" | "pmx" => Crossover::PartiallyMapped,
+            "edge_recombination" | "erx" => Crossover::EdgeRecombination,
+            "cycle" | "cx" => Crossover::Cycle,
+            _ => bail!(
+                "unknown crossover `{}`, expected order, partially_mapped, edge_recombination or cycle",
+                name
+            ),
+        })
+    }
+}
 
-use rand::{thread_rng, Rng};
+impl fmt::Display for Crossover {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Crossover::Order => "order",
+            Crossover::PartiallyMapped => "partially_mapped",
+            Crossover::EdgeRecombination => "edge_recombination",
+            Crossover::Cycle => "cycle",
+        })
+    }
+}
+
+/// How parents are drawn from the population, configured as
+/// `selection = "tournament"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Selection {
+    /// With chances in proportion to the inverse of their length.
+    #[default]
+    Roulette,
+    /// With chances in proportion to their rank, from the population size
+    /// for the shortest down to 1 for the longest.
+    Rank,
+    /// The shortest of `tournament_size` chromosomes drawn at random.
+    Tournament,
+}
+
+impl Selection {
+    pub fn is_roulette(&self) -> bool {
+        *self == Selection::Roulette
+    }
+}
 
-use crate::tsplib::{City, HeuristicAlgorithm, Route, TspLib};
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "roulette" | "proportional" => Selection::Roulette,
+            "rank" => Selection::Rank,
+            "tournament" => Selection::Tournament,
+            _ => bail!(
+                "unknown selection `{}`, expected roulette, rank or tournament",
+                name
+            ),
+        })
+    }
+}
 
-#[derive(Clone)]
+impl fmt::Display for Selection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Selection::Roulette => "roulette",
+            Selection::Rank => "rank",
+            Selection::Tournament => "tournament",
+        })
+    }
+}
+
+/// Two distinct random cut points `left < right` in a route of `len`
+/// cities.
+fn cut_points(len: usize, rng: &mut impl Rng) -> (usize, usize) {
+    let i1 = rng.gen_range(0..len);
+    let mut i2 = rng.gen_range(0..len);
+    while i2 == i1 {
+        i2 = rng.gen_range(0..len);
+    }
+    (i1.min(i2), i1.max(i2))
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 struct Chromosome {
     route: Vec<usize>,
     distance: u64,
 }
 
 impl Chromosome {
-    fn new(route: Option<Vec<usize>>, distance_matrix: &[Vec<u64>]) -> Self {
-        let route = match route {
-            Some(r) => r,
-            None => initialize_nearest_neighbor(distance_matrix),
-        };
-        let distance = calculate_distance(&route, distance_matrix);
+    fn new(route: Vec<usize>, distance_matrix: &DistanceMatrix) -> Self {
+        let distance = tsplib::tour_length(&route, distance_matrix);
 
         Chromosome { route, distance }
     }
 
-    fn crossover(&self, other: &Chromosome, distance_matrix: &[Vec<u64>]) -> Chromosome {
+    /// Chromosomes of `routes`, evaluated as one batch.
+    fn evaluate_all(routes: Vec<Vec<usize>>, evaluator: &Evaluator, tsp: &TspLib) -> Vec<Self> {
+        let tours: Vec<&[usize]> = routes.iter().map(Vec::as_slice).collect();
+        let distances = evaluator.evaluate(tsp, &tours);
+        routes
+            .into_iter()
+            .zip(distances)
+            .map(|(route, distance)| Chromosome { route, distance })
+            .collect()
+    }
+
+    /// `crossover` of `self` with `other`; the offspring is left for the
+    /// caller to evaluate.
+    fn crossover(
+        &self,
+        other: &Chromosome,
+        crossover: Crossover,
+        rng: &mut impl Rng,
+    ) -> Vec<usize> {
+        match crossover {
+            Crossover::Order => self.order_crossover(other, rng),
+            Crossover::PartiallyMapped => self.partially_mapped_crossover(other, rng),
+            Crossover::EdgeRecombination => self.edge_recombination(other, rng),
+            Crossover::Cycle => self.cycle_crossover(other),
+        }
+    }
+
+    fn order_crossover(&self, other: &Chromosome, rng: &mut impl Rng) -> Vec<usize> {
         let ln = self.route.len();
-        let mut rng = thread_rng();
-        let (left, right) = {
-            let i1 = rng.gen_range(0..ln);
-            let mut i2 = rng.gen_range(0..ln);
-            while i2 == i1 {
-                i2 = rng.gen_range(0..ln);
-            }
-            if i1 < i2 {
-                (i1, i2)
-            } else {
-                (i2, i1)
-            }
-        };
+        let (left, right) = cut_points(ln, rng);
 
         let mut offspring_route = vec![None; ln];
         (left..right).for_each(|i| {
@@ -63,100 +217,294 @@ impl Chromosome {
             offspring_route[position] = Some(city);
         }
 
-        let final_route = offspring_route.into_iter().map(|x| x.unwrap()).collect();
-
-        Chromosome::new(Some(final_route), distance_matrix)
+        offspring_route.into_iter().map(|x| x.unwrap()).collect()
     }
 
-    fn mutate(&mut self, mutation_probability: f64, distance_matrix: &[Vec<u64>]) {
-        let mut rng = thread_rng();
+    fn partially_mapped_crossover(&self, other: &Chromosome, rng: &mut impl Rng) -> Vec<usize> {
+        let ln = self.route.len();
+        let (left, right) = cut_points(ln, rng);
+        let mut position = vec![0; ln];
+        for (index, &city) in self.route.iter().enumerate() {
+            position[city] = index;
+        }
+        let mut in_stretch = vec![false; ln];
+        let mut offspring_route = other.route.clone();
+        for index in left..right {
+            offspring_route[index] = self.route[index];
+            in_stretch[self.route[index]] = true;
+        }
+
+        for index in (0..left).chain(right..ln) {
+            let mut city = other.route[index];
+            while in_stretch[city] {
+                city = other.route[position[city]];
+            }
+            offspring_route[index] = city;
+        }
+        offspring_route
+    }
 
-        if rng.gen::<f64>() < mutation_probability {
-            let len = self.route.len();
-            let i = rng.gen_range(0..len);
-            let window = (len as f64 * 0.1) as usize;
-            let j = (i + rng.gen_range(2..window)) % len;
+    fn edge_recombination(&self, other: &Chromosome, rng: &mut impl Rng) -> Vec<usize> {
+        let ln = self.route.len();
+        let mut neighbors: Vec<Vec<usize>> = vec![Vec::with_capacity(4); ln];
+        for route in [&self.route, &other.route] {
+            for (index, &city) in route.iter().enumerate() {
+                for neighbor in [route[(index + ln - 1) % ln], route[(index + 1) % ln]] {
+                    if neighbor != city && !neighbors[city].contains(&neighbor) {
+                        neighbors[city].push(neighbor);
+                    }
+                }
+            }
+        }
 
-            let (start, end) = if i < j { (i, j) } else { (j, i) };
+        // Unvisited cities in no particular order, with each city's index in
+        // the list, for the random restarts when a city has no neighbours
+        // left.
+        let mut unvisited: Vec<usize> = (0..ln).collect();
+        let mut position: Vec<usize> = (0..ln).collect();
+        let mut offspring_route = Vec::with_capacity(ln);
+        let mut city = if rng.gen::<bool>() {
+            self.route[0]
+        } else {
+            other.route[0]
+        };
+        loop {
+            offspring_route.push(city);
+            let index = position[city];
+            unvisited.swap_remove(index);
+            if let Some(&moved) = unvisited.get(index) {
+                position[moved] = index;
+            }
+            if unvisited.is_empty() {
+                return offspring_route;
+            }
 
-            self.route[start..=end].reverse();
+            let around = std::mem::take(&mut neighbors[city]);
+            for &neighbor in &around {
+                neighbors[neighbor].retain(|&other| other != city);
+            }
+            city = match around.iter().map(|&next| neighbors[next].len()).min() {
+                Some(fewest) => {
+                    let tied: Vec<usize> = around
+                        .into_iter()
+                        .filter(|&next| neighbors[next].len() == fewest)
+                        .collect();
+                    tied[rng.gen_range(0..tied.len())]
+                }
+                None => unvisited[rng.gen_range(0..unvisited.len())],
+            };
+        }
+    }
 
-            let new_distance = calculate_distance(&self.route, distance_matrix);
-            if new_distance > self.distance && rng.gen::<f64>() > 0.1 {
-                self.route[start..=end].reverse();
-            } else {
-                self.distance = new_distance;
+    fn cycle_crossover(&self, other: &Chromosome) -> Vec<usize> {
+        let ln = self.route.len();
+        let mut position = vec![0; ln];
+        for (index, &city) in self.route.iter().enumerate() {
+            position[city] = index;
+        }
+        let mut offspring_route = vec![None; ln];
+        let mut from_self = true;
+        for start in 0..ln {
+            if offspring_route[start].is_some() {
+                continue;
+            }
+            let mut index = start;
+            loop {
+                let parent = if from_self { &self.route } else { &other.route };
+                offspring_route[index] = Some(parent[index]);
+                index = position[other.route[index]];
+                if index == start {
+                    break;
+                }
             }
+            from_self = !from_self;
         }
+        offspring_route.into_iter().map(|x| x.unwrap()).collect()
     }
-}
 
-fn initialize_nearest_neighbor(distance_matrix: &[Vec<u64>]) -> Vec<usize> {
-    let mut rng = thread_rng();
-    let mut current_city = rng.gen_range(0..distance_matrix.len());
-    let mut unvisited = (0..distance_matrix.len())
-        .filter(|&x| x != current_city)
-        .collect::<Vec<usize>>();
-    let mut route = vec![current_city];
+    /// With `mutation_probability`, reverses a random stretch of the route
+    /// and returns its bounds; whether the move stays is decided by
+    /// [`Chromosome::settle_mutation`] once the new route is evaluated.
+    fn mutate(&mut self, mutation_probability: f64, rng: &mut impl Rng) -> Option<(usize, usize)> {
+        if rng.gen::<f64>() >= mutation_probability {
+            return None;
+        }
+        let len = self.route.len();
+        let i = rng.gen_range(0..len);
+        // At least 3, so instances under 30 cities still have a span to draw.
+        let window = ((len as f64 * 0.1) as usize).max(3);
+        let j = (i + rng.gen_range(2..window)) % len;
 
-    while !unvisited.is_empty() {
-        let next_city = unvisited
-            .iter()
-            .min_by(|&&a, &&b| {
-                let dist_a = distance_matrix[current_city][a];
-                let dist_b = distance_matrix[current_city][b];
-                dist_a.cmp(&dist_b)
-            })
-            .unwrap();
-        let next_index = unvisited.iter().position(|&x| x == *next_city).unwrap();
-        current_city = unvisited.remove(next_index);
-        route.push(current_city);
+        let (start, end) = if i < j { (i, j) } else { (j, i) };
+
+        self.route[start..=end].reverse();
+        Some((start, end))
     }
 
-    route
+    /// Keeps the reversal of `start..=end` made by [`Chromosome::mutate`]
+    /// unless it lengthened the route to `new_distance`, which it still does
+    /// one time in ten.
+    fn settle_mutation(
+        &mut self,
+        (start, end): (usize, usize),
+        new_distance: u64,
+        rng: &mut impl Rng,
+        operator_counts: &mut OperatorCounts,
+    ) {
+        let improving = new_distance < self.distance;
+        if new_distance > self.distance && rng.gen::<f64>() > 0.1 {
+            self.route[start..=end].reverse();
+            operator_counts.record("2-opt mutation", false, improving);
+        } else {
+            self.distance = new_distance;
+            operator_counts.record("2-opt mutation", true, improving);
+        }
+    }
 }
 
-fn calculate_distance(route: &[usize], distance_matrix: &[Vec<u64>]) -> u64 {
-    route
-        .iter()
-        .zip(route.iter().skip(1))
-        .map(|(a, b)| distance_matrix[*a][*b])
-        .sum::<u64>()
-        + distance_matrix[route[route.len() - 1]][route[0]]
+/// `population` split into `islands` runs of neighbouring chromosomes, as
+/// even in size as they can be.
+fn islands_of(population: &mut [Chromosome], islands: usize) -> Vec<&mut [Chromosome]> {
+    let mut rest = population;
+    (0..islands)
+        .map(|island| {
+            let size = rest.len() / (islands - island);
+            let (island, tail) = std::mem::take(&mut rest).split_at_mut(size);
+            rest = tail;
+            island
+        })
+        .collect()
 }
 
-fn selection(population: &Vec<Chromosome>) -> Chromosome {
-    let total_distance = population
-        .iter()
-        .map(|c| (c.distance as f64).powi(-2))
-        .sum::<f64>();
-    let selection_point = rand::random::<f64>() * total_distance;
-    let mut cumulative_distance = 0.0;
+/// A parent drawn from `population`, sorted shortest first, by `selection`.
+fn select<'a>(
+    population: &'a [Chromosome],
+    selection: Selection,
+    tournament_size: usize,
+    rng: &mut impl Rng,
+) -> &'a Chromosome {
+    match selection {
+        Selection::Roulette => {
+            let weight = |c: &Chromosome| (c.distance.max(1) as f64).recip();
+            let total = population.iter().map(weight).sum::<f64>();
+            let mut point = rng.gen::<f64>() * total;
+            for chromosome in population {
+                point -= weight(chromosome);
+                if point <= 0.0 {
+                    return chromosome;
+                }
+            }
+            population.last().unwrap()
+        }
+        Selection::Rank => {
+            let n = population.len();
+            let mut point = rng.gen_range(0..n * (n + 1) / 2);
+            for (index, chromosome) in population.iter().enumerate() {
+                let weight = n - index;
+                if point < weight {
+                    return chromosome;
+                }
+                point -= weight;
+            }
+            population.last().unwrap()
+        }
+        Selection::Tournament => (0..tournament_size.max(1))
+            .map(|_| &population[rng.gen_range(0..population.len())])
+            .min_by_key(|c| c.distance)
+            .unwrap(),
+    }
+}
 
-    let mut selected_chromosome = Chromosome {
-        route: Vec::new(),
-        distance: 0,
-    };
+/// Fitness spread and diversity of one generation's population.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationStats {
+    pub best: u64,
+    pub average: f64,
+    pub worst: u64,
+    /// Average share of a chromosome's edges missing from the best tour:
+    /// 0 when the population has converged onto a single tour.
+    pub diversity: f64,
+    pub diversity_measures: Diversity,
+}
 
-    for chromosome in population {
-        cumulative_distance += (chromosome.distance as f64).powi(-1);
-        if cumulative_distance >= selection_point {
-            selected_chromosome = chromosome.clone();
-            break;
+impl GenerationStats {
+    fn from_population(population: &[Chromosome]) -> Self {
+        let best = population.iter().map(|c| c.distance).min().unwrap_or(0);
+        let worst = population.iter().map(|c| c.distance).max().unwrap_or(0);
+        let average = population.iter().map(|c| c.distance as f64).sum::<f64>()
+            / population.len().max(1) as f64;
+
+        let best_edges = population
+            .iter()
+            .min_by_key(|c| c.distance)
+            .map(|c| diversity::edges(&c.route).collect::<HashSet<_>>())
+            .unwrap_or_default();
+        let diversity = population
+            .iter()
+            .map(|c| diversity::missing_share(&c.route, &best_edges))
+            .sum::<f64>()
+            / population.len().max(1) as f64;
+
+        GenerationStats {
+            best,
+            average,
+            worst,
+            diversity,
+            diversity_measures: Diversity::of(population.iter().map(|c| c.route.as_slice())),
         }
     }
+}
 
-    selected_chromosome
+/// The population, carried between generations.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct GaState {
+    population: Vec<Chromosome>,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct GeneticAlgorithm {
     history: Vec<Route>,
+    history_times: Vec<u64>,
     best_route: Route,
     run_time: u64,
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    checkpointing: Option<Checkpointing>,
+    #[serde(skip)]
+    resumed: Option<RunState<GaState>>,
+    #[serde(skip)]
+    initial_solution: Option<Vec<usize>>,
+    #[serde(skip)]
+    run: Option<RunState<GaState>>,
+    #[serde(skip)]
+    pool: Option<SolutionPool>,
+    #[serde(skip)]
+    evaluator: Evaluator,
+    #[serde(skip)]
+    searcher: Option<LocalSearcher>,
+    archive: Option<SolutionArchive>,
+    seed: Option<u64>,
+    operator_counts: OperatorCounts,
+    generation_stats: Vec<GenerationStats>,
 
     pub population_size: usize,
     pub number_of_generations: usize,
     pub mutation_rate: f64,
+    pub local_search: LocalSearch,
+    pub crossover: Crossover,
+    pub selection: Selection,
+    /// Chromosomes drawn for each tournament of [`Selection::Tournament`].
+    pub tournament_size: usize,
+    /// Sub-populations evolved apart, each on its own thread, 1 for a single
+    /// population.
+    pub islands: usize,
+    /// Generations between migrations, when each island's best
+    /// `migration_size` chromosomes replace the worst of the next island's.
+    pub migration_interval: usize,
+    pub migration_size: usize,
+    /// Builds the first chromosome of a run that has no initial solution.
+    pub construction: Option<Construction>,
 }
 
 impl GeneticAlgorithm {
@@ -168,79 +516,397 @@ impl GeneticAlgorithm {
     ) -> Self {
         GeneticAlgorithm {
             history: Vec::new(),
-            best_route: Route::new(&tsp.cities.clone()),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
             run_time: 0,
+            observers: Observers::default(),
+            checkpointing: None,
+            resumed: None,
+            initial_solution: None,
+            run: None,
+            pool: None,
+            evaluator: Evaluator::default(),
+            searcher: None,
+            archive: None,
+            seed: None,
+            operator_counts: OperatorCounts::default(),
+            generation_stats: Vec::new(),
             population_size,
             number_of_generations,
             mutation_rate,
+            local_search: LocalSearch::None,
+            crossover: Crossover::Order,
+            selection: Selection::Roulette,
+            tournament_size: 5,
+            islands: 1,
+            migration_interval: 10,
+            migration_size: 2,
+            construction: None,
+        }
+    }
+
+    pub fn get_generation_stats(&self) -> &[GenerationStats] {
+        &self.generation_stats
+    }
+
+    /// Breeds the next generation of `population` in its place: its
+    /// [`ELITE_SIZE`] best chromosomes and offspring of the whole population.
+    fn evolve(
+        &self,
+        population: &mut [Chromosome],
+        rng: &mut tsplib::SolverRng,
+        operator_counts: &mut OperatorCounts,
+        tsp: &TspLib,
+    ) {
+        population.sort_by_key(|c| c.distance);
+        let size = population.len();
+
+        // Offspring are bred in pairs in parallel, then evaluated together,
+        // and so are their mutations.
+        let pairs = (size - ELITE_SIZE).div_ceil(2);
+        let parents = &*population;
+        let (bred, parents_best): (Vec<[Vec<usize>; 2]>, Vec<u64>) = tsplib::task_rngs(rng, pairs)
+            .into_par_iter()
+            .map(|mut rng| {
+                let parent1 = select(parents, self.selection, self.tournament_size, &mut rng);
+                let parent2 = select(parents, self.selection, self.tournament_size, &mut rng);
+                (
+                    [
+                        parent1.crossover(parent2, self.crossover, &mut rng),
+                        parent2.crossover(parent1, self.crossover, &mut rng),
+                    ],
+                    parent1.distance.min(parent2.distance),
+                )
+            })
+            .unzip();
+        let mut routes: Vec<Vec<usize>> = bred.into_iter().flatten().collect();
+        routes.truncate(size - ELITE_SIZE);
+        let mut offspring = Chromosome::evaluate_all(routes, &self.evaluator, tsp);
+        for (index, child) in offspring.iter().enumerate() {
+            operator_counts.record(
+                self.crossover.name(),
+                true,
+                child.distance < parents_best[index / 2],
+            );
+        }
+
+        let mutations: Vec<(usize, (usize, usize))> = offspring
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, child)| Some((index, child.mutate(self.mutation_rate, rng)?)))
+            .collect();
+        let tours: Vec<&[usize]> = mutations
+            .iter()
+            .map(|&(index, _)| offspring[index].route.as_slice())
+            .collect();
+        let distances = self.evaluator.evaluate(tsp, &tours);
+        for (&(index, span), distance) in mutations.iter().zip(distances) {
+            offspring[index].settle_mutation(span, distance, rng, operator_counts);
+        }
+
+        if let Some(searcher) = &self.searcher {
+            let improved: Vec<bool> = offspring
+                .par_iter_mut()
+                .map(|child| {
+                    let distance = searcher.improve(&mut child.route, &tsp.distance_matrix);
+                    let improved = distance < child.distance;
+                    child.distance = distance;
+                    improved
+                })
+                .collect();
+            for improved in improved {
+                operator_counts.record("local search", true, improved);
+            }
+        }
+
+        for (slot, child) in population[ELITE_SIZE..].iter_mut().zip(offspring) {
+            *slot = child;
+        }
+    }
+
+    /// Copies each island's best `migration_size` chromosomes over the worst
+    /// of the next island's, round the ring of islands. Every island keeps
+    /// at least its elite.
+    fn migrate(&mut self, population: &mut [Chromosome]) {
+        let mut islands = islands_of(population, self.islands);
+        let migrants: Vec<Vec<Chromosome>> = islands
+            .iter_mut()
+            .map(|island| {
+                island.sort_by_key(|c| c.distance);
+                let size = self.migration_size.min(island.len() - ELITE_SIZE);
+                island[..size].to_vec()
+            })
+            .collect();
+        let count = islands.len();
+        for (index, island) in islands.iter_mut().enumerate() {
+            let incoming = &migrants[(index + count - 1) % count];
+            let size = incoming.len().min(island.len() - ELITE_SIZE);
+            let best = island[0].distance;
+            let kept = island.len() - size;
+            for (slot, migrant) in island[kept..].iter_mut().zip(incoming) {
+                self.operator_counts
+                    .record("migration", true, migrant.distance < best);
+                *slot = migrant.clone();
+            }
         }
     }
+
+    /// The shortest route in `population`.
+    fn best_of(population: &[Chromosome], tsp: &TspLib) -> Route {
+        let best_chromosome = population.iter().min_by_key(|c| c.distance).unwrap();
+        Route::from_measured_tour(&best_chromosome.route, best_chromosome.distance, tsp)
+    }
 }
 
 impl HeuristicAlgorithm for GeneticAlgorithm {
-    fn solve(&mut self, tsp: &crate::tsplib::TspLib) {
-        let start_time = Instant::now();
-        let elite_size = 2;
-
-        let mut population = (0..self.population_size)
-            .map(|_| Chromosome::new(None, &tsp.distance_matrix))
-            .collect::<Vec<Chromosome>>();
-        for generation in 0..self.number_of_generations {
-            population.sort_by(|a, b| a.distance.cmp(&b.distance));
-            if generation % (self.number_of_generations / 10) == 0 {
-                println!(
-                    "GA Generation: {}/{}, Best distance: {}",
-                    generation, self.number_of_generations, population[0].distance
-                );
-            }
-
-            let elite = population[0..elite_size].to_vec();
-
-            let mut next_population = Vec::new();
-            next_population.extend(elite.clone());
-
-            while next_population.len() < self.population_size {
-                let parent1 = selection(&population);
-                let parent2 = selection(&population);
-                let mut offspring1 = parent1.crossover(&parent2, &tsp.distance_matrix);
-                let mut offspring2 = parent2.crossover(&parent1, &tsp.distance_matrix);
-                offspring1.mutate(self.mutation_rate, &tsp.distance_matrix);
-                offspring2.mutate(self.mutation_rate, &tsp.distance_matrix);
-                next_population.push(offspring1);
-                next_population.push(offspring2);
-            }
-
-            next_population.truncate(self.population_size);
-            self.history.push(Route::new(
-                &population[0]
-                    .route
-                    .iter()
-                    .map(|&city| tsp.cities[city])
-                    .collect::<Vec<City>>(),
-            ));
-            population = next_population;
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        tsplib::check_instance(tsp)?;
+        self.observers.start("GA", tsp);
+        self.searcher = LocalSearcher::new(self.local_search, tsp);
+        if self.islands == 0 {
+            return Err(Error::InvalidConfig {
+                algorithm: "GA",
+                reason: "islands is 0, at least one population is needed".to_string(),
+            }
+            .into());
         }
+        if self.population_size < ELITE_SIZE * self.islands {
+            return Err(Error::InvalidConfig {
+                algorithm: "GA",
+                reason: format!(
+                    "population_size is {}, at least the {} elite chromosomes of each of the {} islands are needed",
+                    self.population_size, ELITE_SIZE, self.islands
+                ),
+            }
+            .into());
+        }
+        let run = match self.resumed.take() {
+            Some(run) => run,
+            None => {
+                let mut rng = tsplib::solver_rng(self.seed);
+                let mut routes: Vec<Vec<usize>> = (0..self.population_size)
+                    .map(|_| {
+                        let start = rng.gen_range(0..tsp.dimension);
+                        construct::nearest_neighbor(&tsp.distance_matrix, start)
+                    })
+                    .collect();
+                if let Some(tour) = &self.initial_solution {
+                    routes[0] = Route::validated(tour, tsp)?.tour;
+                } else if let Some(construction) = self.construction {
+                    routes[0] = construction.build(tsp, 0);
+                }
+                let population = Chromosome::evaluate_all(routes, &self.evaluator, tsp);
+                RunState::new(tsp, rng, GaState { population })
+            }
+        };
+        self.best_route = Self::best_of(&run.state.population, tsp);
+        self.run = Some(run);
+        Ok(())
+    }
 
-        let best_chromosome = population.iter().min_by_key(|c| c.distance).unwrap();
-        self.best_route = Route::new(
-            &best_chromosome
-                .route
-                .iter()
-                .map(|&city| tsp.cities[city])
-                .collect::<Vec<City>>(),
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        let _span = self.observers.enter_step(run.iterations);
+
+        if run.iterations < self.number_of_generations {
+            let generation = run.iterations;
+            let population = &mut run.state.population;
+            if let Some(route) = self
+                .pool
+                .as_ref()
+                .and_then(|pool| pool.better_than(self.best_route.distance))
+            {
+                let worst = population.iter_mut().max_by_key(|c| c.distance).unwrap();
+                *worst = Chromosome::new(route.tour, &tsp.distance_matrix);
+            }
+
+            self.generation_stats
+                .push(GenerationStats::from_population(population));
+            let parents_best = Self::best_of(population, tsp);
+
+            if self.islands == 1 {
+                let mut operator_counts = std::mem::take(&mut self.operator_counts);
+                self.evolve(population, &mut run.rng, &mut operator_counts, tsp);
+                self.operator_counts = operator_counts;
+            } else {
+                let rngs = tsplib::task_rngs(&mut run.rng, self.islands);
+                let island_counts: Vec<OperatorCounts> = islands_of(population, self.islands)
+                    .into_par_iter()
+                    .zip(rngs)
+                    .map(|(island, mut rng)| {
+                        let mut operator_counts = OperatorCounts::default();
+                        self.evolve(island, &mut rng, &mut operator_counts, tsp);
+                        operator_counts
+                    })
+                    .collect();
+                for operator_counts in &island_counts {
+                    self.operator_counts.merge(operator_counts);
+                }
+                if (generation + 1) % self.migration_interval.max(1) == 0 {
+                    self.migrate(population);
+                }
+            }
+
+            self.history_times
+                .push(start_time.elapsed().as_millis() as u64);
+            self.history.push(parents_best);
+
+            let generation_stats = self.generation_stats.last().unwrap();
+            self.observers.iteration(IterationInfo {
+                algorithm: "GA",
+                iteration: generation,
+                best: self.history.last().unwrap(),
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+                stats: &[
+                    ("average", generation_stats.average),
+                    ("diversity", generation_stats.diversity),
+                ],
+            });
+            if let Some(archive) = &mut self.archive {
+                for chromosome in population.iter() {
+                    archive.offer(&chromosome.route, chromosome.distance);
+                }
+            }
+            self.best_route = Self::best_of(population, tsp);
+            if let Some(pool) = &self.pool {
+                pool.publish(&self.best_route);
+            }
+            run.iterations = generation + 1;
+        }
+
+        let saved = checkpoint::save_if_due(
+            self.checkpointing.as_ref(),
+            &*self,
+            &mut run,
+            start_time,
+            false,
         );
+        let step = StepResult {
+            iterations: run.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.best_route.distance,
+            done: run.iterations >= self.number_of_generations || self.observers.stop_requested(),
+        };
+        self.run = Some(run);
+        saved?;
+        Ok(step)
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        if run.iterations < self.number_of_generations {
+            checkpoint::save_if_due(
+                self.checkpointing.as_ref(),
+                &*self,
+                &mut run,
+                start_time,
+                true,
+            )?;
+        }
         self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: run.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("GA", &solution, &self.operator_counts);
+        Ok(solution)
+    }
+
+    fn get_history(&self) -> &[Route] {
+        &self.history
     }
 
-    fn get_history(&self) -> Vec<Route> {
-        self.history.clone()
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
     }
 
-    fn get_best_route(&self) -> Route {
-        self.best_route.clone()
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
     }
 
     fn get_run_time(&self) -> u64 {
         self.run_time
     }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    fn get_diversity(&self) -> Option<Diversity> {
+        self.generation_stats
+            .last()
+            .map(|generation| generation.diversity_measures)
+    }
+
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        self.archive.as_ref()
+    }
+
+    fn set_evaluator(&mut self, evaluator: Evaluator) {
+        self.evaluator = evaluator;
+    }
+}
+
+impl Checkpoint for GeneticAlgorithm {
+    type State = GaState;
+
+    fn resume(&mut self, run: RunState<GaState>) {
+        self.resumed = Some(run);
+    }
+}
+
+impl Configurable for GeneticAlgorithm {
+    type Config = GaConfig;
+
+    fn from_config(tsp: &TspLib, config: &GaConfig) -> Self {
+        let mut ga = GeneticAlgorithm::new(
+            tsp,
+            config.population_size,
+            config.generations,
+            config.mutation_rate,
+        );
+        ga.seed = config.seed;
+        ga.local_search = config.local_search;
+        ga.crossover = config.crossover;
+        ga.selection = config.selection;
+        ga.tournament_size = config.tournament_size;
+        ga.islands = config.islands;
+        ga.construction = config.construction;
+        ga.migration_interval = config.migration_interval;
+        ga.migration_size = config.migration_size;
+        ga
+    }
 }
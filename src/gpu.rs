@@ -0,0 +1,290 @@
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use wgpu::util::DeviceExt;
+
+use crate::tsplib::TspLib;
+
+/// Tours times cities below which a batch is cheaper to evaluate on the CPU
+/// than to send to the GPU.
+const MIN_BATCH_WORK: usize = 1 << 16;
+
+/// Distance matrices kept on the GPU, the most recently used last.
+const CACHED_MATRICES: usize = 4;
+
+/// Threads per workgroup of [`SHADER`].
+const WORKGROUP_SIZE: usize = 64;
+
+/// One invocation per tour, summing its edges from the distance matrix. WGSL
+/// has no 64-bit integers, so lengths are carried in two 32-bit halves.
+const SHADER: &str = r#"
+struct Params {
+    cities: u32,
+    tours: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> matrix: array<u32>;
+@group(0) @binding(2) var<storage, read> tours: array<u32>;
+@group(0) @binding(3) var<storage, read_write> lengths: array<vec2<u32>>;
+
+@compute @workgroup_size(64)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    let tour = id.x;
+    if (tour >= params.tours) {
+        return;
+    }
+    let n = params.cities;
+    let base = tour * n;
+    var low = 0u;
+    var high = 0u;
+    for (var k = 0u; k < n; k = k + 1u) {
+        let city = tours[base + k];
+        let next = tours[base + (k + 1u) % n];
+        let sum = low + matrix[city * n + next];
+        if (sum < low) {
+            high = high + 1u;
+        }
+        low = sum;
+    }
+    lengths[tour] = vec2<u32>(low, high);
+}
+"#;
+
+/// A GPU set up to evaluate tour lengths, see
+/// [`crate::fitness::Evaluator::Gpu`].
+pub struct Gpu {
+    name: String,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    /// Distance matrices by instance fingerprint.
+    matrices: Mutex<Vec<(u64, Arc<wgpu::Buffer>)>>,
+    /// Whether a failure has been reported, so it is only reported once.
+    failed: AtomicBool,
+}
+
+impl Gpu {
+    /// Sets up the first GPU wgpu finds, preferring a discrete one.
+    pub fn new() -> Result<Self> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or_else(|| anyhow!("no GPU found"))?;
+        let name = adapter.get_info().name;
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("sapso"),
+                required_features: wgpu::Features::empty(),
+                required_limits: adapter.limits(),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .with_context(|| format!("failed to open {}", name))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("tour lengths"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("tour lengths"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        Ok(Gpu {
+            name,
+            device,
+            queue,
+            pipeline,
+            matrices: Mutex::new(Vec::new()),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    /// Name of the adapter, e.g. the GPU model.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Lengths of `tours` of `tsp`, or `None` if the batch is too small to be
+    /// worth it or does not fit on the GPU.
+    pub fn evaluate(&self, tsp: &TspLib, tours: &[&[usize]]) -> Option<Vec<u64>> {
+        if tours.len() * tsp.dimension < MIN_BATCH_WORK {
+            return None;
+        }
+        match self.try_evaluate(tsp, tours) {
+            Ok(lengths) => Some(lengths),
+            Err(err) => {
+                if !self.failed.swap(true, Ordering::Relaxed) {
+                    eprintln!(
+                        "Warning: evaluating on the CPU instead of {}: {:#}",
+                        self.name, err
+                    );
+                }
+                None
+            }
+        }
+    }
+
+    fn try_evaluate(&self, tsp: &TspLib, tours: &[&[usize]]) -> Result<Vec<u64>> {
+        let n = tsp.dimension;
+        let max_binding = self.device.limits().max_storage_buffer_binding_size as usize;
+        let matrix = self.matrix(tsp, max_binding)?;
+        let max_workgroups = self.device.limits().max_compute_workgroups_per_dimension as usize;
+        let batch = (max_binding / (n * 4))
+            .min(max_workgroups * WORKGROUP_SIZE)
+            .max(1);
+
+        let mut lengths = Vec::with_capacity(tours.len());
+        for chunk in tours.chunks(batch) {
+            lengths.extend(self.dispatch(&matrix, n, chunk)?);
+        }
+        Ok(lengths)
+    }
+
+    /// The distance matrix of `tsp` on the GPU, uploaded on first use.
+    fn matrix(&self, tsp: &TspLib, max_binding: usize) -> Result<Arc<wgpu::Buffer>> {
+        let fingerprint = tsp.fingerprint();
+        let mut matrices = self.matrices.lock().unwrap();
+        if let Some(index) = matrices.iter().position(|&(f, _)| f == fingerprint) {
+            let entry = matrices.remove(index);
+            let buffer = Arc::clone(&entry.1);
+            matrices.push(entry);
+            return Ok(buffer);
+        }
+
+        if tsp.dimension * tsp.dimension * 4 > max_binding {
+            bail!("the distance matrix of {} does not fit", tsp.name);
+        }
+        let distances = tsp
+            .distance_matrix
+            .rows()
+            .flat_map(Cow::into_owned)
+            .map(u32::try_from)
+            .collect::<Result<Vec<u32>, _>>()
+            .with_context(|| format!("{} has distances beyond 32 bits", tsp.name))?;
+        let buffer = Arc::new(
+            self.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some("distance matrix"),
+                    contents: bytemuck::cast_slice(&distances),
+                    usage: wgpu::BufferUsages::STORAGE,
+                }),
+        );
+        if matrices.len() == CACHED_MATRICES {
+            matrices.remove(0);
+        }
+        matrices.push((fingerprint, Arc::clone(&buffer)));
+        Ok(buffer)
+    }
+
+    /// Runs the shader on `tours` and reads back their lengths.
+    fn dispatch(&self, matrix: &wgpu::Buffer, n: usize, tours: &[&[usize]]) -> Result<Vec<u64>> {
+        let cities: Vec<u32> = tours
+            .iter()
+            .flat_map(|tour| tour.iter().map(|&city| city as u32))
+            .collect();
+        if cities.len() != tours.len() * n {
+            bail!("tours must visit every city once");
+        }
+
+        let params = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("params"),
+                contents: bytemuck::cast_slice(&[n as u32, tours.len() as u32]),
+                usage: wgpu::BufferUsages::UNIFORM,
+            });
+        let input = self
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("tours"),
+                contents: bytemuck::cast_slice(&cities),
+                usage: wgpu::BufferUsages::STORAGE,
+            });
+        let size = (tours.len() * 8) as wgpu::BufferAddress;
+        let output = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lengths"),
+            size,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let staging = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("lengths readback"),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: matrix.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: input.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: output.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(tours.len().div_ceil(WORKGROUP_SIZE) as u32, 1, 1);
+        }
+        encoder.copy_buffer_to_buffer(&output, 0, &staging, 0, size);
+        self.queue.submit([encoder.finish()]);
+
+        let slice = staging.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .context("the GPU went away")?
+            .context("failed to read the tour lengths back")?;
+
+        let mapped = slice.get_mapped_range();
+        let lengths = bytemuck::cast_slice::<u8, u32>(&mapped)
+            .chunks_exact(2)
+            .map(|half| u64::from(half[0]) | u64::from(half[1]) << 32)
+            .collect();
+        drop(mapped);
+        staging.unmap();
+        Ok(lengths)
+    }
+}
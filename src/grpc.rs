@@ -0,0 +1,216 @@
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+use crate::{
+    config::{self, SolverConfig},
+    hyper::Algorithm,
+    progress::{status, IterationInfo, Observer},
+    tsplib::{self, CancellationToken, Termination, TspLib},
+};
+
+/// Types generated from `proto/sapso.proto`.
+pub mod proto {
+    tonic::include_proto!("sapso");
+}
+
+use proto::{
+    progress_update::Event,
+    solver_server::{Solver, SolverServer},
+    Iteration, ProgressUpdate, SolveRequest, SolveResponse,
+};
+
+/// Settings of the `grpc` subcommand.
+#[derive(Debug, Clone)]
+pub struct GrpcOptions {
+    /// Address to listen on, e.g. `127.0.0.1:50051`.
+    pub address: String,
+    /// Solver settings that requests override with their own `params`.
+    pub solvers: SolverConfig,
+}
+
+/// Serves the `sapso.Solver` service until the process is killed.
+pub fn run(options: GrpcOptions) -> Result<()> {
+    let address: SocketAddr = options
+        .address
+        .parse()
+        .with_context(|| format!("invalid address `{}`", options.address))?;
+    let service = Service {
+        solvers: Arc::new(options.solvers),
+    };
+
+    let runtime = tokio::runtime::Runtime::new().context("failed to start the runtime")?;
+    runtime.block_on(async {
+        status!("Listening on {}", address);
+        Server::builder()
+            .add_service(SolverServer::new(service))
+            .serve(address)
+            .await
+            .with_context(|| format!("failed to serve on {}", address))
+    })
+}
+
+struct Service {
+    solvers: Arc<SolverConfig>,
+}
+
+/// A request turned into everything a run needs.
+struct Job {
+    tsp: TspLib,
+    algorithm: Algorithm,
+    solvers: SolverConfig,
+    seed: Option<u64>,
+    termination: Termination,
+}
+
+impl Job {
+    fn new(request: &SolveRequest, solvers: &SolverConfig) -> Result<Job> {
+        let tsp = if request.tsplib.is_empty() {
+            let name = match request.name.as_str() {
+                "" => "instance",
+                name => name,
+            };
+            let cities = request.cities.iter().map(|c| (c.x, c.y)).collect();
+            TspLib::from_cities(name, cities)
+        } else {
+            tsplib::parse_tsp(&request.tsplib)?
+        };
+        tsplib::check_instance(&tsp)?;
+
+        let mut solvers = solvers.clone();
+        for param in &request.params {
+            solvers.apply_param(param)?;
+        }
+        let termination = Termination {
+            time_limit: request
+                .time_limit
+                .map(Duration::try_from_secs_f64)
+                .transpose()
+                .context("time_limit expects a non-negative number of seconds")?,
+            max_iterations: request.max_iterations.map(|n| n as usize),
+            stall_iterations: request.stall_iterations.map(|n| n as usize),
+            target_distance: request.target,
+            target_gap: request.target_gap,
+            cancellation: Some(CancellationToken::new()),
+        };
+
+        Ok(Job {
+            tsp,
+            algorithm: request.algorithm.parse()?,
+            solvers,
+            seed: request.seed,
+            termination,
+        })
+    }
+
+    /// Runs the solver to completion, reporting to `observer` if given.
+    fn solve(&self, observer: Option<Arc<dyn Observer>>) -> Result<SolveResponse> {
+        let spec = self.solvers.spec(self.algorithm);
+        let mut solver = config::build_solver(&self.tsp, &spec);
+        if let Some(seed) = self.seed {
+            solver.set_seed(seed);
+        }
+        if let Some(observer) = observer {
+            solver.add_observer(observer);
+        }
+        let solution = solver.solve(&self.tsp, &self.termination)?;
+
+        Ok(SolveResponse {
+            algorithm: self.algorithm.name().to_string(),
+            distance: solution.route.distance,
+            tour: solution.route.tour.iter().map(|&i| i as u32).collect(),
+            iterations: solution.iterations as u64,
+            run_time_ms: solution.run_time,
+        })
+    }
+}
+
+/// Forwards every iteration to a `Progress` stream, and stops the run once
+/// the client has gone away.
+struct StreamObserver {
+    updates: mpsc::Sender<Result<ProgressUpdate, Status>>,
+}
+
+impl Observer for StreamObserver {
+    fn on_iteration(&self, info: &IterationInfo) {
+        let update = ProgressUpdate {
+            event: Some(Event::Iteration(Iteration {
+                iteration: info.iteration as u64,
+                best_distance: info.best.distance,
+                elapsed_ms: info.elapsed_ms,
+            })),
+        };
+        // A closed stream is picked up by `should_stop`.
+        let _ = self.updates.blocking_send(Ok(update));
+    }
+
+    fn should_stop(&self) -> bool {
+        self.updates.is_closed()
+    }
+}
+
+fn invalid(error: anyhow::Error) -> Status {
+    Status::invalid_argument(format!("{:#}", error))
+}
+
+fn failed(error: anyhow::Error) -> Status {
+    Status::internal(format!("{:#}", error))
+}
+
+#[tonic::async_trait]
+impl Solver for Service {
+    async fn solve(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<SolveResponse>, Status> {
+        let job = Job::new(request.get_ref(), &self.solvers).map_err(invalid)?;
+        // Dropping the request future stops the run rather than leaving it
+        // to finish for nobody.
+        let cancellation = job.termination.cancellation.clone();
+        let _guard = CancelOnDrop(cancellation);
+        let response = tokio::task::spawn_blocking(move || job.solve(None))
+            .await
+            .map_err(|_| Status::internal("the solver panicked"))?
+            .map_err(failed)?;
+        Ok(Response::new(response))
+    }
+
+    type ProgressStream = ReceiverStream<Result<ProgressUpdate, Status>>;
+
+    async fn progress(
+        &self,
+        request: Request<SolveRequest>,
+    ) -> Result<Response<Self::ProgressStream>, Status> {
+        let job = Job::new(request.get_ref(), &self.solvers).map_err(invalid)?;
+        let (sender, receiver) = mpsc::channel(64);
+        let observer = Arc::new(StreamObserver {
+            updates: sender.clone(),
+        });
+
+        tokio::task::spawn_blocking(move || {
+            let last = match job.solve(Some(observer)) {
+                Ok(result) => Ok(ProgressUpdate {
+                    event: Some(Event::Result(result)),
+                }),
+                Err(error) => Err(failed(error)),
+            };
+            let _ = sender.blocking_send(last);
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+/// Cancels a run when the call it belongs to is dropped.
+struct CancelOnDrop(Option<CancellationToken>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = &self.0 {
+            token.cancel();
+        }
+    }
+}
@@ -1,188 +1,1927 @@
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "tables")]
 use prettytable::{row, Table};
-use rand::Rng;
-use rayon::prelude::*;
-use serde::Serialize;
-use std::sync::{Arc, Mutex};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rayon::{prelude::*, ThreadPool, ThreadPoolBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::Write,
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
-    aco::AntColonyOptimization,
-    ga::GeneticAlgorithm,
-    pso::ParticleSwarmOptimization,
-    sa::SimulatedAnnealing,
-    tsplib::{HeuristicAlgorithm, TspLib},
+    cmaes::CmaEs,
+    config::{build_solver, AcoConfig, GaConfig, PsoConfig, SaConfig, SolverSpec, TabuConfig},
+    distributed::{Cluster, Task},
+    fitness::Evaluator,
+    ga,
+    local_search::LocalSearch,
+    progress::{status, IterationInfo, Observer},
+    stats,
+    tsplib::{self, Termination, TspLib},
 };
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OptimizationResult {
     pub algorithm: String,
-    pub parameters: String,
+    pub parameters: Parameters,
+    /// Best distance over the configuration's evaluations.
     pub distance: u64,
+    /// Mean distance over the configuration's evaluations; trials are
+    /// ranked by it.
+    pub mean_distance: f64,
+    /// Sample standard deviation of the distances, 0 for a single evaluation.
+    pub std_distance: f64,
+    /// Number of times the configuration was run.
+    pub evaluations: usize,
+    /// Seed of the first evaluation; evaluation `k` ran with `seed + k`.
+    pub seed: u64,
+    /// Whether the trial was stopped early for converging far worse than the
+    /// trials before it; its distances are those reached when stopped.
+    #[serde(default)]
+    pub pruned: bool,
+    /// Mean runtime of an evaluation.
     pub runtime_ms: u64,
 }
 
-#[derive(Debug)]
-struct AcoParams {
-    alpha: f64,        // pheromone importance [0.5..4.0]
-    beta: f64,         // distance importance [1.0..5.0]
-    decay: f64,        // evaporation rate [0.01..0.5]
-    q: f64,            // pheromone deposit factor [1.0..500.0]
-    ants: usize,       // number of ants [50..500]
-    iterations: usize, // number of iterations [200..2000]
+impl OptimizationResult {
+    /// The trial's parameters as `(name, value)` pairs, in declaration order.
+    pub fn parameter_values(&self) -> Vec<(String, f64)> {
+        self.parameters.values()
+    }
+}
+
+/// The configuration a trial was run with. Records carry no tag, so every
+/// params struct denies unknown fields to be read back only as its own
+/// variant.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Parameters {
+    Aco(AcoParams),
+    Sa(SaParams),
+    Ga(GaParams),
+    Pso(PsoParams),
+    Tabu(TabuParams),
+}
+
+impl Parameters {
+    /// The configuration of `algorithm` at `point` in the unit hypercube of
+    /// its search space.
+    fn from_unit(algorithm: Algorithm, point: &[f64], spaces: &SearchSpaces) -> Self {
+        let params = spaces.get(algorithm).params(point);
+        match algorithm {
+            Algorithm::Aco => Parameters::Aco(AcoParams::from_params(&params)),
+            Algorithm::Sa => Parameters::Sa(SaParams::from_params(&params)),
+            Algorithm::Ga => Parameters::Ga(GaParams::from_params(&params)),
+            Algorithm::Pso => Parameters::Pso(PsoParams::from_params(&params)),
+            Algorithm::Tabu => Parameters::Tabu(TabuParams::from_params(&params)),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        match self {
+            Parameters::Aco(params) => params.values(),
+            Parameters::Sa(params) => params.values(),
+            Parameters::Ga(params) => params.values(),
+            Parameters::Pso(params) => params.values(),
+            Parameters::Tabu(params) => params.values(),
+        }
+    }
+
+    /// The configuration as the settings of its solver.
+    fn to_spec(&self) -> SolverSpec {
+        match self {
+            Parameters::Aco(params) => SolverSpec::Aco(AcoConfig {
+                alpha: params.alpha,
+                beta: params.beta,
+                decay: params.decay,
+                q: params.q,
+                ants: params.ants,
+                iterations: params.iterations,
+                snapshot_interval: None,
+                seed: None,
+                local_search: LocalSearch::None,
+                ..AcoConfig::default()
+            }),
+            Parameters::Sa(params) => SolverSpec::Sa(SaConfig {
+                temperature: params.initial_temp,
+                cooling_rate: params.cooling_rate,
+                min_temperature: params.final_temp,
+                seed: None,
+                local_search: LocalSearch::None,
+                ..SaConfig::default()
+            }),
+            Parameters::Ga(params) => SolverSpec::Ga(GaConfig {
+                population_size: params.population_size,
+                generations: params.generations,
+                mutation_rate: params.mutation_rate,
+                seed: None,
+                local_search: LocalSearch::None,
+                islands: params.islands,
+                migration_interval: params.migration_interval,
+                migration_size: params.migration_size,
+                ..GaConfig::default()
+            }),
+            Parameters::Pso(params) => SolverSpec::Pso(PsoConfig {
+                particles: params.num_particles,
+                iterations: params.iterations,
+                cognitive_weight: params.cognitive_weight,
+                social_weight: params.social_weight,
+                inertia_weight: params.inertia_weight,
+                seed: None,
+                local_search: LocalSearch::None,
+                construction: None,
+            }),
+            Parameters::Tabu(params) => SolverSpec::Tabu(TabuConfig {
+                tenure: params.tenure,
+                iterations: params.iterations,
+                candidates: params.candidates,
+                seed: None,
+            }),
+        }
+    }
+
+    /// Tours a full run of the configuration builds or evaluates on an
+    /// instance of `cities` cities, the common unit of work across algorithms.
+    fn tour_evaluations(&self, cities: usize) -> f64 {
+        match self {
+            Parameters::Aco(params) => (params.ants * params.iterations) as f64,
+            Parameters::Sa(params) => {
+                // The temperature is cooled until it falls below the final one,
+                // with two moves per city at every temperature.
+                let epochs = (params.final_temp / params.initial_temp).ln()
+                    / (1.0 - params.cooling_rate).ln();
+                epochs.ceil().max(1.0) * (2 * cities) as f64
+            }
+            Parameters::Ga(params) => (params.population_size * params.generations) as f64,
+            Parameters::Pso(params) => (params.num_particles * params.iterations) as f64,
+            Parameters::Tabu(params) => (params.candidates * params.iterations) as f64,
+        }
+    }
+
+    /// Iterations or generations the configuration runs for, if it has a
+    /// fixed budget.
+    fn budget(&self) -> Option<usize> {
+        match self {
+            Parameters::Aco(params) => Some(params.iterations),
+            Parameters::Sa(_) => None,
+            Parameters::Ga(params) => Some(params.generations),
+            Parameters::Pso(params) => Some(params.iterations),
+            Parameters::Tabu(params) => Some(params.iterations),
+        }
+    }
+}
+
+/// `name=value` pairs, with counts as integers and other values rounded.
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, (name, value)) in self.values().into_iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            if value.fract() == 0.0 {
+                write!(f, "{}={}", name, value)?;
+            } else if value.abs() >= 0.01 {
+                write!(f, "{}={:.3}", name, value)?;
+            } else {
+                write!(f, "{}={:.2e}", name, value)?;
+            }
+        }
+        Ok(())
+    }
 }
 
-#[derive(Debug)]
-struct SaParams {
-    initial_temp: f64, // [1000.0..50000.0]
-    final_temp: f64,   // [0.0001..0.1]
-    cooling_rate: f64, // [0.001..0.3]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AcoParams {
+    pub alpha: f64,        // pheromone importance [0.5..4.0]
+    pub beta: f64,         // distance importance [1.0..5.0]
+    pub decay: f64,        // evaporation rate [0.01..0.5]
+    pub q: f64,            // pheromone deposit factor [1.0..500.0]
+    pub ants: usize,       // number of ants [50..500]
+    pub iterations: usize, // number of iterations [200..2000]
 }
 
-#[derive(Debug)]
-struct GaParams {
-    population_size: usize, // [100..2000]
-    generations: usize,     // [100..5000]
-    mutation_rate: f64,     // [0.001..0.3]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SaParams {
+    pub initial_temp: f64, // [1000.0..50000.0]
+    pub final_temp: f64,   // [0.0001..0.1]
+    pub cooling_rate: f64, // [0.001..0.3]
 }
 
-#[derive(Debug)]
-struct PsoParams {
-    num_particles: usize,  // [50..1000]
-    iterations: usize,     // [200..5000]
-    cognitive_weight: f64, // [0.5..4.0]
-    social_weight: f64,    // [0.5..4.0]
-    inertia_weight: f64,   // [0.1..0.9]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GaParams {
+    pub population_size: usize, // [100..2000]
+    pub generations: usize,     // [100..5000]
+    pub mutation_rate: f64,     // [0.001..0.3]
+    #[serde(default = "default_islands")]
+    pub islands: usize, // [1..8]
+    #[serde(default = "default_migration_interval")]
+    pub migration_interval: usize, // [5..50]
+    #[serde(default = "default_migration_size")]
+    pub migration_size: usize, // [1..10]
 }
 
-pub fn optimize_hyperparameters(tsp: &TspLib, num_trials: usize) -> Vec<OptimizationResult> {
-    let tsp = Arc::new(tsp.clone());
-    let results = Arc::new(Mutex::new(Vec::new()));
+// Island settings of trials recorded before they were searched.
+fn default_islands() -> usize {
+    GaConfig::default().islands
+}
 
-    (0..num_trials).into_par_iter().for_each(|_| {
-        let mut rng = rand::thread_rng();
-        let tsp = Arc::clone(&tsp);
-        let results = Arc::clone(&results);
+fn default_migration_interval() -> usize {
+    GaConfig::default().migration_interval
+}
 
-        let aco_params = AcoParams {
-            alpha: rng.gen_range(1.0..5.0),
-            beta: rng.gen_range(1.0..8.0),
-            decay: rng.gen_range(0.02..0.6),
-            q: rng.gen_range(10.0..600.0),
-            ants: rng.gen_range(100..600),
-            iterations: rng.gen_range(500..3000),
-        };
+fn default_migration_size() -> usize {
+    GaConfig::default().migration_size
+}
 
-        let mut aco = AntColonyOptimization::new(
-            &tsp,
-            aco_params.alpha,
-            aco_params.beta,
-            aco_params.decay,
-            aco_params.q,
-            aco_params.ants,
-            aco_params.iterations,
-        );
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PsoParams {
+    pub num_particles: usize,  // [50..1000]
+    pub iterations: usize,     // [200..5000]
+    pub cognitive_weight: f64, // [0.5..4.0]
+    pub social_weight: f64,    // [0.5..4.0]
+    pub inertia_weight: f64,   // [0.1..0.9]
+}
 
-        aco.solve(&tsp);
-        let aco_result = OptimizationResult {
-            algorithm: "ACO".to_string(),
-            parameters: format!("{:?}", aco_params),
-            distance: aco.get_best_route().distance,
-            runtime_ms: aco.get_run_time(),
-        };
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TabuParams {
+    pub tenure: usize,     // [5..100]
+    pub iterations: usize, // [1000..20000]
+    pub candidates: usize, // [50..1000]
+}
 
-        let sa_params = SaParams {
-            initial_temp: rng.gen_range(5000.0..80000.0),
-            final_temp: rng.gen_range(0.00001..0.2),
-            cooling_rate: rng.gen_range(0.0005..0.4),
-        };
+/// Maps `u` in `[0, 1)` linearly onto `range`.
+fn scale(u: f64, range: Range<f64>) -> f64 {
+    range.start + u * (range.end - range.start)
+}
 
-        let mut sa = SimulatedAnnealing::new(
-            &tsp,
-            sa_params.initial_temp,
-            sa_params.final_temp,
-            sa_params.cooling_rate,
-        );
+/// Maps `u` in `[0, 1)` onto the integers of `range`, each equally likely.
+fn scale_int(u: f64, range: Range<usize>) -> usize {
+    let value = range.start + (u * (range.end - range.start) as f64) as usize;
+    value.min(range.end - 1)
+}
 
-        sa.solve(&tsp);
-        let sa_result = OptimizationResult {
-            algorithm: "SA".to_string(),
-            parameters: format!("{:?}", sa_params),
-            distance: sa.get_best_route().distance,
-            runtime_ms: sa.get_run_time(),
-        };
+/// How a single hyperparameter is drawn. In a search space file each is
+/// written as a one-key table, e.g. `alpha = { log_uniform = [0.5, 8.0] }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    /// Any real in `[low, high)`, evenly.
+    Uniform([f64; 2]),
+    /// Any real in `[low, high)`, evenly on a log scale; for parameters that
+    /// span orders of magnitude.
+    LogUniform([f64; 2]),
+    /// An integer in `[low, high]`, each equally likely.
+    Integer([usize; 2]),
+    /// One of the listed values, each equally likely.
+    Categorical(Vec<f64>),
+}
 
-        let ga_params = GaParams {
-            population_size: rng.gen_range(200..3000),
-            generations: rng.gen_range(500..7000),
-            mutation_rate: rng.gen_range(0.001..0.4),
+impl Distribution {
+    /// Maps `u` in `[0, 1)` onto the distribution.
+    fn value(&self, u: f64) -> f64 {
+        match self {
+            Distribution::Uniform([low, high]) => scale(u, *low..*high),
+            Distribution::LogUniform([low, high]) => scale(u, low.ln()..high.ln()).exp(),
+            Distribution::Integer([low, high]) => scale_int(u, *low..*high + 1) as f64,
+            Distribution::Categorical(values) => values[scale_int(u, 0..values.len())],
+        }
+    }
+
+    /// Inverse of [`Distribution::value`]: where in `[0, 1)` `value` lies.
+    /// Values outside the distribution map to the nearest end.
+    fn unit(&self, value: f64) -> f64 {
+        let u = match self {
+            Distribution::Uniform([low, high]) => (value - low) / (high - low),
+            Distribution::LogUniform([low, high]) => {
+                (value.ln() - low.ln()) / (high.ln() - low.ln())
+            }
+            Distribution::Integer([low, high]) => {
+                (value - *low as f64 + 0.5) / (high - low + 1) as f64
+            }
+            Distribution::Categorical(values) => {
+                let index = (0..values.len())
+                    .min_by(|&a, &b| {
+                        (values[a] - value)
+                            .abs()
+                            .total_cmp(&(values[b] - value).abs())
+                    })
+                    .unwrap_or(0);
+                (index as f64 + 0.5) / values.len() as f64
+            }
         };
+        // A degenerate range divides by zero; any point maps onto it anyway.
+        if u.is_nan() {
+            0.5
+        } else {
+            u.clamp(0.0, 1.0 - f64::EPSILON)
+        }
+    }
+
+    /// The lowest and highest value the distribution takes.
+    fn range(&self) -> (f64, f64) {
+        match self {
+            Distribution::Uniform([low, high]) | Distribution::LogUniform([low, high]) => {
+                (*low, *high)
+            }
+            Distribution::Integer([low, high]) => (*low as f64, *high as f64),
+            Distribution::Categorical(values) => (
+                values.iter().copied().fold(f64::INFINITY, f64::min),
+                values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            ),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Distribution::Uniform([low, high]) if low > high => {
+                bail!("empty range [{}, {}]", low, high)
+            }
+            Distribution::LogUniform([low, high]) if *low <= 0.0 || low > high => {
+                bail!("log-uniform range [{}, {}] must be positive", low, high)
+            }
+            Distribution::Integer([low, high]) if low > high => {
+                bail!("empty range [{}, {}]", low, high)
+            }
+            Distribution::Categorical(values) if values.is_empty() => {
+                bail!("no categorical values")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Named dimensions that configurations are sampled from, built up one
+/// dimension at a time:
+///
+/// ```text
+/// let space = SearchSpace::new()
+///     .log_uniform("learning_rate", 1e-4, 1e-1)
+///     .integer("depth", 2, 8)
+///     .categorical("momentum", &[0.0, 0.9]);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SearchSpace {
+    dimensions: Vec<(String, Distribution)>,
+}
+
+impl SearchSpace {
+    pub fn new() -> Self {
+        SearchSpace::default()
+    }
+
+    /// Adds a dimension drawn from `distribution`.
+    pub fn dimension(mut self, name: &str, distribution: Distribution) -> Self {
+        self.dimensions.push((name.to_string(), distribution));
+        self
+    }
+
+    /// Adds a real dimension in `[low, high)`.
+    pub fn uniform(self, name: &str, low: f64, high: f64) -> Self {
+        self.dimension(name, Distribution::Uniform([low, high]))
+    }
+
+    /// Adds a real dimension in `[low, high)`, evenly spread on a log scale.
+    pub fn log_uniform(self, name: &str, low: f64, high: f64) -> Self {
+        self.dimension(name, Distribution::LogUniform([low, high]))
+    }
+
+    /// Adds an integer dimension in `[low, high]`.
+    pub fn integer(self, name: &str, low: usize, high: usize) -> Self {
+        self.dimension(name, Distribution::Integer([low, high]))
+    }
+
+    /// Adds a dimension taking one of `values`.
+    pub fn categorical(self, name: &str, values: &[f64]) -> Self {
+        self.dimension(name, Distribution::Categorical(values.to_vec()))
+    }
+
+    /// Dimension names, in the order they were added.
+    pub fn names(&self) -> Vec<&str> {
+        self.dimensions
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+impl SearchSpace {
+    fn size(&self) -> usize {
+        self.dimensions.len()
+    }
+
+    /// Replaces the distribution of the existing dimension `name`.
+    fn set(&mut self, name: &str, distribution: Distribution) -> Result<()> {
+        distribution
+            .validate()
+            .with_context(|| format!("invalid distribution for `{}`", name))?;
+        match self.dimensions.iter_mut().find(|(n, _)| n == name) {
+            Some((_, current)) => *current = distribution,
+            None => bail!(
+                "unknown parameter `{}`, expected one of {}",
+                name,
+                self.names().join(", ")
+            ),
+        }
+        Ok(())
+    }
+
+    /// The lowest and highest value of dimension `name`.
+    fn range(&self, name: &str) -> (f64, f64) {
+        self.dimensions
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, distribution)| distribution.range())
+            .unwrap_or_else(|| panic!("no parameter `{}` in the search space", name))
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (name, distribution) in &self.dimensions {
+            distribution
+                .validate()
+                .with_context(|| format!("invalid distribution for `{}`", name))?;
+        }
+        Ok(())
+    }
+
+    /// The configuration at `point` in the unit hypercube, one coordinate per
+    /// dimension.
+    fn params(&self, point: &[f64]) -> Params {
+        Params {
+            values: self
+                .dimensions
+                .iter()
+                .zip(point)
+                .map(|((name, distribution), &u)| (name.clone(), distribution.value(u)))
+                .collect(),
+        }
+    }
+
+    /// Inverse of [`SearchSpace::params`] for a configuration given as
+    /// `(name, value)` pairs; dimensions missing from it land mid-range.
+    fn point(&self, values: &[(String, f64)]) -> Vec<f64> {
+        self.dimensions
+            .iter()
+            .map(|(name, distribution)| {
+                values
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map_or(0.5, |&(_, value)| distribution.unit(value))
+            })
+            .collect()
+    }
+}
+
+/// A configuration sampled from a [`SearchSpace`]: one value per dimension.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Params {
+    values: Vec<(String, f64)>,
+}
+
+impl Params {
+    /// The value of dimension `name`.
+    ///
+    /// # Panics
+    ///
+    /// If the search space has no dimension `name`.
+    pub fn get(&self, name: &str) -> f64 {
+        self.values
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|&(_, value)| value)
+            .unwrap_or_else(|| panic!("no parameter `{}` in the search space", name))
+    }
+
+    /// The value of dimension `name` rounded to a count, at least one.
+    pub fn count(&self, name: &str) -> usize {
+        count(self.get(name))
+    }
+
+    /// `(name, value)` pairs in the search space's dimension order.
+    pub fn values(&self) -> &[(String, f64)] {
+        &self.values
+    }
+}
+
+/// Samples `trials` configurations from `space` with `sampling`, reproducibly
+/// for a given `seed`, scores them in parallel with `objective` and returns
+/// them lowest score first. This is the entry point for tuning solvers or
+/// objectives other than the built-in ones.
+pub fn tune<F>(
+    space: &SearchSpace,
+    trials: usize,
+    sampling: Sampling,
+    seed: u64,
+    objective: F,
+) -> Result<Vec<(Params, f64)>>
+where
+    F: Fn(&Params) -> f64 + Sync,
+{
+    space.validate()?;
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut ranked: Vec<(Params, f64)> = sample_points(sampling, trials, space.size(), &mut rng)
+        .par_iter()
+        .map(|point| {
+            let params = space.params(point);
+            let score = objective(&params);
+            (params, score)
+        })
+        .collect();
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+    Ok(ranked)
+}
+
+/// The search space of each built-in algorithm.
+#[derive(Debug, Clone)]
+pub struct SearchSpaces {
+    aco: SearchSpace,
+    sa: SearchSpace,
+    ga: SearchSpace,
+    pso: SearchSpace,
+    tabu: SearchSpace,
+}
+
+impl Default for SearchSpaces {
+    fn default() -> Self {
+        SearchSpaces {
+            aco: SearchSpace::new()
+                .uniform("alpha", 1.0, 5.0)
+                .uniform("beta", 1.0, 8.0)
+                .uniform("decay", 0.02, 0.6)
+                .uniform("q", 10.0, 600.0)
+                .integer("ants", 100, 599)
+                .integer("iterations", 500, 2999),
+            sa: SearchSpace::new()
+                .uniform("initial_temp", 5000.0, 80000.0)
+                .uniform("final_temp", 0.00001, 0.2)
+                .uniform("cooling_rate", 0.0005, 0.4),
+            ga: SearchSpace::new()
+                .integer("population_size", 200, 2999)
+                .integer("generations", 500, 6999)
+                .uniform("mutation_rate", 0.001, 0.4)
+                .integer("islands", 1, 8)
+                .integer("migration_interval", 5, 50)
+                .integer("migration_size", 1, 10),
+            pso: SearchSpace::new()
+                .integer("num_particles", 100, 1999)
+                .integer("iterations", 500, 6999)
+                .uniform("cognitive_weight", 1.0, 5.0)
+                .uniform("social_weight", 1.0, 5.0)
+                .uniform("inertia_weight", 0.05, 0.95),
+            tabu: SearchSpace::new()
+                .integer("tenure", 5, 100)
+                .integer("iterations", 1000, 19999)
+                .integer("candidates", 50, 999),
+        }
+    }
+}
+
+impl SearchSpaces {
+    /// Reads a TOML file with a table per algorithm overriding the default
+    /// distributions of some of its parameters:
+    ///
+    /// ```toml
+    /// [aco]
+    /// alpha = { uniform = [0.5, 3.0] }
+    /// ants = { integer = [20, 100] }
+    ///
+    /// [sa]
+    /// cooling_rate = { log_uniform = [0.0001, 0.1] }
+    /// ```
+    pub fn load(path: &str) -> Result<Self> {
+        let contents =
+            fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+        let file: BTreeMap<String, BTreeMap<String, Distribution>> =
+            toml::from_str(&contents).with_context(|| format!("failed to parse {}", path))?;
+
+        let mut spaces = SearchSpaces::default();
+        for (algorithm, parameters) in file {
+            let algorithm: Algorithm = algorithm.parse()?;
+            let space = match algorithm {
+                Algorithm::Aco => &mut spaces.aco,
+                Algorithm::Sa => &mut spaces.sa,
+                Algorithm::Ga => &mut spaces.ga,
+                Algorithm::Pso => &mut spaces.pso,
+                Algorithm::Tabu => &mut spaces.tabu,
+            };
+            for (name, distribution) in parameters {
+                space.set(&name, distribution).with_context(|| {
+                    format!("invalid {} search space in {}", algorithm.name(), path)
+                })?;
+            }
+        }
+        spaces
+            .check()
+            .with_context(|| format!("invalid search space in {}", path))?;
+
+        Ok(spaces)
+    }
+
+    /// Rejects ranges whose configurations their solver refuses to run.
+    fn check(&self) -> Result<()> {
+        let (low, high) = self.sa.range("cooling_rate");
+        if low <= 0.0 || high >= 1.0 {
+            bail!(
+                "SA cooling_rate ranges over [{}, {}], it must stay between 0 and 1",
+                low,
+                high
+            );
+        }
+        let population = count(self.ga.range("population_size").0);
+        let islands = count(self.ga.range("islands").1);
+        if population < ga::ELITE_SIZE * islands {
+            bail!(
+                "GA population_size can be {}, too few for the {} elite chromosomes of each of up to {} islands",
+                population,
+                ga::ELITE_SIZE,
+                islands
+            );
+        }
+        Ok(())
+    }
+
+    fn get(&self, algorithm: Algorithm) -> &SearchSpace {
+        match algorithm {
+            Algorithm::Aco => &self.aco,
+            Algorithm::Sa => &self.sa,
+            Algorithm::Ga => &self.ga,
+            Algorithm::Pso => &self.pso,
+            Algorithm::Tabu => &self.tabu,
+        }
+    }
+}
+
+/// Rounds a sampled value for a count parameter, which must be at least one.
+fn count(value: f64) -> usize {
+    (value.round() as usize).max(1)
+}
+
+impl AcoParams {
+    fn from_params(params: &Params) -> Self {
+        AcoParams {
+            alpha: params.get("alpha"),
+            beta: params.get("beta"),
+            decay: params.get("decay"),
+            q: params.get("q"),
+            ants: params.count("ants"),
+            iterations: params.count("iterations"),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        vec![
+            ("alpha".to_string(), self.alpha),
+            ("beta".to_string(), self.beta),
+            ("decay".to_string(), self.decay),
+            ("q".to_string(), self.q),
+            ("ants".to_string(), self.ants as f64),
+            ("iterations".to_string(), self.iterations as f64),
+        ]
+    }
+}
+
+impl SaParams {
+    fn from_params(params: &Params) -> Self {
+        SaParams {
+            initial_temp: params.get("initial_temp"),
+            final_temp: params.get("final_temp"),
+            cooling_rate: params.get("cooling_rate"),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        vec![
+            ("initial_temp".to_string(), self.initial_temp),
+            ("final_temp".to_string(), self.final_temp),
+            ("cooling_rate".to_string(), self.cooling_rate),
+        ]
+    }
+}
+
+impl GaParams {
+    fn from_params(params: &Params) -> Self {
+        GaParams {
+            population_size: params.count("population_size"),
+            generations: params.count("generations"),
+            mutation_rate: params.get("mutation_rate"),
+            islands: params.count("islands"),
+            migration_interval: params.count("migration_interval"),
+            migration_size: params.count("migration_size"),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        vec![
+            ("population_size".to_string(), self.population_size as f64),
+            ("generations".to_string(), self.generations as f64),
+            ("mutation_rate".to_string(), self.mutation_rate),
+            ("islands".to_string(), self.islands as f64),
+            (
+                "migration_interval".to_string(),
+                self.migration_interval as f64,
+            ),
+            ("migration_size".to_string(), self.migration_size as f64),
+        ]
+    }
+}
+
+impl PsoParams {
+    fn from_params(params: &Params) -> Self {
+        PsoParams {
+            num_particles: params.count("num_particles"),
+            iterations: params.count("iterations"),
+            cognitive_weight: params.get("cognitive_weight"),
+            social_weight: params.get("social_weight"),
+            inertia_weight: params.get("inertia_weight"),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        vec![
+            ("num_particles".to_string(), self.num_particles as f64),
+            ("iterations".to_string(), self.iterations as f64),
+            ("cognitive_weight".to_string(), self.cognitive_weight),
+            ("social_weight".to_string(), self.social_weight),
+            ("inertia_weight".to_string(), self.inertia_weight),
+        ]
+    }
+}
+
+impl TabuParams {
+    fn from_params(params: &Params) -> Self {
+        TabuParams {
+            tenure: params.count("tenure"),
+            iterations: params.count("iterations"),
+            candidates: params.count("candidates"),
+        }
+    }
+
+    fn values(&self) -> Vec<(String, f64)> {
+        vec![
+            ("tenure".to_string(), self.tenure as f64),
+            ("iterations".to_string(), self.iterations as f64),
+            ("candidates".to_string(), self.candidates as f64),
+        ]
+    }
+}
+
+/// Solvers the optimizer can tune.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Aco,
+    Sa,
+    Ga,
+    Pso,
+    Tabu,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 5] = [
+        Algorithm::Aco,
+        Algorithm::Sa,
+        Algorithm::Ga,
+        Algorithm::Pso,
+        Algorithm::Tabu,
+    ];
+
+    /// Short name results are reported under.
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Aco => "ACO",
+            Algorithm::Sa => "SA",
+            Algorithm::Ga => "GA",
+            Algorithm::Pso => "PSO",
+            Algorithm::Tabu => "Tabu",
+        }
+    }
+
+    /// Whether the algorithm has an iteration count that trials can be cut
+    /// short with.
+    fn has_budget(self) -> bool {
+        !matches!(self, Algorithm::Sa)
+    }
+}
+
+/// How trial configurations are drawn from the parameter ranges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sampling {
+    /// Every parameter of every trial independently uniform.
+    #[default]
+    Random,
+    /// Each parameter's range is split into one stratum per trial and every
+    /// stratum is used exactly once, which covers the space more evenly.
+    LatinHypercube,
+    /// The centres of a grid with the same number of levels on every
+    /// parameter, the fewest whose points are at least as many as the
+    /// trials; when there are more points than trials, a random subset.
+    Grid,
+}
+
+impl FromStr for Sampling {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "random" => Ok(Sampling::Random),
+            "lhs" => Ok(Sampling::LatinHypercube),
+            "grid" => Ok(Sampling::Grid),
+            _ => bail!("unknown sampling `{}`, expected random, lhs or grid", name),
+        }
+    }
+}
+
+/// Settings of a hyperparameter optimization run.
+#[derive(Debug, Clone)]
+pub struct HyperOptions {
+    pub trials: usize,
+    pub algorithms: Vec<Algorithm>,
+    pub sampling: Sampling,
+    pub search_spaces: SearchSpaces,
+    /// Independent runs per configuration; more runs make the ranking less
+    /// sensitive to a lucky or unlucky run.
+    pub evals_per_trial: usize,
+    /// Weed out configurations on short runs before giving the rest full
+    /// budgets; see [`successive_halving`].
+    pub successive_halving: bool,
+    /// Wall-clock cap on a single solver run.
+    pub trial_time_limit: Option<Duration>,
+    /// Wall-clock cap on the whole optimization; trials not started by then
+    /// are skipped and the results so far returned.
+    pub time_budget: Option<Duration>,
+    /// JSONL file every finished trial is appended to.
+    pub journal: Option<PathBuf>,
+    /// Count the trials already in `journal` as done and only run the rest.
+    pub resume: bool,
+    /// Trials run at the same time; by default as many as fit on the
+    /// available cores given `threads_per_trial`.
+    pub concurrent_trials: Option<usize>,
+    /// Threads a single solver run may use for its own parallel work.
+    pub threads_per_trial: usize,
+    /// Master seed the sampled configurations and every trial's seed are
+    /// derived from; drawn at random and printed when unset.
+    pub seed: Option<u64>,
+    /// Results of earlier runs: their configurations are not tried again,
+    /// and part of the new trials are drawn around the best of them.
+    pub warm_start: Vec<OptimizationResult>,
+    /// Stop trials whose convergence falls far behind earlier trials'; see
+    /// [`Pruner`].
+    pub pruning: bool,
+    /// Draw configurations with CMA-ES, adapting to the trials finished so
+    /// far, instead of sampling them all up front; see [`cma_es_search`].
+    pub cma_es: bool,
+    /// Screen every configuration on a cheap proxy and only run the best at
+    /// full fidelity; see [`screen`].
+    pub screening: Option<Screening>,
+    /// Remote workers the solver runs are sent to instead of running here;
+    /// by default as many trials run at once as there are workers. Remote
+    /// runs are not pruned.
+    pub workers: Option<Arc<Cluster>>,
+    /// Where solver runs here evaluate their batches of tours.
+    pub evaluator: Evaluator,
+}
+
+/// Cheaper stand-in for the real evaluation that configurations are screened
+/// with before the most promising are re-evaluated.
+#[derive(Debug, Clone)]
+pub struct Screening {
+    /// Share (0..=1) of its iterations or generations a screening run gets.
+    pub fidelity: f64,
+    /// Screen on a random sub-instance of this many cities rather than on the
+    /// whole instance.
+    pub cities: Option<usize>,
+    /// Best screened configurations per algorithm re-evaluated at full
+    /// fidelity.
+    pub finalists: usize,
+}
+
+/// Prints a line as each trial finishes, with an estimate of the time the
+/// remaining ones will take at the pace of those finished so far.
+struct TrialProgress {
+    total: usize,
+    finished: AtomicUsize,
+    start: Instant,
+}
+
+impl TrialProgress {
+    fn new(total: usize) -> Self {
+        TrialProgress {
+            total,
+            finished: AtomicUsize::new(0),
+            start: Instant::now(),
+        }
+    }
 
-        let mut ga = GeneticAlgorithm::new(
-            &tsp,
-            ga_params.population_size,
-            ga_params.generations,
-            ga_params.mutation_rate,
+    fn trial_finished(&self, result: &OptimizationResult) {
+        let finished = self.finished.fetch_add(1, Ordering::Relaxed) + 1;
+        let remaining = self.total.saturating_sub(finished) as u32;
+        let eta = self.start.elapsed() / finished as u32 * remaining;
+        status!(
+            "[{:>width$}/{}] {} distance {:.1} in {}ms{}, ETA {}",
+            finished,
+            self.total,
+            result.algorithm,
+            result.mean_distance,
+            result.runtime_ms,
+            if result.pruned { " (pruned)" } else { "" },
+            format_duration(eta),
+            width = self.total.to_string().len()
         );
+    }
+}
 
-        ga.solve(&tsp);
-        let ga_result = OptimizationResult {
-            algorithm: "GA".to_string(),
-            parameters: format!("{:?}", ga_params),
-            distance: ga.get_best_route().distance,
-            runtime_ms: ga.get_run_time(),
-        };
+/// Formats `duration` as e.g. `1h 02m 03s`, `2m 03s` or `3s`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{}s", s),
+        (0, m, s) => format!("{}m {:02}s", m, s),
+        (h, m, s) => format!("{}h {:02}m {:02}s", h, m, s),
+    }
+}
 
-        let pso_params = PsoParams {
-            num_particles: rng.gen_range(100..2000),
-            iterations: rng.gen_range(500..7000),
-            cognitive_weight: rng.gen_range(1.0..5.0),
-            social_weight: rng.gen_range(1.0..5.0),
-            inertia_weight: rng.gen_range(0.05..0.95),
+/// Points, as tenths of a trial's budget, at which trials are compared.
+const PRUNING_CHECKPOINTS: usize = 10;
+
+/// How much worse than the median a trial's best distance at a checkpoint
+/// may be before it is pruned.
+const PRUNING_TOLERANCE: f64 = 1.1;
+
+/// Trials that must have passed a checkpoint before any is pruned there.
+const PRUNING_MIN_TRIALS: usize = 5;
+
+/// Best distances an algorithm's trials reached at each checkpoint, shared by
+/// its trials so each can compare itself with those before it.
+struct Pruner {
+    checkpoints: Mutex<Vec<Vec<u64>>>,
+}
+
+impl Pruner {
+    fn new() -> Self {
+        Pruner {
+            checkpoints: Mutex::new(vec![Vec::new(); PRUNING_CHECKPOINTS]),
+        }
+    }
+
+    /// Records `distance` at `checkpoint` and tells whether it is far worse
+    /// than the median of the distances recorded there before.
+    fn report(&self, checkpoint: usize, distance: u64) -> bool {
+        let mut checkpoints = self.checkpoints.lock().unwrap();
+        let earlier = &mut checkpoints[checkpoint];
+        let prune = earlier.len() >= PRUNING_MIN_TRIALS && {
+            let mut sorted = earlier.clone();
+            sorted.sort_unstable();
+            distance as f64 > sorted[sorted.len() / 2] as f64 * PRUNING_TOLERANCE
         };
+        earlier.push(distance);
+        prune
+    }
+}
 
-        let mut pso = ParticleSwarmOptimization::new(
-            &tsp,
-            pso_params.num_particles,
-            pso_params.iterations,
-            pso_params.cognitive_weight,
-            pso_params.social_weight,
-            pso_params.inertia_weight,
-        );
+/// Follows one solver run, reporting its best distance to the [`Pruner`] at
+/// every checkpoint and stopping the run once it is pruned.
+struct TrialMonitor {
+    pruner: Arc<Pruner>,
+    budget: usize,
+    next_checkpoint: AtomicUsize,
+    pruned: AtomicBool,
+}
+
+impl Observer for TrialMonitor {
+    fn on_iteration(&self, info: &IterationInfo) {
+        let checkpoint = self.next_checkpoint.load(Ordering::Relaxed);
+        // The last checkpoint is the end of the run, which is never pruned.
+        if checkpoint + 1 >= PRUNING_CHECKPOINTS
+            || (info.iteration + 1) * PRUNING_CHECKPOINTS < (checkpoint + 1) * self.budget
+        {
+            return;
+        }
+        self.next_checkpoint
+            .store(checkpoint + 1, Ordering::Relaxed);
+        if self.pruner.report(checkpoint, info.best.distance) {
+            self.pruned.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn should_stop(&self) -> bool {
+        self.pruned.load(Ordering::Relaxed)
+    }
+}
+
+/// Share of the trials drawn around the best warm-start configurations.
+const WARM_START_SHARE: f64 = 0.5;
+
+/// Largest distance, per unit-hypercube coordinate, of a warm-start trial
+/// from the configuration it was drawn around.
+const WARM_START_RADIUS: f64 = 0.1;
+
+/// Times a configuration that was already tried is redrawn before it is kept.
+const MAX_REDRAWS: usize = 10;
+
+/// A configuration to try, as a point in the unit hypercube of its
+/// algorithm's search space, and the seed its solver runs start from.
+struct Trial {
+    point: Vec<f64>,
+    seed: u64,
+}
+
+/// Thread pools that bound the optimizer's parallelism: trials run on
+/// `trials`, and a trial scheduled on its `i`-th thread runs its solver on
+/// `solvers[i]`, so nested parallel work never oversubscribes the cores.
+struct TrialPools {
+    trials: ThreadPool,
+    solvers: Vec<ThreadPool>,
+}
+
+impl TrialPools {
+    fn new(options: &HyperOptions) -> Result<Self> {
+        let threads_per_trial = options.threads_per_trial.max(1);
+        let concurrent_trials = options.concurrent_trials.unwrap_or_else(|| {
+            if let Some(workers) = &options.workers {
+                return workers.workers();
+            }
+            let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+            (cores / threads_per_trial).max(1)
+        });
+
+        let trials = ThreadPoolBuilder::new()
+            .num_threads(concurrent_trials)
+            .thread_name(|i| format!("hyper-trial-{}", i))
+            .build()
+            .context("failed to start the trial thread pool")?;
+        let solvers = (0..concurrent_trials)
+            .map(|_| {
+                ThreadPoolBuilder::new()
+                    .num_threads(threads_per_trial)
+                    .build()
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .context("failed to start the solver thread pools")?;
+
+        Ok(TrialPools { trials, solvers })
+    }
+
+    /// Runs `work`, which schedules trials with rayon, on the trial pool.
+    fn install<R: Send>(&self, work: impl FnOnce() -> R + Send) -> R {
+        self.trials.install(work)
+    }
+
+    /// Runs a single trial on the solver pool of the current trial thread.
+    fn run_trial<R: Send>(&self, trial: impl FnOnce() -> R + Send) -> R {
+        let index = rayon::current_thread_index().unwrap_or(0);
+        self.solvers[index % self.solvers.len()].install(trial)
+    }
+}
+
+/// Draws `count` points in the unit hypercube of `algorithm`'s search space.
+///
+/// With warm-start results, [`WARM_START_SHARE`] of the points lie around the
+/// best quarter of the earlier configurations, and points whose configuration
+/// was tried before are redrawn.
+fn propose_points(
+    algorithm: Algorithm,
+    count: usize,
+    options: &HyperOptions,
+    rng: &mut impl Rng,
+) -> Vec<Vec<f64>> {
+    let space = options.search_spaces.get(algorithm);
+    let mut previous: Vec<&OptimizationResult> = options
+        .warm_start
+        .iter()
+        .filter(|r| r.algorithm == algorithm.name())
+        .collect();
+    previous.sort_by(|a, b| a.mean_distance.total_cmp(&b.mean_distance));
+    let elites: Vec<Vec<f64>> = previous
+        .iter()
+        .take(previous.len().div_ceil(4))
+        .map(|r| space.point(&r.parameter_values()))
+        .collect();
+
+    let local = if elites.is_empty() {
+        0
+    } else {
+        (count as f64 * WARM_START_SHARE).round() as usize
+    };
+    let mut points = sample_points(options.sampling, count - local, space.size(), rng);
+    points.extend((0..local).map(|i| {
+        elites[i % elites.len()]
+            .iter()
+            .map(|&u| {
+                let offset = rng.gen_range(-WARM_START_RADIUS..WARM_START_RADIUS);
+                (u + offset).clamp(0.0, 1.0 - f64::EPSILON)
+            })
+            .collect()
+    }));
+
+    let tried: Vec<Vec<(String, f64)>> = previous.iter().map(|r| r.parameter_values()).collect();
+    for point in &mut points {
+        for _ in 0..MAX_REDRAWS {
+            let parameters = Parameters::from_unit(algorithm, point, &options.search_spaces);
+            if !tried.contains(&parameters.values()) {
+                break;
+            }
+            *point = (0..space.size()).map(|_| rng.gen()).collect();
+        }
+    }
+
+    points
+}
+
+/// Proposes `counts[i]` trials of the `i`-th of `options.algorithms`.
+fn propose_trials(counts: &[usize], options: &HyperOptions, rng: &mut impl Rng) -> Vec<Vec<Trial>> {
+    options
+        .algorithms
+        .iter()
+        .zip(counts)
+        .map(|(&algorithm, &count)| {
+            propose_points(algorithm, count, options, rng)
+                .into_iter()
+                .map(|point| Trial {
+                    point,
+                    seed: rng.gen(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Initial CMA-ES step size in unit-hypercube coordinates, wide enough for
+/// the first generations to explore most of each parameter's range.
+const CMA_ES_SIGMA: f64 = 0.3;
+
+/// Runs `count` trials of `algorithm` a generation at a time, each generation
+/// drawn by CMA-ES around `start` from the distribution adapted to the ones
+/// before it. Generations run in parallel; `finish` sees every trial as it
+/// finishes.
+#[allow(clippy::too_many_arguments)]
+fn cma_es_search(
+    algorithm: Algorithm,
+    tsp: &TspLib,
+    count: usize,
+    start: Vec<f64>,
+    options: &HyperOptions,
+    pools: &TrialPools,
+    deadline: Option<Instant>,
+    rng: &mut impl Rng,
+    finish: &(dyn Fn(&OptimizationResult) + Sync),
+) -> Vec<OptimizationResult> {
+    let mut search = CmaEs::new(start, CMA_ES_SIGMA);
+    let pruner = (options.pruning && algorithm.has_budget()).then(|| Arc::new(Pruner::new()));
+    let mut results = Vec::with_capacity(count);
+    // Trials sampled so far, some of which may have been skipped.
+    let mut sampled = 0;
+
+    while sampled < count && !expired(deadline) {
+        let points = search.ask(rng);
+        let trials: Vec<Trial> = points
+            .into_iter()
+            .take(count - sampled)
+            .map(|point| Trial {
+                point,
+                seed: rng.gen(),
+            })
+            .collect();
+        sampled += trials.len();
+        let generation: Vec<OptimizationResult> = pools.install(|| {
+            trials
+                .par_iter()
+                .filter(|_| !expired(deadline))
+                .filter_map(|trial| {
+                    let result = pools.run_trial(|| {
+                        run_trial(algorithm, tsp, trial, 1.0, options, pruner.as_ref())
+                    })?;
+                    finish(&result);
+                    Some(result)
+                })
+                .collect()
+        });
+
+        // Only a complete generation says enough to adapt the distribution.
+        if generation.len() == search.population_size() {
+            let points: Vec<Vec<f64>> = trials.iter().map(|t| t.point.clone()).collect();
+            let fitness: Vec<f64> = generation.iter().map(|r| r.mean_distance).collect();
+            search.tell(&points, &fitness);
+        }
+        results.extend(generation);
+    }
+
+    results
+}
+
+/// Draws `trials` points in the unit hypercube of `dimensions` dimensions.
+fn sample_points(
+    sampling: Sampling,
+    trials: usize,
+    dimensions: usize,
+    rng: &mut impl Rng,
+) -> Vec<Vec<f64>> {
+    match sampling {
+        Sampling::Random => (0..trials)
+            .map(|_| (0..dimensions).map(|_| rng.gen()).collect())
+            .collect(),
+        Sampling::LatinHypercube => {
+            let mut points = vec![Vec::with_capacity(dimensions); trials];
+            for _ in 0..dimensions {
+                let mut strata: Vec<usize> = (0..trials).collect();
+                strata.shuffle(rng);
+                for (point, stratum) in points.iter_mut().zip(strata) {
+                    point.push((stratum as f64 + rng.gen::<f64>()) / trials as f64);
+                }
+            }
+            points
+        }
+        Sampling::Grid if dimensions == 0 => vec![Vec::new(); trials],
+        Sampling::Grid => {
+            let mut levels: usize = 1;
+            while levels.pow(dimensions as u32) < trials {
+                levels += 1;
+            }
+            let mut cells: Vec<usize> = (0..levels.pow(dimensions as u32)).collect();
+            cells.shuffle(rng);
+            cells
+                .into_iter()
+                .take(trials)
+                .map(|mut cell| {
+                    (0..dimensions)
+                        .map(|_| {
+                            let level = cell % levels;
+                            cell /= levels;
+                            (level as f64 + 0.5) / levels as f64
+                        })
+                        .collect()
+                })
+                .collect()
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "aco" => Ok(Algorithm::Aco),
+            "sa" => Ok(Algorithm::Sa),
+            "ga" => Ok(Algorithm::Ga),
+            "pso" => Ok(Algorithm::Pso),
+            "tabu" => Ok(Algorithm::Tabu),
+            _ => bail!(
+                "unknown algorithm `{}`, expected aco, sa, ga, pso or tabu",
+                name
+            ),
+        }
+    }
+}
+
+/// Runs `options.trials` sampled configurations of each of `options.algorithms`
+/// on `tsp`.
+pub fn optimize_hyperparameters(
+    tsp: &TspLib,
+    options: &HyperOptions,
+) -> Result<Vec<OptimizationResult>> {
+    // Every trial borrows `tsp`; solvers only read the instance, so it is
+    // shared across threads rather than copied per trial. Checking it here
+    // fails the search once rather than every trial; trials whose settings
+    // the solver rejects are skipped.
+    tsplib::check_instance(tsp)?;
+    let pools = TrialPools::new(options)?;
+    let deadline = options.time_budget.map(|budget| Instant::now() + budget);
+
+    let (journal, previous) = match &options.journal {
+        Some(path) => {
+            let (journal, previous) = Journal::open(path, options.resume)?;
+            (Some(journal), previous)
+        }
+        None => (None, Vec::new()),
+    };
+    if !previous.is_empty() {
+        status!("Resuming after {} finished trials", previous.len());
+    }
+    let results = Mutex::new(previous);
+
+    let seed = options.seed.unwrap_or_else(rand::random);
+    status!("Hyperparameter search seed: {}", seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Trials already in the journal count towards each algorithm's total.
+    let counts: Vec<usize> = options
+        .algorithms
+        .iter()
+        .map(|algorithm| {
+            let finished = results
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|r| r.algorithm == algorithm.name())
+                .count();
+            options.trials.saturating_sub(finished)
+        })
+        .collect();
 
-        pso.solve(&tsp);
-        let pso_result = OptimizationResult {
-            algorithm: "PSO".to_string(),
-            parameters: format!("{:?}", pso_params),
-            distance: pso.get_best_route().distance,
-            runtime_ms: pso.get_run_time(),
+    if let Some(screening) = &options.screening {
+        let trials = propose_trials(&counts, options, &mut rng);
+        let proxy = match screening.cities {
+            Some(cities) => tsp.subsample(cities, &mut rng),
+            None => tsp.clone(),
         };
+        let mut results: Vec<OptimizationResult> = options
+            .algorithms
+            .iter()
+            .zip(&trials)
+            .flat_map(|(&algorithm, trials)| {
+                screen(algorithm, tsp, &proxy, trials, options, &pools, deadline)
+            })
+            .collect();
+        sort_results(&mut results);
+        print_results_table(&results, tsp);
+        return Ok(results);
+    }
 
-        let mut results = results.lock().unwrap();
-        results.push(aco_result);
-        results.push(sa_result);
-        results.push(ga_result);
-        results.push(pso_result);
-    });
+    if options.successive_halving {
+        let trials = propose_trials(&counts, options, &mut rng);
+        let mut results: Vec<OptimizationResult> = options
+            .algorithms
+            .iter()
+            .zip(&trials)
+            .flat_map(|(&algorithm, trials)| {
+                successive_halving(algorithm, tsp, trials, options, &pools, deadline)
+            })
+            .collect();
+        sort_results(&mut results);
+        print_results_table(&results, tsp);
+        return Ok(results);
+    }
 
-    let results = Arc::try_unwrap(results).unwrap().into_inner().unwrap();
+    let progress = TrialProgress::new(counts.iter().sum());
+    let finish = |result: &OptimizationResult| {
+        progress.trial_finished(result);
+        if let Some(journal) = &journal {
+            if let Err(err) = journal.record(result) {
+                eprintln!("Warning: {:#}", err);
+            }
+        }
+    };
+
+    if options.cma_es {
+        for (&algorithm, &count) in options.algorithms.iter().zip(&counts) {
+            // Resume from the best configuration known so far, if any.
+            let space = options.search_spaces.get(algorithm);
+            let start = results
+                .lock()
+                .unwrap()
+                .iter()
+                .chain(&options.warm_start)
+                .filter(|r| r.algorithm == algorithm.name())
+                .min_by(|a, b| a.mean_distance.total_cmp(&b.mean_distance))
+                .map_or_else(
+                    || vec![0.5; space.size()],
+                    |r| space.point(&r.parameter_values()),
+                );
+            let found = cma_es_search(
+                algorithm, tsp, count, start, options, &pools, deadline, &mut rng, &finish,
+            );
+            results.lock().unwrap().extend(found);
+        }
+    } else {
+        let trials = propose_trials(&counts, options, &mut rng);
+        let pruners: Vec<Option<Arc<Pruner>>> = options
+            .algorithms
+            .iter()
+            .map(|algorithm| {
+                (options.pruning && algorithm.has_budget()).then(|| Arc::new(Pruner::new()))
+            })
+            .collect();
+        let remaining = trials.iter().map(Vec::len).max().unwrap_or(0);
+        pools.install(|| {
+            (0..remaining).into_par_iter().for_each(|trial| {
+                let trial: Vec<OptimizationResult> = options
+                    .algorithms
+                    .iter()
+                    .zip(&trials)
+                    .zip(&pruners)
+                    .filter_map(|((&algorithm, trials), pruner)| {
+                        Some((algorithm, trials.get(trial)?, pruner.as_ref()))
+                    })
+                    .take_while(|_| !expired(deadline))
+                    .filter_map(|(algorithm, trial, pruner)| {
+                        let result = pools
+                            .run_trial(|| run_trial(algorithm, tsp, trial, 1.0, options, pruner))?;
+                        finish(&result);
+                        Some(result)
+                    })
+                    .collect();
+
+                results.lock().unwrap().extend(trial);
+            })
+        });
+    }
+
+    let results = results.into_inner().unwrap();
+    if options.pruning {
+        status!(
+            "Pruned {} of {} trials",
+            results.iter().filter(|r| r.pruned).count(),
+            results.len()
+        );
+    }
+    if expired(deadline) {
+        status!(
+            "Time budget exhausted, keeping the {} trials finished so far",
+            results.len()
+        );
+    }
     let mut final_results = results;
-    final_results.sort_by(|a, b| {
+    sort_results(&mut final_results);
+
+    print_results_table(&final_results, tsp);
+
+    Ok(final_results)
+}
+
+/// Append-only JSONL log of finished trials, one result per line, that an
+/// interrupted optimization can be resumed from.
+struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Opens the journal at `path` and returns the trials it already holds
+    /// when resuming, or truncates it otherwise. A line cut short by a crash
+    /// is skipped.
+    fn open(path: &Path, resume: bool) -> Result<(Self, Vec<OptimizationResult>)> {
+        let mut previous = Vec::new();
+        if resume && path.exists() {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            for (number, line) in contents.lines().enumerate() {
+                match serde_json::from_str(line) {
+                    Ok(result) => previous.push(result),
+                    Err(err) if !line.trim().is_empty() => eprintln!(
+                        "Warning: skipping line {} of {}: {}",
+                        number + 1,
+                        path.display(),
+                        err
+                    ),
+                    Err(_) => {}
+                }
+            }
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(path)
+            .with_context(|| format!("failed to open {}", path.display()))?;
+
+        Ok((
+            Journal {
+                path: path.to_path_buf(),
+                file: Mutex::new(file),
+            },
+            previous,
+        ))
+    }
+
+    fn record(&self, result: &OptimizationResult) -> Result<()> {
+        let line = serde_json::to_string(result)? + "\n";
+        let mut file = self.file.lock().unwrap();
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .with_context(|| format!("failed to write {}", self.path.display()))
+    }
+}
+
+/// Writes the best configuration of each algorithm in `results`, which must be
+/// sorted best first, to `path` in the format `--config` reads, so the tuned
+/// solvers can be run directly.
+pub fn export_best_config(
+    results: &[OptimizationResult],
+    instance: &str,
+    path: &str,
+) -> Result<()> {
+    let mut table = toml::Table::new();
+    for result in results {
+        let (name, config) = result.parameters.to_spec().to_table()?;
+        table.entry(name).or_insert(config);
+    }
+
+    let contents = format!(
+        "# Best configurations found by --hyper on {}\n\n{}",
+        instance,
+        toml::to_string_pretty(&table)?
+    );
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path))
+}
+
+/// Reads results written by [`export_results`] (a JSON array) or a trial
+/// journal (JSON lines).
+pub fn load_results(path: &str) -> Result<Vec<OptimizationResult>> {
+    let contents = fs::read_to_string(path).with_context(|| format!("failed to read {}", path))?;
+    if contents.trim_start().starts_with('[') {
+        return serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path));
+    }
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            serde_json::from_str(line)
+                .with_context(|| format!("failed to parse line {} of {}", number + 1, path))
+        })
+        .collect()
+}
+
+fn expired(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Writes all results to `{stem}.json` and each algorithm's results to
+/// `{stem}_{algorithm}.csv`, one column per parameter.
+pub fn export_results(results: &[OptimizationResult], stem: &str) -> Result<()> {
+    let path = format!("{}.json", stem);
+    let json = serde_json::to_string_pretty(results)?;
+    fs::write(&path, json).with_context(|| format!("failed to write {}", path))?;
+
+    let mut algorithms: Vec<&str> = results.iter().map(|r| r.algorithm.as_str()).collect();
+    algorithms.dedup();
+    for algorithm in algorithms {
+        let trials: Vec<&OptimizationResult> = results
+            .iter()
+            .filter(|r| r.algorithm == algorithm)
+            .collect();
+        let mut contents = trials[0]
+            .parameter_values()
+            .into_iter()
+            .map(|(name, _)| name)
+            .chain(
+                [
+                    "distance",
+                    "mean_distance",
+                    "std_distance",
+                    "evaluations",
+                    "seed",
+                    "runtime_ms",
+                ]
+                .map(String::from),
+            )
+            .collect::<Vec<_>>()
+            .join(",");
+        contents.push('\n');
+        for trial in trials {
+            let row: Vec<String> = trial
+                .parameter_values()
+                .into_iter()
+                .map(|(_, value)| value.to_string())
+                .chain([
+                    trial.distance.to_string(),
+                    trial.mean_distance.to_string(),
+                    trial.std_distance.to_string(),
+                    trial.evaluations.to_string(),
+                    trial.seed.to_string(),
+                    trial.runtime_ms.to_string(),
+                ])
+                .collect();
+            contents += &row.join(",");
+            contents.push('\n');
+        }
+
+        let path = format!("{}_{}.csv", stem, algorithm.to_lowercase());
+        fs::write(&path, contents).with_context(|| format!("failed to write {}", path))?;
+    }
+
+    Ok(())
+}
+
+/// Flags the results on their algorithm's Pareto front of mean distance and
+/// runtime: those no other trial of the same algorithm beats on one without
+/// losing on the other. Pruned trials did not run to the end and are never on
+/// the front.
+pub fn pareto_optimal(results: &[OptimizationResult]) -> Vec<bool> {
+    let dominates = |a: &OptimizationResult, b: &OptimizationResult| {
+        a.algorithm == b.algorithm
+            && !a.pruned
+            && a.mean_distance <= b.mean_distance
+            && a.runtime_ms <= b.runtime_ms
+            && (a.mean_distance < b.mean_distance || a.runtime_ms < b.runtime_ms)
+    };
+    results
+        .iter()
+        .map(|result| !result.pruned && !results.iter().any(|other| dominates(other, result)))
+        .collect()
+}
+
+/// Groups results by algorithm, lowest mean distance first and pruned trials
+/// last.
+fn sort_results(results: &mut [OptimizationResult]) {
+    results.sort_by(|a, b| {
         if a.algorithm == b.algorithm {
-            a.distance.cmp(&b.distance)
+            a.pruned
+                .cmp(&b.pruned)
+                .then(a.mean_distance.total_cmp(&b.mean_distance))
         } else {
             a.algorithm.cmp(&b.algorithm)
         }
     });
+}
 
-    print_results_table(&final_results);
+/// Share of configurations promoted from one successive-halving rung to the next.
+const HALVING_RATE: usize = 3;
 
-    final_results
+/// Fewest iterations a low-fidelity trial is run for.
+const MIN_BUDGET: usize = 10;
+
+fn scale_budget(iterations: usize, fidelity: f64) -> usize {
+    ((iterations as f64 * fidelity).ceil() as usize).clamp(MIN_BUDGET.min(iterations), iterations)
 }
 
-fn print_results_table(results: &[OptimizationResult]) {
+/// Evaluates every configuration of `trials` with a small iteration budget and
+/// promotes the best `1 / HALVING_RATE` of them to a budget `HALVING_RATE`
+/// times larger, until the survivors run at full budget. Returns the results
+/// of the full-budget rung.
+///
+/// Algorithms without an iteration count to cut (SA) run every configuration
+/// at full budget once. Past `deadline`, the results of the highest rung
+/// reached are returned instead.
+fn successive_halving(
+    algorithm: Algorithm,
+    tsp: &TspLib,
+    trials: &[Trial],
+    options: &HyperOptions,
+    pools: &TrialPools,
+    deadline: Option<Instant>,
+) -> Vec<OptimizationResult> {
+    let mut rungs = 1;
+    if algorithm.has_budget() {
+        while HALVING_RATE.pow(rungs as u32) <= trials.len() {
+            rungs += 1;
+        }
+    }
+
+    let mut survivors: Vec<&Trial> = trials.iter().collect();
+    let mut previous = Vec::new();
+    for rung in 1..=rungs {
+        let fidelity = 1.0 / HALVING_RATE.pow((rungs - rung) as u32) as f64;
+        status!(
+            "{} rung {}/{}: {} configurations at {:.1}% budget",
+            algorithm.name(),
+            rung,
+            rungs,
+            survivors.len(),
+            fidelity * 100.0
+        );
+        let progress = TrialProgress::new(survivors.len());
+        let mut results: Vec<(&Trial, OptimizationResult)> = pools.install(|| {
+            survivors
+                .par_iter()
+                .filter(|_| !expired(deadline))
+                .filter_map(|&trial| {
+                    let result = pools
+                        .run_trial(|| run_trial(algorithm, tsp, trial, fidelity, options, None))?;
+                    progress.trial_finished(&result);
+                    Some((trial, result))
+                })
+                .collect()
+        });
+        let out_of_time = expired(deadline);
+        if out_of_time {
+            status!(
+                "Time budget exhausted during {} rung {}",
+                algorithm.name(),
+                rung
+            );
+            if results.is_empty() {
+                return previous;
+            }
+        }
+        if rung == rungs || out_of_time {
+            return results.into_iter().map(|(_, result)| result).collect();
+        }
+
+        results.sort_by(|(_, a), (_, b)| a.mean_distance.total_cmp(&b.mean_distance));
+        let promoted = (survivors.len() / HALVING_RATE).max(1);
+        survivors = results
+            .iter()
+            .take(promoted)
+            .map(|&(trial, _)| trial)
+            .collect();
+        previous = results.into_iter().map(|(_, result)| result).collect();
+    }
+
+    previous
+}
+
+/// Evaluates every configuration of `trials` on `proxy` with the screening
+/// fidelity of `options.screening`, then re-evaluates the best of them on
+/// `tsp` at full fidelity and returns those results. Past `deadline`, the
+/// finals run so far are returned, or the screening results if none ran.
+fn screen(
+    algorithm: Algorithm,
+    tsp: &TspLib,
+    proxy: &TspLib,
+    trials: &[Trial],
+    options: &HyperOptions,
+    pools: &TrialPools,
+    deadline: Option<Instant>,
+) -> Vec<OptimizationResult> {
+    let (fidelity, finalists) = options
+        .screening
+        .as_ref()
+        .map_or((1.0, trials.len()), |s| (s.fidelity, s.finalists.max(1)));
+
+    status!(
+        "{} screening: {} configurations at {:.1}% budget on {} cities",
+        algorithm.name(),
+        trials.len(),
+        fidelity * 100.0,
+        proxy.dimension
+    );
+    let progress = TrialProgress::new(trials.len());
+    let mut screened: Vec<(&Trial, OptimizationResult)> = pools.install(|| {
+        trials
+            .par_iter()
+            .filter(|_| !expired(deadline))
+            .filter_map(|trial| {
+                let result = pools
+                    .run_trial(|| run_trial(algorithm, proxy, trial, fidelity, options, None))?;
+                progress.trial_finished(&result);
+                Some((trial, result))
+            })
+            .collect()
+    });
+    screened.sort_by(|(_, a), (_, b)| a.mean_distance.total_cmp(&b.mean_distance));
+    screened.truncate(finalists);
+
+    status!(
+        "{} finals: {} configurations at full budget on {} cities",
+        algorithm.name(),
+        screened.len(),
+        tsp.dimension
+    );
+    let progress = TrialProgress::new(screened.len());
+    let finals: Vec<OptimizationResult> = pools.install(|| {
+        screened
+            .par_iter()
+            .filter(|_| !expired(deadline))
+            .filter_map(|&(trial, _)| {
+                let result =
+                    pools.run_trial(|| run_trial(algorithm, tsp, trial, 1.0, options, None))?;
+                progress.trial_finished(&result);
+                Some(result)
+            })
+            .collect()
+    });
+
+    if finals.is_empty() {
+        screened.into_iter().map(|(_, result)| result).collect()
+    } else {
+        finals
+    }
+}
+
+/// Solves `tsp` `options.evals_per_trial` times with the configuration of
+/// `algorithm` at `trial`, each run using `fidelity` (0..=1) of its iterations
+/// or generations and stopping early after the trial time limit. With a
+/// `pruner`, the first run that is pruned ends the trial. A configuration the
+/// solver refuses to run is skipped with a warning.
+fn run_trial(
+    algorithm: Algorithm,
+    tsp: &TspLib,
+    trial: &Trial,
+    fidelity: f64,
+    options: &HyperOptions,
+    pruner: Option<&Arc<Pruner>>,
+) -> Option<OptimizationResult> {
+    let parameters = Parameters::from_unit(algorithm, &trial.point, &options.search_spaces);
+    let mut runs: Vec<(u64, u64)> = Vec::new();
+    let mut pruned = false;
+    for k in 0..options.evals_per_trial.max(1) as u64 {
+        let monitor = pruner.zip(parameters.budget()).map(|(pruner, budget)| {
+            Arc::new(TrialMonitor {
+                pruner: Arc::clone(pruner),
+                budget: scale_budget(budget, fidelity),
+                next_checkpoint: AtomicUsize::new(0),
+                pruned: AtomicBool::new(false),
+            })
+        });
+        let seed = trial.seed.wrapping_add(k);
+        match evaluate(&parameters, tsp, fidelity, seed, monitor.clone(), options) {
+            Ok(run) => runs.push(run),
+            Err(err) => {
+                eprintln!(
+                    "Warning: skipping {} trial with {}: {:#}",
+                    algorithm.name(),
+                    parameters,
+                    err
+                );
+                return None;
+            }
+        }
+        if monitor.is_some_and(|monitor| monitor.should_stop()) {
+            pruned = true;
+            break;
+        }
+    }
+
+    let count = runs.len() as f64;
+    let mean_distance = runs.iter().map(|&(d, _)| d as f64).sum::<f64>() / count;
+    let variance = runs
+        .iter()
+        .map(|&(d, _)| (d as f64 - mean_distance).powi(2))
+        .sum::<f64>()
+        / (count - 1.0).max(1.0);
+    Some(OptimizationResult {
+        algorithm: algorithm.name().to_string(),
+        parameters,
+        distance: runs.iter().map(|&(d, _)| d).min().unwrap(),
+        mean_distance,
+        std_distance: variance.sqrt(),
+        evaluations: runs.len(),
+        seed: trial.seed,
+        pruned,
+        runtime_ms: runs.iter().map(|&(_, t)| t).sum::<u64>() / runs.len() as u64,
+    })
+}
+
+/// Runs the solver configured by `parameters` once with `seed`, stopping it
+/// after the trial time limit, and returns the distance it reached and its
+/// runtime in milliseconds. With workers, the run goes to one of them, and
+/// only runs here if none is left.
+fn evaluate(
+    parameters: &Parameters,
+    tsp: &TspLib,
+    fidelity: f64,
+    seed: u64,
+    monitor: Option<Arc<TrialMonitor>>,
+    options: &HyperOptions,
+) -> Result<(u64, u64)> {
+    let mut spec = parameters.to_spec();
+    match &mut spec {
+        SolverSpec::Aco(config) => config.iterations = scale_budget(config.iterations, fidelity),
+        SolverSpec::Sa(_) => {}
+        SolverSpec::Ga(config) => config.generations = scale_budget(config.generations, fidelity),
+        SolverSpec::Pso(config) => config.iterations = scale_budget(config.iterations, fidelity),
+        SolverSpec::Tabu(config) => config.iterations = scale_budget(config.iterations, fidelity),
+    }
+
+    let time_limit = options.trial_time_limit;
+    if let Some(workers) = &options.workers {
+        let task = Task {
+            spec: spec.clone(),
+            seed,
+            time_limit,
+            max_iterations: None,
+            stats: false,
+        };
+        match workers.run(tsp, &task) {
+            Ok(report) => return Ok((report.distance, report.run_time_ms)),
+            Err(err) => eprintln!("Warning: {:#}, running the trial here", err),
+        }
+    }
+
+    let mut solver = build_solver(tsp, &spec);
+    if let Some(monitor) = monitor {
+        solver.add_observer(monitor);
+    }
+    solver.set_seed(seed);
+    solver.set_evaluator(options.evaluator.clone());
+    let termination = Termination {
+        time_limit,
+        ..Termination::default()
+    };
+    let solution = solver.solve(tsp, &termination)?;
+    Ok((solution.route.distance, solution.run_time))
+}
+
+/// Prints a table per algorithm. With a known optimum, each trial also gets its
+/// gap to it and its quality (optimum / mean distance, in percent) per 10k
+/// tour evaluations, which compares algorithms whose iterations differ in cost.
+#[cfg(feature = "tables")]
+fn print_results_table(results: &[OptimizationResult], tsp: &TspLib) {
     let mut current_algo = String::new();
     let mut table = Table::new();
+    let pareto = pareto_optimal(results);
 
-    for result in results {
+    for (result, &pareto) in results.iter().zip(&pareto) {
         if result.algorithm != current_algo {
             if !current_algo.is_empty() {
                 table.printstd();
@@ -192,11 +1931,128 @@ fn print_results_table(results: &[OptimizationResult]) {
             current_algo = result.algorithm.clone();
 
             table.add_row(row![bFg => format!("{} Results", current_algo)]);
-            table.add_row(row![bFg => "Parameters", "Distance", "Runtime (ms)"]);
+            table.add_row(
+                row![bFg => "Parameters", "Mean distance", "Gap", "Std", "Best", "Best gap", "Runs", "Tours (10k)", "Quality/10k tours", "Runtime (ms)", "Pareto"],
+            );
         }
 
-        table.add_row(row![result.parameters, result.distance, result.runtime_ms]);
+        let tours = result.parameters.tour_evaluations(tsp.dimension) / 10_000.0;
+        let gap = |distance: f64| {
+            stats::gap(distance, tsp).map_or_else(|| "-".to_string(), stats::format_gap)
+        };
+        let quality = match tsp.optimal_tour_length {
+            Some(optimum) => format!(
+                "{:.2}",
+                optimum as f64 / result.mean_distance * 100.0 / tours
+            ),
+            None => "-".to_string(),
+        };
+
+        table.add_row(row![
+            result.parameters,
+            format!("{:.1}", result.mean_distance),
+            gap(result.mean_distance),
+            format!("{:.1}", result.std_distance),
+            result.distance,
+            gap(result.distance as f64),
+            if result.pruned {
+                format!("{} (pruned)", result.evaluations)
+            } else {
+                result.evaluations.to_string()
+            },
+            format!("{:.2}", tours),
+            quality,
+            result.runtime_ms,
+            if pareto { "*" } else { "" }
+        ]);
     }
 
     table.printstd();
 }
+
+/// Prints a line per trial, for builds without tables.
+#[cfg(not(feature = "tables"))]
+fn print_results_table(results: &[OptimizationResult], tsp: &TspLib) {
+    for result in results {
+        println!(
+            "{} {}: mean {:.1} ± {:.1}{}, best {}, {:.2}×10k tours, {}ms",
+            result.algorithm,
+            result.parameters,
+            result.mean_distance,
+            result.std_distance,
+            stats::gap(result.mean_distance, tsp).map_or_else(String::new, |gap| format!(
+                ", gap {}",
+                stats::format_gap(gap)
+            )),
+            result.distance,
+            result.parameters.tour_evaluations(tsp.dimension) / 10_000.0,
+            result.runtime_ms
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(parameters: &Parameters) -> Parameters {
+        let json = serde_json::to_string(parameters).unwrap();
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn parameters_round_trip_as_their_own_variant() {
+        let all = [
+            Parameters::Aco(AcoParams {
+                alpha: 1.0,
+                beta: 2.0,
+                decay: 0.1,
+                q: 100.0,
+                ants: 50,
+                iterations: 200,
+            }),
+            Parameters::Sa(SaParams {
+                initial_temp: 1000.0,
+                final_temp: 0.01,
+                cooling_rate: 0.01,
+            }),
+            Parameters::Ga(GaParams {
+                population_size: 100,
+                generations: 200,
+                mutation_rate: 0.05,
+                islands: 4,
+                migration_interval: 20,
+                migration_size: 3,
+            }),
+            Parameters::Pso(PsoParams {
+                num_particles: 50,
+                iterations: 200,
+                cognitive_weight: 1.5,
+                social_weight: 1.5,
+                inertia_weight: 0.7,
+            }),
+            Parameters::Tabu(TabuParams {
+                tenure: 10,
+                iterations: 1000,
+                candidates: 50,
+            }),
+        ];
+        for parameters in &all {
+            let read = round_trip(parameters);
+            assert_eq!(
+                std::mem::discriminant(&read),
+                std::mem::discriminant(parameters)
+            );
+            assert_eq!(read.values(), parameters.values());
+        }
+    }
+
+    #[test]
+    fn ga_parameters_without_islands_read_with_defaults() {
+        let json = r#"{"population_size":100,"generations":200,"mutation_rate":0.05}"#;
+        let Parameters::Ga(params) = serde_json::from_str(json).unwrap() else {
+            panic!("read as another algorithm's parameters");
+        };
+        assert_eq!(params.islands, GaConfig::default().islands);
+    }
+}
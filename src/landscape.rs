@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use anyhow::{bail, Context, Result};
+use rand::seq::SliceRandom;
+
+use crate::{
+    diversity,
+    local_search::{LocalSearch, LocalSearcher},
+    tsplib::{self, read_tsp_file, TspLib},
+};
+
+/// Correlations beyond which a landscape counts as guiding or misleading.
+const FDC_THRESHOLD: f64 = 0.15;
+
+/// How the length of local optima relates to their distance from a reference
+/// tour: the optimum if known, otherwise the shortest local optimum found.
+#[derive(Debug, Clone)]
+pub struct FitnessDistance {
+    /// Whether the reference is the known optimal tour.
+    pub reference_is_optimal: bool,
+    /// `(length, distance)` of every sampled local optimum, the distance being
+    /// the number of its edges the reference lacks.
+    pub samples: Vec<(u64, usize)>,
+    /// Pearson correlation of length and distance.
+    pub correlation: f64,
+}
+
+/// Samples `samples` 2-opt local optima from random tours of `tsp` and
+/// computes their fitness-distance correlation.
+pub fn fitness_distance_correlation(
+    tsp: &TspLib,
+    samples: usize,
+    seed: Option<u64>,
+) -> FitnessDistance {
+    let mut rng = tsplib::solver_rng(seed);
+    let searcher =
+        LocalSearcher::with_neighbors(LocalSearch::TwoOpt, &tsp.distance_matrix, tsp.dimension);
+    let optima: Vec<(Vec<usize>, u64)> = (0..samples)
+        .map(|_| {
+            let mut tour: Vec<usize> = (0..tsp.dimension).collect();
+            tour.shuffle(&mut rng);
+            let length = searcher.improve(&mut tour, &tsp.distance_matrix);
+            (tour, length)
+        })
+        .collect();
+
+    let reference = match &tsp.optimal_tour {
+        Some(tour) => tour.clone(),
+        None => optima
+            .iter()
+            .min_by_key(|(_, length)| *length)
+            .map(|(tour, _)| tour.clone())
+            .unwrap_or_default(),
+    };
+    let reference_edges: HashSet<(usize, usize)> = diversity::edges(&reference).collect();
+    let samples: Vec<(u64, usize)> = optima
+        .iter()
+        .map(|(tour, length)| {
+            let missing = diversity::edges(tour)
+                .filter(|edge| !reference_edges.contains(edge))
+                .count();
+            (*length, missing)
+        })
+        .collect();
+
+    FitnessDistance {
+        reference_is_optimal: tsp.optimal_tour.is_some(),
+        correlation: correlation(&samples),
+        samples,
+    }
+}
+
+/// Pearson correlation of the pairs, 0 if either side is constant.
+fn correlation(samples: &[(u64, usize)]) -> f64 {
+    let n = samples.len().max(1) as f64;
+    let mean_x = samples.iter().map(|&(x, _)| x as f64).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|&(_, y)| y as f64).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for &(x, y) in samples {
+        let (dx, dy) = (x as f64 - mean_x, y as f64 - mean_y);
+        covariance += dx * dy;
+        variance_x += dx * dx;
+        variance_y += dy * dy;
+    }
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return 0.0;
+    }
+
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+/// Samples local optima of `instance` and prints its fitness-distance
+/// correlation with a hint at which kind of metaheuristic suits it.
+pub fn run(instance: &str, samples: usize, seed: Option<u64>) -> Result<()> {
+    if samples < 2 {
+        bail!("--samples must be at least 2");
+    }
+    let tsp = read_tsp_file(instance).with_context(|| format!("failed to parse {}", instance))?;
+    println!("{:?}", tsp);
+
+    let fdc = fitness_distance_correlation(&tsp, samples, seed);
+    let lengths = fdc.samples.iter().map(|&(length, _)| length);
+    let distances = fdc.samples.iter().map(|&(_, distance)| distance);
+    println!(
+        "{} 2-opt local optima: length {} to {}, {} to {} edges from the {}",
+        fdc.samples.len(),
+        lengths.clone().min().unwrap_or(0),
+        lengths.max().unwrap_or(0),
+        distances.clone().min().unwrap_or(0),
+        distances.max().unwrap_or(0),
+        if fdc.reference_is_optimal {
+            "optimal tour"
+        } else {
+            "best local optimum"
+        }
+    );
+    println!("Fitness-distance correlation: {:.3}", fdc.correlation);
+    println!(
+        "{}",
+        if fdc.correlation > FDC_THRESHOLD {
+            "Shorter local optima lie closer to the best tour (a big valley): methods that \
+             intensify around good tours, such as GA or ACO with local search, should do well."
+        } else if fdc.correlation < -FDC_THRESHOLD {
+            "Shorter local optima lie further from the best tour: the landscape is deceptive \
+             and favours strongly diversifying methods."
+        } else {
+            "Length and distance to the best tour are unrelated: local optima give little \
+             guidance, so favour methods that keep exploring, such as SA or restarts."
+        }
+    );
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! Metaheuristic solvers for the Euclidean travelling salesman problem: ant
+//! colony optimization, simulated annealing, a genetic algorithm, particle
+//! swarm optimization and tabu search, chained with [`Pipeline`] or run
+//! cooperatively with [`pool`]. Every solver but tabu search can polish its
+//! tours with a shared [`LocalSearch`].
+//!
+//! Read an instance with [`read_tsp_file`] or [`parse_tsp`], build a solver
+//! from its settings with [`Configurable::from_config`] or the config's
+//! builder, e.g. `AcoConfig::default().ants(50).build(&tsp)` (or from a
+//! [`SolverSpec`] with [`config::build_solver`]) and call
+//! [`HeuristicAlgorithm::solve`] with a [`Termination`]. `solve` takes any
+//! [`Problem`], so distance matrices built in code ([`problem::MatrixProblem`])
+//! and random instances ([`problem::RandomProblem`]) need no `.tsp` file.
+//!
+//! Plotting needs the `plot` feature, result tables `tables` and YAML config
+//! files `yaml`. All come with the default `cli` feature, which builds the
+//! `sapso` binary; library users who only want the solvers can turn off the
+//! default features.
+
+pub mod aco;
+pub mod archive;
+#[cfg(all(feature = "plot", feature = "tables"))]
+pub mod bench;
+pub mod checkpoint;
+pub mod cmaes;
+pub mod config;
+pub mod construct;
+pub mod distance;
+pub mod distributed;
+pub mod diversity;
+pub mod error;
+pub mod experiment;
+pub mod fitness;
+pub mod ga;
+#[cfg(feature = "gpu")]
+pub mod gpu;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod hyper;
+pub mod landscape;
+pub mod local_search;
+#[cfg(feature = "plot")]
+pub mod manifest;
+pub mod memory;
+pub mod pipeline;
+#[cfg(feature = "plot")]
+pub mod plot;
+pub mod pool;
+pub mod problem;
+pub mod progress;
+pub mod pso;
+pub mod sa;
+#[cfg(feature = "serve")]
+pub mod serve;
+pub mod stats;
+pub mod store;
+pub mod tabu;
+pub mod telemetry;
+pub mod tsplib;
+pub mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+pub use aco::{AcoVariant, AntColonyOptimization};
+pub use config::{AcoConfig, GaConfig, PsoConfig, SaConfig, SolverConfig, SolverSpec, TabuConfig};
+pub use construct::Construction;
+pub use distance::DistanceKind;
+pub use ga::{Crossover, GeneticAlgorithm, Selection};
+pub use local_search::LocalSearch;
+pub use pipeline::Pipeline;
+pub use problem::Problem;
+pub use pso::ParticleSwarmOptimization;
+pub use sa::{Cooling, SimulatedAnnealing};
+pub use tabu::TabuSearch;
+pub use tsplib::{
+    parse_tsp, read_tsp_file, City, Configurable, HeuristicAlgorithm, Route, Solution, Termination,
+    TspLib,
+};
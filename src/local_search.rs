@@ -0,0 +1,489 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    distance::DistanceMatrix,
+    tsplib::{self, TspLib},
+};
+
+/// Nearest neighbours of each city that moves try to connect it to.
+const NEIGHBORS: usize = 10;
+
+/// Most 2-opt moves chained into a single Lin-Kernighan move.
+const MAX_DEPTH: usize = 6;
+
+/// Longest stretch of cities an Or-opt move relocates.
+const MAX_SEGMENT: usize = 3;
+
+/// A local search solvers can run on the tours they build, configured as
+/// `local_search = "or_opt"`. Each includes the cheaper moves before it, and
+/// every move only connects a city to one of its nearest neighbours.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LocalSearch {
+    #[default]
+    None,
+    /// Reverses a stretch of the tour.
+    TwoOpt,
+    /// 2-opt, and moves a stretch of up to three cities elsewhere in the tour,
+    /// either way round.
+    OrOpt,
+    /// Or-opt, and swaps two adjacent stretches of the tour.
+    ThreeOpt,
+    /// Chains of up to six 2-opt moves, each allowed to lengthen the tour as
+    /// long as the chain as a whole shortens it, and Or-opt.
+    LinKernighan,
+}
+
+impl LocalSearch {
+    pub fn is_none(&self) -> bool {
+        *self == LocalSearch::None
+    }
+}
+
+impl FromStr for LocalSearch {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "none" => LocalSearch::None,
+            "two_opt" | "2-opt" => LocalSearch::TwoOpt,
+            "or_opt" | "or-opt" => LocalSearch::OrOpt,
+            "three_opt" | "3-opt" => LocalSearch::ThreeOpt,
+            "lin_kernighan" | "lk" => LocalSearch::LinKernighan,
+            _ => bail!(
+                "unknown local search `{}`, expected none, two_opt, or_opt, three_opt or lin_kernighan",
+                name
+            ),
+        })
+    }
+}
+
+impl fmt::Display for LocalSearch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            LocalSearch::None => "none",
+            LocalSearch::TwoOpt => "two_opt",
+            LocalSearch::OrOpt => "or_opt",
+            LocalSearch::ThreeOpt => "three_opt",
+            LocalSearch::LinKernighan => "lin_kernighan",
+        })
+    }
+}
+
+/// A [`LocalSearch`] set up for one instance, with its candidate neighbour
+/// lists.
+#[derive(Debug, Clone)]
+pub struct LocalSearcher {
+    method: LocalSearch,
+    /// Every city's nearest neighbours, nearest first.
+    neighbors: Vec<Vec<usize>>,
+    /// Whether distances differ both ways, so moves must not reverse any
+    /// stretch of the tour.
+    directed: bool,
+}
+
+impl LocalSearcher {
+    /// `method` on `tsp`, trying each city's nearest neighbours from its
+    /// candidate lists, or `None` for no local search.
+    pub fn new(method: LocalSearch, tsp: &TspLib) -> Option<Self> {
+        (!method.is_none()).then(|| LocalSearcher {
+            method,
+            neighbors: tsp
+                .neighbors()
+                .iter()
+                .map(|neighbors| neighbors[..NEIGHBORS.min(neighbors.len())].to_vec())
+                .collect(),
+            directed: tsp.is_asymmetric(),
+        })
+    }
+
+    /// `method` trying each city's `k` nearest neighbours; with every other
+    /// city, moves are found wherever they exist.
+    pub fn with_neighbors(method: LocalSearch, matrix: &DistanceMatrix, k: usize) -> Self {
+        LocalSearcher {
+            method,
+            neighbors: matrix.nearest_neighbors(k),
+            directed: !matrix.is_symmetric(),
+        }
+    }
+
+    pub fn method(&self) -> LocalSearch {
+        self.method
+    }
+
+    /// Improves `tour` until none of the method's moves shortens it, and
+    /// returns its length.
+    pub fn improve(&self, tour: &mut [usize], matrix: &DistanceMatrix) -> u64 {
+        if tour.len() >= 5 {
+            let mut tour = Tour::new(tour, matrix);
+            loop {
+                // `|` rather than `||`, so every neighbourhood gets its turn.
+                let improved = match self.method {
+                    LocalSearch::None => false,
+                    // 2-opt and Lin-Kernighan reverse stretches of the tour,
+                    // so on asymmetric distances every method keeps to the
+                    // moves that do not.
+                    _ if self.directed => {
+                        let three_opt = matches!(
+                            self.method,
+                            LocalSearch::ThreeOpt | LocalSearch::LinKernighan
+                        );
+                        self.or_opt(&mut tour) | (three_opt && self.three_opt(&mut tour))
+                    }
+                    LocalSearch::TwoOpt => self.two_opt(&mut tour),
+                    LocalSearch::OrOpt => self.two_opt(&mut tour) | self.or_opt(&mut tour),
+                    LocalSearch::ThreeOpt => {
+                        self.two_opt(&mut tour) | self.or_opt(&mut tour) | self.three_opt(&mut tour)
+                    }
+                    LocalSearch::LinKernighan => {
+                        self.lin_kernighan(&mut tour) | self.or_opt(&mut tour)
+                    }
+                };
+                if !improved {
+                    break;
+                }
+            }
+        }
+        tsplib::tour_length(tour, matrix)
+    }
+
+    /// Applies improving 2-opt moves until there are none; returns whether
+    /// it found any.
+    fn two_opt(&self, tour: &mut Tour) -> bool {
+        let mut improved_any = false;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for a in 0..tour.len() {
+                for forward in [true, false] {
+                    let b = tour.step(a, forward);
+                    let d_ab = tour.d(a, b);
+                    for &c in &self.neighbors[a] {
+                        let g1 = d_ab - tour.d(a, c);
+                        if g1 <= 0 {
+                            break;
+                        }
+                        let e = tour.step(c, forward);
+                        if c == b || e == a {
+                            continue;
+                        }
+                        if g1 + tour.d(c, e) - tour.d(b, e) > 0 {
+                            // Edges (a, b) and (c, e) become (a, c) and (b, e).
+                            if forward {
+                                tour.two_opt(tour.position[a], tour.position[c]);
+                            } else {
+                                tour.two_opt(tour.position[b], tour.position[e]);
+                            }
+                            improved = true;
+                            improved_any = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        improved_any
+    }
+
+    /// Applies improving Or-opt moves until there are none; returns whether
+    /// it found any.
+    fn or_opt(&self, tour: &mut Tour) -> bool {
+        let n = tour.len();
+        let mut improved_any = false;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for length in 1..=MAX_SEGMENT.min(n - 3) {
+                for start in 0..n {
+                    if self.relocate(tour, start, length) {
+                        improved = true;
+                        improved_any = true;
+                    }
+                }
+            }
+        }
+        improved_any
+    }
+
+    /// Moves the `length` cities from position `start` to the best spot next
+    /// to a neighbour of either end, if that shortens the tour. On
+    /// asymmetric distances the cities keep their order.
+    fn relocate(&self, tour: &mut Tour, start: usize, length: usize) -> bool {
+        let n = tour.len();
+        let segment: Vec<usize> = (0..length).map(|k| tour.at(start + k)).collect();
+        let (first, last) = (segment[0], segment[length - 1]);
+        let (prev, next) = (tour.at(start + n - 1), tour.at(start + length));
+        let removal = tour.d(prev, first) + tour.d(last, next) - tour.d(prev, next);
+        let inside = |city: usize, tour: &Tour| tour.offset(city, start) < length;
+
+        for (end, other, at_first) in [(first, last, true), (last, first, false)] {
+            for &c in &self.neighbors[end] {
+                // Neighbours are nearest first from `end`, which on
+                // asymmetric distances says nothing about the edge from c
+                // into `first`.
+                if self.directed && at_first {
+                    if tour.d(c, end) >= removal {
+                        continue;
+                    }
+                } else if tour.d(end, c) >= removal {
+                    break;
+                }
+                if inside(c, tour) {
+                    continue;
+                }
+                for forward in [true, false] {
+                    // Without reversing, `first` can only follow c and
+                    // `last` only precede it.
+                    if self.directed && forward != at_first {
+                        continue;
+                    }
+                    let e = tour.step(c, forward);
+                    if inside(e, tour) {
+                        continue;
+                    }
+                    // The segment goes between c and e, `end` next to c.
+                    let insertion = if self.directed && !at_first {
+                        tour.d(e, first) + tour.d(last, c) - tour.d(e, c)
+                    } else {
+                        tour.d(c, end) + tour.d(other, e) - tour.d(c, e)
+                    };
+                    if insertion < removal {
+                        let rest: Vec<usize> = (0..n - length)
+                            .map(|k| tour.at(start + length + k))
+                            .collect();
+                        let k = rest.iter().position(|&city| city == c).unwrap();
+                        let mut oriented = segment.clone();
+                        // Walking the tour forwards, c comes before the
+                        // segment if e follows c.
+                        let c_first = rest[(k + 1) % rest.len()] == e;
+                        if c_first != at_first {
+                            oriented.reverse();
+                        }
+                        let at = if c_first { k + 1 } else { k };
+                        let mut order = rest;
+                        order.splice(at..at, oriented);
+                        tour.rearrange(&order);
+                        return true;
+                    }
+                }
+            }
+        }
+        false
+    }
+
+    /// Applies improving moves that swap two adjacent stretches of the tour
+    /// until there are none; returns whether it found any.
+    fn three_opt(&self, tour: &mut Tour) -> bool {
+        let n = tour.len();
+        let mut improved_any = false;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            'start: for i in 0..n {
+                // Positions are counted from a, so the stretches never wrap:
+                // a | b .. c | d .. e | f becomes a | d .. e | b .. c | f.
+                let (a, b) = (tour.at(i), tour.at(i + 1));
+                for &d in &self.neighbors[a] {
+                    let g1 = tour.d(a, b) - tour.d(a, d);
+                    if g1 <= 0 {
+                        break;
+                    }
+                    let od = tour.offset(d, i);
+                    if od < 2 {
+                        continue;
+                    }
+                    let c = tour.at(i + od - 1);
+                    let g2 = g1 + tour.d(c, d);
+                    for &e in &self.neighbors[b] {
+                        // The new edge runs from e into b, which on
+                        // asymmetric distances is not what b's neighbours
+                        // are sorted by.
+                        let g3 = g2 - tour.d(e, b);
+                        if g3 <= 0 {
+                            if self.directed {
+                                continue;
+                            }
+                            break;
+                        }
+                        let oe = tour.offset(e, i);
+                        if oe < od {
+                            continue;
+                        }
+                        let f = tour.at(i + oe + 1);
+                        if g3 + tour.d(e, f) - tour.d(c, f) > 0 {
+                            let order: Vec<usize> = std::iter::once(0)
+                                .chain(od..=oe)
+                                .chain(1..od)
+                                .chain(oe + 1..n)
+                                .map(|k| tour.at(i + k))
+                                .collect();
+                            tour.rearrange(&order);
+                            improved = true;
+                            improved_any = true;
+                            continue 'start;
+                        }
+                    }
+                }
+            }
+        }
+        improved_any
+    }
+
+    /// Applies improving Lin-Kernighan moves from every city until there are
+    /// none; returns whether it found any.
+    fn lin_kernighan(&self, tour: &mut Tour) -> bool {
+        let mut improved_any = false;
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for t1 in 0..tour.len() {
+                if self.lin_kernighan_move(tour, t1) {
+                    improved = true;
+                    improved_any = true;
+                }
+            }
+        }
+        improved_any
+    }
+
+    /// Chains 2-opt moves that each break the edge at `t1` closed by the one
+    /// before, and keeps the chain up to its shortest tour, if shorter than
+    /// where it started. Edges the chain added are never broken again.
+    fn lin_kernighan_move(&self, tour: &mut Tour, t1: usize) -> bool {
+        for first_forward in [true, false] {
+            let mut t2 = tour.step(t1, first_forward);
+            let mut gain = 0;
+            let mut best = (0, 0);
+            let mut reversals = Vec::new();
+            let mut added = Vec::new();
+            for _ in 0..MAX_DEPTH {
+                let forward = tour.step(t1, true) == t2;
+                let d12 = tour.d(t1, t2);
+                let mut choice = None;
+                let mut best_score = i64::MIN;
+                for &t3 in &self.neighbors[t2] {
+                    if gain + d12 - tour.d(t2, t3) <= 0 {
+                        break;
+                    }
+                    let t4 = tour.step(t3, !forward);
+                    if t3 == t1 || t4 == t2 || t4 == t1 || added.contains(&edge(t3, t4)) {
+                        continue;
+                    }
+                    let score = tour.d(t3, t4) - tour.d(t2, t3);
+                    if score > best_score {
+                        best_score = score;
+                        choice = Some((t3, t4));
+                    }
+                }
+                let Some((t3, t4)) = choice else {
+                    break;
+                };
+
+                // Edges (t1, t2) and (t3, t4) become (t2, t3) and (t1, t4).
+                let reversed = if forward {
+                    tour.two_opt(tour.position[t1], tour.position[t4])
+                } else {
+                    tour.two_opt(tour.position[t2], tour.position[t3])
+                };
+                reversals.push(reversed);
+                gain += d12 + tour.d(t3, t4) - tour.d(t2, t3) - tour.d(t1, t4);
+                added.push(edge(t2, t3));
+                if gain > best.0 {
+                    best = (gain, reversals.len());
+                }
+                t2 = t4;
+            }
+
+            while reversals.len() > best.1 {
+                let (from, to) = reversals.pop().unwrap();
+                tour.reverse(from, to);
+            }
+            if best.0 > 0 {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+fn edge(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+/// A tour being improved, with the position of every city kept in step.
+struct Tour<'a> {
+    cities: &'a mut [usize],
+    position: Vec<usize>,
+    matrix: &'a DistanceMatrix,
+}
+
+impl<'a> Tour<'a> {
+    fn new(cities: &'a mut [usize], matrix: &'a DistanceMatrix) -> Self {
+        let mut position = vec![0; cities.len()];
+        for (index, &city) in cities.iter().enumerate() {
+            position[city] = index;
+        }
+        Tour {
+            cities,
+            position,
+            matrix,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.cities.len()
+    }
+
+    fn d(&self, a: usize, b: usize) -> i64 {
+        self.matrix.get(a, b) as i64
+    }
+
+    /// The city at `index`, counted round the tour.
+    fn at(&self, index: usize) -> usize {
+        self.cities[index % self.len()]
+    }
+
+    /// How many positions after `start` `city` is.
+    fn offset(&self, city: usize, start: usize) -> usize {
+        (self.position[city] + self.len() - start % self.len()) % self.len()
+    }
+
+    /// The city after `city`, or before it if not `forward`.
+    fn step(&self, city: usize, forward: bool) -> usize {
+        let n = self.len();
+        let index = self.position[city];
+        self.cities[if forward {
+            (index + 1) % n
+        } else {
+            (index + n - 1) % n
+        }]
+    }
+
+    /// Reverses the cities at positions `from..=to`.
+    fn reverse(&mut self, from: usize, to: usize) {
+        self.cities[from..=to].reverse();
+        for index in from..=to {
+            self.position[self.cities[index]] = index;
+        }
+    }
+
+    /// Replaces the edges leaving positions `i` and `j` with one joining the
+    /// cities at `i` and `j` and one joining their successors, and returns
+    /// the positions reversed to do so.
+    fn two_opt(&mut self, i: usize, j: usize) -> (usize, usize) {
+        let (from, to) = if i < j { (i + 1, j) } else { (j + 1, i) };
+        self.reverse(from, to);
+        (from, to)
+    }
+
+    /// Makes `order` the tour.
+    fn rearrange(&mut self, order: &[usize]) {
+        self.cities.copy_from_slice(order);
+        for (index, &city) in order.iter().enumerate() {
+            self.position[city] = index;
+        }
+    }
+}
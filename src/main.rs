@@ -1,41 +1,1239 @@
-mod aco;
-mod ga;
-mod hyper;
-mod plot;
-mod pso;
-mod sa;
-mod tsplib;
-
 use colorful::Colorful;
-use std::{fs::File, io::Write};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
-use anyhow::Result;
-use clap::{App, Arg};
+use anyhow::{bail, Context, Result};
+use clap::{App, Arg, ArgMatches};
 use plotters::style::RGBColor;
-use tsplib::{read_tsp_file, HeuristicAlgorithm, TspLib};
-
-fn run_algorithm<T>(mut algorithm: T, name: &str, tsp: &TspLib, style: &RGBColor)
-where
-    T: HeuristicAlgorithm,
-{
-    algorithm.solve(tsp);
-    let best_route = algorithm.get_best_route();
-    let run_time = algorithm.get_run_time();
+use sapso::{
+    aco, archive, bench,
+    checkpoint::{self, Checkpoint, Checkpointing},
+    config::{self, Config},
+    construct::Construction,
+    distance::DistanceProvider,
+    distributed, experiment,
+    fitness::Evaluator,
+    ga, hyper, landscape,
+    local_search::LocalSearch,
+    manifest, memory, pipeline, plot, pool,
+    problem::{Distribution, Problem, RandomProblem},
+    progress::{self, ConsoleProgress, NdjsonProgress, Observer},
+    pso, sa, stats,
+    store::{self, StoredRun},
+    tabu, telemetry,
+    tsplib::{
+        self, read_tsp_file_with, CancellationToken, Configurable, HeuristicAlgorithm, Termination,
+        TspLib,
+    },
+    validate,
+};
+use tracing::level_filters::LevelFilter;
+
+/// How often each solver's best distance is printed while it runs.
+const CONSOLE_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+struct RunOptions {
+    observers: Vec<Arc<dyn Observer>>,
+    plot: plot::PlotConfig,
+    runs: usize,
+    seed: Option<u64>,
+    history_csv: Option<PathBuf>,
+    termination: Termination,
+    /// Distinct tours each run archives, see `--archive`.
+    archive: Option<usize>,
+    evaluator: Evaluator,
+    /// Where the solver saves its run as it goes, see `--checkpoint`.
+    checkpointing: Option<Checkpointing>,
+    /// Checkpoint the first run continues from, see `--resume-run`.
+    resume: Option<PathBuf>,
+    /// Tour every run starts from, see `--initial-tour`.
+    initial_tour: Option<Vec<usize>>,
+}
+
+/// Solves `tsp` with `options.runs` fresh solvers built from `config`, the
+/// first one resumed from `options.resume` if set, plots the best run and,
+/// for repeated runs, the spread of their convergence. Returns the best run
+/// along with the statistics of every run.
+fn run_algorithm<T: Configurable + Checkpoint>(
+    config: &T::Config,
+    name: &str,
+    tsp: &TspLib,
+    style: &RGBColor,
+    options: &RunOptions,
+) -> Result<(T, Vec<stats::RunStats>)> {
+    let mut best: Option<T> = None;
+    let mut histories = Vec::with_capacity(options.runs);
+    let mut run_stats = Vec::with_capacity(options.runs);
+
+    for run in 1..=options.runs {
+        let mut algorithm = match &options.resume {
+            Some(path) if run == 1 => {
+                println!("Resuming {} from {}", name, path.display());
+                checkpoint::load::<T>(path, tsp)?
+            }
+            _ => T::from_config(tsp, config),
+        };
+        if let Some(seed) = options.seed {
+            algorithm.set_seed(seed.wrapping_add(run as u64 - 1));
+        }
+        if let Some(checkpointing) = &options.checkpointing {
+            algorithm.set_checkpointing(checkpointing.clone());
+        }
+        if let Some(tour) = &options.initial_tour {
+            algorithm.set_initial_solution(tour.clone());
+        }
+        for observer in &options.observers {
+            algorithm.add_observer(Arc::clone(observer));
+        }
+        if let Some(capacity) = options.archive {
+            algorithm.set_archive(archive::SolutionArchive::new(
+                capacity,
+                archive::DEFAULT_MAX_OVERLAP,
+            ));
+        }
+        algorithm.set_evaluator(options.evaluator.clone());
+        let tracker = memory::MemoryTracker::start();
+        algorithm.solve(tsp, &options.termination)?;
+        let mut stats = stats::RunStats::from(&algorithm, tsp);
+        stats.memory = tracker.finish();
+        let label = if options.runs > 1 {
+            format!("{} (run {}/{})", name, run, options.runs)
+        } else {
+            name.to_string()
+        };
+        println!(
+            "\n{} Best Route: {:?}",
+            label.clone().bold().rgb(style.0, style.1, style.2),
+            stats.final_distance
+        );
+        println!(
+            "{} Convergence: best {} at iteration {}/{} ({}ms), {:.1} shorter per iteration, area {:.2}%{}",
+            label.clone().bold().rgb(style.0, style.1, style.2),
+            stats.best_distance,
+            stats.iterations_to_best,
+            stats.iterations,
+            stats.time_to_best_ms,
+            stats.improvement_rate,
+            stats.convergence_area,
+            stats.gap.map_or(String::new(), |gap| format!(
+                ", gap {}",
+                stats::format_gap(gap)
+            )) + &stats
+                .optimal_edge_overlap
+                .map_or(String::new(), |overlap| format!(
+                    ", {:.1}% of edges optimal",
+                    overlap
+                ))
+        );
+        if tsp.optimal_tour_length.is_some() {
+            println!(
+                "{} Time to target gap: {}",
+                label.clone().bold().rgb(style.0, style.1, style.2),
+                stats::format_targets(&stats.targets)
+            );
+        }
+        if !stats.operators.is_empty() {
+            println!(
+                "{} Operators: {}",
+                label.clone().bold().rgb(style.0, style.1, style.2),
+                stats::format_operators(&stats.operators)
+            );
+        }
+        let search: Vec<String> = [
+            stats
+                .population_diversity
+                .map(|diversity| format!("population diversity {:.1}%", diversity * 100.0)),
+            stats
+                .acceptance_rate
+                .map(|rate| format!("{:.1}% of moves accepted", rate * 100.0)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !search.is_empty() {
+            println!(
+                "{} Search: {}",
+                label.clone().bold().rgb(style.0, style.1, style.2),
+                search.join(", ")
+            );
+        }
+        if let Some(memory) = stats.memory.describe() {
+            println!(
+                "{} Memory: {}",
+                label.clone().bold().rgb(style.0, style.1, style.2),
+                memory
+            );
+        }
+        println!(
+            "{} Run Time: {}ms\n\n",
+            label.bold().rgb(style.0, style.1, style.2),
+            stats.run_time_ms
+        );
+
+        histories.push(algorithm.get_history().to_vec());
+        if best
+            .as_ref()
+            .is_none_or(|b| stats.final_distance < b.get_best_route().distance)
+        {
+            best = Some(algorithm);
+        }
+        run_stats.push(stats);
+    }
+
+    if options.runs > 1 {
+        let summary = stats::aggregate(&run_stats);
+        println!(
+            "{} over {} runs: mean {:.1} ± {:.1}, median {:.1}, min {}, max {}{}{}",
+            name.bold().rgb(style.0, style.1, style.2),
+            summary.runs,
+            summary.mean,
+            summary.std,
+            summary.median,
+            summary.min,
+            summary.max,
+            stats::gap(summary.mean, tsp).map_or(String::new(), |gap| format!(
+                ", mean gap {}",
+                stats::format_gap(gap)
+            )),
+            summary.success_rate.map_or(String::new(), |rate| format!(
+                ", {:.0}% within {}% of the optimum",
+                rate * 100.0,
+                stats::SUCCESS_GAP
+            ))
+        );
+        if tsp.optimal_tour_length.is_some() {
+            let reached: Vec<String> = stats::TARGET_GAPS
+                .iter()
+                .zip(&summary.targets)
+                .map(|(gap, (count, mean_ms))| match mean_ms {
+                    Some(mean_ms) => format!(
+                        "{}% by {}/{} runs in {:.0}ms on average",
+                        gap, count, summary.runs, mean_ms
+                    ),
+                    None => format!("{}% by no run", gap),
+                })
+                .collect();
+            println!(
+                "{} Time to target gap: {}",
+                name.bold().rgb(style.0, style.1, style.2),
+                reached.join(", ")
+            );
+        }
+        let variance = stats::seed_variance(&run_stats);
+        println!(
+            "{} Seed variance: CV {:.2}%, spread {} ({:.2}%), {} runs needed for a mean within {}% at 95% confidence{}",
+            name.bold().rgb(style.0, style.1, style.2),
+            variance.coefficient_of_variation,
+            variance.spread,
+            variance.relative_spread,
+            variance.repetitions_needed,
+            stats::SEED_PRECISION,
+            if variance.repetitions_needed > variance.runs {
+                format!(" (ran {})", variance.runs)
+            } else {
+                String::new()
+            }
+        );
+        let mut operators = stats::OperatorCounts::default();
+        for run in &run_stats {
+            operators.merge(&run.operators);
+        }
+        if !operators.is_empty() {
+            println!(
+                "{} Operators over all runs: {}",
+                name.bold().rgb(style.0, style.1, style.2),
+                stats::format_operators(&operators)
+            );
+        }
+        if let Some(target) = stats::target_distance(tsp, stats::SUCCESS_GAP) {
+            let rld = stats::run_length_distribution(&run_stats, target);
+            println!(
+                "{} Run-length distribution to a {}% gap: reached by {}/{} runs, {}",
+                name.bold().rgb(style.0, style.1, style.2),
+                stats::SUCCESS_GAP,
+                rld.by_time.len(),
+                rld.runs,
+                match rld.median() {
+                    Some((time, iteration)) =>
+                        format!("half of them by {}ms (iteration {})", time, iteration),
+                    None => "never by half of them".to_string(),
+                }
+            );
+        }
+        println!("\n");
+    }
+
+    let algorithm = best.expect("at least one run");
+    if let Some(archive) = algorithm.get_archive() {
+        let path = options.plot.output_dir.join(format!(
+            "{}_{}_archive.json",
+            tsp.name,
+            name.to_lowercase().replace(' ', "_")
+        ));
+        archive.save(&path)?;
+        println!(
+            "{} distinct tours written to {}",
+            archive.tours().len(),
+            path.display()
+        );
+    }
+    let parameters = config::parameter_summary(config);
+    plot::plot_algo_result(&algorithm, tsp, name, style, &parameters, &options.plot)?;
+    if histories.len() > 1 {
+        plot::plot_history_band(&histories, tsp, name, style, &options.plot)?;
+    }
+
+    Ok((algorithm, run_stats))
+}
+
+/// Final distances of `runs`, for the distribution plot.
+fn final_distances(runs: &[stats::RunStats]) -> Vec<u64> {
+    runs.iter().map(|run| run.final_distance).collect()
+}
+
+/// Runs every algorithm at once, sharing their best routes through a
+/// [`pool::SolutionPool`], then reports and plots each one's share of the
+/// result.
+fn run_cooperative(
+    tsp: &TspLib,
+    solvers: &config::SolverConfig,
+    colors: &[RGBColor],
+    options: &RunOptions,
+) -> Result<()> {
+    let specs: Vec<config::SolverSpec> = hyper::Algorithm::ALL
+        .iter()
+        .map(|&algorithm| solvers.spec(algorithm))
+        .collect();
+    let pool = pool::SolutionPool::new();
+    if let Some(tour) = &options.initial_tour {
+        pool.publish(&tsplib::Route::validated(tour, tsp)?);
+    }
+    let results = pool::solve_cooperatively(
+        tsp,
+        &specs,
+        &pool,
+        options.seed,
+        &options.termination,
+        &options.observers,
+        &options.evaluator,
+    )?;
+
+    let mut best_routes = Vec::new();
+    for (index, ((solver, solution), spec)) in results.iter().zip(&specs).enumerate() {
+        let name = spec.algorithm().name();
+        let style = colors[index % colors.len()];
+        println!(
+            "{} Best Route: {} after {} iterations in {}ms",
+            name.bold().rgb(style.0, style.1, style.2),
+            solution.route.distance,
+            solution.iterations,
+            solution.run_time
+        );
+        let parameters = config::parameter_summary(&spec.to_table()?.1);
+        plot::plot_algo_result(
+            solver.as_ref(),
+            tsp,
+            name,
+            &style,
+            &parameters,
+            &options.plot,
+        )?;
+        best_routes.push((name, solution.route.clone(), style));
+    }
+    if let Some(best) = pool.best() {
+        println!(
+            "Cooperative best route: {}{}",
+            best.distance,
+            stats::gap(best.distance as f64, tsp).map_or(String::new(), |gap| format!(
+                ", gap {}",
+                stats::format_gap(gap)
+            ))
+        );
+    }
+    plot::plot_best_routes_grid(&best_routes, tsp, &options.plot)?;
+
+    Ok(())
+}
+
+/// A `--pipeline` chain such as `greedy -> sa(10s) -> ga(30s) -> lk`, links
+/// separated by `->` or commas: the solvers of `solvers`, each with an
+/// optional time limit, after an optional `greedy` start and before an
+/// optional local search.
+fn parse_chain(
+    tsp: &TspLib,
+    chain: &str,
+    solvers: &config::SolverConfig,
+) -> Result<pipeline::Pipeline> {
+    let links: Vec<&str> = chain
+        .split("->")
+        .flat_map(|links| links.split(','))
+        .map(str::trim)
+        .collect();
+    let mut stages = Vec::new();
+    let (mut greedy, mut polish) = (false, LocalSearch::None);
+    for (index, link) in links.iter().enumerate() {
+        let (name, limit) = match link.split_once('(') {
+            Some((name, limit)) => {
+                let limit = limit
+                    .strip_suffix(')')
+                    .and_then(parse_duration)
+                    .with_context(|| {
+                        format!("`{}` expects a time limit such as sa(10s) or ga(2m)", link)
+                    })?;
+                (name.trim(), Some(limit.as_secs_f64()))
+            }
+            None => (*link, None),
+        };
+        if name.eq_ignore_ascii_case("greedy") && limit.is_none() {
+            if index != 0 {
+                bail!("`greedy` can only start a --pipeline chain");
+            }
+            greedy = true;
+        } else if let Ok(algorithm) = name.parse() {
+            if !polish.is_none() {
+                bail!("`{}` can only end a --pipeline chain", polish);
+            }
+            stages.push(pipeline::Stage {
+                solver: solvers.spec(algorithm),
+                time_limit: limit,
+            });
+        } else {
+            polish = match name.parse() {
+                Ok(LocalSearch::None) | Err(_) => bail!(
+                    "unknown --pipeline stage `{}`, expected greedy, aco, sa, ga, pso, tabu or a local search such as lk",
+                    link
+                ),
+                Ok(_) if limit.is_some() => {
+                    bail!("the local search `{}` takes no time limit", link)
+                }
+                Ok(_) if !polish.is_none() => {
+                    bail!("a --pipeline chain ends with at most one local search")
+                }
+                Ok(method) => method,
+            };
+        }
+    }
+    if stages.is_empty() {
+        bail!("a --pipeline chain needs at least one of aco, sa, ga, pso or tabu");
+    }
+    let mut pipeline = pipeline::Pipeline::new(tsp, stages).with_polish(polish);
+    if greedy {
+        pipeline = pipeline.with_greedy_start();
+    }
+    Ok(pipeline)
+}
+
+/// Runs `pipeline` and reports and plots it like a single algorithm.
+fn run_pipeline(
+    tsp: &TspLib,
+    mut pipeline: pipeline::Pipeline,
+    style: &RGBColor,
+    options: &RunOptions,
+) -> Result<()> {
+    if let Some(seed) = options.seed {
+        pipeline.set_seed(seed);
+    }
+    for observer in &options.observers {
+        pipeline.add_observer(Arc::clone(observer));
+    }
+    pipeline.set_evaluator(options.evaluator.clone());
+    if let Some(tour) = &options.initial_tour {
+        pipeline.set_initial_solution(tour.clone());
+    }
+    let solution = pipeline.solve(tsp, &options.termination)?;
+
+    let name = pipeline.describe();
+    let stats = stats::RunStats::from(&pipeline, tsp);
     println!(
-        "\n{} Best Route: {:?}",
-        name.bold().rgb(style.0, style.1, style.2),
-        best_route.distance
+        "\n{} Best Route: {} after {} iterations in {}ms{}",
+        name.clone().bold().rgb(style.0, style.1, style.2),
+        solution.route.distance,
+        solution.iterations,
+        solution.run_time,
+        stats.gap.map_or(String::new(), |gap| format!(
+            ", gap {}",
+            stats::format_gap(gap)
+        ))
+    );
+    let parameters = pipeline
+        .stages()
+        .iter()
+        .map(|stage| {
+            Ok(format!(
+                "{}: {}",
+                stage.solver.algorithm().name(),
+                config::parameter_summary(&stage.solver.to_table()?.1)
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .join("; ");
+    plot::plot_algo_result(
+        &pipeline,
+        tsp,
+        "Pipeline",
+        style,
+        &parameters,
+        &options.plot,
+    )?;
+    export_history(
+        options.history_csv.as_deref(),
+        "Pipeline",
+        &pipeline,
+        tsp,
+        &[],
+    )
+}
+
+/// Writes the history of `algorithm`'s best run, with the solver-specific
+/// `metrics`, to a CSV file in the `--history-csv` directory, if one was given.
+fn export_history(
+    dir: Option<&Path>,
+    name: &str,
+    algorithm: &dyn HeuristicAlgorithm,
+    tsp: &TspLib,
+    metrics: &[stats::Metric],
+) -> Result<()> {
+    let Some(dir) = dir else {
+        return Ok(());
+    };
+    fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    let file_name = format!(
+        "{}_{}_history.csv",
+        tsp.name,
+        name.to_lowercase().replace(' ', "_")
+    );
+    stats::export_history_csv(name, algorithm, tsp, metrics, &dir.join(file_name))
+}
+
+/// Tunes the solvers on `tsp` with `--hyper`, writes the best configurations
+/// to `hyper_best.toml` and prints them.
+fn tune_instance(
+    tsp: &TspLib,
+    matches: &ArgMatches,
+    config: &Config,
+    evaluator: &Evaluator,
+) -> Result<()> {
+    let num_trials = matches
+        .value_of("hyper")
+        .unwrap()
+        .parse()
+        .context("--hyper expects a number of trials")?;
+    let sampling = match matches.value_of("sampling") {
+        Some(sampling) => sampling.parse()?,
+        None => hyper::Sampling::default(),
+    };
+    let algorithms = config.algorithms.clone();
+    let warm_start = match matches.value_of("warm-start") {
+        Some(path) => hyper::load_results(path)?,
+        None => Vec::new(),
+    };
+    let search_spaces = match matches.value_of("search-space") {
+        Some(path) => hyper::SearchSpaces::load(path)?,
+        None => hyper::SearchSpaces::default(),
+    };
+    let screening = if matches.is_present("screen") || matches.is_present("screen-cities") {
+        let fidelity: f64 = matches
+            .value_of("screen")
+            .map_or(Ok(1.0), str::parse)
+            .context("invalid --screen")?;
+        if !(fidelity > 0.0 && fidelity <= 1.0) {
+            bail!("--screen expects a fraction in (0, 1], got {}", fidelity);
+        }
+        Some(hyper::Screening {
+            fidelity,
+            cities: matches
+                .value_of("screen-cities")
+                .map(|n| n.parse().context("invalid --screen-cities"))
+                .transpose()?,
+            finalists: matches
+                .value_of("finalists")
+                .map_or(Ok(10), str::parse)
+                .context("invalid --finalists")?,
+        })
+    } else {
+        None
+    };
+    println!(
+        "Running hyperparameter optimization with {} trials...",
+        num_trials
+    );
+
+    let results = hyper::optimize_hyperparameters(
+        tsp,
+        &hyper::HyperOptions {
+            trials: num_trials,
+            algorithms,
+            sampling,
+            search_spaces,
+            evals_per_trial: matches
+                .value_of("evals-per-trial")
+                .map_or(Ok(1), str::parse)
+                .context("invalid --evals-per-trial")?,
+            successive_halving: matches.is_present("successive-halving"),
+            trial_time_limit: duration_arg(matches, "trial-time-limit")?,
+            time_budget: duration_arg(matches, "time-budget")?,
+            journal: Some(
+                matches
+                    .value_of("journal")
+                    .unwrap_or("hyper_trials.jsonl")
+                    .into(),
+            ),
+            resume: matches.is_present("resume"),
+            concurrent_trials: matches
+                .value_of("concurrent-trials")
+                .map(|n| n.parse().context("invalid --concurrent-trials"))
+                .transpose()?,
+            threads_per_trial: matches
+                .value_of("threads-per-trial")
+                .map_or(Ok(1), str::parse)
+                .context("invalid --threads-per-trial")?,
+            seed: config.seed,
+            warm_start,
+            pruning: matches.is_present("prune"),
+            cma_es: matches.is_present("cma-es"),
+            screening,
+            workers: connect_workers(matches)?,
+            evaluator: evaluator.clone(),
+        },
+    )?;
+
+    if let Some(path) = matches.value_of("store") {
+        store::append(Path::new(path), &StoredRun::from_trials(&results, tsp)?)?;
+    }
+    hyper::export_results(&results, "hyper_results")?;
+    hyper::export_best_config(&results, &tsp.name, "hyper_best.toml")?;
+    println!(
+        "Best configurations written to hyper_best.toml, run them with --config hyper_best.toml"
     );
+
+    plot::plot_hyper_results(&results, &config.plot)?;
+    if matches.is_present("pareto") {
+        plot::plot_pareto_front(&results, &config.plot)?;
+    }
+    write_report(&config.plot)?;
+
+    let mut current_algo = String::new();
+    for result in &results {
+        if result.algorithm != current_algo {
+            current_algo = result.algorithm.clone();
+            println!("\nBest parameters for {}:", current_algo);
+            println!(
+                "Distance: {:.1} ± {:.1} (best {} over {} runs){}",
+                result.mean_distance,
+                result.std_distance,
+                result.distance,
+                result.evaluations,
+                stats::gap(result.mean_distance, tsp).map_or(String::new(), |gap| format!(
+                    ", mean gap {}",
+                    stats::format_gap(gap)
+                ))
+            );
+            println!("Runtime: {}ms", result.runtime_ms);
+            println!("Parameters: {}", result.parameters);
+            println!("Seed: {}", result.seed);
+        }
+    }
+
+    Ok(())
+}
+
+/// What `solve_instance` gathers from each solver of an instance for the
+/// plots that compare them and for the instance's record.
+struct SolverResults<'a> {
+    /// Tour length the run-length distributions count as success.
+    target: Option<u64>,
+    record: experiment::InstanceRecord,
+    tours: Vec<PathBuf>,
+    best_routes: Vec<(&'a str, tsplib::Route, RGBColor)>,
+    distributions: Vec<(&'a str, Vec<u64>)>,
+    anytime_curves: Vec<plot::AnytimeCurve<'a>>,
+    run_length_curves: Vec<plot::RunLengthCurve<'a>>,
+}
+
+impl<'a> SolverResults<'a> {
+    fn new(tsp: &TspLib) -> Self {
+        SolverResults {
+            target: stats::target_distance(tsp, stats::SUCCESS_GAP),
+            record: experiment::InstanceRecord::new(tsp),
+            tours: Vec::new(),
+            best_routes: Vec::new(),
+            distributions: Vec::new(),
+            anytime_curves: Vec::new(),
+            run_length_curves: Vec::new(),
+        }
+    }
+
+    /// Records `runs` of the solver `name` configured by `spec`, whose best
+    /// run is `solver`: writes its best tour and its history with `metrics`
+    /// and keeps what the comparison plots need.
+    #[allow(clippy::too_many_arguments)]
+    fn add(
+        &mut self,
+        name: &'a str,
+        spec: &config::SolverSpec,
+        solver: &dyn HeuristicAlgorithm,
+        runs: &[stats::RunStats],
+        style: RGBColor,
+        metrics: &[stats::Metric],
+        tsp: &TspLib,
+        options: &RunOptions,
+    ) -> Result<()> {
+        self.distributions.push((name, final_distances(runs)));
+        self.record
+            .algorithms
+            .push(experiment::AlgorithmRecord::new(
+                name,
+                spec,
+                runs,
+                options.seed,
+            )?);
+        self.tours
+            .push(export_best_tour(name, solver, tsp, &options.plot)?);
+        if let Some(target) = self.target {
+            let rld = stats::run_length_distribution(runs, target);
+            self.run_length_curves.push((name, rld.by_time, style));
+        }
+        self.best_routes
+            .push((name, solver.get_best_route().clone(), style));
+        self.anytime_curves
+            .push((name, stats::anytime_curve(solver), style));
+        export_history(options.history_csv.as_deref(), name, solver, tsp, metrics)
+    }
+}
+
+/// Solves `tsp` with every solver of `config.algorithms`, or as a pipeline or
+/// cooperatively, and plots the results. When the solvers ran on their own,
+/// writes their best tours and returns the instance's record for
+/// `--experiment-json` and its rows of `results.json`.
+fn solve_instance(
+    tsp: &TspLib,
+    matches: &ArgMatches,
+    config: &Config,
+    evaluator: &Evaluator,
+    cancellation: &CancellationToken,
+) -> Result<Option<(experiment::InstanceRecord, Vec<experiment::ResultRow>)>> {
+    let colors = config.plot.series_colors().context("invalid palette")?;
+    let color = |index: usize| colors[index % colors.len()];
+
+    let mut observers: Vec<Arc<dyn Observer>> = Vec::new();
+    if !matches.is_present("quiet") {
+        observers.push(Arc::new(
+            ConsoleProgress::new(CONSOLE_PROGRESS_INTERVAL)
+                .with_optimum(tsp.optimal_tour_length)
+                .verbose(matches.is_present("verbose")),
+        ));
+    }
+    if let Some(path) = matches.value_of("progress-ndjson") {
+        observers.push(Arc::new(NdjsonProgress::open(path)?));
+    }
+    if let Some(every) = matches.value_of("live") {
+        let every: usize = every.parse().context("--live expects a positive integer")?;
+        #[cfg(feature = "live")]
+        observers.push(Arc::new(plot::live::LiveView::open(
+            config.plot.animation_width as usize,
+            config.plot.animation_height as usize,
+            every,
+            tsp,
+        )?));
+        #[cfg(not(feature = "live"))]
+        bail!("--live {} requires a build with `--features live`", every);
+    }
+    let options = RunOptions {
+        observers,
+        plot: config.plot.clone(),
+        runs: config.runs.unwrap_or(1),
+        seed: config.seed,
+        history_csv: matches.value_of("history-csv").map(PathBuf::from),
+        termination: Termination {
+            time_limit: duration_arg(matches, "time-limit")?,
+            max_iterations: matches
+                .value_of("max-iterations")
+                .map(|n| n.parse())
+                .transpose()
+                .context("--max-iterations expects a positive integer")?,
+            target_distance: matches
+                .value_of("target")
+                .map(|distance| distance.parse())
+                .transpose()
+                .context("--target expects a tour length")?,
+            target_gap: percent_arg(matches, "target-gap")?,
+            stall_iterations: matches
+                .value_of("stall-iterations")
+                .map(|n| n.parse())
+                .transpose()
+                .context("--stall-iterations expects a positive integer")?,
+            cancellation: Some(cancellation.clone()),
+        },
+        archive: matches
+            .value_of("archive")
+            .map(|k| k.parse())
+            .transpose()
+            .context("--archive expects a positive integer")?,
+        evaluator: evaluator.clone(),
+        checkpointing: matches
+            .value_of("checkpoint")
+            .map(|path| -> Result<Checkpointing> {
+                Ok(Checkpointing {
+                    path: PathBuf::from(path),
+                    every: matches
+                        .value_of("checkpoint-every")
+                        .map_or(Ok(100), str::parse)
+                        .ok()
+                        .filter(|&every| every > 0)
+                        .context("--checkpoint-every expects a positive integer")?,
+                })
+            })
+            .transpose()?,
+        resume: matches.value_of("resume-run").map(PathBuf::from),
+        initial_tour: matches
+            .value_of("initial-tour")
+            .map(|path| tsplib::read_tour_file(path, tsp.dimension))
+            .transpose()?,
+    };
+    if (options.checkpointing.is_some() || options.resume.is_some())
+        && (matches.is_present("pipeline")
+            || matches.is_present("cooperative")
+            || config.algorithms.len() != 1)
+    {
+        bail!("--checkpoint and --resume-run follow a single solver, pick it with --algorithms");
+    }
+    if matches.is_present("pipeline") {
+        let pipeline = match matches.value_of("pipeline") {
+            Some(chain) => parse_chain(tsp, chain, &config.solvers)?,
+            None if config.pipeline.is_empty() => {
+                bail!("--pipeline needs a CHAIN of algorithms or [[pipeline]] stages in --config")
+            }
+            None => pipeline::Pipeline::new(tsp, config.pipeline.clone()),
+        };
+        run_pipeline(tsp, pipeline, &color(0), &options)?;
+        write_report(&options.plot)?;
+        return Ok(None);
+    }
+
+    if matches.is_present("cooperative") {
+        run_cooperative(tsp, &config.solvers, &colors, &options)?;
+        write_report(&options.plot)?;
+        return Ok(None);
+    }
+
+    let algorithms = &config.algorithms;
+    let mut results = SolverResults::new(tsp);
+    for (index, &construction) in config.constructions.iter().enumerate() {
+        let style = color(algorithms.len() + index);
+        let route = run_construction(construction, tsp, &style, &options.plot)?;
+        results
+            .best_routes
+            .push((construction.name(), route, style));
+    }
+    let config = &config.solvers;
+
+    if algorithms.contains(&hyper::Algorithm::Aco) {
+        let name = "Ant Colony Optimization";
+        let (aco, runs) = run_algorithm::<aco::AntColonyOptimization>(
+            &config.aco,
+            name,
+            tsp,
+            &color(0),
+            &options,
+        )?;
+        let spec = config.spec(hyper::Algorithm::Aco);
+        results.add(name, &spec, &aco, &runs, color(0), &[], tsp, &options)?;
+        plot::plot_pheromone_snapshots(aco.get_pheromone_snapshots(), tsp, name, &options.plot)?;
+    }
+
+    if algorithms.contains(&hyper::Algorithm::Sa) {
+        let name = "Simulated Annealing";
+        let (sa, runs) =
+            run_algorithm::<sa::SimulatedAnnealing>(&config.sa, name, tsp, &color(1), &options)?;
+        let epochs = sa.get_epoch_stats();
+        let metrics = [
+            (
+                "temperature",
+                epochs.iter().map(|e| e.temperature).collect(),
+            ),
+            (
+                "acceptance_rate",
+                epochs.iter().map(|e| e.acceptance_rate).collect(),
+            ),
+        ];
+        let spec = config.spec(hyper::Algorithm::Sa);
+        results.add(name, &spec, &sa, &runs, color(1), &metrics, tsp, &options)?;
+        plot::plot_sa_schedule(sa.get_history(), epochs, name, &options.plot)?;
+    }
+
+    if algorithms.contains(&hyper::Algorithm::Ga) {
+        let name = "Genetic Algorithm";
+        let (ga, runs) =
+            run_algorithm::<ga::GeneticAlgorithm>(&config.ga, name, tsp, &color(2), &options)?;
+        let generations = ga.get_generation_stats();
+        let metrics = [
+            (
+                "population_average",
+                generations.iter().map(|g| g.average).collect(),
+            ),
+            (
+                "population_worst",
+                generations.iter().map(|g| g.worst as f64).collect(),
+            ),
+            (
+                "diversity",
+                generations.iter().map(|g| g.diversity).collect(),
+            ),
+            (
+                "distinct_tours",
+                generations
+                    .iter()
+                    .map(|g| g.diversity_measures.distinct_tours as f64)
+                    .collect(),
+            ),
+            (
+                "pairwise_distance",
+                generations
+                    .iter()
+                    .map(|g| g.diversity_measures.pairwise_distance)
+                    .collect(),
+            ),
+            (
+                "edge_entropy",
+                generations
+                    .iter()
+                    .map(|g| g.diversity_measures.edge_entropy)
+                    .collect(),
+            ),
+        ];
+        let spec = config.spec(hyper::Algorithm::Ga);
+        results.add(name, &spec, &ga, &runs, color(2), &metrics, tsp, &options)?;
+        plot::plot_ga_population(generations, name, &options.plot)?;
+    }
+
+    if algorithms.contains(&hyper::Algorithm::Pso) {
+        let name = "Particle Swarm Optimization";
+        let (pso, runs) = run_algorithm::<pso::ParticleSwarmOptimization>(
+            &config.pso,
+            name,
+            tsp,
+            &color(3),
+            &options,
+        )?;
+        let swarm = pso.get_swarm_stats();
+        let metrics = [
+            (
+                "mean_fitness",
+                swarm.iter().map(|s| s.mean_fitness).collect(),
+            ),
+            ("diversity", swarm.iter().map(|s| s.diversity).collect()),
+            (
+                "distinct_tours",
+                swarm
+                    .iter()
+                    .map(|s| s.diversity_measures.distinct_tours as f64)
+                    .collect(),
+            ),
+            (
+                "pairwise_distance",
+                swarm
+                    .iter()
+                    .map(|s| s.diversity_measures.pairwise_distance)
+                    .collect(),
+            ),
+            (
+                "edge_entropy",
+                swarm
+                    .iter()
+                    .map(|s| s.diversity_measures.edge_entropy)
+                    .collect(),
+            ),
+        ];
+        let spec = config.spec(hyper::Algorithm::Pso);
+        results.add(name, &spec, &pso, &runs, color(3), &metrics, tsp, &options)?;
+        plot::plot_pso_swarm(pso.get_history(), swarm, name, &options.plot)?;
+    }
+
+    if algorithms.contains(&hyper::Algorithm::Tabu) {
+        let name = "Tabu Search";
+        let (tabu, runs) =
+            run_algorithm::<tabu::TabuSearch>(&config.tabu, name, tsp, &color(4), &options)?;
+        let iterations = tabu.get_tabu_stats();
+        let metrics = [
+            (
+                "current_distance",
+                iterations
+                    .iter()
+                    .map(|s| s.current_distance as f64)
+                    .collect(),
+            ),
+            (
+                "tabu_rate",
+                iterations.iter().map(|s| s.tabu_rate).collect(),
+            ),
+        ];
+        let spec = config.spec(hyper::Algorithm::Tabu);
+        results.add(name, &spec, &tabu, &runs, color(4), &metrics, tsp, &options)?;
+        plot::plot_tabu_search(tabu.get_history(), iterations, name, &options.plot)?;
+    }
+
+    let SolverResults {
+        record,
+        tours,
+        best_routes,
+        distributions,
+        anytime_curves,
+        run_length_curves,
+        ..
+    } = results;
+    plot::plot_best_routes_grid(&best_routes, tsp, &options.plot)?;
+    plot::plot_anytime_curves(&anytime_curves, tsp, &options.plot)?;
+    let metrics: Vec<(&str, stats::AggregateStats)> = record
+        .algorithms
+        .iter()
+        .map(|algorithm| {
+            let runs: Vec<stats::RunStats> =
+                algorithm.runs.iter().map(|run| run.stats.clone()).collect();
+            (algorithm.name.as_str(), stats::aggregate(&runs))
+        })
+        .collect();
+    plot::plot_run_metrics(&metrics, &options.plot)?;
+    if options.runs > 1 {
+        plot::plot_distribution(&distributions, &options.plot)?;
+        plot::plot_run_length_distributions(&run_length_curves, stats::SUCCESS_GAP, &options.plot)?;
+    }
+    write_report(&options.plot)?;
+
+    let rows = record
+        .algorithms
+        .iter()
+        .zip(tours)
+        .filter_map(|(algorithm, tour)| experiment::ResultRow::best(&record, algorithm, Some(tour)))
+        .collect();
+    Ok(Some((record, rows)))
+}
+
+/// Builds a tour of `tsp` with `construction` from city 0, reports it like a
+/// solver's best route and writes it to `{output_dir}/{instance}_{name}.tour`.
+fn run_construction(
+    construction: Construction,
+    tsp: &TspLib,
+    style: &RGBColor,
+    plot: &plot::PlotConfig,
+) -> Result<tsplib::Route> {
+    let start = Instant::now();
+    let route = tsplib::Route::from_tour(&construction.build(tsp, 0), tsp);
+    let run_time = start.elapsed().as_millis();
+    let name = construction.name();
     println!(
-        "{} Run Time: {}ms\n\n",
+        "\n{} Best Route: {} in {}ms{}",
         name.bold().rgb(style.0, style.1, style.2),
-        run_time
+        route.distance,
+        run_time,
+        stats::gap(route.distance as f64, tsp).map_or(String::new(), |gap| format!(
+            ", gap {}",
+            stats::format_gap(gap)
+        ))
     );
-    plot::plot_algo_result(&algorithm, name, style).unwrap();
+    tsplib::write_tour_file(
+        &plot.output_file(name, ".tour")?,
+        tsp,
+        &route.tour,
+        &format!("{} tour of length {}", name, route.distance),
+    )?;
+    Ok(route)
+}
+
+/// Writes the best tour of `algorithm` to `{output_dir}/{instance}_{name}.tour`.
+fn export_best_tour(
+    name: &str,
+    algorithm: &dyn HeuristicAlgorithm,
+    tsp: &TspLib,
+    plot: &plot::PlotConfig,
+) -> Result<PathBuf> {
+    let route = algorithm.get_best_route();
+    let path = plot.output_file(name, ".tour")?;
+    tsplib::write_tour_file(
+        &path,
+        tsp,
+        &route.tour,
+        &format!("{} tour of length {}", name, route.distance),
+    )?;
+    Ok(path)
+}
+
+/// A token cancelled by the first Ctrl-C, so the solvers return the best
+/// routes found so far and the results are still written; a second Ctrl-C
+/// quits straight away.
+fn cancel_on_ctrl_c() -> Result<CancellationToken> {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(130);
+        }
+        eprintln!(
+            "Interrupted, finishing with the best routes found so far (Ctrl-C again to quit)"
+        );
+        handler_token.cancel();
+    })
+    .context("failed to install the Ctrl-C handler")?;
+
+    Ok(token)
+}
+
+/// Where solvers evaluate their batches of tours: on the GPU with `--gpu`,
+/// unless none is found.
+fn evaluator(matches: &ArgMatches) -> Result<Evaluator> {
+    if !matches.is_present("gpu") {
+        return Ok(Evaluator::Cpu);
+    }
+    if cfg!(not(feature = "gpu")) {
+        bail!("--gpu requires a build with `--features gpu`");
+    }
+    Ok(Evaluator::gpu().unwrap_or_else(|err| {
+        eprintln!("Warning: {:#}, evaluating on the CPU", err);
+        Evaluator::Cpu
+    }))
+}
+
+/// Exports the solvers' spans and events as asked with `--trace` and
+/// `--otlp`, until the returned guard is dropped.
+fn telemetry(matches: &ArgMatches) -> Result<telemetry::Telemetry> {
+    let matches = matches.subcommand().map_or(matches, |(_, matches)| matches);
+    let level = matches
+        .value_of("trace-level")
+        .map_or(Ok(LevelFilter::DEBUG), str::parse)
+        .context("--trace-level expects off, error, warn, info, debug or trace")?;
+    telemetry::init(&telemetry::TraceOptions {
+        chrome: matches.value_of("trace").map(PathBuf::from),
+        otlp: matches.value_of("otlp").map(str::to_string),
+        level,
+    })
+}
+
+/// Connects to the workers listed with `--workers`, if any.
+fn connect_workers(matches: &ArgMatches) -> Result<Option<Arc<distributed::Cluster>>> {
+    let Some(list) = matches.value_of("workers") else {
+        return Ok(None);
+    };
+    let addresses: Vec<String> = list.split(',').map(|a| a.trim().to_string()).collect();
+    Ok(Some(Arc::new(distributed::Cluster::connect(&addresses)?)))
+}
+
+/// Writes the PDF report of every figure plotted so far, if one was requested.
+fn write_report(config: &plot::PlotConfig) -> Result<()> {
+    if !config.report {
+        return Ok(());
+    }
+    #[cfg(feature = "pdf")]
+    plot::write_report(config)?;
+
+    Ok(())
+}
+
+/// Parses an optional `--name SECONDS` argument.
+fn duration_arg(matches: &ArgMatches, name: &str) -> Result<Option<Duration>> {
+    matches
+        .value_of(name)
+        .map(|duration| {
+            parse_duration(duration)
+                .with_context(|| format!("--{} expects a duration such as 30s, 2m or 500ms", name))
+        })
+        .transpose()
+}
+
+/// A duration of a number with a unit of `ms`, `s`, `m`, `h` or `d`, or in
+/// seconds without one.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let split = duration
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(duration.len());
+    let (number, unit) = duration.split_at(split);
+    let number: f64 = number.trim().parse().ok()?;
+    let seconds = match unit {
+        "ms" => number / 1000.0,
+        "" | "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        "d" => number * 86_400.0,
+        _ => return None,
+    };
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Parses an optional `--name PERCENT` argument, with or without a `%`.
+fn percent_arg(matches: &ArgMatches, name: &str) -> Result<Option<f64>> {
+    matches
+        .value_of(name)
+        .map(|percent| {
+            percent
+                .trim_end_matches('%')
+                .parse()
+                .ok()
+                .filter(|percent: &f64| *percent >= 0.0)
+                .with_context(|| format!("--{} expects a non-negative percentage", name))
+        })
+        .transpose()
+}
+
+/// The algorithms listed with `--algorithms`, else those of the config, else
+/// all of them.
+fn algorithms_arg(matches: &ArgMatches, config: &Config) -> Result<Vec<hyper::Algorithm>> {
+    match matches.value_of("algorithms") {
+        Some(list) => list
+            .split(',')
+            .filter(|name| name.parse::<Construction>().is_err())
+            .map(str::parse)
+            .collect(),
+        None if !config.algorithms.is_empty() => Ok(config.algorithms.clone()),
+        None => Ok(hyper::Algorithm::ALL.to_vec()),
+    }
+}
+
+/// The construction heuristics named among `--algorithms`, e.g. `greedy`.
+fn constructions_arg(matches: &ArgMatches, config: &Config) -> Vec<Construction> {
+    match matches.value_of("algorithms") {
+        Some(list) => list
+            .split(',')
+            .filter_map(|name| name.parse().ok())
+            .collect(),
+        None => config.constructions.clone(),
+    }
+}
+
+fn instance_path(instance: &str) -> String {
+    if instance.ends_with(".tsp") || instance.ends_with(".atsp") {
+        instance.to_string()
+    } else {
+        format!("instances/{}.tsp", instance)
+    }
 }
 
 fn main() -> Result<()> {
+    // A flag for every solver parameter, e.g. --aco-alpha for aco.alpha.
+    let parameter_flags: Vec<(String, String, &str)> = config::SolverConfig::PARAMETERS
+        .iter()
+        .map(|&key| {
+            (
+                key.replace(['.', '_'], "-"),
+                format!(
+                    "Set {} of the solver config (like --param {}=VALUE)",
+                    key, key
+                ),
+                key,
+            )
+        })
+        .collect();
     let matches = App::new("TSP Solver")
+        .args(
+            parameter_flags.iter().map(|(flag, help, _)| {
+                Arg::with_name(flag.as_str())
+                    .long(flag.as_str())
+                    .help(help.as_str())
+                    .takes_value(true)
+                    .value_name("VALUE")
+            }),
+        )
         .arg(
             Arg::with_name("instance")
                 .help("TSP instance name")
@@ -48,59 +1246,1130 @@ fn main() -> Result<()> {
                 .takes_value(true)
                 .value_name("TRIALS"),
         )
+        .arg(
+            Arg::with_name("algorithms")
+                .long("algorithms")
+                .alias("algo")
+                .help("Comma-separated algorithms to run, or to tune with --hyper (aco, sa, ga, pso, tabu; default: all), and construction heuristics whose tours to report first (nearest_neighbor, greedy, cheapest_insertion, farthest_insertion, christofides)")
+                .takes_value(true)
+                .value_name("LIST"),
+        )
+        .arg(
+            Arg::with_name("sampling")
+                .long("sampling")
+                .help("How --hyper draws trial configurations: random, lhs (Latin hypercube) or grid")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("METHOD"),
+        )
+        .arg(
+            Arg::with_name("successive-halving")
+                .long("successive-halving")
+                .help("Screen --hyper configurations on short runs and give only the best full budgets")
+                .requires("hyper"),
+        )
+        .arg(
+            Arg::with_name("cma-es")
+                .long("cma-es")
+                .help("Draw --hyper configurations with CMA-ES, adapting to the trials finished so far")
+                .requires("hyper")
+                .conflicts_with("successive-halving"),
+        )
+        .arg(
+            Arg::with_name("screen")
+                .long("screen")
+                .help("Screen --hyper configurations at FRACTION of their iterations before re-running the best at full budget")
+                .takes_value(true)
+                .requires("hyper")
+                .conflicts_with_all(&["successive-halving", "cma-es"])
+                .value_name("FRACTION"),
+        )
+        .arg(
+            Arg::with_name("screen-cities")
+                .long("screen-cities")
+                .help("Screen --hyper configurations on a random sub-instance of N cities")
+                .takes_value(true)
+                .requires("hyper")
+                .conflicts_with_all(&["successive-halving", "cma-es"])
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("finalists")
+                .long("finalists")
+                .help("Screened configurations per algorithm re-run at full budget (default: 10)")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("trial-time-limit")
+                .long("trial-time-limit")
+                .help("Stop each --hyper solver run after SECONDS")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("time-budget")
+                .long("time-budget")
+                .help("Stop starting --hyper trials after SECONDS and report the results so far")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("SECONDS"),
+        )
+        .arg(
+            Arg::with_name("search-space")
+                .long("search-space")
+                .help("TOML file overriding the parameter distributions --hyper samples from")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("workers")
+                .long("workers")
+                .help("Comma-separated HOST:PORT of `sapso worker`s to send the --hyper solver runs to")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("LIST"),
+        )
+        .arg(
+            Arg::with_name("concurrent-trials")
+                .long("concurrent-trials")
+                .visible_alias("jobs")
+                .help("Number of --hyper trials run at once (default: cores / threads per trial)")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("threads-per-trial")
+                .long("threads-per-trial")
+                .help("Threads each --hyper solver run may use (default: 1)")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("evals-per-trial")
+                .long("evals-per-trial")
+                .help("Runs per --hyper configuration; trials are ranked by mean distance (default: 1)")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("pareto")
+                .long("pareto")
+                .help("Also plot each --hyper algorithm's Pareto front of distance vs runtime")
+                .requires("hyper"),
+        )
+        .arg(
+            Arg::with_name("prune")
+                .long("prune")
+                .help("Stop --hyper trials that converge far worse than the median trial so far")
+                .requires("hyper"),
+        )
+        .arg(
+            Arg::with_name("warm-start")
+                .long("warm-start")
+                .help("Results of an earlier --hyper run (hyper_results.json or a journal) to skip and refine around")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("journal")
+                .long("journal")
+                .help("JSONL file --hyper logs finished trials to (default: hyper_trials.jsonl)")
+                .takes_value(true)
+                .requires("hyper")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .help("Continue an interrupted --hyper run from its journal")
+                .requires("hyper")
+                .conflicts_with_all(&["successive-halving", "screen", "screen-cities"]),
+        )
+        .arg(
+            Arg::with_name("initial-tour")
+                .long("initial-tour")
+                .help("Start every solver from the tour in a TSPLIB .tour FILE, e.g. another solver's result")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("checkpoint")
+                .long("checkpoint")
+                .help("Save the solver's run to FILE as it goes and when interrupted, for --resume-run")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("checkpoint-every")
+                .long("checkpoint-every")
+                .help("Iterations between two --checkpoint saves (default: 100)")
+                .takes_value(true)
+                .requires("checkpoint")
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("resume-run")
+                .long("resume-run")
+                .help("Continue the solver run saved in a --checkpoint FILE")
+                .takes_value(true)
+                .conflicts_with("hyper")
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("config")
+                .long("config")
+                .help("TOML or YAML file with the instances, algorithms, seed, runs, solver and plot settings")
+                .takes_value(true)
+                .global(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("param")
+                .long("param")
+                .help("Override a solver parameter, e.g. aco.ants=200")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .value_name("KEY=VALUE"),
+        )
+        .arg(
+            Arg::with_name("progress-ndjson")
+                .long("progress-ndjson")
+                .help("Stream per-iteration progress as NDJSON to FILE (`-` for stdout)")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("quiet")
+                .long("quiet")
+                .help("Do not print solver progress or status lines to the console")
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("verbose")
+                .long("verbose")
+                .help("Print every solver iteration, with the algorithm's own statistics")
+                .conflicts_with("quiet"),
+        )
+        .arg(
+            Arg::with_name("animate")
+                .long("animate")
+                .help("Render an animated GIF of the best route, sampling every STEP iterations")
+                .takes_value(true)
+                .value_name("STEP"),
+        )
+        .arg(
+            Arg::with_name("animation-fps")
+                .long("animation-fps")
+                .help("Frames per second of the --animate GIF")
+                .takes_value(true)
+                .value_name("FPS"),
+        )
+        .arg(
+            Arg::with_name("animation-frames")
+                .long("animation-frames")
+                .help("Write --animate as numbered PNG frames instead of a GIF"),
+        )
+        .arg(
+            Arg::with_name("log-y")
+                .long("log-y")
+                .help("Use a logarithmic distance axis in convergence charts"),
+        )
+        .arg(
+            Arg::with_name("log-x")
+                .long("log-x")
+                .help("Use a logarithmic iteration axis in convergence charts"),
+        )
+        .arg(
+            Arg::with_name("overlay-optimal")
+                .long("overlay-optimal")
+                .help("Draw the known optimal tour under each algorithm's best route"),
+        )
+        .arg(
+            Arg::with_name("html")
+                .long("html")
+                .help("Also write interactive HTML charts for each algorithm"),
+        )
+        .arg(
+            Arg::with_name("city-labels")
+                .long("city-labels")
+                .help("Label cities with their index and mark the tour's start and direction"),
+        )
+        .arg(
+            Arg::with_name("live")
+                .long("live")
+                .help("Show a window redrawing the best route every K iterations (needs the `live` feature)")
+                .takes_value(true)
+                .value_name("K"),
+        )
+        .arg(
+            Arg::with_name("edge-colors")
+                .long("edge-colors")
+                .help("Color best-route edges by length, from short (blue) to long (red)"),
+        )
+        .arg(
+            Arg::with_name("csv")
+                .long("csv")
+                .help("Also write the data behind every chart as CSV next to the image"),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("Image format of the figures")
+                .takes_value(true)
+                .possible_values(["png", "svg"])
+                .value_name("FORMAT"),
+        )
+        .arg(
+            Arg::with_name("dpi")
+                .long("dpi")
+                .help("Resolution of PNG figures, scaling their size, fonts and lines [default: 96]")
+                .takes_value(true)
+                .value_name("DPI"),
+        )
+        .arg(
+            Arg::with_name("theme")
+                .long("theme")
+                .help("Color theme of the figures")
+                .takes_value(true)
+                .possible_values(["light", "dark"])
+                .value_name("THEME"),
+        )
+        .arg(
+            Arg::with_name("palette")
+                .long("palette")
+                .help("Comma-separated #rrggbb colors given to the algorithms in turn")
+                .takes_value(true)
+                .value_name("COLORS"),
+        )
+        .arg(
+            Arg::with_name("annotate")
+                .long("annotate")
+                .help("Show the instance, parameters, distance, gap and runtime on route and history plots"),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .help("Also bundle every figure into a multi-page PDF report (needs the `pdf` feature)"),
+        )
+        .arg(
+            Arg::with_name("archive")
+                .long("archive")
+                .help("Keep the K shortest structurally distinct tours of each algorithm's best run and write them as JSON")
+                .takes_value(true)
+                .value_name("K"),
+        )
+        .arg(
+            Arg::with_name("pipeline")
+                .long("pipeline")
+                .help("Run the algorithms in CHAIN one after another, each starting from the best tour so far, e.g. 'greedy -> sa(10s) -> ga(30s) -> lk': an optional nearest-neighbour start, solvers with optional time limits, and an optional final local search; without CHAIN, the [[pipeline]] stages of --config")
+                .takes_value(true)
+                .min_values(0)
+                .value_name("CHAIN"),
+        )
+        .arg(
+            Arg::with_name("cooperative")
+                .long("cooperative")
+                .help("Run the algorithms concurrently, sharing their best routes through a common pool"),
+        )
+        .arg(
+            Arg::with_name("gpu")
+                .long("gpu")
+                .help("Evaluate batches of GA offspring, PSO particles and ACO ant tours on the GPU, falling back to the CPU without one"),
+        )
+        .arg(
+            Arg::with_name("mmap-distances")
+                .long("mmap-distances")
+                .help("Keep the instance's distance matrix in FILE, memory-mapped and shared by every thread, building FILE if it does not hold the matrix yet")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("distances")
+                .long("distances")
+                .help("Keep every distance (dense), each distance once (triangular), or none, computing them from the coordinates (computed); auto picks by the number of cities")
+                .takes_value(true)
+                .possible_values(["auto", "dense", "triangular", "computed"])
+                .default_value("auto")
+                .value_name("STORAGE"),
+        )
+        .arg(
+            Arg::with_name("runs")
+                .long("runs")
+                .help("Run each algorithm N times and plot the spread of their convergence")
+                .takes_value(true)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("time-limit")
+                .long("time-limit")
+                .help("Stop each run after DURATION, e.g. 30s, 2m or 500ms (bare numbers are seconds), keeping the best route so far")
+                .takes_value(true)
+                .value_name("DURATION"),
+        )
+        .arg(
+            Arg::with_name("max-iterations")
+                .long("max-iterations")
+                .help("Stop each run after N iterations (epochs for SA, generations for GA)")
+                .takes_value(true)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("target")
+                .long("target")
+                .help("Stop each run as soon as it finds a route of at most DISTANCE")
+                .takes_value(true)
+                .value_name("DISTANCE"),
+        )
+        .arg(
+            Arg::with_name("benchmark")
+                .long("benchmark")
+                .help("Instead of plotting single runs, run each algorithm N times per instance and report their statistics, like `sapso bench --runs N`")
+                .takes_value(true)
+                .value_name("N")
+                .conflicts_with_all(&["hyper", "pipeline", "cooperative"]),
+        )
+        .arg(
+            Arg::with_name("success-gap")
+                .long("success-gap")
+                .help("With --benchmark, count runs within PERCENT of the optimum as successes (default: 5%)")
+                .takes_value(true)
+                .value_name("PERCENT")
+                .requires("benchmark"),
+        )
+        .arg(
+            Arg::with_name("target-gap")
+                .long("target-gap")
+                .help("Stop each run as soon as it is within PERCENT of the known optimum, e.g. 2%")
+                .takes_value(true)
+                .value_name("PERCENT"),
+        )
+        .arg(
+            Arg::with_name("stall-iterations")
+                .long("stall-iterations")
+                .help("Stop each run after N iterations in a row without a shorter route")
+                .takes_value(true)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("history-csv")
+                .long("history-csv")
+                .help("Write each algorithm's per-iteration history of its best run as CSV into DIR")
+                .takes_value(true)
+                .value_name("DIR"),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .help("Seed the solvers (run k of --runs uses N + k - 1) or, with --hyper, the whole search")
+                .takes_value(true)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("threads")
+                .long("threads")
+                .help("Threads the solvers build and evaluate tours on (default: one per core)")
+                .takes_value(true)
+                .value_name("N"),
+        )
+        .arg(
+            Arg::with_name("experiment-json")
+                .long("experiment-json")
+                .help("Write every run's configuration, seed, statistics and history to FILE as JSON")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("store")
+                .long("store")
+                .help("Append every run's instance, algorithm, parameters, seed, distances, runtime and best distance per iteration to FILE, one JSON document per line, for `sapso report`")
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("output-dir")
+                .long("output-dir")
+                .help("Directory plots, best tours (TSPLIB .tour) and results.json/results.csv are written to (default: results)")
+                .takes_value(true)
+                .value_name("DIR"),
+        )
+        .arg(
+            Arg::with_name("trace")
+                .long("trace")
+                .help("Write the solvers' spans and events to FILE as a Chrome trace (needs the `trace` feature)")
+                .takes_value(true)
+                .global(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("otlp")
+                .long("otlp")
+                .help("Export the solvers' spans and events to the OTLP collector at URL, e.g. http://localhost:4317 (needs the `otlp` feature)")
+                .takes_value(true)
+                .global(true)
+                .value_name("URL"),
+        )
+        .arg(
+            Arg::with_name("trace-level")
+                .long("trace-level")
+                .help("Most verbose level --trace and --otlp export: info for runs, improvements and operator counts, debug (the default) to add every step")
+                .takes_value(true)
+                .global(true)
+                .value_name("LEVEL"),
+        )
+        .subcommand(
+            App::new("run")
+                .about("Run the experiment a --config file describes: its instances, algorithms, parameters, seed, runs and output directory"),
+        )
+        .subcommand(
+            App::new("bench")
+                .about("Run the algorithms over a set of instances and write a Markdown and HTML report")
+                .arg(
+                    Arg::with_name("instances")
+                        .long("instances")
+                        .help("Comma-separated instance names or paths (default: berlin52, kroA100, a280, pcb442)")
+                        .takes_value(true)
+                        .value_name("LIST"),
+                )
+                .arg(
+                    Arg::with_name("algorithms")
+                        .long("algorithms")
+                        .help("Comma-separated algorithms to run (aco, sa, ga, pso, tabu; default: all)")
+                        .takes_value(true)
+                        .value_name("LIST"),
+                )
+                .arg(
+                    Arg::with_name("runs")
+                        .long("runs")
+                        .help("Runs per algorithm and instance (default: 5)")
+                        .takes_value(true)
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed of the first run; run k uses N + k - 1 (default: 0)")
+                        .takes_value(true)
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("time-limit")
+                        .long("time-limit")
+                        .help("Stop each run after SECONDS")
+                        .takes_value(true)
+                        .value_name("SECONDS"),
+                )
+                .arg(
+                    Arg::with_name("success-gap")
+                        .long("success-gap")
+                        .help("Count runs within PERCENT of the optimum as successes (default: 5%)")
+                        .takes_value(true)
+                        .value_name("PERCENT"),
+                )
+                .arg(
+                    Arg::with_name("workers")
+                        .long("workers")
+                        .help("Comma-separated HOST:PORT of `sapso worker`s to spread the runs over")
+                        .takes_value(true)
+                        .value_name("LIST"),
+                ),
+        )
+        .subcommand(
+            App::new("compare")
+                .about("Compare two experiment JSON files and flag significant changes in best distance")
+                .arg(
+                    Arg::with_name("baseline")
+                        .help("Experiment JSON to compare against")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("current")
+                        .help("Experiment JSON to check")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("alpha")
+                        .long("alpha")
+                        .help("Significance level of the Mann-Whitney U test (default: 0.05)")
+                        .takes_value(true)
+                        .value_name("P"),
+                )
+                .arg(
+                    Arg::with_name("fail-on-regression")
+                        .long("fail-on-regression")
+                        .help("Exit with an error if any algorithm regressed"),
+                ),
+        )
+        .subcommand(
+            App::new("landscape")
+                .about("Estimate the fitness-distance correlation of an instance from sampled 2-opt local optima")
+                .arg(
+                    Arg::with_name("instance")
+                        .help("TSP instance name or path")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("samples")
+                        .long("samples")
+                        .help("Local optima to sample (default: 50)")
+                        .takes_value(true)
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed the random starting tours")
+                        .takes_value(true)
+                        .value_name("N"),
+                ),
+        )
+        .subcommand(
+            App::new("generate")
+                .about("Write a random instance as a TSPLIB EUC_2D file")
+                .arg(
+                    Arg::with_name("cities")
+                        .long("cities")
+                        .help("Number of cities")
+                        .takes_value(true)
+                        .value_name("N")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("distribution")
+                        .long("distribution")
+                        .help("How the cities are laid out: uniformly, in Gaussian clusters or on a grid (default: uniform)")
+                        .takes_value(true)
+                        .possible_values(["uniform", "clustered", "grid"]),
+                )
+                .arg(
+                    Arg::with_name("side")
+                        .long("side")
+                        .help("Side of the square the cities lie in (default: 1000000)")
+                        .takes_value(true)
+                        .value_name("LENGTH"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .help("Seed the city layout")
+                        .takes_value(true)
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("out")
+                        .long("out")
+                        .help("File to write (default: <distribution><cities>.tsp, e.g. random500.tsp)")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                ),
+        )
+        .subcommand(
+            App::new("report")
+                .about("Summarize the runs appended to a results store with --store")
+                .arg(
+                    Arg::with_name("store-file")
+                        .help("Results store to read (default: runs.ndjson)"),
+                )
+                .arg(
+                    Arg::with_name("instance")
+                        .long("instance")
+                        .help("Only runs on this instance")
+                        .takes_value(true)
+                        .value_name("NAME"),
+                )
+                .arg(
+                    Arg::with_name("algorithm")
+                        .long("algorithm")
+                        .help("Only runs of this algorithm, e.g. \"Simulated Annealing\"")
+                        .takes_value(true)
+                        .value_name("NAME"),
+                )
+                .arg(
+                    Arg::with_name("source")
+                        .long("source")
+                        .help("Only runs stored by this command")
+                        .takes_value(true)
+                        .possible_values(["run", "bench", "hyper"]),
+                )
+                .arg(
+                    Arg::with_name("since")
+                        .long("since")
+                        .help("Only runs stored at most this long ago, e.g. 12h or 7d")
+                        .takes_value(true)
+                        .value_name("DURATION"),
+                )
+                .arg(
+                    Arg::with_name("last")
+                        .long("last")
+                        .help("Only the last N runs stored of those left")
+                        .takes_value(true)
+                        .value_name("N"),
+                )
+                .arg(
+                    Arg::with_name("runs")
+                        .long("runs")
+                        .help("List the runs themselves before the summary"),
+                ),
+        )
+        .subcommand(
+            App::new("grpc")
+                .about("Serve the solvers over gRPC, streaming per-iteration progress")
+                .arg(
+                    Arg::with_name("address")
+                        .long("address")
+                        .help("Address to listen on (default: 127.0.0.1:50051)")
+                        .takes_value(true)
+                        .value_name("HOST:PORT"),
+                ),
+        )
+        .subcommand(
+            App::new("serve")
+                .about("Serve the solvers over HTTP: submit instances, start solves, poll progress and fetch tours and plots")
+                .arg(
+                    Arg::with_name("address")
+                        .long("address")
+                        .help("Address to listen on (default: 127.0.0.1:8080)")
+                        .takes_value(true)
+                        .value_name("HOST:PORT"),
+                ),
+        )
+        .subcommand(
+            App::new("worker")
+                .about("Run solver runs sent by a coordinator's --workers over TCP")
+                .arg(
+                    Arg::with_name("address")
+                        .long("address")
+                        .help("Address to listen on (default: 127.0.0.1:7070)")
+                        .takes_value(true)
+                        .value_name("HOST:PORT"),
+                ),
+        )
+        .subcommand(
+            App::new("validate")
+                .visible_alias("verify")
+                .about("Check an instance and optionally a tour against it")
+                .arg(
+                    Arg::with_name("instance")
+                        .help("TSP instance name or path")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("tour-file")
+                        .help("TSPLIB .tour file to check against the instance")
+                        .conflicts_with("tour"),
+                )
+                .arg(
+                    Arg::with_name("tour")
+                        .long("tour")
+                        .help("TSPLIB .tour file to check against the instance")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                )
+                .arg(
+                    Arg::with_name("repair")
+                        .long("repair")
+                        .help("Write the tour to FILE with repeated cities dropped and missing ones inserted, instead of failing when it is invalid")
+                        .takes_value(true)
+                        .value_name("FILE"),
+                ),
+        )
         .get_matches();
+    let _telemetry = telemetry(&matches)?;
+    progress::set_quiet(
+        matches
+            .subcommand()
+            .map_or(matches.is_present("quiet"), |(_, matches)| {
+                matches.is_present("quiet")
+            }),
+    );
+    if let Some(threads) = matches.value_of("threads") {
+        let threads: usize = threads
+            .parse()
+            .ok()
+            .filter(|&threads| threads > 0)
+            .context("--threads expects a positive integer")?;
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .context("failed to start the solver threads")?;
+    }
 
-    let instance_name = matches.value_of("instance").unwrap();
-    let instance = format!("instances/{}.tsp", instance_name);
-    let tsp = read_tsp_file(&instance)?;
+    if let Some(matches) = matches.subcommand_matches("validate") {
+        let instance = instance_path(matches.value_of("instance").unwrap());
+        return validate::run(
+            &instance,
+            matches
+                .value_of("tour")
+                .or_else(|| matches.value_of("tour-file")),
+            matches.value_of("repair").map(Path::new),
+        );
+    }
+    if let Some(matches) = matches.subcommand_matches("compare") {
+        let alpha: f64 = matches
+            .value_of("alpha")
+            .map_or(Ok(0.05), str::parse)
+            .context("--alpha expects a probability")?;
+        let comparisons = stats::compare(
+            Path::new(matches.value_of("baseline").unwrap()),
+            Path::new(matches.value_of("current").unwrap()),
+            alpha,
+        )?;
+        stats::print_comparisons(&comparisons);
+        let regressions = comparisons
+            .iter()
+            .filter(|c| c.verdict == stats::Verdict::Regressed)
+            .count();
+        if regressions > 0 && matches.is_present("fail-on-regression") {
+            bail!("{} significant regression(s)", regressions);
+        }
+        return Ok(());
+    }
+    if let Some(matches) = matches.subcommand_matches("landscape") {
+        let instance = instance_path(matches.value_of("instance").unwrap());
+        let samples = matches
+            .value_of("samples")
+            .map_or(Ok(50), str::parse)
+            .context("--samples expects a positive integer")?;
+        let seed = matches
+            .value_of("seed")
+            .map(str::parse)
+            .transpose()
+            .context("--seed expects a non-negative integer")?;
+        return landscape::run(&instance, samples, seed);
+    }
+    if let Some(matches) = matches.subcommand_matches("report") {
+        let path = Path::new(
+            matches
+                .value_of("store-file")
+                .unwrap_or(store::DEFAULT_PATH),
+        );
+        let filter = store::Filter {
+            instance: matches.value_of("instance").map(str::to_string),
+            algorithm: matches.value_of("algorithm").map(str::to_string),
+            source: matches.value_of("source").map(str::parse).transpose()?,
+            since: duration_arg(matches, "since")?,
+            last: matches
+                .value_of("last")
+                .map(str::parse)
+                .transpose()
+                .context("--last expects a positive integer")?,
+        };
+        let runs = store::load(path)?;
+        let matched = filter.apply(&runs);
+        if matched.is_empty() {
+            println!("No runs in {} match", path.display());
+            return Ok(());
+        }
+        let listed = matches.is_present("runs").then_some(matched.as_slice());
+        store::print_report(&store::summarize(&matched), listed);
+        return Ok(());
+    }
+    if let Some(matches) = matches.subcommand_matches("generate") {
+        let cities: usize = matches
+            .value_of("cities")
+            .unwrap()
+            .parse()
+            .context("--cities expects a positive integer")?;
+        if cities < 3 {
+            bail!("--cities must be at least 3 to form a tour");
+        }
+        let distribution: Distribution = matches
+            .value_of("distribution")
+            .map_or(Ok(Distribution::Uniform), str::parse)?;
+        let side: f64 = matches
+            .value_of("side")
+            .map_or(Ok(1_000_000.0), str::parse)
+            .context("--side expects a positive number")?;
+        if !(side > 0.0 && side.is_finite()) {
+            bail!("--side expects a positive number");
+        }
+        let seed = matches
+            .value_of("seed")
+            .map(str::parse)
+            .transpose()
+            .context("--seed expects a non-negative integer")?;
+        let problem =
+            RandomProblem::generate(cities, side, distribution, &mut tsplib::solver_rng(seed));
+        let out = matches.value_of("out").map_or_else(
+            || PathBuf::from(format!("{}.tsp", problem.name)),
+            PathBuf::from,
+        );
+        let comment = match seed {
+            Some(seed) => format!("{} cities, {}, seed {}", cities, distribution, seed),
+            None => format!("{} cities, {}", cities, distribution),
+        };
+        tsplib::write_tsp_file(&out, &problem.instance(), &comment)?;
+        println!("Wrote {}", out.display());
+        return Ok(());
+    }
 
-    println!("{:?}", tsp);
-    plot::plot_tsp_instance(tsp.clone())?;
+    let evaluator = evaluator(&matches)?;
+    if let Some(matches) = matches.subcommand_matches("worker") {
+        return distributed::serve(
+            matches.value_of("address").unwrap_or("127.0.0.1:7070"),
+            evaluator,
+        );
+    }
 
-    if let Some(trials) = matches.value_of("hyper") {
-        let num_trials = trials.parse().unwrap();
-        println!(
-            "Running hyperparameter optimization with {} trials...",
-            num_trials
+    if matches.subcommand_matches("run").is_some() && matches.value_of("config").is_none() {
+        bail!("`sapso run` needs an experiment file, pass --config FILE");
+    }
+    let mut config = match matches.value_of("config") {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    if let Some(params) = matches.values_of("param") {
+        for param in params {
+            config.solvers.apply_param(param)?;
+        }
+    }
+    for (flag, _, key) in &parameter_flags {
+        if let Some(value) = matches.value_of(flag) {
+            config.solvers.apply_param(&format!("{}={}", key, value))?;
+        }
+    }
+    if let Some(step) = matches.value_of("animate") {
+        config.plot.animation_step = Some(
+            step.parse()
+                .context("--animate expects a positive integer")?,
         );
+    }
+    if let Some(fps) = matches.value_of("animation-fps") {
+        config.plot.animation_fps = fps
+            .parse()
+            .context("--animation-fps expects a positive integer")?;
+    }
+    config.plot.animation_frames |= matches.is_present("animation-frames");
+    config.plot.log_x |= matches.is_present("log-x");
+    config.plot.log_y |= matches.is_present("log-y");
+    config.plot.overlay_optimal |= matches.is_present("overlay-optimal");
+    config.plot.html |= matches.is_present("html");
+    config.plot.city_labels |= matches.is_present("city-labels");
+    config.plot.csv |= matches.is_present("csv");
+    config.plot.edge_length_colors |= matches.is_present("edge-colors");
+    if let Some(dir) = matches.value_of("output-dir") {
+        config.plot.output_dir = dir.into();
+    }
+    match matches.value_of("format") {
+        Some("svg") => config.plot.format = plot::ImageFormat::Svg,
+        Some(_) => config.plot.format = plot::ImageFormat::Png,
+        None => {}
+    }
+    if let Some(dpi) = matches.value_of("dpi") {
+        config.plot.dpi = dpi
+            .parse()
+            .ok()
+            .filter(|&dpi| dpi > 0)
+            .context("--dpi expects a positive integer")?;
+    }
+    if config.plot.dpi == 0 {
+        bail!("plot.dpi must be positive");
+    }
+    match matches.value_of("theme") {
+        Some("dark") => config.plot.theme = plot::Theme::Dark,
+        Some(_) => config.plot.theme = plot::Theme::Light,
+        None => {}
+    }
+    if let Some(palette) = matches.value_of("palette") {
+        config.plot.palette = palette.split(',').map(|c| c.trim().to_string()).collect();
+    }
+    config.plot.series_colors().context("invalid palette")?;
+    config.plot.report |= matches.is_present("report");
+    config.plot.annotate |= matches.is_present("annotate");
+    if config.plot.report && cfg!(not(feature = "pdf")) {
+        bail!("--report requires a build with `--features pdf`");
+    }
 
-        let results = hyper::optimize_hyperparameters(&tsp, num_trials);
+    if let Some(matches) = matches.subcommand_matches("serve") {
+        let address = matches.value_of("address").unwrap_or("127.0.0.1:8080");
+        #[cfg(feature = "serve")]
+        return sapso::serve::run(sapso::serve::ServeOptions {
+            address: address.to_string(),
+            solvers: config.solvers,
+            plot: config.plot,
+        });
+        #[cfg(not(feature = "serve"))]
+        bail!(
+            "serving on {} requires a build with `--features serve`",
+            address
+        );
+    }
 
-        let mut file = File::create("hyper_results.txt")?;
-        for result in &results {
-            file.write_all(format!("{:?}\n", result).as_bytes())?;
-        }
+    if let Some(matches) = matches.subcommand_matches("grpc") {
+        let address = matches.value_of("address").unwrap_or("127.0.0.1:50051");
+        #[cfg(feature = "grpc")]
+        return sapso::grpc::run(sapso::grpc::GrpcOptions {
+            address: address.to_string(),
+            solvers: config.solvers,
+        });
+        #[cfg(not(feature = "grpc"))]
+        bail!(
+            "serving gRPC on {} requires a build with `--features grpc`",
+            address
+        );
+    }
 
-        let mut current_algo = String::new();
-        for result in &results {
-            if result.algorithm != current_algo {
-                current_algo = result.algorithm.clone();
-                println!("\nBest parameters for {}:", current_algo);
-                println!("Distance: {}", result.distance);
-                println!("Runtime: {}ms", result.runtime_ms);
-                println!("Parameters: {}", result.parameters);
-            }
-        }
+    let experiment_json = matches.value_of("experiment-json").map(PathBuf::from);
+    let store_path = matches.value_of("store").map(PathBuf::from);
+    if let Some(matches) = matches.subcommand_matches("bench") {
+        let options = bench::BenchOptions {
+            instances: match matches.value_of("instances") {
+                Some(list) => list.split(',').map(|i| instance_path(i.trim())).collect(),
+                None if !config.instances.is_empty() => {
+                    config.instances.iter().map(|i| instance_path(i)).collect()
+                }
+                None => bench::DEFAULT_INSTANCES
+                    .iter()
+                    .map(|i| instance_path(i))
+                    .collect(),
+            },
+            algorithms: algorithms_arg(matches, &config)?,
+            runs: matches
+                .value_of("runs")
+                .map_or(Ok(config.runs.unwrap_or(5)), str::parse)
+                .context("--runs expects a positive integer")?
+                .max(1),
+            seed: matches
+                .value_of("seed")
+                .map_or(Ok(config.seed.unwrap_or(0)), str::parse)
+                .context("--seed expects a non-negative integer")?,
+            time_limit: duration_arg(matches, "time-limit")?,
+            success_gap: percent_arg(matches, "success-gap")?.unwrap_or(stats::SUCCESS_GAP),
+            experiment_json,
+            store: store_path.clone(),
+            cancellation: cancel_on_ctrl_c()?,
+            workers: connect_workers(matches)?,
+            evaluator,
+        };
+        return bench::run(&options, &config.solvers, &config.plot);
+    }
 
-        return Ok(());
+    // Flags override the experiment the config describes, the positional
+    // instance only when given.
+    if matches.occurrences_of("instance") > 0 || config.instances.is_empty() {
+        config.instances = vec![matches.value_of("instance").unwrap().to_string()];
+    }
+    if let Some(seed) = matches.value_of("seed") {
+        config.seed = Some(
+            seed.parse()
+                .context("--seed expects a non-negative integer")?,
+        );
     }
+    config.algorithms = algorithms_arg(&matches, &config)?;
+    config.constructions = constructions_arg(&matches, &config);
+    let instances: Vec<String> = config
+        .instances
+        .iter()
+        .map(|instance| instance_path(instance))
+        .collect();
+    let provider = match matches.value_of("mmap-distances") {
+        Some(path) => DistanceProvider::Mapped(path.into()),
+        None => match matches.value_of("distances") {
+            Some("dense") => DistanceProvider::Dense,
+            Some("triangular") => DistanceProvider::Triangular,
+            Some("computed") => DistanceProvider::Computed,
+            _ => DistanceProvider::Auto,
+        },
+    };
+    let read_instance = |instance: &String, config: &mut Config| -> Result<TspLib> {
+        let tsp = read_tsp_file_with(instance, &provider)
+            .with_context(|| format!("failed to parse {}", instance))?;
+        println!("{:?}", tsp);
+        config.plot.instance = tsp.name.clone();
+        plot::plot_tsp_instance(tsp.clone(), &config.plot)?;
+        Ok(tsp)
+    };
 
-    let aco = aco::AntColonyOptimization::new(&tsp, 1.0, 2.0, 0.5, 50.0, 100, 100);
-    run_algorithm(aco, "Ant Colony Optimization", &tsp, &plotters::style::BLUE);
+    if let Some(runs) = matches.value_of("benchmark") {
+        let options = bench::BenchOptions {
+            instances,
+            algorithms: config.algorithms.clone(),
+            runs: runs
+                .parse()
+                .ok()
+                .filter(|&runs| runs > 0)
+                .context("--benchmark expects a positive integer")?,
+            seed: config.seed.unwrap_or(0),
+            time_limit: duration_arg(&matches, "time-limit")?,
+            success_gap: percent_arg(&matches, "success-gap")?.unwrap_or(stats::SUCCESS_GAP),
+            experiment_json,
+            store: store_path,
+            cancellation: cancel_on_ctrl_c()?,
+            workers: connect_workers(&matches)?,
+            evaluator,
+        };
+        return bench::run(&options, &config.solvers, &config.plot);
+    }
 
-    let sa = sa::SimulatedAnnealing::new(&tsp, 1000.0, 0.001, 0.1);
-    run_algorithm(sa, "Simulated Annealing", &tsp, &plotters::style::RED);
+    if matches.is_present("hyper") {
+        for instance in &instances {
+            let tsp = read_instance(instance, &mut config)?;
+            tune_instance(&tsp, &matches, &config, &evaluator)?;
+        }
+        return Ok(());
+    }
 
-    let ga = ga::GeneticAlgorithm::new(&tsp, 400, 2000, 0.01);
-    run_algorithm(ga, "Genetic Algorithm", &tsp, &plotters::style::GREEN);
+    // Without a seed from --seed or the config, draw one and print it, so
+    // every run can be replayed.
+    if config.seed.is_none() && !config.solvers.is_seeded() {
+        let seed = u64::from(rand::random::<u32>());
+        println!("Seed: {} (pass --seed {} to replay this run)", seed, seed);
+        config.seed = Some(seed);
+    }
+    let runs = match matches.value_of("runs") {
+        Some(runs) => runs.parse().context("--runs expects a positive integer")?,
+        None => config.runs.unwrap_or(1),
+    };
+    if runs == 0 {
+        bail!("--runs expects a positive integer");
+    }
+    config.runs = Some(runs);
+    let seeds = config.seed.map_or(Vec::new(), |seed| {
+        (0..runs as u64).map(|k| seed.wrapping_add(k)).collect()
+    });
+    let manifest_file = match instances.as_slice() {
+        [instance] => format!(
+            "{}_manifest.json",
+            Path::new(instance).file_stem().unwrap().to_string_lossy()
+        ),
+        _ => "experiment_manifest.json".to_string(),
+    };
+    let manifest = manifest::Manifest::new(&config, &instances, seeds)?
+        .write(&config.plot.output_dir, &manifest_file)?;
 
-    let pso = pso::ParticleSwarmOptimization::new(&tsp, 300, 4000, 1.5, 1.5, 0.8);
-    run_algorithm(
-        pso,
-        "Particle Swarm Optimization",
-        &tsp,
-        &plotters::style::MAGENTA,
-    );
+    // Once interrupted, later instances stop after their first iteration.
+    let cancellation = cancel_on_ctrl_c()?;
+    let mut records = Vec::new();
+    let mut rows = Vec::new();
+    for instance in &instances {
+        let tsp = read_instance(instance, &mut config)?;
+        let solved = solve_instance(&tsp, &matches, &config, &evaluator, &cancellation)?;
+        if let Some((record, instance_rows)) = solved {
+            records.push(record);
+            rows.extend(instance_rows);
+        }
+    }
+    if !rows.is_empty() {
+        experiment::write_results(&rows, &config.plot.output_dir)?;
+    }
+    if let Some(path) = &store_path {
+        let runs: Vec<StoredRun> = records
+            .iter()
+            .flat_map(|record| StoredRun::from_record(record, store::Source::Run, Some(&manifest)))
+            .collect();
+        store::append(path, &runs)?;
+    }
+    if let Some(path) = experiment_json.filter(|_| !records.is_empty()) {
+        experiment::Experiment::new(records, Some(&manifest)).write(&path)?;
+        println!("Experiment written to {}", path.display());
+    }
 
     Ok(())
 }
@@ -0,0 +1,104 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{config::Config, progress::status};
+
+/// Everything needed to reproduce the numbers of one invocation: the build,
+/// the exact instances, every setting and seed, and the host it ran on.
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub crate_version: String,
+    /// Commit the binary was built from, if it was built in a git checkout.
+    pub git_commit: Option<String>,
+    /// Whether tracked files differed from that commit at build time.
+    pub git_dirty: Option<bool>,
+    /// Seconds since the Unix epoch at which the invocation started.
+    pub created: u64,
+    pub command_line: Vec<String>,
+    pub instances: Vec<InstanceFile>,
+    pub config: Config,
+    /// Seed of every run, in order; empty if the runs were not seeded and so
+    /// cannot be reproduced exactly.
+    pub seeds: Vec<u64>,
+    pub host: Host,
+}
+
+/// An instance file and a checksum of its contents.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceFile {
+    pub path: String,
+    /// 64-bit FNV-1a hash of the file, in hex.
+    pub checksum: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Host {
+    pub hostname: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub cpus: usize,
+}
+
+impl Manifest {
+    /// Describes an invocation on the instance files `instances` with `config`,
+    /// whose runs were seeded with `seeds`.
+    pub fn new(config: &Config, instances: &[String], seeds: Vec<u64>) -> Result<Self> {
+        let instances = instances
+            .iter()
+            .map(|path| {
+                let contents =
+                    fs::read(path).with_context(|| format!("failed to read {}", path))?;
+                Ok(InstanceFile {
+                    path: path.clone(),
+                    checksum: format!("{:016x}", fnv1a(&contents)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Manifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: option_env!("SAPSO_GIT_COMMIT").map(str::to_string),
+            git_dirty: option_env!("SAPSO_GIT_DIRTY").map(|dirty| dirty == "true"),
+            created: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            command_line: std::env::args().collect(),
+            instances,
+            config: config.clone(),
+            seeds,
+            host: Host {
+                hostname: fs::read_to_string("/proc/sys/kernel/hostname")
+                    .ok()
+                    .or_else(|| std::env::var("HOSTNAME").ok())
+                    .map(|name| name.trim().to_string()),
+                os: std::env::consts::OS.to_string(),
+                arch: std::env::consts::ARCH.to_string(),
+                cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            },
+        })
+    }
+
+    /// Writes the manifest into `dir` as `file_name` and returns its path.
+    pub fn write(&self, dir: &Path, file_name: &str) -> Result<PathBuf> {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+        let path = dir.join(file_name);
+        fs::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        status!("Manifest written to {}", path.display());
+
+        Ok(path)
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across platforms and Rust versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How often the resident set size is sampled while a run is tracked.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(5);
+
+/// Approximate memory use of one solver run.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct MemoryUsage {
+    /// Largest resident set size of the process sampled during the run;
+    /// `None` where it cannot be read (only Linux is supported).
+    pub peak_rss_bytes: Option<u64>,
+    /// Heap allocations made during the run; only counted when built with the
+    /// `alloc-stats` feature.
+    pub allocations: Option<u64>,
+    /// Bytes requested by those allocations.
+    pub allocated_bytes: Option<u64>,
+}
+
+impl MemoryUsage {
+    /// Describes the usage, e.g. "peak RSS 12.4 MiB, 5120 allocations (38.0 MiB)".
+    pub fn describe(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(rss) = self.peak_rss_bytes {
+            parts.push(format!("peak RSS {}", format_bytes(rss)));
+        }
+        if let (Some(count), Some(bytes)) = (self.allocations, self.allocated_bytes) {
+            parts.push(format!("{} allocations ({})", count, format_bytes(bytes)));
+        }
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// Resident set size of the process, read from `/proc/self/status`.
+fn resident_set_size() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kib: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kib * 1024)
+}
+
+/// Measures the memory use of the process between [`MemoryTracker::start`]
+/// and [`MemoryTracker::finish`], sampling its resident set size on a
+/// background thread. Measurements are process-wide, so runs should be tracked
+/// one at a time.
+pub struct MemoryTracker {
+    stop: Arc<AtomicBool>,
+    sampler: Option<JoinHandle<Option<u64>>>,
+    allocations: (u64, u64),
+}
+
+impl MemoryTracker {
+    pub fn start() -> Self {
+        let stop = Arc::new(AtomicBool::new(false));
+        let sampler = resident_set_size().map(|_| {
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                let mut peak = resident_set_size();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(SAMPLE_INTERVAL);
+                    peak = peak.max(resident_set_size());
+                }
+                peak
+            })
+        });
+
+        MemoryTracker {
+            stop,
+            sampler,
+            allocations: allocation_counts(),
+        }
+    }
+
+    pub fn finish(self) -> MemoryUsage {
+        let (count, bytes) = allocation_counts();
+        self.stop.store(true, Ordering::Relaxed);
+        let peak_rss_bytes = self
+            .sampler
+            .and_then(|sampler| sampler.join().ok().flatten())
+            .max(resident_set_size());
+        let counted = cfg!(feature = "alloc-stats");
+
+        MemoryUsage {
+            peak_rss_bytes,
+            allocations: counted.then(|| count - self.allocations.0),
+            allocated_bytes: counted.then(|| bytes - self.allocations.1),
+        }
+    }
+}
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// Allocations and allocated bytes since the start of the process; always 0
+/// without the `alloc-stats` feature.
+fn allocation_counts() -> (u64, u64) {
+    (
+        ALLOCATIONS.load(Ordering::Relaxed),
+        ALLOCATED_BYTES.load(Ordering::Relaxed),
+    )
+}
+
+/// The system allocator, counting every allocation it makes.
+#[cfg(feature = "alloc-stats")]
+struct CountingAllocator;
+
+#[cfg(feature = "alloc-stats")]
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size() as u64, Ordering::Relaxed);
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: std::alloc::Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size as u64, Ordering::Relaxed);
+        std::alloc::System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
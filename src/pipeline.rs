@@ -0,0 +1,351 @@
+use std::sync::Arc;
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::Checkpointing,
+    config::{self, SolverSpec},
+    construct,
+    error::Error,
+    fitness::Evaluator,
+    local_search::{LocalSearch, LocalSearcher},
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::{self, HeuristicAlgorithm, Route, Solution, StepResult, TspLib},
+};
+
+/// One stage of a [`Pipeline`]: a solver, and the seconds after which it
+/// hands its best tour on even if its own budget is not used up, e.g.
+/// `{ algorithm = "sa", time_limit = 10 }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stage {
+    #[serde(flatten)]
+    pub solver: SolverSpec,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_limit: Option<f64>,
+}
+
+impl From<SolverSpec> for Stage {
+    fn from(solver: SolverSpec) -> Self {
+        Stage {
+            solver,
+            time_limit: None,
+        }
+    }
+}
+
+/// Solvers run one after another as a single run, each stage warm-started
+/// from the best tour of the stages before it through a [`SolutionPool`]. In a
+/// `--config` file the stages are `[[pipeline]]` tables, e.g.
+/// `{ algorithm = "aco", iterations = 20 }` followed by `{ algorithm = "sa" }`.
+/// The chain may start from a nearest-neighbour tour and end with a local
+/// search of the best tour, see [`Pipeline::with_greedy_start`] and
+/// [`Pipeline::with_polish`].
+pub struct Pipeline {
+    stages: Vec<Stage>,
+    /// Whether the first stage starts from a nearest-neighbour tour.
+    greedy_start: bool,
+    /// Local search run on the best tour once the last stage is done.
+    polish: LocalSearch,
+    /// The running stage and its index.
+    current: Option<(usize, Box<dyn HeuristicAlgorithm>)>,
+    start_time: Option<Instant>,
+    /// When the running stage started, for its time limit.
+    stage_start: Option<Instant>,
+    observers: Observers,
+    pool: Option<SolutionPool>,
+    /// The pool of the current run: the one given with `set_pool`, if any.
+    run_pool: SolutionPool,
+    archive: Option<SolutionArchive>,
+    evaluator: Evaluator,
+    seed: Option<u64>,
+    /// Tour the first stage starts from, see `set_initial_solution`.
+    initial_solution: Option<Vec<usize>>,
+    history: Vec<Route>,
+    history_times: Vec<u64>,
+    best_route: Route,
+    run_time: u64,
+    iterations: usize,
+    operator_counts: OperatorCounts,
+}
+
+impl Pipeline {
+    pub fn new(tsp: &TspLib, stages: Vec<Stage>) -> Self {
+        Pipeline {
+            stages,
+            greedy_start: false,
+            polish: LocalSearch::None,
+            current: None,
+            start_time: None,
+            stage_start: None,
+            observers: Observers::default(),
+            pool: None,
+            run_pool: SolutionPool::new(),
+            archive: None,
+            evaluator: Evaluator::default(),
+            seed: None,
+            initial_solution: None,
+            history: Vec::new(),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
+            run_time: 0,
+            iterations: 0,
+            operator_counts: OperatorCounts::default(),
+        }
+    }
+
+    /// Starts the first stage from the nearest-neighbour tour from city 0,
+    /// unless given an initial solution.
+    pub fn with_greedy_start(mut self) -> Self {
+        self.greedy_start = true;
+        self
+    }
+
+    /// Improves the best tour with `method` once the last stage is done.
+    pub fn with_polish(mut self, method: LocalSearch) -> Self {
+        self.polish = method;
+        self
+    }
+
+    pub fn stages(&self) -> &[Stage] {
+        &self.stages
+    }
+
+    /// The stages' algorithms joined by arrows, e.g.
+    /// `Greedy → SA (10s) → GA → lin_kernighan`.
+    pub fn describe(&self) -> String {
+        let stages = self.stages.iter().map(|stage| match stage.time_limit {
+            Some(limit) => format!("{} ({}s)", stage.solver.algorithm().name(), limit),
+            None => stage.solver.algorithm().name().to_string(),
+        });
+        self.greedy_start
+            .then(|| "Greedy".to_string())
+            .into_iter()
+            .chain(stages)
+            .chain((!self.polish.is_none()).then(|| self.polish.to_string()))
+            .collect::<Vec<_>>()
+            .join(" → ")
+    }
+
+    /// Whether the running stage has used up its time limit.
+    fn stage_out_of_time(&self, index: usize) -> bool {
+        match (self.stages[index].time_limit, self.stage_start) {
+            (Some(limit), Some(start)) => start.elapsed().as_secs_f64() >= limit,
+            _ => false,
+        }
+    }
+
+    /// Starts stage `index`, seeded with the best tour so far.
+    fn start_stage(&mut self, tsp: &TspLib, index: usize) -> Result<()> {
+        let mut solver = config::build_solver(tsp, &self.stages[index].solver);
+        if let Some(seed) = self.seed {
+            solver.set_seed(seed.wrapping_add(index as u64));
+        }
+        if let Some(archive) = self.archive.take() {
+            solver.set_archive(archive);
+        }
+        if index > 0 {
+            self.run_pool.publish(&self.best_route);
+        } else if let Some(tour) = &self.initial_solution {
+            solver.set_initial_solution(tour.clone());
+        } else if self.greedy_start {
+            solver.set_initial_solution(self.best_route.tour.clone());
+        }
+        solver.set_pool(self.run_pool.clone());
+        solver.set_evaluator(self.evaluator.clone());
+        solver.init(tsp)?;
+        self.stage_start = Some(Instant::now());
+        self.current = Some((index, solver));
+        Ok(())
+    }
+
+    /// Finishes the running stage, if any, keeping its best tour, operator
+    /// counts and archive.
+    fn finish_stage(&mut self) -> Result<()> {
+        let Some((_, mut solver)) = self.current.take() else {
+            return Ok(());
+        };
+        let solution = solver.finish()?;
+        if solution.route.distance < self.best_route.distance {
+            self.best_route = solution.route;
+        }
+        self.operator_counts.merge(solver.get_operator_counts());
+        self.archive = solver.get_archive().cloned();
+        Ok(())
+    }
+
+    /// Runs the polishing local search on the best tour, recorded as one more
+    /// iteration.
+    fn polish(&mut self, tsp: &TspLib, start_time: Instant) {
+        let Some(searcher) = LocalSearcher::new(self.polish, tsp) else {
+            return;
+        };
+        let mut tour = self.best_route.tour.clone();
+        let distance = searcher.improve(&mut tour, &tsp.distance_matrix);
+        if distance < self.best_route.distance {
+            self.best_route = Route::from_measured_tour(&tour, distance, tsp);
+        }
+        let elapsed_ms = start_time.elapsed().as_millis() as u64;
+        self.history_times.push(elapsed_ms);
+        self.history.push(self.best_route.clone());
+        self.observers.iteration(IterationInfo {
+            algorithm: "Pipeline",
+            iteration: self.iterations,
+            best: &self.best_route,
+            elapsed_ms,
+            stats: &[("stage", self.stages.len() as f64)],
+        });
+        self.iterations += 1;
+    }
+}
+
+impl HeuristicAlgorithm for Pipeline {
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        tsplib::check_instance(tsp)?;
+        if self.stages.is_empty() {
+            bail!("a pipeline needs at least one stage");
+        }
+        self.observers.start("Pipeline", tsp);
+        let _span = self.observers.span().clone().entered();
+        self.run_pool = self.pool.clone().unwrap_or_default();
+        self.best_route = if self.greedy_start && self.initial_solution.is_none() {
+            Route::from_tour(&construct::nearest_neighbor(&tsp.distance_matrix, 0), tsp)
+        } else {
+            Route::random_through(tsp, &mut tsplib::solver_rng(self.seed))
+        };
+        self.start_time = Some(Instant::now());
+        self.start_stage(tsp, 0)
+    }
+
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(start_time) = self.start_time else {
+            return Err(Error::NotStarted.into());
+        };
+        let _span = self.observers.enter_step(self.iterations);
+
+        // Stages whose budget is already used up run no iteration, so move on
+        // until one does or none is left.
+        while let Some((index, solver)) = self.current.as_mut() {
+            let index = *index;
+            let before = solver.get_history().len();
+            let step = solver.step(tsp)?;
+            let ran = solver.get_history().len() > before;
+            if ran {
+                let stage_best = solver.get_history().last().unwrap();
+                if stage_best.distance < self.best_route.distance {
+                    self.best_route = stage_best.clone();
+                }
+                self.history_times
+                    .push(start_time.elapsed().as_millis() as u64);
+                self.history.push(self.best_route.clone());
+                self.observers.iteration(IterationInfo {
+                    algorithm: "Pipeline",
+                    iteration: self.iterations,
+                    best: &self.best_route,
+                    elapsed_ms: start_time.elapsed().as_millis() as u64,
+                    stats: &[("stage", index as f64)],
+                });
+                self.iterations += 1;
+            }
+            if step.done || self.stage_out_of_time(index) {
+                self.finish_stage()?;
+                if index + 1 < self.stages.len() {
+                    self.start_stage(tsp, index + 1)?;
+                } else {
+                    self.polish(tsp, start_time);
+                }
+            }
+            if ran {
+                break;
+            }
+        }
+
+        Ok(StepResult {
+            iterations: self.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.best_route.distance,
+            done: self.current.is_none() || self.observers.stop_requested(),
+        })
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(start_time) = self.start_time.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        self.finish_stage()?;
+        self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: self.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("Pipeline", &solution, &self.operator_counts);
+        Ok(solution)
+    }
+
+    fn get_history(&self) -> &[Route] {
+        &self.history
+    }
+
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
+    }
+
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
+    }
+
+    fn get_run_time(&self) -> u64 {
+        self.run_time
+    }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    /// Seeds stage `k` (counting from 0) with `seed + k`.
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    /// A pipeline is not checkpointed: its stages start from each other's
+    /// tours rather than from saved state, so this has no effect.
+    fn set_checkpointing(&mut self, _checkpointing: Checkpointing) {}
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        match &self.current {
+            Some((_, solver)) => solver.get_archive(),
+            None => self.archive.as_ref(),
+        }
+    }
+
+    fn set_evaluator(&mut self, evaluator: Evaluator) {
+        self.evaluator = evaluator;
+    }
+}
@@ -1,12 +1,399 @@
-use anyhow::Result;
-use plotters::prelude::*;
+mod html;
+#[cfg(feature = "live")]
+pub mod live;
+#[cfg(feature = "pdf")]
+mod report;
 
-use crate::tsplib::{HeuristicAlgorithm, Route, TspLib};
+#[cfg(feature = "pdf")]
+pub use report::write_report;
 
-const FIG_SIZE: (u32, u32) = (2500, 1200);
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
-pub fn plot_tsp_instance(tsp: TspLib) -> Result<()> {
-    let coord_range = tsp.cities.iter().fold(
+use anyhow::{bail, Context, Result};
+use plotters::{
+    chart::MeshStyle,
+    coord::{
+        ranged1d::{AsRangedCoord, ValueFormatter},
+        Shift,
+    },
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    diversity::Diversity,
+    ga::GenerationStats,
+    hyper::{self, OptimizationResult},
+    pso::SwarmStats,
+    sa::EpochStats,
+    stats,
+    tabu::TabuStats,
+    tsplib::{City, HeuristicAlgorithm, Route, TspLib},
+};
+
+/// Solver parameters listed per line of a plot annotation.
+const ANNOTATION_PARAMETERS_PER_LINE: usize = 3;
+
+/// Smallest gap drawn on a logarithmic axis, where 0% cannot be represented.
+const LOG_GAP_FLOOR: f64 = 0.01;
+
+/// Resolution at which the configured pixel sizes are drawn as they are.
+const BASE_DPI: u32 = 96;
+
+/// File format figures are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    /// Vector output, suitable for publications and for the PDF report.
+    Svg,
+}
+
+impl ImageFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Svg => "svg",
+        }
+    }
+}
+
+/// Background and foreground colors of every plot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Light,
+    Dark,
+}
+
+impl Theme {
+    fn background(self) -> RGBColor {
+        match self {
+            Theme::Light => WHITE,
+            Theme::Dark => RGBColor(24, 24, 27),
+        }
+    }
+
+    fn foreground(self) -> RGBColor {
+        match self {
+            Theme::Light => BLACK,
+            Theme::Dark => RGBColor(220, 220, 220),
+        }
+    }
+
+    fn default_palette(self) -> &'static [RGBColor] {
+        match self {
+            Theme::Light => &[BLUE, RED, GREEN, MAGENTA, RGBColor(255, 140, 0)],
+            Theme::Dark => &[
+                RGBColor(100, 149, 237),
+                RGBColor(255, 99, 71),
+                RGBColor(60, 179, 113),
+                RGBColor(218, 112, 214),
+                RGBColor(255, 215, 0),
+            ],
+        }
+    }
+}
+
+/// Parses a `#rrggbb` color.
+fn parse_hex_color(color: &str) -> Result<RGBColor> {
+    let hex = color.strip_prefix('#').unwrap_or(color);
+    if hex.len() != 6 || !hex.is_ascii() {
+        bail!("invalid color `{}`, expected #rrggbb", color);
+    }
+    let channel = |i: usize| {
+        u8::from_str_radix(&hex[i..i + 2], 16)
+            .with_context(|| format!("invalid color `{}`, expected #rrggbb", color))
+    };
+
+    Ok(RGBColor(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Size, font and styling options shared by every plot.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlotConfig {
+    pub width: u32,
+    pub height: u32,
+    pub animation_width: u32,
+    pub animation_height: u32,
+    pub caption_font_size: u32,
+    pub label_font_size: u32,
+    pub margin: u32,
+    pub label_area_size: u32,
+    pub point_radius: u32,
+    pub line_width: u32,
+    pub show_captions: bool,
+    pub log_x: bool,
+    pub log_y: bool,
+    pub overlay_optimal: bool,
+    pub animation_step: Option<usize>,
+    /// Frames per second of the animated GIF.
+    pub animation_fps: u32,
+    /// Write the animation as numbered PNG frames in `{name}_evolution/`
+    /// instead of a GIF, e.g. to assemble a video.
+    pub animation_frames: bool,
+    pub html: bool,
+    pub city_labels: bool,
+    pub edge_length_colors: bool,
+    pub csv: bool,
+    pub output_dir: PathBuf,
+    pub format: ImageFormat,
+    /// Resolution of PNG figures and animations; every pixel size above is
+    /// scaled by `dpi / 96`.
+    pub dpi: u32,
+    /// Collect every figure of the run into `{instance}_report.pdf`; figures are
+    /// then rendered as SVG regardless of `format`.
+    pub report: bool,
+    pub theme: Theme,
+    /// Draw a panel with the instance, parameters, distance, gap and runtime on
+    /// route and history plots.
+    pub annotate: bool,
+    /// Algorithm colors as `#rrggbb`, used in turn; empty for the theme's own.
+    pub palette: Vec<String>,
+    /// Name of the instance being plotted, prefixed to every output file.
+    #[serde(skip)]
+    pub instance: String,
+    /// Figures written so far, in order, for the report.
+    #[serde(skip)]
+    figures: Arc<Mutex<Vec<PathBuf>>>,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        PlotConfig {
+            width: 2500,
+            height: 1200,
+            animation_width: 1000,
+            animation_height: 600,
+            caption_font_size: 50,
+            label_font_size: 25,
+            margin: 5,
+            label_area_size: 75,
+            point_radius: 5,
+            line_width: 1,
+            show_captions: true,
+            log_x: false,
+            log_y: false,
+            overlay_optimal: false,
+            animation_step: None,
+            animation_fps: 10,
+            animation_frames: false,
+            html: false,
+            city_labels: false,
+            edge_length_colors: false,
+            csv: false,
+            output_dir: PathBuf::from("results"),
+            format: ImageFormat::Png,
+            dpi: BASE_DPI,
+            report: false,
+            theme: Theme::Light,
+            annotate: false,
+            palette: Vec::new(),
+            instance: String::new(),
+            figures: Arc::default(),
+        }
+    }
+}
+
+impl PlotConfig {
+    /// Returns `{output_dir}/{instance}_{title}{suffix}`, creating the output
+    /// directory if it does not exist yet.
+    pub fn output_file(&self, title: &str, suffix: &str) -> Result<PathBuf> {
+        fs::create_dir_all(&self.output_dir).with_context(|| {
+            format!(
+                "failed to create output directory {}",
+                self.output_dir.display()
+            )
+        })?;
+        let mut name = title.to_lowercase().replace(" ", "_");
+        if !self.instance.is_empty() {
+            name = format!("{}_{}", self.instance, name);
+        }
+
+        Ok(self.output_dir.join(name + suffix))
+    }
+
+    fn image_format(&self) -> ImageFormat {
+        if self.report {
+            ImageFormat::Svg
+        } else {
+            self.format
+        }
+    }
+
+    /// Like [`PlotConfig::output_file`] with the image format's extension, and
+    /// remembers the figure for the report.
+    fn figure_file(&self, title: &str, suffix: &str) -> Result<PathBuf> {
+        let extension = self.image_format().extension();
+        let path = self.output_file(title, &format!("{}.{}", suffix, extension))?;
+        self.figures.lock().unwrap().push(path.clone());
+
+        Ok(path)
+    }
+
+    /// Writes the data behind `image` to a CSV file with the same basename when
+    /// CSV export is enabled.
+    fn write_csv<I>(&self, image: &Path, header: &[&str], rows: I) -> Result<()>
+    where
+        I: IntoIterator<Item = Vec<String>>,
+    {
+        if !self.csv {
+            return Ok(());
+        }
+
+        let path = image.with_extension("csv");
+        let mut contents = header.join(",") + "\n";
+        for row in rows {
+            contents += &row.join(",");
+            contents.push('\n');
+        }
+        fs::write(&path, contents).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// This config with every pixel size scaled to `dpi`, as bitmaps are
+    /// drawn with it.
+    fn scaled(&self) -> PlotConfig {
+        let scale =
+            |pixels: u32| (pixels as f64 * self.dpi as f64 / BASE_DPI as f64).round() as u32;
+        PlotConfig {
+            width: scale(self.width),
+            height: scale(self.height),
+            animation_width: scale(self.animation_width),
+            animation_height: scale(self.animation_height),
+            caption_font_size: scale(self.caption_font_size),
+            label_font_size: scale(self.label_font_size),
+            margin: scale(self.margin),
+            label_area_size: scale(self.label_area_size),
+            point_radius: scale(self.point_radius),
+            line_width: scale(self.line_width),
+            ..self.clone()
+        }
+    }
+
+    fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    fn background(&self) -> RGBColor {
+        self.theme.background()
+    }
+
+    fn foreground(&self) -> RGBColor {
+        self.theme.foreground()
+    }
+
+    /// Colors given to the algorithms in turn: the configured palette, or the
+    /// theme's default one when none is set.
+    pub fn series_colors(&self) -> Result<Vec<RGBColor>> {
+        if self.palette.is_empty() {
+            return Ok(self.theme.default_palette().to_vec());
+        }
+        self.palette
+            .iter()
+            .map(|color| parse_hex_color(color))
+            .collect()
+    }
+
+    fn label_font(&self) -> TextStyle<'static> {
+        ("sans-serif", self.label_font_size)
+            .into_font()
+            .color(&self.foreground())
+    }
+
+    /// Applies the theme's axis, grid and label styles to a chart's mesh.
+    fn mesh<'a, 'b, X, Y, DB>(
+        &self,
+        mut mesh: MeshStyle<'a, 'b, X, Y, DB>,
+    ) -> MeshStyle<'a, 'b, X, Y, DB>
+    where
+        X: Ranged,
+        Y: Ranged,
+        DB: DrawingBackend,
+    {
+        let foreground = self.foreground();
+        mesh.axis_style(foreground)
+            .bold_line_style(foreground.mix(0.2))
+            .light_line_style(foreground.mix(0.1))
+            .x_label_style(self.label_font())
+            .y_label_style(self.label_font());
+        mesh
+    }
+
+    fn chart_builder<'a, 'b, DB: DrawingBackend>(
+        &self,
+        root: &'a DrawingArea<DB, Shift>,
+        title: &str,
+    ) -> ChartBuilder<'a, 'b, DB> {
+        let mut builder = ChartBuilder::on(root);
+        builder
+            .margin(self.margin)
+            .x_label_area_size(self.label_area_size)
+            .y_label_area_size(self.label_area_size);
+        if self.show_captions {
+            builder.caption(
+                title,
+                ("sans-serif", self.caption_font_size)
+                    .into_font()
+                    .color(&self.foreground()),
+            );
+        }
+        builder
+    }
+}
+
+/// Opens `$file` with the backend matching the configured image format, clears
+/// it, runs `$body` on the root drawing area and writes the figure out. PNG
+/// figures see `$config` scaled to its resolution.
+macro_rules! draw_figure {
+    ($config:ident, $file:expr, |$root:ident| $body:block) => {
+        match $config.image_format() {
+            ImageFormat::Png => {
+                let $config = &$config.scaled();
+                let $root = BitMapBackend::new($file, $config.size()).into_drawing_area();
+                $root.fill(&$config.background())?;
+                $body
+                $root.present()?;
+            }
+            ImageFormat::Svg => {
+                let $root = SVGBackend::new($file, $config.size()).into_drawing_area();
+                $root.fill(&$config.background())?;
+                $body
+                $root.present()?;
+            }
+        }
+    };
+}
+
+type EdgeKey = ((u64, u64), (u64, u64));
+
+/// Direction-independent key for the edge between two cities.
+fn edge_key(a: City, b: City) -> EdgeKey {
+    let a = (a.0.to_bits(), a.1.to_bits());
+    let b = (b.0.to_bits(), b.1.to_bits());
+    if a <= b {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+fn tour_edges(cities: &[City]) -> impl Iterator<Item = (City, City)> + '_ {
+    cities
+        .iter()
+        .copied()
+        .zip(cities.iter().copied().cycle().skip(1))
+}
+
+fn coord_range(cities: &[City]) -> (f64, f64, f64, f64) {
+    cities.iter().fold(
         (
             f64::INFINITY,
             f64::NEG_INFINITY,
@@ -14,148 +401,2043 @@ pub fn plot_tsp_instance(tsp: TspLib) -> Result<()> {
             f64::NEG_INFINITY,
         ),
         |acc, &(x, y)| (acc.0.min(x), acc.1.max(x), acc.2.min(y), acc.3.max(y)),
-    );
+    )
+}
 
-    let tsp_root = BitMapBackend::new("./results/tsp.png", (2500, 1200)).into_drawing_area();
-    tsp_root.fill(&WHITE)?;
+pub fn plot_tsp_instance(tsp: TspLib, config: &PlotConfig) -> Result<()> {
+    let coord_range = coord_range(&tsp.cities);
 
-    let mut chart = ChartBuilder::on(&tsp_root)
-        .caption("TSP Layout", ("sans-serif", 50).into_font())
-        .margin(5)
-        .x_label_area_size(75)
-        .y_label_area_size(75)
-        .build_cartesian_2d(
-            coord_range.0 - 1.0..coord_range.1 + 1.0,
-            coord_range.2 - 1.0..coord_range.3 + 1.0,
-        )?;
+    let file_name = config.figure_file("tsp", "")?;
+    config.write_csv(
+        &file_name,
+        &["city", "x", "y"],
+        tsp.cities
+            .iter()
+            .enumerate()
+            .map(|(i, (x, y))| vec![(i + 1).to_string(), x.to_string(), y.to_string()]),
+    )?;
+    draw_figure!(config, &file_name, |tsp_root| {
+        let mut chart = config
+            .chart_builder(&tsp_root, "TSP Layout")
+            .build_cartesian_2d(
+                coord_range.0 - 1.0..coord_range.1 + 1.0,
+                coord_range.2 - 1.0..coord_range.3 + 1.0,
+            )?;
 
-    chart
-        .configure_mesh()
-        .x_desc("X")
-        .y_desc("Y")
-        .x_label_style(("sans-serif", 25).into_font())
-        .y_label_style(("sans-serif", 25).into_font())
-        .x_label_formatter(&|x| format!("{:.2}", x))
-        .y_label_formatter(&|y| format!("{:.2}", y))
-        .draw()?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("X")
+            .y_desc("Y")
+            .x_label_formatter(&|x| format!("{:.2}", x))
+            .y_label_formatter(&|y| format!("{:.2}", y))
+            .draw()?;
 
-    chart.draw_series(PointSeries::of_element(
-        tsp.cities.clone(),
-        5,
-        &BLACK,
-        &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
+        chart.draw_series(PointSeries::of_element(
+            tsp.cities.clone(),
+            config.point_radius,
+            &config.foreground(),
+            &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
+        ))?;
+        if let Some(best_route) = tsp.optimal_tour {
+            let best_route: Vec<(f64, f64)> = best_route.iter().map(|&i| tsp.cities[i]).collect();
+            chart.draw_series(LineSeries::new(
+                best_route.iter().chain(best_route.first()).copied(),
+                RED.stroke_width(config.line_width),
+            ))?;
+        }
+    });
+
+    Ok(())
+}
+
+/// Lines of run metadata shown in the corner of route and history plots.
+fn run_summary(
+    ha: &dyn HeuristicAlgorithm,
+    tsp: &TspLib,
+    parameters: &str,
+    config: &PlotConfig,
+) -> Vec<String> {
+    if !config.annotate {
+        return Vec::new();
+    }
+
+    let distance = ha.get_best_route().distance;
+    let mut lines = vec![format!("Instance: {}", tsp.name)];
+    let parameters: Vec<&str> = parameters.split(", ").collect();
+    for chunk in parameters.chunks(ANNOTATION_PARAMETERS_PER_LINE) {
+        lines.push(chunk.join(", "));
+    }
+    lines.push(format!("Distance: {}", distance));
+    if let Some(gap) = stats::gap(distance as f64, tsp) {
+        lines.push(format!("Gap: {}", stats::format_gap(gap)));
+    }
+    lines.push(format!("Runtime: {} ms", ha.get_run_time()));
+
+    lines
+}
+
+/// Draws `lines` in a boxed panel at the top right of `root`.
+fn draw_annotation<DB>(
+    root: &DrawingArea<DB, Shift>,
+    lines: &[String],
+    config: &PlotConfig,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    let font = config.label_font();
+    let mut width = 0;
+    for line in lines {
+        width = width.max(root.estimate_text_size(line, &font)?.0 as i32);
+    }
+    let padding = config.label_font_size as i32 / 2;
+    let line_height = config.label_font_size as i32 * 5 / 4;
+    let (root_width, _) = root.dim_in_pixel();
+    let top = if config.show_captions {
+        (config.margin + config.caption_font_size) as i32 + padding
+    } else {
+        config.margin as i32 + padding
+    };
+    let left = root_width as i32 - config.margin as i32 - 2 * padding - width - padding;
+    let bottom = top + 2 * padding + line_height * lines.len() as i32;
+    let right = left + 2 * padding + width;
+
+    root.draw(&Rectangle::new(
+        [(left, top), (right, bottom)],
+        config.background().mix(0.85).filled(),
     ))?;
-    if let Some(best_route) = tsp.optimal_tour {
-        let best_route: Vec<(f64, f64)> = best_route.iter().map(|&i| tsp.cities[i]).collect();
-        chart.draw_series(LineSeries::new(best_route.clone(), &RED))?;
-        chart.draw_series(LineSeries::new(
-            vec![best_route[best_route.len() - 1], best_route[0]],
-            &RED,
+    root.draw(&Rectangle::new(
+        [(left, top), (right, bottom)],
+        config.foreground(),
+    ))?;
+    for (i, line) in lines.iter().enumerate() {
+        root.draw(&Text::new(
+            line.as_str(),
+            (left + padding, top + padding + line_height * i as i32),
+            font.clone(),
         ))?;
     }
-    tsp_root.present()?;
 
     Ok(())
 }
 
+/// Plots the best route and convergence of `ha`. `parameters` is a one-line
+/// `key=value, ...` summary of the solver's settings, shown when annotating.
 pub fn plot_algo_result(
     ha: &dyn HeuristicAlgorithm,
+    tsp: &TspLib,
     title: &str,
     color: &plotters::style::RGBColor,
+    parameters: &str,
+    config: &PlotConfig,
 ) -> Result<()> {
-    plot_alg_best_route(ha.get_best_route(), title, color)?;
-    chart_history(ha.get_history(), title)?;
+    let summary = run_summary(ha, tsp, parameters, config);
+    let optimal_tour = match (&tsp.optimal_tour, config.overlay_optimal) {
+        (Some(tour), true) => Some(tour.iter().map(|&i| tsp.cities[i]).collect::<Vec<City>>()),
+        _ => None,
+    };
+    plot_alg_best_route(
+        ha.get_best_route(),
+        tsp,
+        optimal_tour.as_deref(),
+        title,
+        color,
+        &summary,
+        config,
+    )?;
+    let history = ha.get_history();
+    chart_history(history, None, tsp, title, &summary, config)?;
+    chart_history(
+        history,
+        Some(ha.get_history_times()),
+        tsp,
+        title,
+        &summary,
+        config,
+    )?;
+    if let Some(step) = config.animation_step {
+        animate_history(history, tsp, title, step, config)?;
+    }
+    if config.html {
+        let file_name = config.output_file(title, ".html")?;
+        html::write_algo_result(
+            &file_name,
+            title,
+            ha.get_best_route(),
+            history,
+            tsp,
+            (color.0, color.1, color.2),
+        )?;
+    }
 
     Ok(())
 }
 
-fn plot_alg_best_route(route: Route, title: &str, color: &plotters::style::RGBColor) -> Result<()> {
-    let coord_range = route.cities.iter().fold(
-        (
-            f64::INFINITY,
-            f64::NEG_INFINITY,
-            f64::INFINITY,
-            f64::NEG_INFINITY,
-        ),
-        |acc, &(x, y)| (acc.0.min(x), acc.1.max(x), acc.2.min(y), acc.3.max(y)),
-    );
-
-    let file_name = format!(
-        "./results/{}_best_route.png",
-        title.to_lowercase().replace(" ", "_")
-    );
-    let root = BitMapBackend::new(&file_name, FIG_SIZE).into_drawing_area();
-    root.fill(&WHITE)?;
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 50).into_font())
-        .margin(5)
-        .x_label_area_size(75)
-        .y_label_area_size(75)
-        .build_cartesian_2d(
+/// Blue-to-red gradient for an edge whose length is at `t` (0..1) between the
+/// shortest and longest edge of a tour.
+fn edge_length_color(t: f64) -> RGBColor {
+    let t = t.clamp(0.0, 1.0);
+    RGBColor((40.0 + 215.0 * t) as u8, 60, (255.0 * (1.0 - t)) as u8)
+}
+
+/// Draws `route`, optionally over a faint `optimal_tour`; edges missing from the
+/// optimal tour are drawn thicker so the heuristic's mistakes stand out, and
+/// edges can be colored by length so long detours stand out too.
+fn plot_alg_best_route(
+    route: &Route,
+    tsp: &TspLib,
+    optimal_tour: Option<&[City]>,
+    title: &str,
+    color: &plotters::style::RGBColor,
+    annotation: &[String],
+    config: &PlotConfig,
+) -> Result<()> {
+    let cities = route.coordinates(tsp);
+    let coord_range = coord_range(&cities);
+
+    let file_name = config.figure_file(title, "_best_route")?;
+    config.write_csv(
+        &file_name,
+        &["position", "city", "x", "y"],
+        cities
+            .iter()
+            .zip(&route.tour)
+            .enumerate()
+            .map(|(position, ((x, y), city))| {
+                vec![
+                    position.to_string(),
+                    (city + 1).to_string(),
+                    x.to_string(),
+                    y.to_string(),
+                ]
+            }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config.chart_builder(&root, title).build_cartesian_2d(
             coord_range.0 - 1.0..coord_range.1 + 1.0,
             coord_range.2 - 1.0..coord_range.3 + 1.0,
         )?;
 
-    chart
-        .configure_mesh()
-        .x_desc("X")
-        .y_desc("Y")
-        .x_label_style(("sans-serif", 25).into_font())
-        .y_label_style(("sans-serif", 25).into_font())
-        .x_label_formatter(&|x| format!("{:.2}", x))
-        .y_label_formatter(&|y| format!("{:.2}", y))
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("X")
+            .y_desc("Y")
+            .x_label_formatter(&|x| format!("{:.2}", x))
+            .y_label_formatter(&|y| format!("{:.2}", y))
+            .draw()?;
+
+        let optimal_edges: Option<HashSet<EdgeKey>> = optimal_tour.map(|optimal_tour| {
+            tour_edges(optimal_tour)
+                .map(|(a, b)| edge_key(a, b))
+                .collect()
+        });
+        if let Some(optimal_tour) = optimal_tour {
+            chart.draw_series(LineSeries::new(
+                optimal_tour.iter().chain(optimal_tour.first()).copied(),
+                config
+                    .foreground()
+                    .mix(0.2)
+                    .stroke_width(config.line_width * 6),
+            ))?;
+        }
+
+        let lengths: Vec<f64> = tour_edges(&cities)
+            .map(|(a, b)| (a.0 - b.0).hypot(a.1 - b.1))
+            .collect();
+        let shortest = lengths.iter().copied().fold(f64::INFINITY, f64::min);
+        let longest = lengths.iter().copied().fold(0.0, f64::max);
+        chart.draw_series(tour_edges(&cities).zip(&lengths).map(|((a, b), &length)| {
+            let width = match &optimal_edges {
+                Some(optimal_edges) if !optimal_edges.contains(&edge_key(a, b)) => {
+                    config.line_width * 4
+                }
+                _ => config.line_width,
+            };
+            let edge_color = if config.edge_length_colors {
+                edge_length_color((length - shortest) / (longest - shortest).max(f64::EPSILON))
+            } else {
+                *color
+            };
+            PathElement::new(vec![a, b], edge_color.stroke_width(width))
+        }))?;
+        if config.edge_length_colors && !lengths.is_empty() {
+            for (label, t) in [(shortest, 0.0), (longest, 1.0)] {
+                let legend_color = edge_length_color(t);
+                chart
+                    .draw_series(std::iter::empty::<PathElement<City>>())?
+                    .label(format!("edge length {:.1}", label))
+                    .legend(move |(x, y)| {
+                        PathElement::new(vec![(x, y), (x + 20, y)], legend_color.stroke_width(3))
+                    });
+            }
+            chart
+                .configure_series_labels()
+                .label_font(config.label_font())
+                .background_style(config.background().mix(0.8))
+                .border_style(config.foreground())
+                .draw()?;
+        }
+
+        chart.draw_series(PointSeries::of_element(
+            cities.clone(),
+            config.point_radius,
+            &config.foreground(),
+            &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
+        ))?;
+
+        if config.city_labels && cities.len() > 1 {
+            let label_font = config.label_font();
+            chart.draw_series(cities.iter().zip(&route.tour).map(|(&city, index)| {
+                EmptyElement::at(city)
+                    + Text::new(
+                        (index + 1).to_string(),
+                        (config.point_radius as i32, -(config.label_font_size as i32)),
+                        label_font.clone(),
+                    )
+            }))?;
+
+            // Mark the start city and point an arrowhead along the first edge to
+            // show the direction the tour is travelled in.
+            let start = cities[0];
+            chart.draw_series(std::iter::once(Circle::new(
+                start,
+                config.point_radius * 2,
+                GREEN.filled(),
+            )))?;
+            let from = chart.backend_coord(&start);
+            let to = chart.backend_coord(&cities[1]);
+            let (dx, dy) = ((to.0 - from.0) as f64, (to.1 - from.1) as f64);
+            let length = dx.hypot(dy).max(1.0);
+            let (ux, uy) = (dx / length, dy / length);
+            let size = (config.point_radius * 3) as f64;
+            let (mx, my) = ((from.0 + to.0) as f64 / 2.0, (from.1 + to.1) as f64 / 2.0);
+            root.draw(&Polygon::new(
+                vec![
+                    ((mx + ux * size) as i32, (my + uy * size) as i32),
+                    (
+                        (mx - ux * size - uy * size * 0.6) as i32,
+                        (my - uy * size + ux * size * 0.6) as i32,
+                    ),
+                    (
+                        (mx - ux * size + uy * size * 0.6) as i32,
+                        (my - uy * size - ux * size * 0.6) as i32,
+                    ),
+                ],
+                GREEN.filled(),
+            ))?;
+        }
+
+        draw_annotation(&root, annotation, config)?;
+    });
+
+    Ok(())
+}
+
+/// Linearly interpolated quantile of an ascending, non-empty slice.
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// Draws a box-and-whisker plot of the final distances each algorithm reached
+/// over repeated runs, with every individual run marked on top. Whiskers end at
+/// the most extreme runs within 1.5 IQR of the box.
+pub fn plot_distribution(distances: &[(&str, Vec<u64>)], config: &PlotConfig) -> Result<()> {
+    let distances: Vec<&(&str, Vec<u64>)> =
+        distances.iter().filter(|(_, d)| !d.is_empty()).collect();
+    if distances.is_empty() {
+        return Ok(());
+    }
+
+    let names: Vec<&str> = distances.iter().map(|(name, _)| *name).collect();
+    let min_distance = distances
+        .iter()
+        .flat_map(|(_, d)| d)
+        .min()
+        .copied()
+        .unwrap() as f64;
+    let max_distance = (distances
+        .iter()
+        .flat_map(|(_, d)| d)
+        .max()
+        .copied()
+        .unwrap() as f64)
+        .max(min_distance + 1.0);
+    let padding = (max_distance - min_distance) * 0.05;
+
+    let file_name = config.figure_file("distribution", "")?;
+    config.write_csv(
+        &file_name,
+        &["algorithm", "run", "distance"],
+        distances.iter().flat_map(|(name, runs)| {
+            runs.iter().enumerate().map(move |(run, distance)| {
+                vec![
+                    name.to_string(),
+                    (run + 1).to_string(),
+                    distance.to_string(),
+                ]
+            })
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config
+            .chart_builder(&root, "Final distance over runs")
+            .build_cartesian_2d(
+                -0.5..names.len() as f64 - 0.5,
+                min_distance - padding..max_distance + padding,
+            )?;
+        config
+            .mesh(chart.configure_mesh())
+            .disable_x_mesh()
+            .x_labels(names.len())
+            .x_label_formatter(&|x| {
+                let index = x.round();
+                if (x - index).abs() < 1e-6 && index >= 0.0 {
+                    names
+                        .get(index as usize)
+                        .map_or_else(String::new, |n| n.to_string())
+                } else {
+                    String::new()
+                }
+            })
+            .y_desc("Distance")
+            .draw()?;
+
+        let style = BLUE.stroke_width(config.line_width * 2);
+        for (index, (_, runs)) in distances.iter().enumerate() {
+            let mut sorted: Vec<f64> = runs.iter().map(|&d| d as f64).collect();
+            sorted.sort_by(f64::total_cmp);
+            let (q1, median, q3) = (
+                quantile(&sorted, 0.25),
+                quantile(&sorted, 0.5),
+                quantile(&sorted, 0.75),
+            );
+            let fence = 1.5 * (q3 - q1);
+            let low = sorted.iter().copied().find(|&d| d >= q1 - fence).unwrap();
+            let high = sorted.iter().copied().rfind(|&d| d <= q3 + fence).unwrap();
+
+            let x = index as f64;
+            chart.draw_series([
+                PathElement::new(vec![(x, low), (x, q1)], style),
+                PathElement::new(vec![(x, q3), (x, high)], style),
+                PathElement::new(vec![(x - 0.1, low), (x + 0.1, low)], style),
+                PathElement::new(vec![(x - 0.1, high), (x + 0.1, high)], style),
+                PathElement::new(vec![(x - 0.25, median), (x + 0.25, median)], style),
+            ])?;
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(x - 0.25, q1), (x + 0.25, q3)],
+                style,
+            )))?;
+            chart.draw_series(sorted.iter().map(|&distance| {
+                Circle::new((x, distance), config.point_radius, RED.mix(0.5).filled())
+            }))?;
+        }
+    });
+
+    Ok(())
+}
+
+/// Compares the algorithms' mean convergence area, final population diversity
+/// and SA acceptance rate over their runs, one bar chart each; the last two
+/// only when some algorithm measured them. Returns the figure's path.
+pub fn plot_run_metrics(
+    metrics: &[(&str, stats::AggregateStats)],
+    config: &PlotConfig,
+) -> Result<Option<PathBuf>> {
+    if metrics.is_empty() {
+        return Ok(None);
+    }
+
+    let names: Vec<&str> = metrics.iter().map(|(name, _)| *name).collect();
+    let percent = |share: Option<f64>| share.map(|share| share * 100.0);
+    let panels: Vec<(&str, Vec<Option<f64>>)> = [
+        (
+            "Convergence area (%)",
+            metrics
+                .iter()
+                .map(|(_, m)| Some(m.convergence_area))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "Population diversity (%)",
+            metrics
+                .iter()
+                .map(|(_, m)| percent(m.population_diversity))
+                .collect(),
+        ),
+        (
+            "Acceptance rate (%)",
+            metrics
+                .iter()
+                .map(|(_, m)| percent(m.acceptance_rate))
+                .collect(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, values)| values.iter().any(Option::is_some))
+    .collect();
+
+    let file_name = config.figure_file("metrics", "")?;
+    let optional = |value: Option<f64>| value.map_or(String::new(), |v| v.to_string());
+    config.write_csv(
+        &file_name,
+        &[
+            "algorithm",
+            "convergence_area",
+            "population_diversity",
+            "acceptance_rate",
+        ],
+        metrics.iter().map(|(name, m)| {
+            vec![
+                name.to_string(),
+                m.convergence_area.to_string(),
+                optional(m.population_diversity),
+                optional(m.acceptance_rate),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let areas = root.split_evenly((1, panels.len()));
+        for (area, (title, values)) in areas.iter().zip(&panels) {
+            draw_metric_bars(area, title, &names, values, config)?;
+        }
+    });
+
+    Ok(Some(file_name))
+}
+
+/// One bar per algorithm of `names` with a value in `values`.
+fn draw_metric_bars<DB>(
+    area: &DrawingArea<DB, Shift>,
+    title: &str,
+    names: &[&str],
+    values: &[Option<f64>],
+    config: &PlotConfig,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let highest = values
+        .iter()
+        .flatten()
+        .copied()
+        .fold(0.0, f64::max)
+        .max(1.0);
+    let mut chart = config
+        .chart_builder(area, title)
+        .build_cartesian_2d(-0.5..names.len() as f64 - 0.5, 0.0..highest * 1.1)?;
+    config
+        .mesh(chart.configure_mesh())
+        .disable_x_mesh()
+        .x_labels(names.len())
+        .x_label_formatter(&|x| {
+            let index = x.round();
+            if (x - index).abs() < 1e-6 && index >= 0.0 {
+                names
+                    .get(index as usize)
+                    .map_or_else(String::new, |n| n.to_string())
+            } else {
+                String::new()
+            }
+        })
+        .y_desc(title)
         .draw()?;
+    chart.draw_series(values.iter().enumerate().filter_map(|(index, value)| {
+        let x = index as f64;
+        value.map(|value| Rectangle::new([(x - 0.3, 0.0), (x + 0.3, value)], BLUE.filled()))
+    }))?;
+
+    Ok(())
+}
+
+/// Lays out the best route of every algorithm, plus the optimal tour when it is
+/// known, as a grid of panels sharing the same axes.
+pub fn plot_best_routes_grid(
+    routes: &[(&str, Route, RGBColor)],
+    tsp: &TspLib,
+    config: &PlotConfig,
+) -> Result<()> {
+    let mut panels: Vec<(String, u64, Vec<City>, RGBColor)> = routes
+        .iter()
+        .map(|(name, route, color)| {
+            (
+                name.to_string(),
+                route.distance,
+                route.coordinates(tsp),
+                *color,
+            )
+        })
+        .collect();
+    if let (Some(tour), Some(length)) = (&tsp.optimal_tour, tsp.optimal_tour_length) {
+        panels.push((
+            "Optimal tour".to_string(),
+            length,
+            tour.iter().map(|&i| tsp.cities[i]).collect(),
+            config.foreground(),
+        ));
+    }
+    if panels.is_empty() {
+        return Ok(());
+    }
+
+    let coord_range = coord_range(&tsp.cities);
+    let columns = (panels.len() as f64).sqrt().ceil() as usize;
+    let rows = panels.len().div_ceil(columns);
+
+    let file_name = config.figure_file("best routes", "")?;
+    config.write_csv(
+        &file_name,
+        &["panel", "distance", "position", "x", "y"],
+        panels.iter().flat_map(|(name, distance, cities, _)| {
+            cities.iter().enumerate().map(move |(position, (x, y))| {
+                vec![
+                    name.clone(),
+                    distance.to_string(),
+                    position.to_string(),
+                    x.to_string(),
+                    y.to_string(),
+                ]
+            })
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        for (area, (name, distance, cities, color)) in
+            root.split_evenly((rows, columns)).iter().zip(&panels)
+        {
+            let caption = format!("{} ({})", name, distance);
+            let mut chart = config.chart_builder(area, &caption).build_cartesian_2d(
+                coord_range.0 - 1.0..coord_range.1 + 1.0,
+                coord_range.2 - 1.0..coord_range.3 + 1.0,
+            )?;
+            config.mesh(chart.configure_mesh()).disable_mesh().draw()?;
+
+            chart.draw_series(LineSeries::new(
+                cities.iter().chain(cities.first()).copied(),
+                color.stroke_width(config.line_width),
+            ))?;
+            chart.draw_series(PointSeries::of_element(
+                cities.iter().copied(),
+                config.point_radius,
+                &config.foreground(),
+                &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
+            ))?;
+        }
+    });
+
+    Ok(())
+}
+
+/// Renders every `step`-th entry of `history` (plus the final one) as a frame of
+/// an animated GIF showing how the best tour evolved, or as numbered PNG
+/// frames when `animation_frames` is set.
+pub fn animate_history(
+    history: &[Route],
+    tsp: &TspLib,
+    title: &str,
+    step: usize,
+    config: &PlotConfig,
+) -> Result<()> {
+    if history.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.output_file(title, "_evolution.gif")?;
+    let config = &config.scaled();
+    let size = (config.animation_width, config.animation_height);
+    let coord_range = coord_range(&tsp.cities);
+    let step = step.max(1);
+    let last = history.len() - 1;
+    let frames: Vec<usize> = (0..history.len())
+        .step_by(step)
+        .chain((!last.is_multiple_of(step)).then_some(last))
+        .collect();
+    config.write_csv(
+        &file_name,
+        &["iteration", "distance", "position", "x", "y"],
+        frames.iter().flat_map(|&iteration| {
+            let route = &history[iteration];
+            route
+                .tour
+                .iter()
+                .map(|&city| tsp.cities[city])
+                .enumerate()
+                .map(move |(position, (x, y))| {
+                    vec![
+                        iteration.to_string(),
+                        route.distance.to_string(),
+                        position.to_string(),
+                        x.to_string(),
+                        y.to_string(),
+                    ]
+                })
+        }),
+    )?;
+
+    if config.animation_frames {
+        let dir = config.output_file(title, "_evolution")?;
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("failed to create frame directory {}", dir.display()))?;
+        for (frame, &iteration) in frames.iter().enumerate() {
+            let path = dir.join(format!("frame_{:05}.png", frame));
+            let root = BitMapBackend::new(&path, size).into_drawing_area();
+            draw_animation_frame(&root, history, iteration, tsp, title, coord_range, config)?;
+        }
+    } else {
+        let root = BitMapBackend::gif(&file_name, size, 1000 / config.animation_fps.max(1))?
+            .into_drawing_area();
+        for &iteration in &frames {
+            draw_animation_frame(&root, history, iteration, tsp, title, coord_range, config)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draws the best tour at `iteration` of `history` on `root` and presents it
+/// as the next frame.
+fn draw_animation_frame<DB>(
+    root: &DrawingArea<DB, Shift>,
+    history: &[Route],
+    iteration: usize,
+    tsp: &TspLib,
+    title: &str,
+    coord_range: (f64, f64, f64, f64),
+    config: &PlotConfig,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let route = &history[iteration];
+    root.fill(&config.background())?;
+
+    let mut builder = ChartBuilder::on(root);
+    builder.margin(config.margin);
+    if config.show_captions {
+        builder.caption(
+            format!(
+                "{} - iteration {} - distance {}",
+                title, iteration, route.distance
+            ),
+            config.label_font(),
+        );
+    }
+    let mut chart = builder.build_cartesian_2d(
+        coord_range.0 - 1.0..coord_range.1 + 1.0,
+        coord_range.2 - 1.0..coord_range.3 + 1.0,
+    )?;
+
+    let cities = route.coordinates(tsp);
     chart.draw_series(PointSeries::of_element(
-        route.cities.clone(),
-        5,
-        &BLACK,
+        cities.clone(),
+        config.point_radius.div_ceil(2),
+        &config.foreground(),
         &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
     ))?;
-    chart.draw_series(LineSeries::new(route.cities.clone(), color))?;
     chart.draw_series(LineSeries::new(
-        vec![route.cities[route.cities.len() - 1], route.cities[0]],
-        color,
+        cities.iter().chain(cities.first()).copied(),
+        BLUE.stroke_width(config.line_width),
     ))?;
 
     root.present()?;
+    Ok(())
+}
+
+/// Plots the best distance per iteration, or the percentage gap above the
+/// optimum with a reference line when the instance's optimal length is known.
+/// The x-axis is wall-clock time instead when `elapsed_ms` holds the timestamp
+/// of each history entry.
+fn chart_history(
+    history: &[Route],
+    elapsed_ms: Option<&[u64]>,
+    tsp: &TspLib,
+    title: &str,
+    annotation: &[String],
+    config: &PlotConfig,
+) -> Result<()> {
+    let (suffix, x_key, x_desc, x_values): (&str, &str, &str, Vec<f64>) = match elapsed_ms {
+        Some(times) => (
+            "_history_time",
+            "elapsed_ms",
+            "Elapsed time (ms)",
+            times.iter().map(|&t| t as f64).collect(),
+        ),
+        None => (
+            "_history",
+            "iteration",
+            "Iteration",
+            (0..history.len()).map(|i| i as f64).collect(),
+        ),
+    };
+    let file_name = config.figure_file(title, suffix)?;
+    let mut header = vec![x_key, "distance"];
+    if tsp.optimal_tour_length.is_some() {
+        header.push("gap_percent");
+    }
+    config.write_csv(
+        &file_name,
+        &header,
+        x_values.iter().zip(history).map(|(x, route)| {
+            let mut row = vec![x.to_string(), route.distance.to_string()];
+            row.extend(stats::gap(route.distance as f64, tsp).map(|gap| gap.to_string()));
+            row
+        }),
+    )?;
+
+    let (mut values, y_desc): (Vec<f64>, &str) = match tsp.optimal_tour_length {
+        Some(_) => (
+            history
+                .iter()
+                .map(|r| stats::gap(r.distance as f64, tsp).unwrap())
+                .collect(),
+            "Gap to optimum (%)",
+        ),
+        None => (
+            history.iter().map(|r| r.distance as f64).collect(),
+            "Distance",
+        ),
+    };
+    let mut reference = tsp.optimal_tour_length.map(|_| 0.0);
+    if config.log_y {
+        values.iter_mut().for_each(|v| *v = v.max(LOG_GAP_FLOOR));
+        reference = None;
+    }
+
+    let mut min_value = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let mut max_value = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    if values.is_empty() {
+        (min_value, max_value) = (1.0, 200.0);
+    }
+    if let Some(reference) = reference {
+        min_value = min_value.min(reference);
+    }
+    if max_value <= min_value {
+        max_value = min_value + 1.0;
+    }
+
+    // The x-axis is shifted by one so the first entry survives a log scale.
+    let offset = if config.log_x { 1.0 } else { 0.0 };
+    let points: Vec<(f64, f64)> = x_values
+        .iter()
+        .zip(values.iter())
+        .map(|(&x, &v)| (x + offset, v))
+        .collect();
+    let max_x = x_values.last().copied().unwrap_or(0.0).max(1.0);
+    let x_range = offset..max_x + offset;
+    let y_range = min_value..max_value;
+    let series = HistorySeries {
+        title,
+        x_desc,
+        y_desc,
+        points: &points,
+        reference,
+    };
+
+    draw_figure!(config, &file_name, |root| {
+        match (config.log_x, config.log_y) {
+            (false, false) => draw_history(&root, x_range, y_range, &series, config),
+            (true, false) => draw_history(&root, x_range.log_scale(), y_range, &series, config),
+            (false, true) => draw_history(&root, x_range, y_range.log_scale(), &series, config),
+            (true, true) => draw_history(
+                &root,
+                x_range.log_scale(),
+                y_range.log_scale(),
+                &series,
+                config,
+            ),
+        }?;
+        draw_annotation(&root, annotation, config)?;
+    });
 
     Ok(())
 }
 
-fn chart_history(history: Vec<Route>, title: &str) -> Result<()> {
-    let file_name = format!(
-        "./results/{}_history.png",
-        title.to_lowercase().replace(" ", "_")
-    );
-    let root = BitMapBackend::new(&file_name, FIG_SIZE).into_drawing_area();
-    root.fill(&WHITE)?;
+/// Plots the mean convergence curve of repeated runs of one algorithm with a
+/// shaded band between the best and worst run at every iteration.
+pub fn plot_history_band(
+    histories: &[Vec<Route>],
+    tsp: &TspLib,
+    title: &str,
+    color: &RGBColor,
+    config: &PlotConfig,
+) -> Result<()> {
+    let iterations = histories.iter().map(|h| h.len()).min().unwrap_or(0);
+    if iterations == 0 {
+        return Ok(());
+    }
+
+    let value = |route: &Route| match stats::gap(route.distance as f64, tsp) {
+        Some(gap) => gap,
+        None => route.distance as f64,
+    };
+    let y_desc = match tsp.optimal_tour_length {
+        Some(_) => "Gap to optimum (%)",
+        None => "Distance",
+    };
 
-    let min_distance = history.iter().map(|r| r.distance).min().unwrap_or(0);
-    let max_distance = history.iter().map(|r| r.distance).max().unwrap_or(200);
+    // (iteration, min, mean, max) across runs
+    let bands: Vec<(f64, f64, f64, f64)> = (0..iterations)
+        .map(|i| {
+            let values: Vec<f64> = histories.iter().map(|h| value(&h[i])).collect();
+            let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            (i as f64, min, mean, max)
+        })
+        .collect();
+    // The optimum, a gap of 0, is drawn as a reference line when known.
+    let reference = tsp.optimal_tour_length.map(|_| 0.0);
+    let min_value = bands
+        .iter()
+        .map(|b| b.1)
+        .chain(reference)
+        .fold(f64::INFINITY, f64::min);
+    let max_value = bands
+        .iter()
+        .map(|b| b.3)
+        .fold(f64::NEG_INFINITY, f64::max)
+        .max(min_value + 1.0);
 
-    let mut chart = ChartBuilder::on(&root)
-        .caption(title, ("sans-serif", 50).into_font())
-        .margin(5)
-        .x_label_area_size(75)
-        .y_label_area_size(75)
-        .build_cartesian_2d(0..history.len() as u32, min_distance..max_distance)?;
+    let file_name = config.figure_file(title, "_history_runs")?;
+    config.write_csv(
+        &file_name,
+        &["iteration", "min", "mean", "max"],
+        bands.iter().map(|&(x, min, mean, max)| {
+            vec![
+                x.to_string(),
+                min.to_string(),
+                mean.to_string(),
+                max.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let caption = format!("{} ({} runs)", title, histories.len());
+        let mut chart = config
+            .chart_builder(&root, &caption)
+            .build_cartesian_2d(0.0..iterations as f64, min_value..max_value)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Iteration")
+            .y_desc(y_desc)
+            .draw()?;
 
-    chart
-        .configure_mesh()
-        .x_desc("Iteration")
-        .y_desc("Distance")
-        .x_label_style(("sans-serif", 25).into_font())
-        .y_label_style(("sans-serif", 25).into_font())
+        let band: Vec<(f64, f64)> = bands
+            .iter()
+            .map(|&(x, _, _, max)| (x, max))
+            .chain(bands.iter().rev().map(|&(x, min, _, _)| (x, min)))
+            .collect();
+        chart
+            .draw_series(std::iter::once(Polygon::new(band, color.mix(0.2))))?
+            .label("min-max")
+            .legend(|(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.mix(0.2).filled())
+            });
+        chart
+            .draw_series(LineSeries::new(
+                bands.iter().map(|&(x, _, mean, _)| (x, mean)),
+                color.stroke_width(config.line_width),
+            ))?
+            .label("mean")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        if let Some(reference) = reference {
+            let style = config.foreground().stroke_width(config.line_width * 2);
+            chart
+                .draw_series(DashedLineSeries::new(
+                    vec![(0.0, reference), (iterations as f64, reference)],
+                    15,
+                    10,
+                    style,
+                ))?
+                .label("optimum")
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], style));
+        }
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(())
+}
+
+struct HistorySeries<'a> {
+    title: &'a str,
+    x_desc: &'a str,
+    y_desc: &'a str,
+    points: &'a [(f64, f64)],
+    reference: Option<f64>,
+}
+
+fn draw_history<DB, X, Y>(
+    root: &DrawingArea<DB, Shift>,
+    x_range: X,
+    y_range: Y,
+    series: &HistorySeries,
+    config: &PlotConfig,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+    X: AsRangedCoord<Value = f64>,
+    Y: AsRangedCoord<Value = f64>,
+    X::CoordDescType: ValueFormatter<f64>,
+    Y::CoordDescType: ValueFormatter<f64>,
+{
+    let mut chart = config
+        .chart_builder(root, series.title)
+        .build_cartesian_2d(x_range, y_range)?;
+
+    config
+        .mesh(chart.configure_mesh())
+        .x_desc(series.x_desc)
+        .y_desc(series.y_desc)
         .draw()?;
 
+    if let (Some(reference), Some(first), Some(last)) = (
+        series.reference,
+        series.points.first(),
+        series.points.last(),
+    ) {
+        chart.draw_series(DashedLineSeries::new(
+            vec![(first.0, reference), (last.0, reference)],
+            15,
+            10,
+            config.foreground().stroke_width(config.line_width * 2),
+        ))?;
+    }
+
     chart.draw_series(LineSeries::new(
-        history
+        series.points.iter().copied(),
+        RED.stroke_width(config.line_width),
+    ))?;
+
+    Ok(())
+}
+
+/// Most panels drawn in the pheromone figure; extra snapshots are thinned out.
+const MAX_PHEROMONE_PANELS: usize = 9;
+
+/// Draws pheromone-weighted edges over the city layout for a sample of the
+/// recorded snapshots, one panel per snapshot.
+pub fn plot_pheromone_snapshots(
+    snapshots: &[(usize, Vec<Vec<f64>>)],
+    tsp: &TspLib,
+    title: &str,
+    config: &PlotConfig,
+) -> Result<()> {
+    if snapshots.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file(title, "_pheromone")?;
+    let mut drawn_edges = Vec::new();
+    draw_figure!(config, &file_name, |root| {
+        let step = snapshots.len().div_ceil(MAX_PHEROMONE_PANELS);
+        let selected: Vec<&(usize, Vec<Vec<f64>>)> = snapshots
+            .iter()
+            .step_by(step)
+            .chain((!(snapshots.len() - 1).is_multiple_of(step)).then(|| snapshots.last().unwrap()))
+            .collect();
+        let cols = (selected.len() as f64).sqrt().ceil() as usize;
+        let rows = selected.len().div_ceil(cols);
+
+        let coord_range = coord_range(&tsp.cities);
+        for (panel, (iteration, pheromone)) in root.split_evenly((rows, cols)).iter().zip(selected)
+        {
+            let max_pheromone = pheromone
+                .iter()
+                .flatten()
+                .copied()
+                .fold(f64::MIN_POSITIVE, f64::max);
+
+            let mut chart = config
+                .chart_builder(panel, &format!("Iteration {}", iteration))
+                .build_cartesian_2d(
+                    coord_range.0 - 1.0..coord_range.1 + 1.0,
+                    coord_range.2 - 1.0..coord_range.3 + 1.0,
+                )?;
+            config.mesh(chart.configure_mesh()).disable_mesh().draw()?;
+
+            // Faint trails would only add noise and drawing time.
+            let mut edges: Vec<(f64, usize, usize)> = pheromone
+                .iter()
+                .enumerate()
+                .flat_map(|(i, row)| {
+                    row.iter()
+                        .enumerate()
+                        .skip(i + 1)
+                        .map(move |(j, &value)| (value / max_pheromone, i, j))
+                })
+                .filter(|&(intensity, _, _)| intensity >= 0.05)
+                .collect();
+            edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+            drawn_edges.extend(edges.iter().map(|&(_, i, j)| {
+                vec![
+                    iteration.to_string(),
+                    (i + 1).to_string(),
+                    (j + 1).to_string(),
+                    pheromone[i][j].to_string(),
+                ]
+            }));
+
+            chart.draw_series(edges.into_iter().map(|(intensity, i, j)| {
+                let color = RGBColor(255, (200.0 * (1.0 - intensity)) as u8, 0)
+                    .mix(0.15 + 0.85 * intensity);
+                PathElement::new(
+                    vec![tsp.cities[i], tsp.cities[j]],
+                    color.stroke_width(config.line_width),
+                )
+            }))?;
+            chart.draw_series(PointSeries::of_element(
+                tsp.cities.clone(),
+                config.point_radius.div_ceil(2),
+                &config.foreground(),
+                &|c, s, st| EmptyElement::at(c) + Circle::new((0, 0), s, st.filled()),
+            ))?;
+        }
+    });
+    config.write_csv(
+        &file_name,
+        &["iteration", "from", "to", "pheromone"],
+        drawn_edges,
+    )?;
+
+    Ok(())
+}
+
+/// Draws a 0..1 diversity curve on its own panel below a fitness chart.
+fn draw_diversity<DB>(
+    area: &DrawingArea<DB, Shift>,
+    title: &str,
+    x_desc: &str,
+    stats: &[(f64, Diversity)],
+    config: &PlotConfig,
+) -> Result<()>
+where
+    DB: DrawingBackend,
+    DB::ErrorType: 'static,
+{
+    let mut chart = config
+        .chart_builder(area, title)
+        .build_cartesian_2d(0..stats.len() as u32, 0.0..1.0)?;
+    config
+        .mesh(chart.configure_mesh())
+        .x_desc(x_desc)
+        .y_desc("Diversity")
+        .y_labels(5)
+        .draw()?;
+
+    let measure = |f: fn(&(f64, Diversity)) -> f64| stats.iter().map(f).collect::<Vec<f64>>();
+    let series = [
+        ("distance to best", measure(|&(d, _)| d), MAGENTA),
+        (
+            "pairwise distance",
+            measure(|(_, d)| d.pairwise_distance),
+            BLUE,
+        ),
+        ("edge entropy", measure(|(_, d)| d.edge_entropy), GREEN),
+        (
+            "distinct tours",
+            measure(|(_, d)| d.distinct_share()),
+            RGBColor(255, 140, 0),
+        ),
+    ];
+    for (label, values, color) in series {
+        chart
+            .draw_series(LineSeries::new(
+                values.into_iter().enumerate().map(|(i, v)| (i as u32, v)),
+                color.stroke_width(config.line_width),
+            ))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+    }
+    chart
+        .configure_series_labels()
+        .label_font(config.label_font())
+        .background_style(config.background().mix(0.8))
+        .border_style(config.foreground())
+        .draw()?;
+
+    Ok(())
+}
+
+/// Plots the best/average/worst fitness band of the GA population above its
+/// diversity curve, which makes premature convergence easy to spot.
+pub fn plot_ga_population(
+    stats: &[GenerationStats],
+    title: &str,
+    config: &PlotConfig,
+) -> Result<()> {
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file(title, "_population")?;
+    config.write_csv(
+        &file_name,
+        &[
+            "generation",
+            "best",
+            "average",
+            "worst",
+            "diversity",
+            "distinct_tours",
+            "pairwise_distance",
+            "edge_entropy",
+        ],
+        stats.iter().enumerate().map(|(i, s)| {
+            vec![
+                i.to_string(),
+                s.best.to_string(),
+                s.average.to_string(),
+                s.worst.to_string(),
+                s.diversity.to_string(),
+                s.diversity_measures.distinct_tours.to_string(),
+                s.diversity_measures.pairwise_distance.to_string(),
+                s.diversity_measures.edge_entropy.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let (upper, lower) = root.split_vertically(config.height * 2 / 3);
+
+        let generations = stats.len() as u32;
+        let min_distance = stats.iter().map(|s| s.best).min().unwrap() as f64;
+        let max_distance =
+            (stats.iter().map(|s| s.worst).max().unwrap() as f64).max(min_distance + 1.0);
+
+        let mut chart = config
+            .chart_builder(&upper, title)
+            .build_cartesian_2d(0..generations, min_distance..max_distance)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Generation")
+            .y_desc("Distance")
+            .draw()?;
+
+        let band: Vec<(u32, f64)> = stats
             .iter()
             .enumerate()
-            .map(|(i, r)| (i as u32, r.distance)),
-        &RED,
-    ))?;
+            .map(|(i, s)| (i as u32, s.worst as f64))
+            .chain(
+                stats
+                    .iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(i, s)| (i as u32, s.best as f64)),
+            )
+            .collect();
+        chart
+            .draw_series(std::iter::once(Polygon::new(band, GREEN.mix(0.2))))?
+            .label("best-worst")
+            .legend(|(x, y)| {
+                Rectangle::new([(x, y - 5), (x + 20, y + 5)], GREEN.mix(0.2).filled())
+            });
+        chart
+            .draw_series(LineSeries::new(
+                stats.iter().enumerate().map(|(i, s)| (i as u32, s.average)),
+                BLUE.stroke_width(config.line_width),
+            ))?
+            .label("average")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+        chart
+            .draw_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.best as f64)),
+                RED.stroke_width(config.line_width),
+            ))?
+            .label("best")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+
+        draw_diversity(
+            &lower,
+            "Population diversity",
+            "Generation",
+            &stats
+                .iter()
+                .map(|s| (s.diversity, s.diversity_measures))
+                .collect::<Vec<_>>(),
+            config,
+        )?;
+    });
+
+    Ok(())
+}
+
+/// Plots the best distance per epoch above a dual-axis chart of temperature
+/// and acceptance rate, to judge whether the cooling schedule is too fast or
+/// too slow.
+pub fn plot_sa_schedule(
+    history: &[Route],
+    stats: &[EpochStats],
+    title: &str,
+    config: &PlotConfig,
+) -> Result<()> {
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file(title, "_schedule")?;
+    config.write_csv(
+        &file_name,
+        &["epoch", "best_distance", "temperature", "acceptance_rate"],
+        history.iter().zip(stats).enumerate().map(|(i, (r, s))| {
+            vec![
+                i.to_string(),
+                r.distance.to_string(),
+                s.temperature.to_string(),
+                s.acceptance_rate.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let (upper, lower) = root.split_vertically(config.height / 2);
+
+        let epochs = stats.len() as u32;
+        let min_distance = history.iter().map(|r| r.distance).min().unwrap_or(0) as f64;
+        let max_distance =
+            (history.iter().map(|r| r.distance).max().unwrap_or(0) as f64).max(min_distance + 1.0);
+
+        let mut chart = config
+            .chart_builder(&upper, title)
+            .build_cartesian_2d(0..epochs, min_distance..max_distance)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Epoch")
+            .y_desc("Best distance")
+            .draw()?;
+        chart.draw_series(LineSeries::new(
+            history
+                .iter()
+                .enumerate()
+                .map(|(i, r)| (i as u32, r.distance as f64)),
+            RED.stroke_width(config.line_width),
+        ))?;
+
+        let max_temperature = stats.iter().map(|s| s.temperature).fold(f64::MIN, f64::max);
+        let mut chart = ChartBuilder::on(&lower)
+            .margin(config.margin)
+            .x_label_area_size(config.label_area_size)
+            .y_label_area_size(config.label_area_size)
+            .right_y_label_area_size(config.label_area_size)
+            .build_cartesian_2d(0..epochs, 0.0..max_temperature)?
+            .set_secondary_coord(0..epochs, 0.0..1.0);
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Epoch")
+            .y_desc("Temperature")
+            .draw()?;
+        chart
+            .configure_secondary_axes()
+            .y_desc("Acceptance rate")
+            .axis_style(config.foreground())
+            .label_style(config.label_font())
+            .draw()?;
+        chart
+            .draw_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.temperature)),
+                BLUE.stroke_width(config.line_width),
+            ))?
+            .label("temperature")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+        chart
+            .draw_secondary_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.acceptance_rate)),
+                GREEN.stroke_width(config.line_width),
+            ))?
+            .label("acceptance rate")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(())
+}
+
+/// Plots the global best and mean particle fitness above the swarm diversity,
+/// to diagnose a swarm that has collapsed onto a single tour.
+pub fn plot_pso_swarm(
+    history: &[Route],
+    stats: &[SwarmStats],
+    title: &str,
+    config: &PlotConfig,
+) -> Result<()> {
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file(title, "_swarm")?;
+    config.write_csv(
+        &file_name,
+        &[
+            "iteration",
+            "global_best",
+            "mean_fitness",
+            "diversity",
+            "distinct_tours",
+            "pairwise_distance",
+            "edge_entropy",
+        ],
+        history.iter().zip(stats).enumerate().map(|(i, (r, s))| {
+            vec![
+                i.to_string(),
+                r.distance.to_string(),
+                s.mean_fitness.to_string(),
+                s.diversity.to_string(),
+                s.diversity_measures.distinct_tours.to_string(),
+                s.diversity_measures.pairwise_distance.to_string(),
+                s.diversity_measures.edge_entropy.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let (upper, lower) = root.split_vertically(config.height * 2 / 3);
+
+        let iterations = stats.len() as u32;
+        let min_distance = history.iter().map(|r| r.distance).min().unwrap_or(0) as f64;
+        let max_distance = stats
+            .iter()
+            .map(|s| s.mean_fitness)
+            .fold(min_distance + 1.0, f64::max);
+
+        let mut chart = config
+            .chart_builder(&upper, title)
+            .build_cartesian_2d(0..iterations, min_distance..max_distance)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Iteration")
+            .y_desc("Distance")
+            .draw()?;
+        chart
+            .draw_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.mean_fitness)),
+                BLUE.stroke_width(config.line_width),
+            ))?
+            .label("mean fitness")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+        chart
+            .draw_series(LineSeries::new(
+                history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| (i as u32, r.distance as f64)),
+                RED.stroke_width(config.line_width),
+            ))?
+            .label("global best")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+
+        draw_diversity(
+            &lower,
+            "Swarm diversity",
+            "Iteration",
+            &stats
+                .iter()
+                .map(|s| (s.diversity, s.diversity_measures))
+                .collect::<Vec<_>>(),
+            config,
+        )?;
+    });
+
+    Ok(())
+}
+
+/// Plots the best and current tour lengths of a tabu search above the share
+/// of candidate moves it turned down as tabu, with the iterations where a tabu
+/// move was taken for beating the best tour marked.
+pub fn plot_tabu_search(
+    history: &[Route],
+    stats: &[TabuStats],
+    title: &str,
+    config: &PlotConfig,
+) -> Result<()> {
+    if stats.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file(title, "_tabu")?;
+    config.write_csv(
+        &file_name,
+        &[
+            "iteration",
+            "best_distance",
+            "current_distance",
+            "tabu_rate",
+            "aspiration",
+        ],
+        history.iter().zip(stats).enumerate().map(|(i, (r, s))| {
+            vec![
+                i.to_string(),
+                r.distance.to_string(),
+                s.current_distance.to_string(),
+                s.tabu_rate.to_string(),
+                s.aspiration.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let (upper, lower) = root.split_vertically(config.height * 2 / 3);
+
+        let iterations = stats.len() as u32;
+        let min_distance = history.iter().map(|r| r.distance).min().unwrap_or(0) as f64;
+        let max_distance = stats
+            .iter()
+            .map(|s| s.current_distance as f64)
+            .fold(min_distance + 1.0, f64::max);
+
+        let mut chart = config
+            .chart_builder(&upper, title)
+            .build_cartesian_2d(0..iterations, min_distance..max_distance)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Iteration")
+            .y_desc("Distance")
+            .draw()?;
+        chart
+            .draw_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.current_distance as f64)),
+                BLUE.stroke_width(config.line_width),
+            ))?
+            .label("current")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+        chart
+            .draw_series(LineSeries::new(
+                history
+                    .iter()
+                    .enumerate()
+                    .map(|(i, r)| (i as u32, r.distance as f64)),
+                RED.stroke_width(config.line_width),
+            ))?
+            .label("best")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+
+        let mut chart = ChartBuilder::on(&lower)
+            .margin(config.margin)
+            .x_label_area_size(config.label_area_size)
+            .y_label_area_size(config.label_area_size)
+            .build_cartesian_2d(0..iterations, 0.0..1.0)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Iteration")
+            .y_desc("Tabu candidates")
+            .draw()?;
+        chart
+            .draw_series(LineSeries::new(
+                stats
+                    .iter()
+                    .enumerate()
+                    .map(|(i, s)| (i as u32, s.tabu_rate)),
+                GREEN.stroke_width(config.line_width),
+            ))?
+            .label("tabu rate")
+            .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], GREEN));
+        chart
+            .draw_series(
+                stats
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, s)| s.aspiration)
+                    .map(|(i, s)| Circle::new((i as u32, s.tabu_rate), 3, RED.filled())),
+            )?
+            .label("aspiration")
+            .legend(|(x, y)| Circle::new((x + 10, y), 3, RED.filled()));
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(())
+}
+
+/// Scatters every tried value of each hyperparameter against the mean distance
+/// it achieved, one figure per algorithm and one panel per parameter.
+pub fn plot_hyper_results(results: &[OptimizationResult], config: &PlotConfig) -> Result<()> {
+    let mut algorithms: Vec<&str> = results.iter().map(|r| r.algorithm.as_str()).collect();
+    algorithms.dedup();
+
+    for algorithm in algorithms {
+        let trials: Vec<&OptimizationResult> = results
+            .iter()
+            .filter(|r| r.algorithm == algorithm)
+            .collect();
+        let parameters: Vec<String> = trials[0]
+            .parameter_values()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        if parameters.is_empty() {
+            continue;
+        }
+
+        let min_distance = trials
+            .iter()
+            .map(|r| r.mean_distance)
+            .fold(f64::INFINITY, f64::min);
+        let max_distance = trials
+            .iter()
+            .map(|r| r.mean_distance)
+            .fold(f64::NEG_INFINITY, f64::max)
+            .max(min_distance + 1.0);
+        let padding = (max_distance - min_distance) * 0.05;
+
+        let file_name = config.figure_file(&format!("hyper {}", algorithm), "")?;
+        let header: Vec<&str> = std::iter::once("trial")
+            .chain(parameters.iter().map(String::as_str))
+            .chain(["distance", "mean_distance", "std_distance", "runtime_ms"])
+            .collect();
+        config.write_csv(
+            &file_name,
+            &header,
+            trials.iter().enumerate().map(|(trial, r)| {
+                std::iter::once(trial.to_string())
+                    .chain(
+                        r.parameter_values()
+                            .into_iter()
+                            .map(|(_, value)| value.to_string()),
+                    )
+                    .chain([
+                        r.distance.to_string(),
+                        r.mean_distance.to_string(),
+                        r.std_distance.to_string(),
+                        r.runtime_ms.to_string(),
+                    ])
+                    .collect()
+            }),
+        )?;
+        draw_figure!(config, &file_name, |root| {
+            let columns = (parameters.len() as f64).sqrt().ceil() as usize;
+            let rows = parameters.len().div_ceil(columns);
+            for (index, (area, parameter)) in root
+                .split_evenly((rows, columns))
+                .iter()
+                .zip(&parameters)
+                .enumerate()
+            {
+                let points: Vec<(f64, f64)> = trials
+                    .iter()
+                    .map(|r| (r.parameter_values()[index].1, r.mean_distance))
+                    .collect();
+                let min_value = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+                let mut max_value = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+                if max_value <= min_value {
+                    // Every trial used the same value: widen the axis around it.
+                    max_value = (min_value * 1.01).max(min_value + 1.0);
+                }
+                let margin = (max_value - min_value) * 0.05;
+
+                let caption = format!("{} {}", algorithm, parameter);
+                let mut chart = config.chart_builder(area, &caption).build_cartesian_2d(
+                    min_value - margin..max_value + margin,
+                    min_distance - padding..max_distance + padding,
+                )?;
+                config
+                    .mesh(chart.configure_mesh())
+                    .x_desc(parameter)
+                    .y_desc("Mean distance")
+                    .x_labels(5)
+                    .y_labels(5)
+                    .draw()?;
+                chart.draw_series(points.iter().map(|&point| {
+                    Circle::new(point, config.point_radius, BLUE.mix(0.6).filled())
+                }))?;
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Scatters every trial's mean distance against its runtime, one color per
+/// algorithm, and joins each algorithm's Pareto-optimal trials into a front.
+pub fn plot_pareto_front(results: &[OptimizationResult], config: &PlotConfig) -> Result<()> {
+    if results.is_empty() {
+        return Ok(());
+    }
+    let mut algorithms: Vec<&str> = results.iter().map(|r| r.algorithm.as_str()).collect();
+    algorithms.dedup();
+    let pareto = hyper::pareto_optimal(results);
+    let colors = config.series_colors()?;
+
+    let max_runtime = results
+        .iter()
+        .map(|r| r.runtime_ms)
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let min_distance = results
+        .iter()
+        .map(|r| r.mean_distance)
+        .fold(f64::INFINITY, f64::min);
+    let max_distance = results
+        .iter()
+        .map(|r| r.mean_distance)
+        .fold(f64::NEG_INFINITY, f64::max)
+        .max(min_distance + 1.0);
+    let padding = (max_distance - min_distance) * 0.05;
+
+    let file_name = config.figure_file("hyper pareto", "")?;
+    config.write_csv(
+        &file_name,
+        &["algorithm", "mean_distance", "runtime_ms", "pareto"],
+        results.iter().zip(&pareto).map(|(r, pareto)| {
+            vec![
+                r.algorithm.clone(),
+                r.mean_distance.to_string(),
+                r.runtime_ms.to_string(),
+                pareto.to_string(),
+            ]
+        }),
+    )?;
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config
+            .chart_builder(&root, "Distance vs runtime")
+            .build_cartesian_2d(
+                0.0..max_runtime * 1.05,
+                min_distance - padding..max_distance + padding,
+            )?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Runtime (ms)")
+            .y_desc("Mean distance")
+            .draw()?;
+
+        for (index, algorithm) in algorithms.iter().enumerate() {
+            let color = colors[index % colors.len()];
+            let trials: Vec<(&OptimizationResult, bool)> = results
+                .iter()
+                .zip(pareto.iter().copied())
+                .filter(|(r, _)| r.algorithm == *algorithm)
+                .collect();
+            chart.draw_series(trials.iter().map(|(r, _)| {
+                Circle::new(
+                    (r.runtime_ms as f64, r.mean_distance),
+                    config.point_radius,
+                    color.mix(0.4).filled(),
+                )
+            }))?;
+
+            let mut front: Vec<(f64, f64)> = trials
+                .iter()
+                .filter(|(_, pareto)| *pareto)
+                .map(|(r, _)| (r.runtime_ms as f64, r.mean_distance))
+                .collect();
+            front.sort_by(|a, b| a.0.total_cmp(&b.0));
+            chart
+                .draw_series(LineSeries::new(
+                    front.iter().copied(),
+                    color.stroke_width(config.line_width),
+                ))?
+                .label(*algorithm)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            chart.draw_series(
+                front
+                    .iter()
+                    .map(|&point| Circle::new(point, config.point_radius + 1, color.filled())),
+            )?;
+        }
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
 
     Ok(())
 }
+
+/// An algorithm's name, its [`crate::stats::anytime_curve`] and its color.
+pub type AnytimeCurve<'a> = (&'a str, Vec<(u64, u64)>, RGBColor);
+
+/// Overlays the anytime curves of several algorithms, the best distance each
+/// had found (or its gap to the optimum, if known) against wall time, as step
+/// lines so the algorithms can be compared at any time budget. A known
+/// optimum is drawn as a dashed baseline. Returns the figure's path, unless
+/// there was nothing to plot.
+pub fn plot_anytime_curves(
+    curves: &[AnytimeCurve],
+    tsp: &TspLib,
+    config: &PlotConfig,
+) -> Result<Option<PathBuf>> {
+    let curves: Vec<&AnytimeCurve> = curves.iter().filter(|(_, c, _)| !c.is_empty()).collect();
+    if curves.is_empty() {
+        return Ok(None);
+    }
+    let value = |distance: u64| stats::gap(distance as f64, tsp).unwrap_or(distance as f64);
+    let y_desc = match tsp.optimal_tour_length {
+        Some(_) => "Gap to optimum (%)",
+        None => "Best distance",
+    };
+
+    let file_name = config.figure_file("anytime", "")?;
+    config.write_csv(
+        &file_name,
+        &["algorithm", "elapsed_ms", "distance"],
+        curves.iter().flat_map(|(name, curve, _)| {
+            curve.iter().map(|&(time, distance)| {
+                vec![name.to_string(), time.to_string(), distance.to_string()]
+            })
+        }),
+    )?;
+
+    let max_time = curves
+        .iter()
+        .flat_map(|(_, curve, _)| curve.iter().map(|&(time, _)| time))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let values = || {
+        curves
+            .iter()
+            .flat_map(|(_, curve, _)| curve.iter().map(|&(_, distance)| value(distance)))
+    };
+    // The optimum is a gap of 0.
+    let baseline = tsp.optimal_tour_length.map(|_| 0.0);
+    let min_value = values().chain(baseline).fold(f64::INFINITY, f64::min);
+    let max_value = values()
+        .fold(f64::NEG_INFINITY, f64::max)
+        .max(min_value + 1.0);
+    let padding = (max_value - min_value) * 0.05;
+
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config
+            .chart_builder(&root, "Best distance over time")
+            .build_cartesian_2d(
+                0.0..max_time * 1.02,
+                min_value - padding..max_value + padding,
+            )?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Elapsed time (ms)")
+            .y_desc(y_desc)
+            .draw()?;
+
+        if let Some(baseline) = baseline {
+            let foreground = config.foreground();
+            chart
+                .draw_series(DashedLineSeries::new(
+                    vec![(0.0, baseline), (max_time * 1.02, baseline)],
+                    15,
+                    10,
+                    foreground.stroke_width(config.line_width),
+                ))?
+                .label("Optimum")
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], foreground));
+        }
+        for &&(name, ref curve, color) in &curves {
+            // Hold each best distance until the next improvement.
+            let steps = curve.iter().enumerate().flat_map(|(i, &(time, distance))| {
+                let previous = i.checked_sub(1).map(|i| (time as f64, value(curve[i].1)));
+                previous
+                    .into_iter()
+                    .chain(std::iter::once((time as f64, value(distance))))
+            });
+            chart
+                .draw_series(LineSeries::new(
+                    steps,
+                    color.stroke_width(config.line_width),
+                ))?
+                .label(name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(Some(file_name))
+}
+
+/// An algorithm's name, the `(elapsed_ms, probability)` steps of its
+/// [`crate::stats::RunLengthDistribution`] and its color.
+pub type RunLengthCurve<'a> = (&'a str, Vec<(u64, f64)>, RGBColor);
+
+/// Overlays the run-length distributions of several algorithms: the share of
+/// their runs that had come within `gap` percent of the optimum by each point
+/// in time.
+pub fn plot_run_length_distributions(
+    curves: &[RunLengthCurve],
+    gap: f64,
+    config: &PlotConfig,
+) -> Result<()> {
+    if curves.is_empty() {
+        return Ok(());
+    }
+
+    let file_name = config.figure_file("run length distribution", "")?;
+    config.write_csv(
+        &file_name,
+        &["algorithm", "elapsed_ms", "probability"],
+        curves.iter().flat_map(|(name, curve, _)| {
+            curve.iter().map(|&(time, probability)| {
+                vec![name.to_string(), time.to_string(), probability.to_string()]
+            })
+        }),
+    )?;
+
+    let max_time = curves
+        .iter()
+        .flat_map(|(_, curve, _)| curve.iter().map(|&(time, _)| time))
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config
+            .chart_builder(&root, &format!("Runs within {}% of the optimum", gap))
+            .build_cartesian_2d(0.0..max_time * 1.05, 0.0..1.05)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Elapsed time (ms)")
+            .y_desc("Share of runs")
+            .draw()?;
+
+        for &(name, ref curve, color) in curves {
+            // Start at zero and hold each share until the next run succeeds.
+            let mut steps = vec![(0.0, 0.0)];
+            for &(time, probability) in curve {
+                let previous = steps.last().map_or(0.0, |&(_, p)| p);
+                steps.push((time as f64, previous));
+                steps.push((time as f64, probability));
+            }
+            steps.push((max_time * 1.05, steps.last().map_or(0.0, |&(_, p)| p)));
+            chart
+                .draw_series(LineSeries::new(
+                    steps,
+                    color.stroke_width(config.line_width),
+                ))?
+                .label(name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(())
+}
+
+/// Performance profile of one algorithm: its name, the `(tau, share)` steps of
+/// [`stats::performance_profiles`] and its color.
+pub type ProfileCurve<'a> = (&'a str, Vec<(f64, f64)>, RGBColor);
+
+/// Draws the performance profiles of several algorithms, compared by `metric`,
+/// and returns the figure's path; nothing is drawn if no algorithm solved any
+/// instance.
+pub fn plot_performance_profiles(
+    curves: &[ProfileCurve],
+    metric: &str,
+    config: &PlotConfig,
+) -> Result<Option<PathBuf>> {
+    if curves.iter().all(|(_, curve, _)| curve.is_empty()) {
+        return Ok(None);
+    }
+
+    let file_name = config.figure_file(&format!("performance profile {}", metric), "")?;
+    config.write_csv(
+        &file_name,
+        &["algorithm", "tau", "share"],
+        curves.iter().flat_map(|(name, curve, _)| {
+            curve
+                .iter()
+                .map(|&(tau, share)| vec![name.to_string(), tau.to_string(), share.to_string()])
+        }),
+    )?;
+
+    let max_tau = curves
+        .iter()
+        .flat_map(|(_, curve, _)| curve.iter().map(|&(tau, _)| tau))
+        .fold(1.0, f64::max);
+    let max_tau = 1.0 + (max_tau - 1.0).max(0.01) * 1.05;
+
+    draw_figure!(config, &file_name, |root| {
+        let mut chart = config
+            .chart_builder(&root, &format!("Performance profiles ({})", metric))
+            .build_cartesian_2d(1.0..max_tau, 0.0..1.05)?;
+        config
+            .mesh(chart.configure_mesh())
+            .x_desc("Factor of the best algorithm (τ)")
+            .y_desc("Share of instances")
+            .draw()?;
+
+        for &(name, ref curve, color) in curves {
+            // Start at no instance and hold each share until the next one.
+            let mut steps = vec![(1.0, 0.0)];
+            for &(tau, share) in curve {
+                let previous = steps.last().map_or(0.0, |&(_, s)| s);
+                steps.push((tau, previous));
+                steps.push((tau, share));
+            }
+            steps.push((max_tau, steps.last().map_or(0.0, |&(_, s)| s)));
+            chart
+                .draw_series(LineSeries::new(
+                    steps,
+                    color.stroke_width(config.line_width),
+                ))?
+                .label(name)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+        }
+        chart
+            .configure_series_labels()
+            .label_font(config.label_font())
+            .background_style(config.background().mix(0.8))
+            .border_style(config.foreground())
+            .draw()?;
+    });
+
+    Ok(Some(file_name))
+}
@@ -0,0 +1,153 @@
+use std::{fs, path::Path};
+
+use anyhow::Result;
+use serde_json::{json, Value};
+
+use crate::{
+    stats,
+    tsplib::{Route, TspLib},
+};
+
+const PLOTLY_URL: &str = "https://cdn.plot.ly/plotly-2.35.2.min.js";
+
+fn tour_trace(tsp: &TspLib, tour: &[usize], name: &str, color: &str, width: u32) -> Value {
+    let closed: Vec<usize> = tour.iter().chain(tour.first()).copied().collect();
+    let mut text: Vec<String> = closed
+        .iter()
+        .zip(closed.iter().skip(1))
+        .map(|(&a, &b)| {
+            format!(
+                "city {}<br>edge to city {}: {}",
+                a + 1,
+                b + 1,
+                tsp.distance_matrix.get(a, b)
+            )
+        })
+        .collect();
+    text.push(text[0].clone());
+
+    json!({
+        "type": "scatter",
+        "mode": "lines+markers",
+        "name": name,
+        "x": closed.iter().map(|&i| tsp.cities[i].0).collect::<Vec<f64>>(),
+        "y": closed.iter().map(|&i| tsp.cities[i].1).collect::<Vec<f64>>(),
+        "text": text,
+        "hoverinfo": "text",
+        "line": { "color": color, "width": width },
+        "marker": { "color": "black", "size": 6 },
+    })
+}
+
+fn history_trace(history: &[Route], tsp: &TspLib, color: &str) -> Value {
+    let text: Vec<String> = history
+        .iter()
+        .enumerate()
+        .map(|(i, r)| match stats::gap(r.distance as f64, tsp) {
+            Some(gap) => format!(
+                "iteration {}<br>distance {}<br>gap {}",
+                i,
+                r.distance,
+                stats::format_gap(gap)
+            ),
+            None => format!("iteration {}<br>distance {}", i, r.distance),
+        })
+        .collect();
+
+    json!({
+        "type": "scatter",
+        "mode": "lines",
+        "name": "best distance",
+        "x": (0..history.len()).collect::<Vec<usize>>(),
+        "y": history.iter().map(|r| r.distance).collect::<Vec<u64>>(),
+        "text": text,
+        "hoverinfo": "text",
+        "line": { "color": color },
+    })
+}
+
+/// Writes an interactive page with the best route and the convergence
+/// history of one algorithm. The data is embedded, but the page loads plotly
+/// from its CDN, so viewing it needs network access.
+pub fn write_algo_result(
+    file_name: &Path,
+    title: &str,
+    best_route: &Route,
+    history: &[Route],
+    tsp: &TspLib,
+    color: (u8, u8, u8),
+) -> Result<()> {
+    let color = format!("rgb({},{},{})", color.0, color.1, color.2);
+
+    let mut route_traces = Vec::new();
+    if let Some(optimal_tour) = &tsp.optimal_tour {
+        route_traces.push(tour_trace(tsp, optimal_tour, "optimal tour", "#c8c8c8", 6));
+    }
+    route_traces.push(tour_trace(
+        tsp,
+        &best_route.tour,
+        &format!("best route ({})", best_route.distance),
+        &color,
+        2,
+    ));
+
+    let mut history_traces = vec![history_trace(history, tsp, &color)];
+    if let Some(optimum) = tsp.optimal_tour_length {
+        history_traces.push(json!({
+            "type": "scatter",
+            "mode": "lines",
+            "name": "optimum",
+            "x": [0, history.len()],
+            "y": [optimum, optimum],
+            "line": { "color": "black", "dash": "dash" },
+        }));
+    }
+
+    let data = json!({
+        "route": {
+            "traces": route_traces,
+            "layout": {
+                "title": format!("{} - {}", title, tsp.name),
+                "xaxis": { "title": "X" },
+                "yaxis": { "title": "Y", "scaleanchor": "x" },
+                "hovermode": "closest",
+            },
+        },
+        "history": {
+            "traces": history_traces,
+            "layout": {
+                "title": "Convergence",
+                "xaxis": { "title": "Iteration" },
+                "yaxis": { "title": "Distance" },
+                "hovermode": "closest",
+            },
+        },
+    });
+
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<script src="{plotly}"></script>
+</head>
+<body>
+<div id="route" style="height:60vh"></div>
+<div id="history" style="height:35vh"></div>
+<script>
+const data = {data};
+Plotly.newPlot("route", data.route.traces, data.route.layout);
+Plotly.newPlot("history", data.history.traces, data.history.layout);
+</script>
+</body>
+</html>
+"#,
+        title = title,
+        plotly = PLOTLY_URL,
+        data = data,
+    );
+    fs::write(file_name, page)?;
+
+    Ok(())
+}
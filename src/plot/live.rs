@@ -0,0 +1,187 @@
+use std::{
+    sync::{mpsc, Mutex},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use minifb::{Window, WindowOptions};
+use plotters::prelude::*;
+
+use super::coord_range;
+use crate::{
+    progress::{IterationInfo, Observer},
+    tsplib::{City, TspLib},
+};
+
+/// How long the window thread waits for an update before polling input events.
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+
+struct Update {
+    algorithm: String,
+    iteration: usize,
+    cities: Vec<City>,
+    distance: u64,
+}
+
+/// A window that redraws the current best route through an instance and the
+/// convergence curve every `every` iterations while the solvers run.
+///
+/// The window lives on its own thread; solvers only push updates over a channel.
+pub struct LiveView {
+    every: usize,
+    cities: Vec<City>,
+    sender: Mutex<Option<mpsc::Sender<Update>>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LiveView {
+    pub fn open(width: usize, height: usize, every: usize, tsp: &TspLib) -> Result<Self> {
+        let (sender, receiver) = mpsc::channel::<Update>();
+        let (ready_sender, ready_receiver) = mpsc::channel::<Result<(), String>>();
+
+        let handle = thread::spawn(move || {
+            let mut window = match Window::new("sapso", width, height, WindowOptions::default()) {
+                Ok(window) => {
+                    let _ = ready_sender.send(Ok(()));
+                    window
+                }
+                Err(err) => {
+                    let _ = ready_sender.send(Err(err.to_string()));
+                    return;
+                }
+            };
+            run_window(&mut window, receiver, width, height);
+        });
+
+        ready_receiver
+            .recv()
+            .map_err(|_| anyhow!("live view thread exited unexpectedly"))?
+            .map_err(|err| anyhow!("failed to open live view window: {}", err))?;
+
+        Ok(LiveView {
+            every: every.max(1),
+            cities: tsp.cities.clone(),
+            sender: Mutex::new(Some(sender)),
+            handle: Some(handle),
+        })
+    }
+}
+
+impl Observer for LiveView {
+    fn on_iteration(&self, info: &IterationInfo) {
+        if !info.iteration.is_multiple_of(self.every) {
+            return;
+        }
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            // The user may have closed the window; the solver keeps going regardless.
+            let _ = sender.send(Update {
+                algorithm: info.algorithm.to_string(),
+                iteration: info.iteration,
+                cities: info
+                    .best
+                    .tour
+                    .iter()
+                    .map(|&city| self.cities[city])
+                    .collect(),
+                distance: info.best.distance,
+            });
+        }
+    }
+}
+
+impl Drop for LiveView {
+    fn drop(&mut self) {
+        self.sender.lock().unwrap().take();
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_window(window: &mut Window, receiver: mpsc::Receiver<Update>, width: usize, height: usize) {
+    let mut rgb = vec![255u8; width * height * 3];
+    let mut pixels = vec![0u32; width * height];
+    let mut algorithm = String::new();
+    let mut curve: Vec<(usize, u64)> = Vec::new();
+
+    while window.is_open() {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(update) => {
+                // A new algorithm, or a new run of the same one, starts a fresh curve.
+                if update.algorithm != algorithm || update.iteration == 0 {
+                    algorithm = update.algorithm.clone();
+                    curve.clear();
+                }
+                curve.push((update.iteration, update.distance));
+
+                if draw(&mut rgb, (width as u32, height as u32), &update, &curve).is_err() {
+                    continue;
+                }
+                for (pixel, rgb) in pixels.iter_mut().zip(rgb.chunks_exact(3)) {
+                    *pixel = (rgb[0] as u32) << 16 | (rgb[1] as u32) << 8 | rgb[2] as u32;
+                }
+                if window.update_with_buffer(&pixels, width, height).is_err() {
+                    return;
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => window.update(),
+            Err(mpsc::RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+fn draw(
+    buffer: &mut [u8],
+    size: (u32, u32),
+    update: &Update,
+    curve: &[(usize, u64)],
+) -> Result<()> {
+    let root = BitMapBackend::with_buffer(buffer, size).into_drawing_area();
+    root.fill(&WHITE)?;
+    let (left, right) = root.split_horizontally(size.0 / 2);
+
+    let coord_range = coord_range(&update.cities);
+    let caption = format!(
+        "{} - iteration {} ({})",
+        update.algorithm, update.iteration, update.distance
+    );
+    let mut chart = ChartBuilder::on(&left)
+        .caption(caption, ("sans-serif", 18))
+        .margin(5)
+        .build_cartesian_2d(
+            coord_range.0 - 1.0..coord_range.1 + 1.0,
+            coord_range.2 - 1.0..coord_range.3 + 1.0,
+        )?;
+    chart.draw_series(LineSeries::new(
+        update.cities.iter().chain(update.cities.first()).copied(),
+        &BLUE,
+    ))?;
+    chart.draw_series(
+        update
+            .cities
+            .iter()
+            .map(|&city| Circle::new(city, 2, BLACK.filled())),
+    )?;
+
+    let max_iteration = curve.last().map_or(1, |&(i, _)| i.max(1));
+    let min_distance = curve.iter().map(|&(_, d)| d).min().unwrap_or(0);
+    let max_distance = curve
+        .iter()
+        .map(|&(_, d)| d)
+        .max()
+        .unwrap_or(1)
+        .max(min_distance + 1);
+    let mut chart = ChartBuilder::on(&right)
+        .caption("Best distance", ("sans-serif", 18))
+        .margin(5)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..max_iteration, min_distance..max_distance)?;
+    chart.configure_mesh().draw()?;
+    chart.draw_series(LineSeries::new(curve.iter().copied(), &RED))?;
+
+    root.present()?;
+
+    Ok(())
+}
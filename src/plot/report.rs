@@ -0,0 +1,72 @@
+use std::fs;
+
+use anyhow::{Context, Result};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref};
+use svg2pdf::usvg::{self, fontdb, TreeParsing, TreePostProc};
+
+use super::PlotConfig;
+
+/// Bundles every SVG figure written with `config` into a single PDF,
+/// `{output_dir}/{instance}_report.pdf`, one figure per page.
+///
+/// Text is converted to outlines using the system fonts, so the report looks
+/// the same wherever it is opened.
+pub fn write_report(config: &PlotConfig) -> Result<()> {
+    let figures = config.figures.lock().unwrap().clone();
+    if figures.is_empty() {
+        return Ok(());
+    }
+
+    let mut fonts = fontdb::Database::new();
+    fonts.load_system_fonts();
+
+    let mut next_id = Ref::new(1);
+    let catalog_id = alloc(&mut next_id);
+    let page_tree_id = alloc(&mut next_id);
+    let mut page_ids = Vec::with_capacity(figures.len());
+    let mut pdf = Pdf::new();
+
+    for figure in &figures {
+        let svg = fs::read_to_string(figure)
+            .with_context(|| format!("failed to read {}", figure.display()))?;
+        let mut tree = usvg::Tree::from_str(&svg, &usvg::Options::default())
+            .with_context(|| format!("failed to parse {}", figure.display()))?;
+        tree.postprocess(usvg::PostProcessingSteps::default(), &fonts);
+
+        let page_id = alloc(&mut next_id);
+        let content_id = alloc(&mut next_id);
+        let svg_id = alloc(&mut next_id);
+        // The figure takes `svg_id` and as many ids after it as it needs.
+        next_id = svg2pdf::convert_tree_into(&tree, svg2pdf::Options::default(), &mut pdf, svg_id);
+
+        let (width, height) = (tree.size.width(), tree.size.height());
+        let svg_name = Name(b"Figure");
+        let mut page = pdf.page(page_id);
+        page.media_box(Rect::new(0.0, 0.0, width, height));
+        page.parent(page_tree_id);
+        page.contents(content_id);
+        page.resources().x_objects().pair(svg_name, svg_id);
+        page.finish();
+
+        let mut content = Content::new();
+        content
+            .transform([width, 0.0, 0.0, height, 0.0, 0.0])
+            .x_object(svg_name);
+        pdf.stream(content_id, &content.finish());
+        page_ids.push(page_id);
+    }
+
+    pdf.catalog(catalog_id).pages(page_tree_id);
+    pdf.pages(page_tree_id)
+        .kids(page_ids.iter().copied())
+        .count(page_ids.len() as i32);
+
+    let path = config.output_file("report", ".pdf")?;
+    fs::write(&path, pdf.finish()).with_context(|| format!("failed to write {}", path.display()))
+}
+
+fn alloc(next_id: &mut Ref) -> Ref {
+    let id = *next_id;
+    *next_id = Ref::new(id.get() + 1);
+    id
+}
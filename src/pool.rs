@@ -0,0 +1,95 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    config::{self, SolverSpec},
+    fitness::Evaluator,
+    progress::Observer,
+    tsplib::{HeuristicAlgorithm, Route, Solution, Termination, TspLib},
+};
+
+/// The best route found by any of the solvers sharing it. Solvers publish
+/// their improvements to it and pick up anything shorter than their own best,
+/// each in its own way: SA continues from it, GA swaps it in for its worst
+/// chromosome, ACO lays pheromone along it, PSO makes it the global best and
+/// Tabu Search moves its current tour there. Clones share the pool.
+#[derive(Clone, Default)]
+pub struct SolutionPool(Arc<Mutex<Option<Route>>>);
+
+impl SolutionPool {
+    pub fn new() -> Self {
+        SolutionPool::default()
+    }
+
+    /// Offers `route` to the pool, which keeps it if it is shorter than the
+    /// pool's best. Returns whether it was kept.
+    pub fn publish(&self, route: &Route) -> bool {
+        let mut best = self.0.lock().unwrap();
+        if best.as_ref().is_some_and(|b| b.distance <= route.distance) {
+            return false;
+        }
+        *best = Some(route.clone());
+        true
+    }
+
+    /// The pool's best route, if it is shorter than `distance`.
+    pub fn better_than(&self, distance: u64) -> Option<Route> {
+        self.0
+            .lock()
+            .unwrap()
+            .as_ref()
+            .filter(|best| best.distance < distance)
+            .cloned()
+    }
+
+    pub fn best(&self) -> Option<Route> {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Runs one solver per entry of `specs` concurrently on `tsp`, all sharing
+/// `pool` and `evaluator`, and returns each solver with its result in the
+/// order of `specs`.
+pub fn solve_cooperatively(
+    tsp: &TspLib,
+    specs: &[SolverSpec],
+    pool: &SolutionPool,
+    seed: Option<u64>,
+    termination: &Termination,
+    observers: &[Arc<dyn Observer>],
+    evaluator: &Evaluator,
+) -> Result<Vec<(Box<dyn HeuristicAlgorithm>, Solution)>> {
+    thread::scope(|scope| {
+        let handles: Vec<_> = specs
+            .iter()
+            .map(|spec| {
+                scope.spawn(move || {
+                    let mut solver = config::build_solver(tsp, spec);
+                    if let Some(seed) = seed {
+                        solver.set_seed(seed);
+                    }
+                    for observer in observers {
+                        solver.add_observer(Arc::clone(observer));
+                    }
+                    solver.set_pool(pool.clone());
+                    solver.set_evaluator(evaluator.clone());
+                    let solution = solver.solve(tsp, termination)?;
+                    Ok((solver, solution))
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .map_err(|_| anyhow!("a cooperating solver panicked"))?
+            })
+            .collect()
+    })
+}
@@ -0,0 +1,280 @@
+use std::{borrow::Cow, f64::consts::TAU, fmt, str::FromStr};
+
+use anyhow::{bail, Result};
+use rand::Rng;
+
+use crate::{
+    distance::{DistanceKind, DistanceMatrix},
+    tsplib::{self, City, TspLib},
+};
+
+/// A routing problem the solvers can run on: a number of cities and the
+/// distance from each to every other, which may differ both ways. Solvers
+/// run on the [`TspLib`] instance of a problem, which [`Problem::instance`]
+/// builds unless the problem already is one.
+pub trait Problem {
+    fn name(&self) -> &str;
+    /// Number of cities, numbered from 0.
+    fn dimension(&self) -> usize;
+    /// Distance from city `a` to city `b`.
+    fn distance(&self, a: usize, b: usize) -> u64;
+
+    /// Coordinates of the cities for plots, if the problem has any.
+    fn coordinates(&self) -> Option<&[City]> {
+        None
+    }
+
+    /// Length of the shortest tour, if known.
+    fn optimal_tour_length(&self) -> Option<u64> {
+        None
+    }
+
+    /// The instance solvers run on, with the problem's distances in memory
+    /// and its cities laid out on a circle if it has no coordinates.
+    fn instance(&self) -> Cow<'_, TspLib> {
+        let n = self.dimension();
+        let mut tsp = TspLib::from_distances(
+            self.name(),
+            self.coordinates()
+                .map_or_else(|| tsplib::circle_layout(n), <[City]>::to_vec),
+            DistanceMatrix::from_fn(n, |a, b| self.distance(a, b)),
+        );
+        tsp.optimal_tour_length = self.optimal_tour_length();
+        Cow::Owned(tsp)
+    }
+}
+
+impl Problem for TspLib {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn distance(&self, a: usize, b: usize) -> u64 {
+        self.distance_matrix.get(a, b)
+    }
+
+    fn coordinates(&self) -> Option<&[City]> {
+        Some(&self.cities)
+    }
+
+    fn optimal_tour_length(&self) -> Option<u64> {
+        self.optimal_tour_length
+    }
+
+    fn instance(&self) -> Cow<'_, TspLib> {
+        Cow::Borrowed(self)
+    }
+}
+
+/// A problem given by its distance matrix, e.g. travel times between the
+/// stops of a delivery round.
+#[derive(Debug, Clone)]
+pub struct MatrixProblem {
+    pub name: String,
+    /// `distances[a][b]` is the distance from city `a` to city `b`.
+    pub distances: Vec<Vec<u64>>,
+    pub coordinates: Option<Vec<City>>,
+}
+
+impl MatrixProblem {
+    /// The problem with `distances`, which must be square.
+    pub fn new(name: &str, distances: Vec<Vec<u64>>) -> Result<Self> {
+        let n = distances.len();
+        if let Some((row, distances)) = distances.iter().enumerate().find(|(_, row)| row.len() != n)
+        {
+            bail!(
+                "distance matrix of {} is not square: row {} has {} distances, expected {}",
+                name,
+                row,
+                distances.len(),
+                n
+            );
+        }
+        Ok(MatrixProblem {
+            name: name.to_string(),
+            distances,
+            coordinates: None,
+        })
+    }
+
+    /// Draws the cities at `coordinates` in plots, one per row of the matrix.
+    pub fn with_coordinates(mut self, coordinates: Vec<City>) -> Result<Self> {
+        if coordinates.len() != self.distances.len() {
+            bail!(
+                "{} coordinates given for the {} cities of {}",
+                coordinates.len(),
+                self.distances.len(),
+                self.name
+            );
+        }
+        self.coordinates = Some(coordinates);
+        Ok(self)
+    }
+}
+
+impl Problem for MatrixProblem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dimension(&self) -> usize {
+        self.distances.len()
+    }
+
+    fn distance(&self, a: usize, b: usize) -> u64 {
+        self.distances[a][b]
+    }
+
+    fn coordinates(&self) -> Option<&[City]> {
+        self.coordinates.as_deref()
+    }
+}
+
+/// Cities scattered at random over a square, with rounded Euclidean
+/// distances, for testing solvers without instance files.
+#[derive(Debug, Clone)]
+pub struct RandomProblem {
+    pub name: String,
+    pub cities: Vec<City>,
+}
+
+/// How [`RandomProblem::generate`] lays out the cities over the square.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Distribution {
+    /// Uniformly at random.
+    #[default]
+    Uniform,
+    /// In Gaussian clusters of about [`CITIES_PER_CLUSTER`] cities around
+    /// centres placed uniformly at random.
+    Clustered,
+    /// On the points of a square lattice, filled row by row.
+    Grid,
+}
+
+/// Cities per cluster of [`Distribution::Clustered`].
+pub const CITIES_PER_CLUSTER: usize = 100;
+
+impl FromStr for Distribution {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "uniform" => Distribution::Uniform,
+            "clustered" => Distribution::Clustered,
+            "grid" => Distribution::Grid,
+            _ => bail!(
+                "unknown distribution `{}`, expected uniform, clustered or grid",
+                name
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Distribution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Distribution::Uniform => "uniform",
+            Distribution::Clustered => "clustered",
+            Distribution::Grid => "grid",
+        })
+    }
+}
+
+impl RandomProblem {
+    /// `cities` cities uniformly at random over a square of side `side`.
+    pub fn new(cities: usize, side: f64, rng: &mut impl Rng) -> Self {
+        RandomProblem {
+            name: format!("random{}", cities),
+            cities: (0..cities)
+                .map(|_| (rng.gen::<f64>() * side, rng.gen::<f64>() * side))
+                .collect(),
+        }
+    }
+
+    /// `cities` cities laid out over a square of side `side` as
+    /// `distribution` says, at whole-number coordinates so that the problem
+    /// written as a TSPLIB file reads back the same.
+    pub fn generate(
+        cities: usize,
+        side: f64,
+        distribution: Distribution,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let coordinates: Vec<City> = match distribution {
+            Distribution::Uniform => (0..cities)
+                .map(|_| (rng.gen::<f64>() * side, rng.gen::<f64>() * side))
+                .collect(),
+            Distribution::Clustered => {
+                let clusters = cities.div_ceil(CITIES_PER_CLUSTER).max(1);
+                let centres: Vec<City> = (0..clusters)
+                    .map(|_| (rng.gen::<f64>() * side, rng.gen::<f64>() * side))
+                    .collect();
+                // Clusters spread over about a quarter of their share of the
+                // square.
+                let spread = side / (4.0 * (clusters as f64).sqrt());
+                (0..cities)
+                    .map(|_| {
+                        let (x, y) = centres[rng.gen_range(0..clusters)];
+                        // Box-Muller: two independent standard normals.
+                        let radius = (-2.0 * (1.0 - rng.gen::<f64>()).ln()).sqrt();
+                        let angle = TAU * rng.gen::<f64>();
+                        (
+                            (x + spread * radius * angle.cos()).clamp(0.0, side),
+                            (y + spread * radius * angle.sin()).clamp(0.0, side),
+                        )
+                    })
+                    .collect()
+            }
+            Distribution::Grid => {
+                let columns = (cities as f64).sqrt().ceil().max(1.0) as usize;
+                let spacing = side / columns as f64;
+                (0..cities)
+                    .map(|city| {
+                        (
+                            (city % columns) as f64 * spacing,
+                            (city / columns) as f64 * spacing,
+                        )
+                    })
+                    .collect()
+            }
+        };
+        let name = match distribution {
+            Distribution::Uniform => "random",
+            Distribution::Clustered => "clustered",
+            Distribution::Grid => "grid",
+        };
+        RandomProblem {
+            name: format!("{}{}", name, cities),
+            cities: coordinates
+                .into_iter()
+                .map(|(x, y)| (x.round(), y.round()))
+                .collect(),
+        }
+    }
+}
+
+impl Problem for RandomProblem {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn dimension(&self) -> usize {
+        self.cities.len()
+    }
+
+    fn distance(&self, a: usize, b: usize) -> u64 {
+        DistanceKind::Euc2d.distance(self.cities[a], self.cities[b])
+    }
+
+    fn coordinates(&self) -> Option<&[City]> {
+        Some(&self.cities)
+    }
+
+    fn instance(&self) -> Cow<'_, TspLib> {
+        Cow::Owned(TspLib::from_cities(&self.name, self.cities.clone()))
+    }
+}
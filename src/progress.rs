@@ -0,0 +1,327 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::{span::EnteredSpan, Span};
+
+use crate::{
+    stats::{self, OperatorCounts},
+    tsplib::{Route, Solution, TspLib},
+};
+
+/// Whether [`status!`] lines are left out, as `--quiet` asks.
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Leaves out the status lines of long-running work from now on, or prints
+/// them again.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints a line about how long-running work such as a search or a worker is
+/// getting on, to stderr so stdout carries only results, and not at all once
+/// [`set_quiet`] was called. Warnings go to stderr with `eprintln!` instead, so
+/// they are never left out.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if !$crate::progress::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// One iteration of a solver run, as reported to observers.
+#[derive(Clone, Copy)]
+pub struct IterationInfo<'a> {
+    pub algorithm: &'a str,
+    pub iteration: usize,
+    /// Best route found so far in the run.
+    pub best: &'a Route,
+    pub elapsed_ms: u64,
+    /// Figures particular to the algorithm, e.g. SA's temperature, by name.
+    pub stats: &'a [(&'static str, f64)],
+}
+
+/// Follows a solver run. Every event is ignored by default, so an observer
+/// only implements the ones it needs.
+pub trait Observer: Send + Sync {
+    /// Called after every iteration.
+    fn on_iteration(&self, _info: &IterationInfo) {}
+
+    /// Called after the first iteration and after every iteration that
+    /// shortened the best route.
+    fn on_improvement(&self, _info: &IterationInfo) {}
+
+    /// Called once the run has stopped, with its result.
+    fn on_finish(&self, _algorithm: &str, _solution: &Solution) {}
+
+    /// Asked after every iteration; returning true stops the solver, which
+    /// keeps the best route found so far.
+    fn should_stop(&self) -> bool {
+        false
+    }
+}
+
+/// The observers registered with a solver, along with the best distance
+/// already reported to them in the current run. Everything reported is also
+/// emitted as `tracing` events in the run's span.
+pub struct Observers {
+    observers: Vec<Arc<dyn Observer>>,
+    best_distance: Option<u64>,
+    span: Span,
+}
+
+/// The spans entered for a solver step, see [`Observers::enter_step`].
+pub struct StepSpan {
+    _step: EnteredSpan,
+    _run: EnteredSpan,
+}
+
+impl Default for Observers {
+    fn default() -> Self {
+        Observers {
+            observers: Vec::new(),
+            best_distance: None,
+            span: Span::none(),
+        }
+    }
+}
+
+impl Observers {
+    pub fn add(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.push(observer);
+    }
+
+    /// Opens the span of a run of `algorithm` on `tsp`, the parent of the
+    /// spans and events of its steps. A run started inside another solver's
+    /// step, e.g. a pipeline stage, nests in that step.
+    pub fn start(&mut self, algorithm: &'static str, tsp: &TspLib) {
+        self.span = tracing::info_span!(
+            "solve",
+            algorithm,
+            instance = %tsp.name,
+            cities = tsp.dimension,
+        );
+    }
+
+    /// The span of the current run.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Enters the run's span and a `step` span within it until the guard is
+    /// dropped.
+    pub fn enter_step(&self, iteration: usize) -> StepSpan {
+        let run = self.span.clone().entered();
+        StepSpan {
+            _step: tracing::debug_span!("step", iteration).entered(),
+            _run: run,
+        }
+    }
+
+    /// Reports an iteration to every observer, and an improvement too if
+    /// its best route is shorter than any reported before in this run.
+    pub fn iteration(&mut self, info: IterationInfo) {
+        let improved = self
+            .best_distance
+            .is_none_or(|distance| info.best.distance < distance);
+        if improved {
+            self.best_distance = Some(info.best.distance);
+        }
+        let (iteration, best_distance, elapsed_ms) =
+            (info.iteration, info.best.distance, info.elapsed_ms);
+        tracing::debug!(iteration, best_distance, elapsed_ms, "iteration");
+        if improved {
+            tracing::info!(iteration, best_distance, elapsed_ms, "improvement");
+        }
+        for observer in &self.observers {
+            observer.on_iteration(&info);
+            if improved {
+                observer.on_improvement(&info);
+            }
+        }
+    }
+
+    /// Reports the end of the run, with the use of its operators; the next
+    /// iteration reported starts a new one.
+    pub fn finish(&mut self, algorithm: &str, solution: &Solution, operators: &OperatorCounts) {
+        self.span.in_scope(|| {
+            tracing::info!(
+                iterations = solution.iterations,
+                distance = solution.route.distance,
+                run_time_ms = solution.run_time,
+                "finished",
+            );
+            for (operator, stats) in operators.iter() {
+                tracing::info!(
+                    operator,
+                    applied = stats.applied,
+                    accepted = stats.accepted,
+                    improving = stats.improving,
+                    "operator",
+                );
+            }
+        });
+        self.span = Span::none();
+        self.best_distance = None;
+        for observer in &self.observers {
+            observer.on_finish(algorithm, solution);
+        }
+    }
+
+    /// Whether any observer asks the solver to stop.
+    pub fn stop_requested(&self) -> bool {
+        self.observers.iter().any(|observer| observer.should_stop())
+    }
+}
+
+/// Prints the best distance to the console at most once per `interval`, and
+/// a summary when the run finishes.
+pub struct ConsoleProgress {
+    interval: Duration,
+    next_print_ms: AtomicU64,
+    /// Known optimal tour length the gap is printed against.
+    optimum: Option<u64>,
+    verbose: bool,
+}
+
+impl ConsoleProgress {
+    pub fn new(interval: Duration) -> Self {
+        ConsoleProgress {
+            interval,
+            next_print_ms: AtomicU64::new(0),
+            optimum: None,
+            verbose: false,
+        }
+    }
+
+    /// Prints every iteration, along with the algorithm's own figures.
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Also prints the gap of the best distance to `optimum`, if known.
+    pub fn with_optimum(mut self, optimum: Option<u64>) -> Self {
+        self.optimum = optimum;
+        self
+    }
+
+    /// `distance`, followed by its gap to the optimum when known.
+    fn describe(&self, distance: u64) -> String {
+        match self.optimum {
+            Some(optimum) => format!(
+                "{} (gap {})",
+                distance,
+                stats::format_gap((distance as f64 - optimum as f64) / optimum as f64 * 100.0)
+            ),
+            None => distance.to_string(),
+        }
+    }
+}
+
+impl Observer for ConsoleProgress {
+    fn on_iteration(&self, info: &IterationInfo) {
+        if !self.verbose {
+            if info.elapsed_ms < self.next_print_ms.load(Ordering::Relaxed) {
+                return;
+            }
+            self.next_print_ms.store(
+                info.elapsed_ms + self.interval.as_millis() as u64,
+                Ordering::Relaxed,
+            );
+        }
+        let mut line = format!(
+            "{} Iteration: {}, Elapsed: {}ms, Best distance: {}",
+            info.algorithm,
+            info.iteration,
+            info.elapsed_ms,
+            self.describe(info.best.distance)
+        );
+        if self.verbose {
+            for (name, value) in info.stats {
+                if value.fract() == 0.0 {
+                    line.push_str(&format!(", {}: {}", name, value));
+                } else {
+                    line.push_str(&format!(", {}: {:.4}", name, value));
+                }
+            }
+        }
+        println!("{}", line);
+    }
+
+    fn on_finish(&self, algorithm: &str, solution: &Solution) {
+        self.next_print_ms.store(0, Ordering::Relaxed);
+        println!(
+            "{} finished after {} iterations in {}ms, Best distance: {}",
+            algorithm,
+            solution.iterations,
+            solution.run_time,
+            self.describe(solution.route.distance)
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct ProgressEvent<'a> {
+    algorithm: &'a str,
+    iteration: usize,
+    best_distance: u64,
+    elapsed_ms: u64,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    stats: BTreeMap<&'static str, f64>,
+}
+
+/// Streams one JSON object per solver iteration, flushing after every line so
+/// external tools can follow a run while it is still going.
+pub struct NdjsonProgress {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl NdjsonProgress {
+    /// Opens `path` for writing, with `-` meaning stdout.
+    pub fn open(path: &str) -> Result<Self> {
+        let writer: Box<dyn Write + Send> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            let file = File::create(path).with_context(|| format!("failed to create {}", path))?;
+            Box::new(BufWriter::new(file))
+        };
+
+        Ok(NdjsonProgress {
+            writer: Mutex::new(writer),
+        })
+    }
+}
+
+impl Observer for NdjsonProgress {
+    fn on_iteration(&self, info: &IterationInfo) {
+        let event = ProgressEvent {
+            algorithm: info.algorithm,
+            iteration: info.iteration,
+            best_distance: info.best.distance,
+            elapsed_ms: info.elapsed_ms,
+            stats: info.stats.iter().copied().collect(),
+        };
+        let mut writer = self.writer.lock().unwrap();
+        // Progress is best effort; a closed pipe must not abort the solver.
+        if serde_json::to_writer(&mut *writer, &event).is_ok() {
+            let _ = writer.write_all(b"\n");
+            let _ = writer.flush();
+        }
+    }
+}
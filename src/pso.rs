@@ -1,13 +1,32 @@
-use rand::{thread_rng, Rng};
-use std::time::Instant;
-
-use crate::tsplib::{City, HeuristicAlgorithm, Route, TspLib};
-
+use anyhow::Result;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, sync::Arc};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::{self, Checkpoint, Checkpointing, RunState},
+    config::PsoConfig,
+    construct::{self, Construction},
+    diversity::{self, Diversity},
+    error::Error,
+    fitness::Evaluator,
+    local_search::{LocalSearch, LocalSearcher},
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::{self, Configurable, HeuristicAlgorithm, Route, Solution, StepResult, TspLib},
+    validate,
+};
+
+#[derive(Serialize, Deserialize)]
 struct Particle {
     position: Vec<usize>,
     velocity: Vec<(usize, usize)>,
     best_position: Vec<usize>,
     best_fitness: u64,
+    fitness: u64,
 }
 
 impl Particle {
@@ -18,43 +37,19 @@ impl Particle {
             velocity: Vec::new(),
             best_position: position,
             best_fitness: u64::MAX,
+            fitness: u64::MAX,
         }
     }
 
-    fn initialize_nearest_neighbor(&mut self, distance_matrix: &[Vec<u64>]) {
-        let mut rng = thread_rng();
-        let mut current_city = rng.gen_range(0..self.position.len());
-        let mut unvisited = (0..self.position.len())
-            .filter(|&x| x != current_city)
-            .collect::<Vec<usize>>();
-        let mut route = vec![current_city];
-
-        while !unvisited.is_empty() {
-            let next_city = unvisited
-                .iter()
-                .min_by(|&&a, &&b| {
-                    let dist_a = distance_matrix[current_city][a];
-                    let dist_b = distance_matrix[current_city][b];
-                    dist_a.partial_cmp(&dist_b).unwrap()
-                })
-                .unwrap();
-            let next_index = unvisited.iter().position(|&x| x == *next_city).unwrap();
-            current_city = unvisited.remove(next_index);
-            route.push(current_city);
-        }
-
-        self.position = route;
-    }
-
     fn update_personal_best(&mut self, fitness: u64) {
+        self.fitness = fitness;
         if fitness < self.best_fitness {
             self.best_fitness = fitness;
             self.best_position = self.position.clone();
         }
     }
 
-    fn crossover(&self, route1: &[usize], route2: &[usize]) -> Vec<usize> {
-        let mut rng = thread_rng();
+    fn crossover(&self, route1: &[usize], route2: &[usize], rng: &mut impl Rng) -> Vec<usize> {
         let size = route1.len();
         let start = rng.gen_range(0..size);
         let end = rng.gen_range(start..size);
@@ -75,24 +70,27 @@ impl Particle {
         offspring
     }
 
-    fn mutate(&self, route: &mut [usize], mutation_rate: f64) {
-        let mut rng = thread_rng();
+    fn mutate(&self, route: &mut [usize], mutation_rate: f64, rng: &mut impl Rng) -> bool {
         if rng.gen::<f64>() < mutation_rate {
             let i = rng.gen_range(0..route.len());
             let j = rng.gen_range(0..route.len());
             route.swap(i, j);
+            return true;
         }
+        false
     }
 
+    /// Moves the particle and returns the operators the move applied.
     fn update_velocity(
         &mut self,
         cognitive_weight: f64,
         social_weight: f64,
         inertia_weight: f64,
         global_best_position: &[usize],
-    ) {
-        let mut rng = thread_rng();
+        rng: &mut impl Rng,
+    ) -> Vec<&'static str> {
         let mut new_route = self.position.clone();
+        let mut operators = Vec::new();
 
         let previous_swaps = self.velocity.clone();
         for swap in previous_swaps {
@@ -103,16 +101,21 @@ impl Particle {
         }
 
         if rng.gen::<f64>() < cognitive_weight {
-            new_route = self.crossover(&new_route, &self.best_position);
+            new_route = self.crossover(&new_route, &self.best_position, rng);
+            operators.push("cognitive crossover");
         }
 
         if rng.gen::<f64>() < social_weight {
-            new_route = self.crossover(&new_route, global_best_position);
+            new_route = self.crossover(&new_route, global_best_position, rng);
+            operators.push("social crossover");
         }
 
-        self.mutate(&mut new_route, 0.1);
+        if self.mutate(&mut new_route, 0.1, rng) {
+            operators.push("swap mutation");
+        }
 
-        self.velocity = self.get_swap_sequence(&new_route)
+        self.velocity = self.get_swap_sequence(&new_route);
+        operators
     }
 
     fn get_swap_sequence(&self, to_route: &[usize]) -> Vec<(usize, usize)> {
@@ -138,22 +141,67 @@ impl Particle {
     }
 }
 
-fn calculate_fitness(route: &[usize], distance_matrix: &[Vec<u64>]) -> u64 {
-    let mut total_distance = 0;
-    for i in 0..route.len() {
-        let from_city = route[i];
-        let to_city = route[(i + 1) % route.len()];
-        total_distance += distance_matrix[from_city][to_city];
-    }
+/// State of the swarm after one iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwarmStats {
+    pub mean_fitness: f64,
+    /// Average share of a particle's edges missing from the global best tour:
+    /// 0 when the swarm has collapsed onto it.
+    pub diversity: f64,
+    pub diversity_measures: Diversity,
+}
 
-    total_distance
+impl SwarmStats {
+    fn from_particles(particles: &[Particle], global_best_position: &[usize]) -> Self {
+        let best_edges =
+            diversity::edges(global_best_position).collect::<HashSet<(usize, usize)>>();
+        let count = particles.len().max(1) as f64;
+
+        let mean_fitness = particles.iter().map(|p| p.fitness as f64).sum::<f64>() / count;
+        let diversity = particles
+            .iter()
+            .map(|p| diversity::missing_share(&p.position, &best_edges))
+            .sum::<f64>()
+            / count;
+
+        SwarmStats {
+            mean_fitness,
+            diversity,
+            diversity_measures: Diversity::of(particles.iter().map(|p| p.position.as_slice())),
+        }
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct ParticleSwarmOptimization {
     history: Vec<Route>,
+    history_times: Vec<u64>,
     best_route: Route,
     run_time: u64,
-
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    checkpointing: Option<Checkpointing>,
+    /// The swarm itself lives in the solver's fields, so a run carries
+    /// nothing else between iterations.
+    #[serde(skip)]
+    resumed: Option<RunState<()>>,
+    #[serde(skip)]
+    initial_solution: Option<Vec<usize>>,
+    #[serde(skip)]
+    run: Option<RunState<()>>,
+    #[serde(skip)]
+    pool: Option<SolutionPool>,
+    #[serde(skip)]
+    evaluator: Evaluator,
+    #[serde(skip)]
+    searcher: Option<LocalSearcher>,
+    archive: Option<SolutionArchive>,
+    seed: Option<u64>,
+    operator_counts: OperatorCounts,
+    swarm_stats: Vec<SwarmStats>,
+
+    num_particles: usize,
     particles: Vec<Particle>,
     global_best_position: Vec<usize>,
     global_best_fitness: u64,
@@ -161,6 +209,10 @@ pub struct ParticleSwarmOptimization {
     cognitive_weight: f64,
     social_weight: f64,
     inertia_weight: f64,
+    pub local_search: LocalSearch,
+    /// Builds the first particle's position in a run that has no initial
+    /// solution.
+    pub construction: Option<Construction>,
 }
 
 impl ParticleSwarmOptimization {
@@ -172,58 +224,176 @@ impl ParticleSwarmOptimization {
         social_weight: f64,
         inertia_weight: f64,
     ) -> Self {
-        let mut particles = Vec::with_capacity(num_particles);
-        let num_cities = tsp.dimension;
-        let global_best_position = (0..num_cities).collect();
-
-        for _ in 0..num_particles {
-            let mut particle = Particle::new(num_cities);
-            particle.initialize_nearest_neighbor(&tsp.distance_matrix);
-            particles.push(particle);
-        }
+        let global_best_position = (0..tsp.dimension).collect();
 
         ParticleSwarmOptimization {
             history: Vec::new(),
-            best_route: Route::new(&tsp.cities.clone()),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
             run_time: 0,
-            particles,
+            observers: Observers::default(),
+            checkpointing: None,
+            resumed: None,
+            initial_solution: None,
+            run: None,
+            pool: None,
+            evaluator: Evaluator::default(),
+            searcher: None,
+            archive: None,
+            seed: None,
+            operator_counts: OperatorCounts::default(),
+            swarm_stats: Vec::new(),
+            num_particles,
+            particles: Vec::new(),
             global_best_position,
             global_best_fitness: u64::MAX,
             max_iterations,
             cognitive_weight,
             social_weight,
             inertia_weight,
+            local_search: LocalSearch::None,
+            construction: None,
         }
     }
+
+    pub fn get_swarm_stats(&self) -> &[SwarmStats] {
+        &self.swarm_stats
+    }
+
+    /// The swarm's best position as a route through `tsp`.
+    fn global_best_route(&self, tsp: &TspLib) -> Route {
+        Route::from_tour(&self.global_best_position, tsp)
+    }
 }
 
 impl HeuristicAlgorithm for ParticleSwarmOptimization {
-    fn solve(&mut self, tsp: &TspLib) {
-        let start_time = Instant::now();
-        let mut current_best_fitness = self.global_best_fitness;
-
-        for particle in &mut self.particles {
-            let fitness = calculate_fitness(&particle.position, &tsp.distance_matrix);
-            particle.update_personal_best(fitness);
-            if fitness < self.global_best_fitness {
-                self.global_best_fitness = fitness;
-                self.global_best_position = particle.position.clone();
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        tsplib::check_instance(tsp)?;
+        self.observers.start("PSO", tsp);
+        self.searcher = LocalSearcher::new(self.local_search, tsp);
+        let run = match self.resumed.take() {
+            Some(run) => run,
+            None => {
+                let mut rng = tsplib::solver_rng(self.seed);
+                self.particles = (0..self.num_particles)
+                    .map(|_| {
+                        let mut particle = Particle::new(tsp.dimension);
+                        let start = rng.gen_range(0..tsp.dimension);
+                        particle.position =
+                            construct::nearest_neighbor(&tsp.distance_matrix, start);
+                        particle
+                    })
+                    .collect();
+                if let (Some(tour), Some(particle)) =
+                    (&self.initial_solution, self.particles.first_mut())
+                {
+                    particle.position = Route::validated(tour, tsp)?.tour;
+                } else if let (Some(construction), Some(particle)) =
+                    (self.construction, self.particles.first_mut())
+                {
+                    particle.position = construction.build(tsp, 0);
+                }
+                let tours: Vec<&[usize]> = self
+                    .particles
+                    .iter()
+                    .map(|particle| particle.position.as_slice())
+                    .collect();
+                let fitnesses = self.evaluator.evaluate(tsp, &tours);
+                for (particle, fitness) in self.particles.iter_mut().zip(fitnesses) {
+                    particle.update_personal_best(fitness);
+                    if fitness < self.global_best_fitness {
+                        self.global_best_fitness = fitness;
+                        self.global_best_position = particle.position.clone();
+                    }
+                }
+                RunState::new(tsp, rng, ())
             }
-        }
+        };
+        self.best_route = self.global_best_route(tsp);
+        self.run = Some(run);
+        Ok(())
+    }
 
-        for iteration in 0..self.max_iterations {
-            for particle in &mut self.particles {
-                particle.update_velocity(
-                    self.cognitive_weight,
-                    self.social_weight,
-                    self.inertia_weight,
-                    &self.global_best_position,
-                );
-                particle.apply_velocity();
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        let _span = self.observers.enter_step(run.iterations);
+
+        if run.iterations < self.max_iterations {
+            if let Some(route) = self
+                .pool
+                .as_ref()
+                .and_then(|pool| pool.better_than(self.best_route.distance))
+            {
+                self.global_best_position = route.tour.clone();
+                self.global_best_fitness = route.distance;
+                self.best_route = route;
+            }
 
-                let fitness = calculate_fitness(&particle.position, &tsp.distance_matrix);
+            // The whole swarm moves towards the global best of the previous
+            // iteration, so its new positions are evaluated as one batch.
+            let iteration = run.iterations;
+            let moves: Vec<_> = self
+                .particles
+                .iter_mut()
+                .map(|particle| {
+                    let operators = particle.update_velocity(
+                        self.cognitive_weight,
+                        self.social_weight,
+                        self.inertia_weight,
+                        &self.global_best_position,
+                        &mut run.rng,
+                    );
+                    particle.apply_velocity();
+                    operators
+                })
+                .collect();
+            let tours: Vec<&[usize]> = self
+                .particles
+                .iter()
+                .map(|particle| particle.position.as_slice())
+                .collect();
+            debug_assert!(
+                tours
+                    .iter()
+                    .all(|tour| validate::is_permutation(tour, tsp.dimension)),
+                "a particle moved to a tour that is not a permutation of the cities"
+            );
+            let mut fitnesses = self.evaluator.evaluate(tsp, &tours);
+            if let Some(searcher) = &self.searcher {
+                let improved: Vec<bool> = self
+                    .particles
+                    .par_iter_mut()
+                    .zip(&mut fitnesses)
+                    .map(|(particle, fitness)| {
+                        let distance =
+                            searcher.improve(&mut particle.position, &tsp.distance_matrix);
+                        let improved = distance < *fitness;
+                        *fitness = distance;
+                        improved
+                    })
+                    .collect();
+                for improved in improved {
+                    self.operator_counts.record("local search", true, improved);
+                }
+            }
+
+            for ((particle, operators), fitness) in
+                self.particles.iter_mut().zip(moves).zip(fitnesses)
+            {
+                // A particle always moves, so its operators are always accepted
+                // and credited with the move's improvement.
+                for operator in operators {
+                    self.operator_counts
+                        .record(operator, true, fitness < particle.fitness);
+                }
 
                 particle.update_personal_best(fitness);
+                if let Some(archive) = &mut self.archive {
+                    archive.offer(&particle.position, fitness);
+                }
 
                 if fitness < self.global_best_fitness {
                     self.global_best_fitness = fitness;
@@ -231,48 +401,161 @@ impl HeuristicAlgorithm for ParticleSwarmOptimization {
                 }
             }
 
-            if self.global_best_fitness < current_best_fitness {
-                current_best_fitness = self.global_best_fitness;
-            }
-
-            self.history.push(Route::new(
-                &self
-                    .global_best_position
-                    .iter()
-                    .map(|&city| tsp.cities[city])
-                    .collect::<Vec<City>>(),
+            self.swarm_stats.push(SwarmStats::from_particles(
+                &self.particles,
+                &self.global_best_position,
             ));
 
-            if iteration % (self.max_iterations / 10) == 0 {
-                println!(
-                    "PSO Iteration {}/{}, Best distance: {}",
-                    iteration, self.max_iterations, self.global_best_fitness
-                );
+            self.history_times
+                .push(start_time.elapsed().as_millis() as u64);
+
+            self.best_route = self.global_best_route(tsp);
+            self.history.push(self.best_route.clone());
+            if let Some(pool) = &self.pool {
+                pool.publish(&self.best_route);
             }
-        }
 
-        self.global_best_fitness =
-            calculate_fitness(&self.global_best_position, &tsp.distance_matrix);
+            let swarm_stats = self.swarm_stats.last().unwrap();
+            self.observers.iteration(IterationInfo {
+                algorithm: "PSO",
+                iteration,
+                best: &self.best_route,
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+                stats: &[
+                    ("mean_fitness", swarm_stats.mean_fitness),
+                    ("diversity", swarm_stats.diversity),
+                ],
+            });
+
+            run.iterations = iteration + 1;
+        }
 
-        self.best_route = Route::new(
-            &self
-                .global_best_position
-                .iter()
-                .map(|&city| tsp.cities[city])
-                .collect::<Vec<City>>(),
+        let saved = checkpoint::save_if_due(
+            self.checkpointing.as_ref(),
+            &*self,
+            &mut run,
+            start_time,
+            false,
         );
+        let step = StepResult {
+            iterations: run.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.global_best_fitness,
+            done: run.iterations >= self.max_iterations || self.observers.stop_requested(),
+        };
+        self.run = Some(run);
+        saved?;
+        Ok(step)
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        if run.iterations < self.max_iterations {
+            checkpoint::save_if_due(
+                self.checkpointing.as_ref(),
+                &*self,
+                &mut run,
+                start_time,
+                true,
+            )?;
+        }
         self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: run.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("PSO", &solution, &self.operator_counts);
+        Ok(solution)
+    }
+
+    fn get_history(&self) -> &[Route] {
+        &self.history
     }
 
-    fn get_history(&self) -> Vec<Route> {
-        self.history.clone()
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
     }
 
-    fn get_best_route(&self) -> Route {
-        self.best_route.clone()
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
     }
 
     fn get_run_time(&self) -> u64 {
         self.run_time
     }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    fn get_diversity(&self) -> Option<Diversity> {
+        self.swarm_stats
+            .last()
+            .map(|swarm| swarm.diversity_measures)
+    }
+
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        self.archive.as_ref()
+    }
+
+    fn set_evaluator(&mut self, evaluator: Evaluator) {
+        self.evaluator = evaluator;
+    }
+}
+
+impl Checkpoint for ParticleSwarmOptimization {
+    type State = ();
+
+    fn resume(&mut self, run: RunState<()>) {
+        self.resumed = Some(run);
+    }
+}
+
+impl Configurable for ParticleSwarmOptimization {
+    type Config = PsoConfig;
+
+    fn from_config(tsp: &TspLib, config: &PsoConfig) -> Self {
+        let mut pso = ParticleSwarmOptimization::new(
+            tsp,
+            config.particles,
+            config.iterations,
+            config.cognitive_weight,
+            config.social_weight,
+            config.inertia_weight,
+        );
+        pso.seed = config.seed;
+        pso.local_search = config.local_search;
+        pso.construction = config.construction;
+        pso
+    }
 }
@@ -1,90 +1,496 @@
-use crate::tsplib::*;
+use std::{fmt, str::FromStr, sync::Arc};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::{self, Checkpoint, Checkpointing, RunState},
+    config::SaConfig,
+    error::Error,
+    fitness::Evaluator,
+    local_search::{LocalSearch, LocalSearcher},
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::*,
+};
 use rand::prelude::*;
 
+/// How the temperature changes from one epoch to the next, configured as
+/// `cooling = "lam"`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cooling {
+    /// Multiplied by `1 - cooling_rate`.
+    #[default]
+    Geometric,
+    /// Lowered by `cooling_rate` times the starting temperature.
+    Linear,
+    /// Lam and Delosme's adaptive schedule: steered, by at most 10% an epoch,
+    /// towards the temperature that accepts a target share of the moves,
+    /// which falls from 100% to 44% over the first 15% of the run, holds
+    /// there until 65% and then falls towards 0. The run lasts the epochs
+    /// geometric cooling would take to reach `min_temperature`.
+    Lam,
+}
+
+impl Cooling {
+    pub fn is_geometric(&self) -> bool {
+        *self == Cooling::Geometric
+    }
+}
+
+impl FromStr for Cooling {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "geometric" | "exponential" => Cooling::Geometric,
+            "linear" => Cooling::Linear,
+            "lam" | "adaptive" => Cooling::Lam,
+            _ => bail!(
+                "unknown cooling schedule `{}`, expected geometric, linear or lam",
+                name
+            ),
+        })
+    }
+}
+
+impl fmt::Display for Cooling {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Cooling::Geometric => "geometric",
+            Cooling::Linear => "linear",
+            Cooling::Lam => "lam",
+        })
+    }
+}
+
+/// Acceptance rate Lam's schedule aims for `progress` of the way through a
+/// run.
+fn lam_target(progress: f64) -> f64 {
+    if progress < 0.15 {
+        0.44 + 0.56 * 560f64.powf(-progress / 0.15)
+    } else if progress < 0.65 {
+        0.44
+    } else {
+        0.44 * 440f64.powf(-(progress - 0.65) / 0.35)
+    }
+}
+
+/// Cooling schedule state of one SA epoch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EpochStats {
+    pub temperature: f64,
+    /// Fraction of the epoch's proposed moves that were accepted.
+    pub acceptance_rate: f64,
+}
+
+/// The route SA is currently at, carried between epochs.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SaState {
+    current_route: Route,
+    initial_temperature: f64,
+    reheats: usize,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct SimulatedAnnealing {
     history: Vec<Route>,
+    history_times: Vec<u64>,
     best_route: Route,
     run_time: u64,
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    checkpointing: Option<Checkpointing>,
+    #[serde(skip)]
+    resumed: Option<RunState<SaState>>,
+    #[serde(skip)]
+    initial_solution: Option<Vec<usize>>,
+    #[serde(skip)]
+    run: Option<RunState<SaState>>,
+    #[serde(skip)]
+    pool: Option<SolutionPool>,
+    #[serde(skip)]
+    searcher: Option<LocalSearcher>,
+    archive: Option<SolutionArchive>,
+    seed: Option<u64>,
+    operator_counts: OperatorCounts,
+    epoch_stats: Vec<EpochStats>,
 
     pub temperature: f64,
     pub cooling_rate: f64,
     pub min_temperature: f64,
+    /// Run on the current route at the end of every epoch.
+    pub local_search: LocalSearch,
+    pub cooling: Cooling,
+    /// Most times the temperature is raised again after an epoch accepts
+    /// fewer than `reheat_below` of its moves: back to half the starting
+    /// temperature, a quarter the second time, and so on.
+    pub reheats: usize,
+    pub reheat_below: f64,
+    /// How often each move is proposed.
+    pub moves: MoveWeights,
 }
 
 impl SimulatedAnnealing {
     pub fn new(tsp: &TspLib, temperature: f64, cooling_rate: f64, min_temperature: f64) -> Self {
         SimulatedAnnealing {
             history: Vec::new(),
-            best_route: Route::new(&tsp.cities),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
             run_time: 0,
+            observers: Observers::default(),
+            checkpointing: None,
+            resumed: None,
+            initial_solution: None,
+            run: None,
+            pool: None,
+            searcher: None,
+            archive: None,
+            seed: None,
+            operator_counts: OperatorCounts::default(),
+            epoch_stats: Vec::new(),
 
             temperature,
             cooling_rate,
             min_temperature,
+            local_search: LocalSearch::None,
+            cooling: Cooling::Geometric,
+            reheats: 0,
+            reheat_below: 0.01,
+            moves: MoveWeights::default(),
+        }
+    }
+
+    pub fn get_epoch_stats(&self) -> &[EpochStats] {
+        &self.epoch_stats
+    }
+
+    /// Epochs geometric cooling takes from `initial_temperature` down to
+    /// `min_temperature`, the length of a run cooled by Lam's schedule.
+    fn planned_epochs(&self, initial_temperature: f64) -> usize {
+        ((self.min_temperature / initial_temperature).ln() / (1.0 - self.cooling_rate).ln())
+            .ceil()
+            .max(1.0) as usize
+    }
+
+    /// Whether the run is over: the temperature is down to
+    /// `min_temperature`, or for Lam's schedule the planned epochs are done.
+    fn cooled(&self, run: &RunState<SaState>) -> bool {
+        match self.cooling {
+            Cooling::Lam => run.iterations >= self.planned_epochs(run.state.initial_temperature),
+            _ => self.temperature <= self.min_temperature,
+        }
+    }
+
+    /// Sets the temperature for the epoch after `epoch`, which accepted
+    /// `acceptance_rate` of its moves.
+    fn cool(&mut self, state: &mut SaState, epoch: usize, acceptance_rate: f64) {
+        match self.cooling {
+            Cooling::Geometric => self.temperature *= 1.0 - self.cooling_rate,
+            Cooling::Linear => self.temperature -= self.cooling_rate * state.initial_temperature,
+            Cooling::Lam => {
+                let progress =
+                    (epoch + 1) as f64 / self.planned_epochs(state.initial_temperature) as f64;
+                let target = lam_target(progress);
+                let ratio = (target / acceptance_rate.max(f64::MIN_POSITIVE)).clamp(0.9, 1.1);
+                self.temperature *= ratio;
+            }
+        }
+
+        if acceptance_rate < self.reheat_below && state.reheats < self.reheats {
+            state.reheats += 1;
+            let reheated = state.initial_temperature / 2f64.powi(state.reheats as i32);
+            if reheated > self.temperature {
+                self.temperature = reheated;
+                self.operator_counts.record("reheat", true, false);
+            }
         }
     }
 }
 
 impl HeuristicAlgorithm for SimulatedAnnealing {
-    fn solve(&mut self, tsp: &TspLib) {
-        let start_time = std::time::Instant::now();
-        let mut rng = rand::thread_rng();
-        let mut epoch = 0;
-
-        let mut current_route = Route::new_random(&tsp.cities);
-        let mut current_distance = current_route.distance;
-        let mut best_distance = current_distance;
-        self.best_route = current_route.clone();
-
-        let moves_per_temp = tsp.dimension * 2;
-
-        while self.temperature > self.min_temperature {
-            if epoch % 1150 == 0 {
-                println!(
-                    "SA Epoch: {}, Temperature: {}, Best distance: {}",
-                    epoch, self.temperature, best_distance
-                );
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        check_instance(tsp)?;
+        self.observers.start("SA", tsp);
+        self.searcher = LocalSearcher::new(self.local_search, tsp);
+        if !(self.cooling_rate > 0.0 && self.cooling_rate < 1.0) {
+            return Err(Error::InvalidConfig {
+                algorithm: "SA",
+                reason: format!(
+                    "cooling_rate is {}, it must be between 0 and 1 for the temperature to fall",
+                    self.cooling_rate
+                ),
             }
+            .into());
+        }
+        let weights = [self.moves.swap, self.moves.two_opt, self.moves.or_opt];
+        if weights.iter().any(|&weight| weight < 0.0) || self.moves.total() <= 0.0 {
+            return Err(Error::InvalidConfig {
+                algorithm: "SA",
+                reason: format!(
+                    "move weights are {:?}, they must not be negative and one must be positive",
+                    weights
+                ),
+            }
+            .into());
+        }
+        let run = match self.resumed.take() {
+            Some(mut run) => {
+                run.state.current_route.measure_on(tsp);
+                run
+            }
+            None => {
+                let mut rng = solver_rng(self.seed);
+                let current_route = match &self.initial_solution {
+                    Some(tour) => Route::validated(tour, tsp)?,
+                    None => Route::random_through(tsp, &mut rng),
+                };
+                self.best_route = current_route.clone();
+                RunState::new(
+                    tsp,
+                    rng,
+                    SaState {
+                        current_route,
+                        initial_temperature: self.temperature,
+                        reheats: 0,
+                    },
+                )
+            }
+        };
+        self.run = Some(run);
+        Ok(())
+    }
+
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        let _span = self.observers.enter_step(run.iterations);
 
+        if !self.cooled(&run) {
+            if let Some(route) = self
+                .pool
+                .as_ref()
+                .and_then(|pool| pool.better_than(self.best_route.distance))
+            {
+                run.state.current_route = route.clone();
+                self.best_route = route;
+            }
+
+            let moves_per_temp = tsp.dimension * 2;
+            let epoch = run.iterations;
+            let mut accepted = 0;
             for _ in 0..moves_per_temp {
-                let new_route = current_route.random_move(&mut rng);
-                let new_distance = new_route.distance;
+                let current_route = &mut run.state.current_route;
+                let (new_route, operator) = current_route.random_move(&self.moves, &mut run.rng);
 
-                let delta = new_distance as f64 - current_distance as f64;
+                let delta = new_route.distance as f64 - current_route.distance as f64;
                 let acceptance_probability = if delta < 0.0 {
                     1.0
                 } else {
                     (-delta / self.temperature).exp()
                 };
 
-                if acceptance_probability > rng.gen::<f64>() {
-                    current_route = new_route;
-                    current_distance = new_distance;
+                let accept = acceptance_probability > run.rng.gen::<f64>();
+                self.operator_counts.record(operator, accept, delta < 0.0);
+                if accept {
+                    accepted += 1;
+                    *current_route = new_route;
 
-                    if current_distance < best_distance {
-                        best_distance = current_distance;
+                    if current_route.distance < self.best_route.distance {
                         self.best_route = current_route.clone();
                     }
                 }
             }
 
+            if let Some(searcher) = &self.searcher {
+                let current_route = &mut run.state.current_route;
+                let mut tour = current_route.tour.clone();
+                let distance = searcher.improve(&mut tour, &tsp.distance_matrix);
+                let improved = distance < current_route.distance;
+                self.operator_counts
+                    .record("local search", improved, improved);
+                if improved {
+                    *current_route = Route::from_measured_tour(&tour, distance, tsp);
+                    if current_route.distance < self.best_route.distance {
+                        self.best_route = current_route.clone();
+                    }
+                }
+            }
+
+            if let Some(archive) = &mut self.archive {
+                archive.offer_route(&run.state.current_route);
+            }
+            self.history_times
+                .push(start_time.elapsed().as_millis() as u64);
+
             self.history.push(self.best_route.clone());
-            self.temperature *= 1.0 - self.cooling_rate;
-            epoch += 1;
+            let acceptance_rate = accepted as f64 / moves_per_temp as f64;
+            self.epoch_stats.push(EpochStats {
+                temperature: self.temperature,
+                acceptance_rate,
+            });
+            self.observers.iteration(IterationInfo {
+                algorithm: "SA",
+                iteration: epoch,
+                best: &self.best_route,
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+                stats: &[
+                    ("temperature", self.temperature),
+                    ("acceptance_rate", acceptance_rate),
+                ],
+            });
+            if let Some(pool) = &self.pool {
+                pool.publish(&self.best_route);
+            }
+            self.cool(&mut run.state, epoch, acceptance_rate);
+            run.iterations = epoch + 1;
         }
 
+        let saved = checkpoint::save_if_due(
+            self.checkpointing.as_ref(),
+            &*self,
+            &mut run,
+            start_time,
+            false,
+        );
+        let step = StepResult {
+            iterations: run.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.best_route.distance,
+            done: self.cooled(&run) || self.observers.stop_requested(),
+        };
+        self.run = Some(run);
+        saved?;
+        Ok(step)
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        if !self.cooled(&run) {
+            checkpoint::save_if_due(
+                self.checkpointing.as_ref(),
+                &*self,
+                &mut run,
+                start_time,
+                true,
+            )?;
+        }
         self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: run.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("SA", &solution, &self.operator_counts);
+        Ok(solution)
     }
 
-    fn get_history(&self) -> Vec<Route> {
-        self.history.clone()
+    fn get_history(&self) -> &[Route] {
+        &self.history
     }
 
-    fn get_best_route(&self) -> Route {
-        self.best_route.clone()
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
+    }
+
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
     }
 
     fn get_run_time(&self) -> u64 {
         self.run_time
     }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    /// The Metropolis acceptance rate over the whole run, of the proposed
+    /// moves only: reheats and the local search polishing each epoch are
+    /// counted as operators too.
+    fn get_acceptance_rate(&self) -> Option<f64> {
+        let (applied, accepted) = self
+            .operator_counts
+            .iter()
+            .filter(|(name, _)| !matches!(*name, "reheat" | "local search"))
+            .fold((0, 0), |(applied, accepted), (_, stats)| {
+                (applied + stats.applied, accepted + stats.accepted)
+            });
+        (applied > 0).then(|| accepted as f64 / applied as f64)
+    }
+
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        self.archive.as_ref()
+    }
+
+    /// SA moves one tour at a time, so it has no batches to evaluate and
+    /// this has no effect.
+    fn set_evaluator(&mut self, _evaluator: Evaluator) {}
+}
+
+impl Checkpoint for SimulatedAnnealing {
+    type State = SaState;
+
+    fn resume(&mut self, run: RunState<SaState>) {
+        self.resumed = Some(run);
+    }
+}
+
+impl Configurable for SimulatedAnnealing {
+    type Config = SaConfig;
+
+    fn from_config(tsp: &TspLib, config: &SaConfig) -> Self {
+        let mut sa = SimulatedAnnealing::new(
+            tsp,
+            config.temperature,
+            config.cooling_rate,
+            config.min_temperature,
+        );
+        sa.seed = config.seed;
+        sa.local_search = config.local_search;
+        sa.cooling = config.cooling;
+        sa.reheats = config.reheats;
+        sa.reheat_below = config.reheat_below;
+        sa.moves = MoveWeights {
+            swap: config.swap_weight,
+            two_opt: config.two_opt_weight,
+            or_opt: config.or_opt_weight,
+        };
+        sa
+    }
 }
@@ -0,0 +1,411 @@
+use std::{
+    fs,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::{
+    config::{self, SolverConfig, SolverSpec},
+    hyper::Algorithm,
+    plot::{self, PlotConfig},
+    progress::{status, IterationInfo, Observer},
+    tsplib::{self, CancellationToken, City, Solution, Termination, TspLib},
+};
+
+/// Settings of the `serve` subcommand.
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    /// Address to listen on, e.g. `127.0.0.1:8080`.
+    pub address: String,
+    /// Solver settings that requests override with their own `params`.
+    pub solvers: SolverConfig,
+    /// Plot settings; each job's figures go to `job-{id}` in its output
+    /// directory.
+    pub plot: PlotConfig,
+}
+
+/// An instance submitted as JSON rather than TSPLIB text.
+#[derive(Deserialize)]
+struct InstanceRequest {
+    #[serde(default)]
+    name: String,
+    cities: Vec<City>,
+}
+
+/// The body of a solve request.
+#[derive(Deserialize)]
+struct SolveRequest {
+    algorithm: String,
+    /// `algorithm.parameter=value` overrides, as taken by `--param`.
+    #[serde(default)]
+    params: Vec<String>,
+    seed: Option<u64>,
+    /// Seconds after which the run stops.
+    time_limit: Option<f64>,
+    max_iterations: Option<usize>,
+    /// Iterations in a row without a shorter route after which the run stops.
+    stall_iterations: Option<usize>,
+    target: Option<u64>,
+    /// Percent above the instance's known optimum within which the run stops.
+    target_gap: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum JobState {
+    Running,
+    Finished,
+    Failed,
+}
+
+/// What a client sees of a job when polling it.
+#[derive(Debug, Clone, Serialize)]
+struct JobStatus {
+    state: JobState,
+    iteration: usize,
+    elapsed_ms: u64,
+    best_distance: Option<u64>,
+    /// City indices of the best tour, once the run has finished.
+    tour: Option<Vec<usize>>,
+    /// File names of the figures, fetched from `/jobs/{id}/plots/{file}`.
+    plots: Vec<String>,
+    error: Option<String>,
+}
+
+struct Job {
+    instance: usize,
+    algorithm: Algorithm,
+    status: Mutex<JobStatus>,
+    cancellation: CancellationToken,
+}
+
+impl Observer for Job {
+    fn on_iteration(&self, info: &IterationInfo) {
+        let mut status = self.status.lock().unwrap();
+        status.iteration = info.iteration;
+        status.elapsed_ms = info.elapsed_ms;
+        status.best_distance = Some(info.best.distance);
+    }
+}
+
+#[derive(Default)]
+struct State {
+    instances: Mutex<Vec<Arc<TspLib>>>,
+    jobs: Mutex<Vec<Arc<Job>>>,
+}
+
+/// A reply to a request: a status code and either JSON or a file.
+enum Reply {
+    Json(u16, serde_json::Value),
+    File(Vec<u8>, &'static str),
+}
+
+impl Reply {
+    fn not_found(what: &str) -> Reply {
+        Reply::Json(404, json!({ "error": format!("no such {}", what) }))
+    }
+}
+
+/// Serves the solvers over HTTP until the process is killed:
+///
+/// - `POST /instances` with TSPLIB text or `{"name", "cities": [[x, y], ...]}`
+/// - `GET /instances/{id}`
+/// - `POST /instances/{id}/solve` with `{"algorithm", "params", "seed",
+///   "time_limit", "max_iterations", "stall_iterations", "target",
+///   "target_gap"}`, which starts a job
+/// - `GET /jobs/{id}` for its progress and, once finished, its tour
+/// - `DELETE /jobs/{id}` to stop it early
+/// - `GET /jobs/{id}/plots/{file}` for its figures
+pub fn run(options: ServeOptions) -> Result<()> {
+    let server = Server::http(&options.address)
+        .map_err(|error| anyhow!("failed to listen on {}: {}", options.address, error))?;
+    status!("Listening on http://{}", options.address);
+
+    let options = Arc::new(options);
+    let state = Arc::new(State::default());
+    for mut request in server.incoming_requests() {
+        let reply = handle(&mut request, &options, &state)
+            .unwrap_or_else(|error| Reply::Json(400, json!({ "error": format!("{:#}", error) })));
+        let response = match reply {
+            Reply::Json(status, body) => Response::from_string(body.to_string())
+                .with_status_code(status)
+                .with_header(content_type("application/json")),
+            Reply::File(contents, mime) => {
+                Response::from_data(contents).with_header(content_type(mime))
+            }
+        };
+        if let Err(error) = request.respond(response) {
+            eprintln!("Failed to respond: {}", error);
+        }
+    }
+
+    Ok(())
+}
+
+fn content_type(mime: &str) -> Header {
+    Header::from_bytes("Content-Type", mime).expect("valid header")
+}
+
+fn handle(request: &mut Request, options: &Arc<ServeOptions>, state: &Arc<State>) -> Result<Reply> {
+    let url = request.url().to_string();
+    let path: Vec<&str> = url
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let method = request.method().clone();
+
+    match (method, path.as_slice()) {
+        (Method::Post, ["instances"]) => {
+            let tsp = parse_instance(&read_body(request)?)?;
+            tsplib::check_instance(&tsp)?;
+            // Figures are named after the instance, so its name must not
+            // lead out of the job's directory.
+            if tsp.name.contains(['/', '\\', '\0']) || tsp.name.contains("..") {
+                bail!("invalid instance name `{}`", tsp.name);
+            }
+            let mut instances = state.instances.lock().unwrap();
+            let reply = instance_json(instances.len(), &tsp);
+            instances.push(Arc::new(tsp));
+            Ok(Reply::Json(201, reply))
+        }
+        (Method::Get, ["instances", id]) => match instance(state, id) {
+            Some((id, tsp)) => Ok(Reply::Json(200, instance_json(id, &tsp))),
+            None => Ok(Reply::not_found("instance")),
+        },
+        (Method::Post, ["instances", id, "solve"]) => {
+            let Some((id, tsp)) = instance(state, id) else {
+                return Ok(Reply::not_found("instance"));
+            };
+            let solve: SolveRequest =
+                serde_json::from_str(&read_body(request)?).context("invalid solve request")?;
+            let job = start_job(id, tsp, &solve, options, state)?;
+            Ok(Reply::Json(202, json!({ "job": job })))
+        }
+        (Method::Get, ["jobs", id]) => match job(state, id) {
+            Some(job) => Ok(Reply::Json(
+                200,
+                json!({
+                    "instance": job.instance,
+                    "algorithm": job.algorithm.name(),
+                    "status": *job.status.lock().unwrap(),
+                }),
+            )),
+            None => Ok(Reply::not_found("job")),
+        },
+        (Method::Delete, ["jobs", id]) => match job(state, id) {
+            Some(job) => {
+                job.cancellation.cancel();
+                Ok(Reply::Json(202, json!({ "cancelled": true })))
+            }
+            None => Ok(Reply::not_found("job")),
+        },
+        (Method::Get, ["jobs", id, "plots", file]) => {
+            let Some(id) = id
+                .parse::<usize>()
+                .ok()
+                .filter(|_| job(state, id).is_some())
+            else {
+                return Ok(Reply::not_found("job"));
+            };
+            if file.contains("..") {
+                bail!("invalid file name `{}`", file);
+            }
+            let path = job_plot_config(options, id).output_dir.join(file);
+            let Ok(contents) = fs::read(&path) else {
+                return Ok(Reply::not_found("plot"));
+            };
+            let mime = match path.extension().and_then(|e| e.to_str()) {
+                Some("svg") => "image/svg+xml",
+                Some("png") => "image/png",
+                Some("gif") => "image/gif",
+                Some("html") => "text/html",
+                Some("csv") => "text/csv",
+                _ => "application/octet-stream",
+            };
+            Ok(Reply::File(contents, mime))
+        }
+        _ => Ok(Reply::Json(404, json!({ "error": "no such endpoint" }))),
+    }
+}
+
+fn read_body(request: &mut Request) -> Result<String> {
+    let mut body = String::new();
+    request
+        .as_reader()
+        .read_to_string(&mut body)
+        .context("failed to read request body")?;
+    Ok(body)
+}
+
+/// Parses an instance given as TSPLIB text or as a JSON object.
+fn parse_instance(body: &str) -> Result<TspLib> {
+    if body.trim_start().starts_with('{') {
+        let instance: InstanceRequest =
+            serde_json::from_str(body).context("invalid instance JSON")?;
+        let name = match instance.name.as_str() {
+            "" => "instance",
+            name => name,
+        };
+        Ok(TspLib::from_cities(name, instance.cities))
+    } else {
+        tsplib::parse_tsp(body)
+    }
+}
+
+fn instance_json(id: usize, tsp: &TspLib) -> serde_json::Value {
+    json!({
+        "id": id,
+        "name": tsp.name,
+        "dimension": tsp.dimension,
+        "optimal_tour_length": tsp.optimal_tour_length,
+    })
+}
+
+fn instance(state: &State, id: &str) -> Option<(usize, Arc<TspLib>)> {
+    let id: usize = id.parse().ok()?;
+    let tsp = state.instances.lock().unwrap().get(id)?.clone();
+    Some((id, tsp))
+}
+
+fn job(state: &State, id: &str) -> Option<Arc<Job>> {
+    let id: usize = id.parse().ok()?;
+    state.jobs.lock().unwrap().get(id).cloned()
+}
+
+fn job_plot_config(options: &ServeOptions, id: usize) -> PlotConfig {
+    let mut plot = options.plot.clone();
+    plot.output_dir = plot.output_dir.join(format!("job-{}", id));
+    plot
+}
+
+/// Starts solving `tsp` on a new thread and returns the job's id.
+fn start_job(
+    instance: usize,
+    tsp: Arc<TspLib>,
+    request: &SolveRequest,
+    options: &Arc<ServeOptions>,
+    state: &State,
+) -> Result<usize> {
+    let algorithm: Algorithm = request.algorithm.parse()?;
+    let mut solvers = options.solvers.clone();
+    for param in &request.params {
+        solvers.apply_param(param)?;
+    }
+    let cancellation = CancellationToken::new();
+    let termination = Termination {
+        time_limit: request
+            .time_limit
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .context("time_limit expects a non-negative number of seconds")?,
+        max_iterations: request.max_iterations,
+        stall_iterations: request.stall_iterations,
+        target_distance: request.target,
+        target_gap: request.target_gap,
+        cancellation: Some(cancellation.clone()),
+    };
+    let job = Arc::new(Job {
+        instance,
+        algorithm,
+        status: Mutex::new(JobStatus {
+            state: JobState::Running,
+            iteration: 0,
+            elapsed_ms: 0,
+            best_distance: None,
+            tour: None,
+            plots: Vec::new(),
+            error: None,
+        }),
+        cancellation,
+    });
+    let id = {
+        let mut jobs = state.jobs.lock().unwrap();
+        jobs.push(job.clone());
+        jobs.len() - 1
+    };
+
+    let spec = solvers.spec(algorithm);
+    let mut plot = job_plot_config(options, id);
+    plot.instance = tsp.name.clone();
+    let seed = request.seed;
+    thread::spawn(move || {
+        // A panicking solver fails its job rather than leaving it running forever.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            run_job(&tsp, &spec, seed, &termination, &job, &plot)
+        }))
+        .unwrap_or_else(|_| Err(anyhow!("the solver panicked")));
+
+        let mut status = job.status.lock().unwrap();
+        match result {
+            Ok(Solution { route, .. }) => {
+                status.state = JobState::Finished;
+                status.best_distance = Some(route.distance);
+                status.tour = Some(route.tour);
+                status.plots = plot_files(&plot);
+            }
+            Err(error) => {
+                status.state = JobState::Failed;
+                status.error = Some(format!("{:#}", error));
+            }
+        }
+    });
+
+    Ok(id)
+}
+
+/// Solves `tsp` with `spec`, reporting progress to `job`, and plots the result.
+fn run_job(
+    tsp: &TspLib,
+    spec: &SolverSpec,
+    seed: Option<u64>,
+    termination: &Termination,
+    job: &Arc<Job>,
+    plot: &PlotConfig,
+) -> Result<Solution> {
+    let mut solver = config::build_solver(tsp, spec);
+    if let Some(seed) = seed {
+        solver.set_seed(seed);
+    }
+    solver.add_observer(job.clone());
+    let solution = solver.solve(tsp, termination)?;
+
+    let parameters = config::parameter_summary(&spec.to_table()?.1);
+    let colors = plot.series_colors().context("invalid palette")?;
+    let index = Algorithm::ALL
+        .iter()
+        .position(|&a| a == job.algorithm)
+        .unwrap_or(0);
+    plot::plot_algo_result(
+        solver.as_ref(),
+        tsp,
+        job.algorithm.name(),
+        &colors[index % colors.len()],
+        &parameters,
+        plot,
+    )?;
+
+    Ok(solution)
+}
+
+/// Names of the files written to a job's plot directory.
+fn plot_files(plot: &PlotConfig) -> Vec<String> {
+    let mut files: Vec<String> = fs::read_dir(&plot.output_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
@@ -0,0 +1,914 @@
+use std::{borrow::Cow, collections::HashSet, fmt, fmt::Write, fs, path::Path};
+
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "tables")]
+use prettytable::{row, Table};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{
+    memory::MemoryUsage,
+    tsplib::{HeuristicAlgorithm, TspLib},
+};
+
+/// Percentage by which `distance`, a tour length or a mean of several,
+/// exceeds the known optimal tour length of `tsp`.
+pub fn gap(distance: f64, tsp: &TspLib) -> Option<f64> {
+    tsp.optimal_tour_length
+        .map(|optimum| (distance - optimum as f64) / optimum as f64 * 100.0)
+}
+
+/// Formats a gap the way every report shows it, e.g. "6.81%".
+pub fn format_gap(gap: f64) -> String {
+    format!("{:.2}%", gap)
+}
+
+/// Longest tour within `gap` percent of the known optimum of `tsp`.
+pub fn target_distance(tsp: &TspLib, gap: f64) -> Option<u64> {
+    tsp.optimal_tour_length
+        .map(|optimum| (optimum as f64 * (1.0 + gap / 100.0)).floor() as u64)
+}
+
+/// Gaps to the optimum, in percent, at which a run's convergence speed is
+/// measured.
+pub const TARGET_GAPS: [f64; 3] = [10.0, 5.0, 1.0];
+
+/// When a run first came within a target gap of the optimum.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TargetHit {
+    /// Iteration, counted from 1.
+    pub iteration: usize,
+    pub time_ms: u64,
+}
+
+/// A new best distance a run found, and when.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Improvement {
+    /// Iteration, counted from 1.
+    pub iteration: usize,
+    pub time_ms: u64,
+    pub distance: u64,
+}
+
+/// How often a solver applied one of its operators and with what outcome.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct OperatorStats {
+    pub applied: u64,
+    /// Applications whose result the solver kept.
+    pub accepted: u64,
+    /// Applications whose result was shorter than what it was derived from.
+    pub improving: u64,
+}
+
+impl OperatorStats {
+    /// Share of applications that were accepted, 0 if there were none.
+    pub fn acceptance_rate(&self) -> f64 {
+        self.accepted as f64 / self.applied.max(1) as f64
+    }
+
+    /// Share of applications that improved the tour, 0 if there were none.
+    pub fn improvement_rate(&self) -> f64 {
+        self.improving as f64 / self.applied.max(1) as f64
+    }
+}
+
+/// Usage counters of a solver's operators (moves, crossovers, mutations...),
+/// in the order they were first applied.
+#[derive(Debug, Clone, Default)]
+pub struct OperatorCounts {
+    operators: Vec<(Cow<'static, str>, OperatorStats)>,
+}
+
+impl OperatorCounts {
+    /// Counts one application of `operator`.
+    pub fn record(&mut self, operator: &'static str, accepted: bool, improving: bool) {
+        let index = match self
+            .operators
+            .iter()
+            .position(|(name, _)| *name == operator)
+        {
+            Some(index) => index,
+            None => {
+                self.operators
+                    .push((Cow::Borrowed(operator), OperatorStats::default()));
+                self.operators.len() - 1
+            }
+        };
+        let stats = &mut self.operators[index].1;
+        stats.applied += 1;
+        stats.accepted += accepted as u64;
+        stats.improving += improving as u64;
+    }
+
+    /// Adds the counts of `other`, e.g. another run of the same solver.
+    pub fn merge(&mut self, other: &OperatorCounts) {
+        for (name, theirs) in &other.operators {
+            match self.operators.iter_mut().find(|(ours, _)| ours == name) {
+                Some((_, ours)) => {
+                    ours.applied += theirs.applied;
+                    ours.accepted += theirs.accepted;
+                    ours.improving += theirs.improving;
+                }
+                None => self.operators.push((name.clone(), *theirs)),
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.operators.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &OperatorStats)> {
+        self.operators
+            .iter()
+            .map(|(name, stats)| (name.as_ref(), stats))
+    }
+}
+
+impl Serialize for OperatorCounts {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Reads the map written by `Serialize`, keeping the operators in order.
+impl<'de> Deserialize<'de> for OperatorCounts {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CountsVisitor;
+
+        impl<'de> Visitor<'de> for CountsVisitor {
+            type Value = OperatorCounts;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a map from operator names to their counts")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut operators = Vec::new();
+                while let Some((name, stats)) = map.next_entry::<String, OperatorStats>()? {
+                    operators.push((Cow::Owned(name), stats));
+                }
+                Ok(OperatorCounts { operators })
+            }
+        }
+
+        deserializer.deserialize_map(CountsVisitor)
+    }
+}
+
+/// Describes every operator's usage, e.g.
+/// "swap 8000 applied, 12.5% accepted, 3.1% improving; 2-opt ...".
+pub fn format_operators(counts: &OperatorCounts) -> String {
+    counts
+        .iter()
+        .map(|(name, stats)| {
+            format!(
+                "{} {} applied, {:.1}% accepted, {:.1}% improving",
+                name,
+                stats.applied,
+                stats.acceptance_rate() * 100.0,
+                stats.improvement_rate() * 100.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Percentage of the edges of `tour` that `optimal_tour` also has, regardless
+/// of direction; both are closed tours over city indices.
+pub fn edge_overlap(tour: &[usize], optimal_tour: &[usize]) -> f64 {
+    let edges = |tour: &[usize]| -> HashSet<(usize, usize)> {
+        tour.iter()
+            .zip(tour.iter().cycle().skip(1))
+            .map(|(&a, &b)| (a.min(b), a.max(b)))
+            .collect()
+    };
+    let optimal = edges(optimal_tour);
+    let shared = edges(tour).intersection(&optimal).count();
+
+    shared as f64 / tour.len().max(1) as f64 * 100.0
+}
+
+/// Quality and convergence of a single solver run, derived from its history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStats {
+    /// Shortest tour the run found.
+    pub best_distance: u64,
+    /// Distance of the route the solver returned.
+    pub final_distance: u64,
+    /// Percentage by which `best_distance` exceeds the known optimum.
+    pub gap: Option<f64>,
+    /// Percentage of the returned route's edges that the known optimal tour
+    /// shares; tours with the same gap can differ widely in structure.
+    pub optimal_edge_overlap: Option<f64>,
+    /// Iterations (or generations) the run recorded.
+    pub iterations: usize,
+    /// First iteration, counted from 1, whose route was as short as
+    /// `best_distance`.
+    pub iterations_to_best: usize,
+    /// Wall time, in milliseconds, at which `best_distance` was first reached.
+    pub time_to_best_ms: u64,
+    /// Average shortening of the tour per iteration between the first
+    /// iteration and `iterations_to_best`.
+    pub improvement_rate: f64,
+    /// Area under the run's convergence curve, see [`convergence_area`],
+    /// measured against the optimum or, when it is unknown, `best_distance`.
+    #[serde(default)]
+    pub convergence_area: f64,
+    /// Mean share of edges two members of the population did not have in
+    /// common after the last iteration, for GA and PSO.
+    #[serde(default)]
+    pub population_diversity: Option<f64>,
+    /// Share of its moves the solver accepted, for SA.
+    #[serde(default)]
+    pub acceptance_rate: Option<f64>,
+    /// When the run first came within each of [`TARGET_GAPS`] of the optimum;
+    /// `None` if it never did or the optimum is unknown.
+    pub targets: [Option<TargetHit>; TARGET_GAPS.len()],
+    /// Every iteration that shortened the best tour, starting with the first.
+    pub improvements: Vec<Improvement>,
+    /// `(elapsed_ms, distance)` of the route recorded at every iteration.
+    pub history: Vec<(u64, u64)>,
+    /// How often each of the solver's operators was applied, accepted and
+    /// improving.
+    pub operators: OperatorCounts,
+    /// Memory the run used, when it was tracked with a
+    /// [`crate::memory::MemoryTracker`].
+    pub memory: MemoryUsage,
+    pub run_time_ms: u64,
+}
+
+impl RunStats {
+    /// Summarizes the last run of `algorithm` on `tsp`.
+    pub fn from(algorithm: &dyn HeuristicAlgorithm, tsp: &TspLib) -> Self {
+        let history = algorithm.get_history();
+        let times = algorithm.get_history_times();
+        let final_distance = algorithm.get_best_route().distance;
+        let best_distance = history
+            .iter()
+            .map(|route| route.distance)
+            .min()
+            .map_or(final_distance, |best| best.min(final_distance));
+
+        let (iterations_to_best, time_to_best_ms) = match history
+            .iter()
+            .position(|route| route.distance == best_distance)
+        {
+            Some(i) => (i + 1, times.get(i).copied().unwrap_or(0)),
+            // Only the returned route reached it, after the last iteration.
+            None => (history.len(), algorithm.get_run_time()),
+        };
+        let improvement_rate = history.first().map_or(0.0, |first| {
+            first.distance.saturating_sub(best_distance) as f64
+                / iterations_to_best.saturating_sub(1).max(1) as f64
+        });
+
+        let mut improvements: Vec<Improvement> = Vec::new();
+        for (i, route) in history.iter().enumerate() {
+            if improvements
+                .last()
+                .is_none_or(|last| route.distance < last.distance)
+            {
+                improvements.push(Improvement {
+                    iteration: i + 1,
+                    time_ms: times.get(i).copied().unwrap_or(0),
+                    distance: route.distance,
+                });
+            }
+        }
+        let targets = TARGET_GAPS.map(|gap| {
+            let target = target_distance(tsp, gap)?;
+            improvements
+                .iter()
+                .find(|improvement| improvement.distance <= target)
+                .map(|improvement| TargetHit {
+                    iteration: improvement.iteration,
+                    time_ms: improvement.time_ms,
+                })
+        });
+
+        let history: Vec<(u64, u64)> = history
+            .iter()
+            .enumerate()
+            .map(|(i, route)| (times.get(i).copied().unwrap_or(0), route.distance))
+            .collect();
+
+        RunStats {
+            best_distance,
+            final_distance,
+            gap: gap(best_distance as f64, tsp),
+            optimal_edge_overlap: tsp
+                .optimal_tour
+                .as_ref()
+                .map(|optimal_tour| edge_overlap(&algorithm.get_best_route().tour, optimal_tour)),
+            iterations: history.len(),
+            iterations_to_best,
+            time_to_best_ms,
+            improvement_rate,
+            convergence_area: convergence_area(
+                &history,
+                algorithm.get_run_time(),
+                tsp.optimal_tour_length.unwrap_or(best_distance),
+            ),
+            population_diversity: algorithm
+                .get_diversity()
+                .map(|diversity| diversity.pairwise_distance),
+            acceptance_rate: algorithm.get_acceptance_rate(),
+            targets,
+            improvements,
+            history,
+            operators: algorithm.get_operator_counts().clone(),
+            memory: MemoryUsage::default(),
+            run_time_ms: algorithm.get_run_time(),
+        }
+    }
+}
+
+/// Gap to the optimum, in percent, within which a run counts as a success.
+pub const SUCCESS_GAP: f64 = 5.0;
+
+/// Distribution of the best distances of repeated runs.
+#[derive(Debug, Clone)]
+pub struct AggregateStats {
+    pub runs: usize,
+    pub mean: f64,
+    pub median: f64,
+    /// Sample standard deviation, 0 for a single run.
+    pub std: f64,
+    pub min: u64,
+    pub max: u64,
+    /// Share of runs within [`SUCCESS_GAP`] of the known optimum.
+    pub success_rate: Option<f64>,
+    /// For each of [`TARGET_GAPS`], the runs that reached it and their mean
+    /// wall time to do so.
+    pub targets: [(usize, Option<f64>); TARGET_GAPS.len()],
+    /// Mean [`RunStats::convergence_area`].
+    pub convergence_area: f64,
+    /// Mean [`RunStats::population_diversity`] of the runs that measured it.
+    pub population_diversity: Option<f64>,
+    /// Mean [`RunStats::acceptance_rate`] of the runs that measured it.
+    pub acceptance_rate: Option<f64>,
+}
+
+/// Mean of the values present, `None` if there are none.
+fn mean_of(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let values: Vec<f64> = values.flatten().collect();
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Share of `runs` within `gap` percent of the known optimum, `None` if it is
+/// unknown.
+pub fn success_rate(runs: &[RunStats], gap: f64) -> Option<f64> {
+    runs.iter()
+        .map(|run| run.gap.map(|run_gap| run_gap <= gap))
+        .collect::<Option<Vec<bool>>>()
+        .map(|successes| successes.iter().filter(|&&s| s).count() as f64 / runs.len() as f64)
+}
+
+/// Aggregates the best distances of `runs`, which must not be empty.
+pub fn aggregate(runs: &[RunStats]) -> AggregateStats {
+    let mut distances: Vec<u64> = runs.iter().map(|run| run.best_distance).collect();
+    distances.sort_unstable();
+    let count = distances.len() as f64;
+
+    let mean = distances.iter().map(|&d| d as f64).sum::<f64>() / count;
+    let middle = distances.len() / 2;
+    let median = if distances.len().is_multiple_of(2) {
+        (distances[middle - 1] + distances[middle]) as f64 / 2.0
+    } else {
+        distances[middle] as f64
+    };
+    let variance = distances
+        .iter()
+        .map(|&d| (d as f64 - mean).powi(2))
+        .sum::<f64>()
+        / (count - 1.0).max(1.0);
+    let success_rate = success_rate(runs, SUCCESS_GAP);
+
+    let targets = std::array::from_fn(|t| {
+        let times: Vec<u64> = runs
+            .iter()
+            .filter_map(|run| run.targets[t].map(|hit| hit.time_ms))
+            .collect();
+        let mean =
+            (!times.is_empty()).then(|| times.iter().sum::<u64>() as f64 / times.len() as f64);
+        (times.len(), mean)
+    });
+
+    AggregateStats {
+        runs: runs.len(),
+        mean,
+        median,
+        std: variance.sqrt(),
+        min: distances[0],
+        max: distances[distances.len() - 1],
+        success_rate,
+        targets,
+        convergence_area: runs.iter().map(|run| run.convergence_area).sum::<f64>() / count,
+        population_diversity: mean_of(runs.iter().map(|run| run.population_diversity)),
+        acceptance_rate: mean_of(runs.iter().map(|run| run.acceptance_rate)),
+    }
+}
+
+/// Relative precision, in percent, to which [`SeedVariance`] sizes the number
+/// of repetitions: the mean best distance within this much of its true value.
+pub const SEED_PRECISION: f64 = 1.0;
+
+/// How much the best distance of a fixed configuration varies with the seed.
+#[derive(Debug, Clone)]
+pub struct SeedVariance {
+    pub runs: usize,
+    /// Standard deviation over the mean, in percent.
+    pub coefficient_of_variation: f64,
+    /// Difference between the worst and the best run.
+    pub spread: u64,
+    /// `spread` relative to the best run, in percent.
+    pub relative_spread: f64,
+    /// Runs needed for the mean to be within [`SEED_PRECISION`] percent of its
+    /// true value with 95% confidence, under a normal approximation.
+    pub repetitions_needed: usize,
+}
+
+/// Seed variance of `runs` of one configuration with different seeds, which
+/// must not be empty.
+pub fn seed_variance(runs: &[RunStats]) -> SeedVariance {
+    let summary = aggregate(runs);
+    let coefficient_of_variation = summary.std / summary.mean.max(f64::MIN_POSITIVE) * 100.0;
+    let spread = summary.max - summary.min;
+
+    SeedVariance {
+        runs: summary.runs,
+        coefficient_of_variation,
+        spread,
+        relative_spread: spread as f64 / summary.min.max(1) as f64 * 100.0,
+        repetitions_needed: ((1.96 * coefficient_of_variation / SEED_PRECISION)
+            .powi(2)
+            .ceil() as usize)
+            .max(2),
+    }
+}
+
+/// Describes when each of [`TARGET_GAPS`] was reached, e.g.
+/// "10% at iteration 12 (35ms), 5% at iteration 40 (110ms), 1% never".
+pub fn format_targets(targets: &[Option<TargetHit>]) -> String {
+    TARGET_GAPS
+        .iter()
+        .zip(targets)
+        .map(|(gap, hit)| match hit {
+            Some(hit) => format!(
+                "{}% at iteration {} ({}ms)",
+                gap, hit.iteration, hit.time_ms
+            ),
+            None => format!("{}% never", gap),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Best-so-far distance of `algorithm`'s last run as a function of wall time:
+/// `(elapsed_ms, distance)` at the start and at every improvement, ending at
+/// the run's total runtime.
+pub fn anytime_curve(algorithm: &dyn HeuristicAlgorithm) -> Vec<(u64, u64)> {
+    let history = algorithm.get_history();
+    let times = algorithm.get_history_times();
+    let mut curve: Vec<(u64, u64)> = Vec::new();
+    for (route, &time) in history.iter().zip(times) {
+        if curve.last().is_none_or(|&(_, best)| route.distance < best) {
+            curve.push((time, route.distance));
+        }
+    }
+    if let Some(&(time, best)) = curve.last() {
+        let end = algorithm.get_run_time().max(time);
+        let best = best.min(algorithm.get_best_route().distance);
+        curve.push((end, best));
+    }
+
+    curve
+}
+
+/// Area under the best-so-far curve of a run whose history is `history`,
+/// `(elapsed_ms, distance)` per iteration, divided by its span up to
+/// `run_time_ms`: the percentage by which the best tour so far exceeded
+/// `reference`, averaged over the run's wall time. It rewards getting close
+/// early as well as getting close at all, and is 0 for a run that starts at
+/// `reference`. Runs too quick to time are averaged over their iterations.
+pub fn convergence_area(history: &[(u64, u64)], run_time_ms: u64, reference: u64) -> f64 {
+    let excess =
+        |distance: u64| distance.saturating_sub(reference) as f64 / reference.max(1) as f64 * 100.0;
+    let best_so_far: Vec<(u64, f64)> = history
+        .iter()
+        .scan(u64::MAX, |best, &(time, distance)| {
+            *best = (*best).min(distance);
+            Some((time, excess(*best)))
+        })
+        .collect();
+    let (Some(&(start, _)), Some(&(last, _))) = (best_so_far.first(), best_so_far.last()) else {
+        return 0.0;
+    };
+    let end = run_time_ms.max(last);
+    if end == start {
+        return best_so_far.iter().map(|&(_, gap)| gap).sum::<f64>() / best_so_far.len() as f64;
+    }
+    let area: f64 = best_so_far
+        .iter()
+        .zip(
+            best_so_far
+                .iter()
+                .skip(1)
+                .map(|&(time, _)| time)
+                .chain([end]),
+        )
+        .map(|(&(time, gap), next)| gap * next.saturating_sub(time) as f64)
+        .sum();
+    area / (end - start) as f64
+}
+
+/// Empirical run-length distribution of repeated runs for a target distance:
+/// the probability of a run having reached the target as a function of wall
+/// time and of iterations.
+#[derive(Debug, Clone)]
+pub struct RunLengthDistribution {
+    pub runs: usize,
+    /// `(elapsed_ms, probability)`, one step per run that reached the target.
+    pub by_time: Vec<(u64, f64)>,
+    /// `(iteration, probability)`, one step per run that reached the target.
+    pub by_iteration: Vec<(usize, f64)>,
+}
+
+impl RunLengthDistribution {
+    /// Wall time and iterations by which half of the runs had reached the
+    /// target, if they did.
+    pub fn median(&self) -> Option<(u64, usize)> {
+        let k = self
+            .by_time
+            .iter()
+            .position(|&(_, probability)| probability >= 0.5)?;
+        Some((self.by_time[k].0, self.by_iteration[k].0))
+    }
+}
+
+/// Run-length distribution of `runs` for reaching a tour of at most `target`.
+pub fn run_length_distribution(runs: &[RunStats], target: u64) -> RunLengthDistribution {
+    let hits: Vec<&Improvement> = runs
+        .iter()
+        .filter_map(|run| {
+            run.improvements
+                .iter()
+                .find(|improvement| improvement.distance <= target)
+        })
+        .collect();
+
+    RunLengthDistribution {
+        runs: runs.len(),
+        by_time: cumulative(hits.iter().map(|hit| hit.time_ms).collect(), runs.len()),
+        by_iteration: cumulative(hits.iter().map(|hit| hit.iteration).collect(), runs.len()),
+    }
+}
+
+/// Empirical distribution function of `values` out of `runs` runs in all.
+fn cumulative<T: Ord>(mut values: Vec<T>, runs: usize) -> Vec<(T, f64)> {
+    values.sort_unstable();
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(k, value)| (value, (k + 1) as f64 / runs.max(1) as f64))
+        .collect()
+}
+
+/// Dolan-Moré performance profiles of several algorithms over several
+/// instances from `costs[instance][algorithm]`, lower being better and `None`
+/// marking a failure. For each algorithm, the profile is a step function of
+/// `(tau, share)` points: the share of instances on which its cost was within
+/// a factor `tau` of the best algorithm's, one step per instance it solved.
+pub fn performance_profiles(costs: &[Vec<Option<f64>>]) -> Vec<Vec<(f64, f64)>> {
+    let algorithms = costs.iter().map(Vec::len).max().unwrap_or(0);
+    let mut ratios = vec![Vec::new(); algorithms];
+    for instance in costs {
+        let best = instance
+            .iter()
+            .flatten()
+            .copied()
+            .filter(|cost| cost.is_finite())
+            .fold(f64::INFINITY, f64::min);
+        for (algorithm, cost) in instance.iter().enumerate() {
+            if let Some(cost) = cost.filter(|cost| cost.is_finite()) {
+                // Equal costs of 0 (e.g. instant runs) tie rather than divide by 0.
+                let ratio = if cost == best { 1.0 } else { cost / best };
+                ratios[algorithm].push(ratio);
+            }
+        }
+    }
+
+    ratios
+        .into_iter()
+        .map(|mut ratios| {
+            ratios.sort_by(f64::total_cmp);
+            ratios
+                .into_iter()
+                .enumerate()
+                .map(|(k, tau)| (tau, (k + 1) as f64 / costs.len().max(1) as f64))
+                .collect()
+        })
+        .collect()
+}
+
+/// Rank, from 1, of each algorithm on one instance by `costs[algorithm]`,
+/// lower being better and `None` marking a failure, which ranks last. Tied
+/// algorithms share the mean of the ranks they span.
+pub fn ranks(costs: &[Option<f64>]) -> Vec<f64> {
+    let keys: Vec<f64> = costs
+        .iter()
+        .map(|cost| cost.unwrap_or(f64::INFINITY))
+        .collect();
+    keys.iter()
+        .map(|key| {
+            let better = keys.iter().filter(|other| *other < key).count();
+            let tied = keys.iter().filter(|other| *other == key).count();
+            better as f64 + (tied as f64 + 1.0) / 2.0
+        })
+        .collect()
+}
+
+/// How one algorithm ranked across a set of instances.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RankSummary {
+    pub average_rank: f64,
+    /// Instances on which it alone ranked first.
+    pub wins: usize,
+    /// Instances on which it shared the first rank.
+    pub ties: usize,
+    /// Instances on which another algorithm was better.
+    pub losses: usize,
+}
+
+/// Ranks the algorithms on every instance of `costs[instance][algorithm]`, as
+/// in [`ranks`], and summarizes each algorithm's ranks.
+pub fn rank_summary(costs: &[Vec<Option<f64>>]) -> Vec<RankSummary> {
+    let algorithms = costs.iter().map(Vec::len).max().unwrap_or(0);
+    let mut summaries = vec![RankSummary::default(); algorithms];
+    for instance in costs {
+        let ranks = ranks(instance);
+        let first = ranks.iter().copied().fold(f64::INFINITY, f64::min);
+        let sharing_first = ranks.iter().filter(|&&rank| rank == first).count();
+        for (summary, &rank) in summaries.iter_mut().zip(&ranks) {
+            summary.average_rank += rank / costs.len() as f64;
+            if rank > first {
+                summary.losses += 1;
+            } else if sharing_first == 1 {
+                summary.wins += 1;
+            } else {
+                summary.ties += 1;
+            }
+        }
+    }
+
+    summaries
+}
+
+/// A per-iteration value a solver records besides its best route: a column
+/// name and one value per iteration.
+pub type Metric<'a> = (&'a str, Vec<f64>);
+
+/// Writes the convergence of `algorithm`'s last run to `path` as CSV, one row
+/// per iteration with the best distance so far, the elapsed time, the gap to
+/// the optimum when known and a column per entry of `metrics`.
+pub fn export_history_csv(
+    algo_name: &str,
+    algorithm: &dyn HeuristicAlgorithm,
+    tsp: &TspLib,
+    metrics: &[Metric],
+    path: &Path,
+) -> Result<()> {
+    let history = algorithm.get_history();
+    let times = algorithm.get_history_times();
+
+    let mut contents = String::from("algorithm,iteration,best_distance,elapsed_ms");
+    if tsp.optimal_tour_length.is_some() {
+        contents += ",gap_percent";
+    }
+    for (name, _) in metrics {
+        write!(contents, ",{}", name)?;
+    }
+    contents.push('\n');
+
+    let mut best = u64::MAX;
+    for (iteration, route) in history.iter().enumerate() {
+        best = best.min(route.distance);
+        write!(
+            contents,
+            "{},{},{},{}",
+            algo_name,
+            iteration,
+            best,
+            times.get(iteration).copied().unwrap_or(0)
+        )?;
+        if let Some(gap) = gap(best as f64, tsp) {
+            write!(contents, ",{}", gap)?;
+        }
+        for (_, values) in metrics {
+            match values.get(iteration) {
+                Some(value) => write!(contents, ",{}", value)?,
+                None => contents.push(','),
+            }
+        }
+        contents.push('\n');
+    }
+
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Two-sided p-value of the Mann-Whitney U test that `a` and `b` come from
+/// the same distribution, by the normal approximation with a correction for
+/// ties; `None` if either sample has fewer than two values.
+pub fn mann_whitney_p(a: &[f64], b: &[f64]) -> Option<f64> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let mut pooled: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&x| (x, true))
+        .chain(b.iter().map(|&x| (x, false)))
+        .collect();
+    pooled.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // Average ranks over runs of ties, accumulating the tie correction.
+    let mut rank_sum_a = 0.0;
+    let mut ties = 0.0;
+    let mut start = 0;
+    while start < pooled.len() {
+        let end = start
+            + pooled[start..]
+                .iter()
+                .take_while(|(x, _)| *x == pooled[start].0)
+                .count();
+        let rank = (start + end + 1) as f64 / 2.0;
+        rank_sum_a += rank * pooled[start..end].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        let t = (end - start) as f64;
+        ties += t * t * t - t;
+        start = end;
+    }
+
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let n = n1 + n2;
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let mean = n1 * n2 / 2.0;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return Some(1.0);
+    }
+    // Continuity correction towards the mean.
+    let z = ((u - mean).abs() - 0.5).max(0.0) / variance.sqrt();
+
+    Some((2.0 * (1.0 - standard_normal_cdf(z))).min(1.0))
+}
+
+/// Standard normal CDF, via the Abramowitz and Stegun approximation of erf
+/// (absolute error below 1.5e-7).
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let polynomial = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - polynomial * (-x * x).exp();
+    if z >= 0.0 {
+        (1.0 + erf) / 2.0
+    } else {
+        (1.0 - erf) / 2.0
+    }
+}
+
+/// Outcome of comparing an algorithm's runs on an instance across two
+/// experiments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Improved,
+    Regressed,
+    /// No significant difference.
+    Unchanged,
+    /// Too few runs on either side to test.
+    Untested,
+}
+
+/// An algorithm's best distances on one instance in a baseline and a current
+/// experiment.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub instance: String,
+    pub algorithm: String,
+    pub baseline_mean: f64,
+    pub current_mean: f64,
+    /// Change of the mean best distance, in percent of the baseline's.
+    pub change: f64,
+    pub p_value: Option<f64>,
+    pub verdict: Verdict,
+}
+
+/// Best distances of an algorithm's runs on an instance, keyed by instance and
+/// algorithm name.
+type RecordedRuns = ((String, String), Vec<f64>);
+
+/// Best distances of every run in an experiment JSON document, in document
+/// order.
+fn experiment_distances(path: &Path) -> Result<Vec<RecordedRuns>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let document: serde_json::Value = serde_json::from_str(&contents)
+        .with_context(|| format!("failed to parse {}", path.display()))?;
+    let version = document["version"].as_u64();
+    if version != Some(crate::experiment::FORMAT_VERSION as u64) {
+        bail!(
+            "{} has experiment format version {:?}, expected {}",
+            path.display(),
+            version,
+            crate::experiment::FORMAT_VERSION
+        );
+    }
+
+    let mut distances = Vec::new();
+    for instance in document["instances"].as_array().into_iter().flatten() {
+        let instance_name = instance["name"].as_str().unwrap_or_default();
+        for algorithm in instance["algorithms"].as_array().into_iter().flatten() {
+            let runs = algorithm["runs"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|run| run["best_distance"].as_f64())
+                .collect();
+            let key = (
+                instance_name.to_string(),
+                algorithm["name"].as_str().unwrap_or_default().to_string(),
+            );
+            distances.push((key, runs));
+        }
+    }
+
+    Ok(distances)
+}
+
+/// Matches the runs of the experiments recorded at `baseline` and `current`
+/// by instance and algorithm and tests each pair for a change in best
+/// distance at significance level `alpha`. Pairs missing from either side
+/// are left out.
+pub fn compare(baseline: &Path, current: &Path, alpha: f64) -> Result<Vec<Comparison>> {
+    let baseline = experiment_distances(baseline)?;
+    let current = experiment_distances(current)?;
+
+    Ok(current
+        .iter()
+        .filter_map(|(key, after)| {
+            let (_, before) = baseline.iter().find(|(other, _)| other == key)?;
+            if before.is_empty() || after.is_empty() {
+                return None;
+            }
+            let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+            let (baseline_mean, current_mean) = (mean(before), mean(after));
+            let p_value = mann_whitney_p(before, after);
+            let verdict = match p_value {
+                None => Verdict::Untested,
+                Some(p) if p >= alpha => Verdict::Unchanged,
+                Some(_) if current_mean < baseline_mean => Verdict::Improved,
+                Some(_) => Verdict::Regressed,
+            };
+            Some(Comparison {
+                instance: key.0.clone(),
+                algorithm: key.1.clone(),
+                baseline_mean,
+                current_mean,
+                change: (current_mean - baseline_mean) / baseline_mean.max(f64::MIN_POSITIVE)
+                    * 100.0,
+                p_value,
+                verdict,
+            })
+        })
+        .collect())
+}
+
+/// Prints `comparisons` as a table, one row per instance and algorithm.
+#[cfg(feature = "tables")]
+pub fn print_comparisons(comparisons: &[Comparison]) {
+    let mut table = Table::new();
+    table.add_row(row![bFg => "Instance", "Algorithm", "Baseline mean", "Current mean", "Change", "p-value", "Verdict"]);
+    for comparison in comparisons {
+        let verdict = match comparison.verdict {
+            Verdict::Improved => "improved",
+            Verdict::Regressed => "REGRESSED",
+            Verdict::Unchanged => "unchanged",
+            Verdict::Untested => "too few runs",
+        };
+        table.add_row(row![
+            comparison.instance,
+            comparison.algorithm,
+            format!("{:.1}", comparison.baseline_mean),
+            format!("{:.1}", comparison.current_mean),
+            format!("{:+.2}%", comparison.change),
+            comparison
+                .p_value
+                .map_or("-".to_string(), |p| format!("{:.4}", p)),
+            verdict
+        ]);
+    }
+    table.printstd();
+}
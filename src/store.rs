@@ -0,0 +1,373 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Context, Result};
+#[cfg(feature = "tables")]
+use prettytable::{row, Table};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    experiment::InstanceRecord, hyper::OptimizationResult, progress::status, stats, tsplib::TspLib,
+};
+
+/// Store `sapso report` reads when not given one.
+pub const DEFAULT_PATH: &str = "runs.ndjson";
+
+/// Which command a stored run came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Run,
+    Bench,
+    /// A trial of `--hyper`, summarized over its evaluations.
+    Hyper,
+}
+
+impl FromStr for Source {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Ok(match name.trim().to_lowercase().as_str() {
+            "run" => Source::Run,
+            "bench" => Source::Bench,
+            "hyper" => Source::Hyper,
+            _ => bail!("unknown source `{}`, expected run, bench or hyper", name),
+        })
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Source::Run => "run",
+            Source::Bench => "bench",
+            Source::Hyper => "hyper",
+        })
+    }
+}
+
+/// One run as a line of the store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredRun {
+    /// Seconds since the Unix epoch at which the run was stored.
+    pub recorded: u64,
+    pub source: Source,
+    pub instance: String,
+    pub dimension: usize,
+    pub optimal_tour_length: Option<u64>,
+    pub algorithm: String,
+    pub parameters: serde_json::Value,
+    pub seed: Option<u64>,
+    /// Shortest tour the run found.
+    pub best_distance: u64,
+    /// Distance of the route the solver returned.
+    pub final_distance: u64,
+    /// Percentage by which `best_distance` exceeds the known optimum.
+    pub gap: Option<f64>,
+    pub runtime_ms: u64,
+    /// Shortest tour found up to each iteration; empty for hyperparameter
+    /// trials.
+    #[serde(default)]
+    pub best_history: Vec<u64>,
+    /// Manifest of the invocation the run was part of.
+    #[serde(default)]
+    pub manifest: Option<PathBuf>,
+}
+
+impl StoredRun {
+    /// Every run of every algorithm in `record`.
+    pub fn from_record(
+        record: &InstanceRecord,
+        source: Source,
+        manifest: Option<&Path>,
+    ) -> Vec<Self> {
+        let recorded = now();
+        record
+            .algorithms
+            .iter()
+            .flat_map(|algorithm| {
+                algorithm.runs.iter().map(move |run| StoredRun {
+                    recorded,
+                    source,
+                    instance: record.name.clone(),
+                    dimension: record.dimension,
+                    optimal_tour_length: record.optimal_tour_length,
+                    algorithm: algorithm.name.clone(),
+                    parameters: algorithm.config.clone(),
+                    seed: run.seed,
+                    best_distance: run.stats.best_distance,
+                    final_distance: run.stats.final_distance,
+                    gap: run.stats.gap,
+                    runtime_ms: run.stats.run_time_ms,
+                    best_history: run
+                        .stats
+                        .history
+                        .iter()
+                        .scan(u64::MAX, |best, &(_, distance)| {
+                            *best = (*best).min(distance);
+                            Some(*best)
+                        })
+                        .collect(),
+                    manifest: manifest.map(Path::to_path_buf),
+                })
+            })
+            .collect()
+    }
+
+    /// The trials of a hyperparameter optimization on `tsp`, each with its
+    /// best distance and mean runtime.
+    pub fn from_trials(results: &[OptimizationResult], tsp: &TspLib) -> Result<Vec<Self>> {
+        let recorded = now();
+        results
+            .iter()
+            .map(|result| {
+                Ok(StoredRun {
+                    recorded,
+                    source: Source::Hyper,
+                    instance: tsp.name.clone(),
+                    dimension: tsp.dimension,
+                    optimal_tour_length: tsp.optimal_tour_length,
+                    algorithm: result.algorithm.clone(),
+                    parameters: serde_json::to_value(&result.parameters)?,
+                    seed: Some(result.seed),
+                    best_distance: result.distance,
+                    final_distance: result.distance,
+                    gap: stats::gap(result.distance as f64, tsp),
+                    runtime_ms: result.runtime_ms,
+                    best_history: Vec::new(),
+                    manifest: None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Appends `runs` to the store at `path`, one JSON document per line,
+/// creating it if needed.
+pub fn append(path: &Path, runs: &[StoredRun]) -> Result<()> {
+    if runs.is_empty() {
+        return Ok(());
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).with_context(|| format!("failed to create {}", dir.display()))?;
+    }
+    let mut contents = String::new();
+    for run in runs {
+        contents += &serde_json::to_string(run)?;
+        contents.push('\n');
+    }
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(contents.as_bytes()))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    status!("{} run(s) stored in {}", runs.len(), path.display());
+
+    Ok(())
+}
+
+/// Reads every run stored at `path`. A line cut short by a crash is skipped.
+pub fn load(path: &Path) -> Result<Vec<StoredRun>> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let mut runs = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(run) => runs.push(run),
+            Err(err) if !line.trim().is_empty() => eprintln!(
+                "Warning: skipping line {} of {}: {}",
+                number + 1,
+                path.display(),
+                err
+            ),
+            Err(_) => {}
+        }
+    }
+
+    Ok(runs)
+}
+
+/// Which stored runs a report covers; unset fields match every run.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    /// Instance name, ignoring case.
+    pub instance: Option<String>,
+    /// Algorithm name, ignoring case.
+    pub algorithm: Option<String>,
+    pub source: Option<Source>,
+    /// Only runs stored at most this long ago.
+    pub since: Option<Duration>,
+    /// Only the most recently stored this many runs of those left.
+    pub last: Option<usize>,
+}
+
+impl Filter {
+    /// The runs of `runs` the filter matches, in the order they were stored.
+    pub fn apply<'a>(&self, runs: &'a [StoredRun]) -> Vec<&'a StoredRun> {
+        let oldest = self
+            .since
+            .map_or(0, |since| now().saturating_sub(since.as_secs()));
+        let same = |wanted: &Option<String>, name: &str| {
+            wanted
+                .as_ref()
+                .is_none_or(|wanted| wanted.eq_ignore_ascii_case(name))
+        };
+        let matched: Vec<&StoredRun> = runs
+            .iter()
+            .filter(|run| {
+                same(&self.instance, &run.instance)
+                    && same(&self.algorithm, &run.algorithm)
+                    && self.source.is_none_or(|source| source == run.source)
+                    && run.recorded >= oldest
+            })
+            .collect();
+        let skip = self
+            .last
+            .map_or(0, |last| matched.len().saturating_sub(last));
+        matched[skip..].to_vec()
+    }
+}
+
+/// The runs of one algorithm on one instance, summarized.
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub instance: String,
+    pub algorithm: String,
+    pub runs: usize,
+    /// Shortest tour of any of the runs.
+    pub best: u64,
+    /// Mean and sample standard deviation of the runs' shortest tours.
+    pub mean: f64,
+    pub std: f64,
+    pub best_gap: Option<f64>,
+    pub mean_gap: Option<f64>,
+    pub mean_runtime_ms: f64,
+    /// When the last of the runs was stored.
+    pub last_recorded: u64,
+}
+
+/// `runs` grouped by instance and algorithm, sorted by instance and then by
+/// mean distance.
+pub fn summarize(runs: &[&StoredRun]) -> Vec<Group> {
+    let mut groups: BTreeMap<(&str, &str), Vec<&StoredRun>> = BTreeMap::new();
+    for run in runs {
+        groups
+            .entry((&run.instance, &run.algorithm))
+            .or_default()
+            .push(run);
+    }
+    let mut groups: Vec<Group> = groups
+        .into_iter()
+        .map(|((instance, algorithm), runs)| {
+            let count = runs.len() as f64;
+            let mean = runs.iter().map(|run| run.best_distance as f64).sum::<f64>() / count;
+            let variance = runs
+                .iter()
+                .map(|run| (run.best_distance as f64 - mean).powi(2))
+                .sum::<f64>()
+                / (count - 1.0).max(1.0);
+            let gaps: Vec<f64> = runs.iter().filter_map(|run| run.gap).collect();
+            Group {
+                instance: instance.to_string(),
+                algorithm: algorithm.to_string(),
+                runs: runs.len(),
+                best: runs.iter().map(|run| run.best_distance).min().unwrap_or(0),
+                mean,
+                std: variance.sqrt(),
+                best_gap: gaps.iter().copied().reduce(f64::min),
+                mean_gap: (!gaps.is_empty()).then(|| gaps.iter().sum::<f64>() / gaps.len() as f64),
+                mean_runtime_ms: runs.iter().map(|run| run.runtime_ms as f64).sum::<f64>() / count,
+                last_recorded: runs.iter().map(|run| run.recorded).max().unwrap_or(0),
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| a.instance.cmp(&b.instance).then(a.mean.total_cmp(&b.mean)));
+    groups
+}
+
+/// `seconds` since the Unix epoch as a UTC date and time, e.g.
+/// `2024-03-01 14:05`.
+pub fn format_timestamp(seconds: u64) -> String {
+    let days = (seconds / 86_400) as i64;
+    let minutes = seconds % 86_400 / 60;
+    // Civil date from days since 1970-01-01, after Howard Hinnant.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}",
+        year,
+        month,
+        day,
+        minutes / 60,
+        minutes % 60
+    )
+}
+
+/// Prints a table of `groups`, and with `runs` one of the runs themselves.
+#[cfg(feature = "tables")]
+pub fn print_report(groups: &[Group], runs: Option<&[&StoredRun]>) {
+    let optional_gap = |gap: Option<f64>| gap.map_or("-".to_string(), stats::format_gap);
+    if let Some(runs) = runs {
+        let mut table = Table::new();
+        table.add_row(row![bFg => "Stored", "Source", "Instance", "Algorithm", "Seed", "Best", "Final", "Gap", "Runtime (ms)"]);
+        for run in runs {
+            table.add_row(row![
+                format_timestamp(run.recorded),
+                run.source,
+                run.instance,
+                run.algorithm,
+                run.seed.map_or("-".to_string(), |seed| seed.to_string()),
+                run.best_distance,
+                run.final_distance,
+                optional_gap(run.gap),
+                run.runtime_ms
+            ]);
+        }
+        table.printstd();
+    }
+
+    let mut table = Table::new();
+    table.add_row(row![bFg => "Instance", "Algorithm", "Runs", "Best", "Mean", "Std", "Best gap", "Mean gap", "Mean runtime (ms)", "Last stored"]);
+    for group in groups {
+        table.add_row(row![
+            group.instance,
+            group.algorithm,
+            group.runs,
+            group.best,
+            format!("{:.1}", group.mean),
+            format!("{:.1}", group.std),
+            optional_gap(group.best_gap),
+            optional_gap(group.mean_gap),
+            format!("{:.0}", group.mean_runtime_ms),
+            format_timestamp(group.last_recorded)
+        ]);
+    }
+    table.printstd();
+}
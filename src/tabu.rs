@@ -0,0 +1,466 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::{self, Checkpoint, Checkpointing, RunState},
+    config::TabuConfig,
+    distance::DistanceMatrix,
+    error::Error,
+    fitness::Evaluator,
+    pool::SolutionPool,
+    progress::{IterationInfo, Observer, Observers},
+    stats::OperatorCounts,
+    tsplib::*,
+};
+use rand::prelude::*;
+
+/// An undirected edge between two cities, smaller index first.
+type Edge = (usize, usize);
+
+fn edge(a: usize, b: usize) -> Edge {
+    (a.min(b), a.max(b))
+}
+
+/// State of the search after one tabu iteration.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TabuStats {
+    /// Length of the tour the search moved to, which may be longer than the
+    /// one it left.
+    pub current_distance: u64,
+    /// Fraction of the candidate moves turned down for being tabu.
+    pub tabu_rate: f64,
+    /// Whether the move taken was tabu and allowed for beating the best tour.
+    pub aspiration: bool,
+}
+
+/// The tour the search is at and the edges it may not add back yet, each
+/// with the iteration it becomes allowed again.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TabuState {
+    tour: Vec<usize>,
+    distance: u64,
+    tabu: Vec<(Edge, usize)>,
+}
+
+impl TabuState {
+    fn is_tabu(&self, edge: Edge) -> bool {
+        self.tabu.iter().any(|&(tabu, _)| tabu == edge)
+    }
+}
+
+/// A move between tour positions `i < j`.
+#[derive(Debug, Clone, Copy)]
+enum Move {
+    /// Reverses the tour from position `i` to `j`.
+    TwoOpt(usize, usize),
+    /// Exchanges the cities at positions `i` and `j`.
+    Swap(usize, usize),
+}
+
+impl Move {
+    /// A random move on a tour of `n` cities, at least 4.
+    fn random(n: usize, rng: &mut impl Rng) -> Self {
+        if rng.gen::<f64>() < 0.5 {
+            // Position 0 stays put, and reversing everything else would give
+            // the same tour.
+            loop {
+                let i = rng.gen_range(1..n - 1);
+                let j = rng.gen_range(i + 1..n);
+                if !(i == 1 && j == n - 1) {
+                    return Move::TwoOpt(i, j);
+                }
+            }
+        } else {
+            let i = rng.gen_range(0..n - 1);
+            let j = rng.gen_range(i + 1..n);
+            Move::Swap(i, j)
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Move::TwoOpt(..) => "2-opt",
+            Move::Swap(..) => "swap",
+        }
+    }
+
+    /// The edges of `tour` the move removes and the ones it adds.
+    fn edges(self, tour: &[usize]) -> (Vec<Edge>, Vec<Edge>) {
+        let n = tour.len();
+        match self {
+            Move::TwoOpt(i, j) => {
+                let (prev, next) = (tour[i - 1], tour[(j + 1) % n]);
+                (
+                    vec![edge(prev, tour[i]), edge(tour[j], next)],
+                    vec![edge(prev, tour[j]), edge(tour[i], next)],
+                )
+            }
+            Move::Swap(i, j) => {
+                let swapped = |k: usize| match k {
+                    _ if k == i => tour[j],
+                    _ if k == j => tour[i],
+                    _ => tour[k],
+                };
+                let mut starts = vec![(i + n - 1) % n, i, (j + n - 1) % n, j];
+                starts.sort_unstable();
+                starts.dedup();
+                starts
+                    .into_iter()
+                    .map(|k| {
+                        let next = (k + 1) % n;
+                        (edge(tour[k], tour[next]), edge(swapped(k), swapped(next)))
+                    })
+                    .unzip()
+            }
+        }
+    }
+
+    /// How much longer the move makes a tour whose edges it changes as
+    /// `edges` says.
+    fn delta(edges: &(Vec<Edge>, Vec<Edge>), matrix: &DistanceMatrix) -> i64 {
+        let length =
+            |edges: &[Edge]| -> i64 { edges.iter().map(|&(a, b)| matrix.get(a, b) as i64).sum() };
+        length(&edges.1) - length(&edges.0)
+    }
+
+    fn apply(self, tour: &mut [usize]) {
+        match self {
+            Move::TwoOpt(i, j) => tour[i..=j].reverse(),
+            Move::Swap(i, j) => tour.swap(i, j),
+        }
+    }
+}
+
+/// Tabu search over 2-opt and swap moves: every iteration it moves to the
+/// shortest of a sample of neighbouring tours, even a longer one, and for
+/// `tenure` iterations forbids adding back the edges the move removed, so it
+/// does not cycle back into the local optimum it left. A tabu move is still
+/// taken if it beats the best tour found so far.
+#[derive(Serialize, Deserialize)]
+pub struct TabuSearch {
+    history: Vec<Route>,
+    history_times: Vec<u64>,
+    best_route: Route,
+    run_time: u64,
+    #[serde(skip)]
+    observers: Observers,
+    #[serde(skip)]
+    checkpointing: Option<Checkpointing>,
+    #[serde(skip)]
+    resumed: Option<RunState<TabuState>>,
+    #[serde(skip)]
+    initial_solution: Option<Vec<usize>>,
+    #[serde(skip)]
+    run: Option<RunState<TabuState>>,
+    #[serde(skip)]
+    pool: Option<SolutionPool>,
+    archive: Option<SolutionArchive>,
+    seed: Option<u64>,
+    operator_counts: OperatorCounts,
+    tabu_stats: Vec<TabuStats>,
+    /// Whether the instance's distances differ both ways, so a 2-opt move
+    /// also changes the length of the stretch it reverses.
+    #[serde(skip)]
+    directed: bool,
+
+    pub tenure: usize,
+    pub iterations: usize,
+    pub candidates: usize,
+}
+
+impl TabuSearch {
+    pub fn new(tsp: &TspLib, tenure: usize, iterations: usize, candidates: usize) -> Self {
+        TabuSearch {
+            history: Vec::new(),
+            history_times: Vec::new(),
+            best_route: Route::through(tsp),
+            run_time: 0,
+            observers: Observers::default(),
+            checkpointing: None,
+            resumed: None,
+            initial_solution: None,
+            run: None,
+            pool: None,
+            archive: None,
+            seed: None,
+            operator_counts: OperatorCounts::default(),
+            tabu_stats: Vec::new(),
+            directed: false,
+
+            tenure,
+            iterations,
+            candidates,
+        }
+    }
+
+    pub fn get_tabu_stats(&self) -> &[TabuStats] {
+        &self.tabu_stats
+    }
+
+    /// Moves `state` to the best admissible of `self.candidates` random moves
+    /// and returns how the iteration went. Tours of fewer than 4 cities all
+    /// have the same length, so they are left as they are.
+    fn search(
+        &mut self,
+        tsp: &TspLib,
+        state: &mut TabuState,
+        rng: &mut SolverRng,
+        iteration: usize,
+    ) -> TabuStats {
+        let n = state.tour.len();
+        let mut stats = TabuStats {
+            current_distance: state.distance,
+            tabu_rate: 0.0,
+            aspiration: false,
+        };
+        if n < 4 || self.candidates == 0 {
+            return stats;
+        }
+
+        state.tabu.retain(|&(_, until)| until > iteration);
+        let mut candidates = Vec::with_capacity(self.candidates);
+        let mut tabu_moves = 0;
+        let mut chosen: Option<(usize, i64)> = None;
+        for index in 0..self.candidates {
+            let candidate = Move::random(n, rng);
+            let edges = candidate.edges(&state.tour);
+            let delta = if self.directed {
+                let mut tour = state.tour.clone();
+                candidate.apply(&mut tour);
+                tour_length(&tour, &tsp.distance_matrix) as i64 - state.distance as i64
+            } else {
+                Move::delta(&edges, &tsp.distance_matrix)
+            };
+            let tabu = edges.1.iter().any(|&added| state.is_tabu(added));
+            let aspiration = state.distance as i64 + delta < self.best_route.distance as i64;
+            if tabu {
+                tabu_moves += 1;
+            }
+            if (!tabu || aspiration) && chosen.is_none_or(|(_, best)| delta < best) {
+                chosen = Some((index, delta));
+                stats.aspiration = tabu;
+            }
+            candidates.push((candidate, edges, delta));
+        }
+        stats.tabu_rate = tabu_moves as f64 / self.candidates as f64;
+
+        for (index, (candidate, _, delta)) in candidates.iter().enumerate() {
+            let taken = chosen.is_some_and(|(chosen, _)| chosen == index);
+            self.operator_counts
+                .record(candidate.name(), taken, *delta < 0);
+        }
+        let Some((index, _)) = chosen else {
+            return stats;
+        };
+        let (candidate, (removed, _), _) = candidates.swap_remove(index);
+        candidate.apply(&mut state.tour);
+        state.distance = tour_length(&state.tour, &tsp.distance_matrix);
+        state.tabu.extend(
+            removed
+                .into_iter()
+                .map(|edge| (edge, iteration + 1 + self.tenure)),
+        );
+        stats.current_distance = state.distance;
+
+        if state.distance < self.best_route.distance {
+            self.best_route = Route::from_measured_tour(&state.tour, state.distance, tsp);
+        }
+        if let Some(archive) = &mut self.archive {
+            archive.offer(&state.tour, state.distance);
+        }
+        stats
+    }
+}
+
+impl HeuristicAlgorithm for TabuSearch {
+    fn init(&mut self, tsp: &TspLib) -> Result<()> {
+        check_instance(tsp)?;
+        self.observers.start("Tabu", tsp);
+        self.directed = tsp.is_asymmetric();
+        let run = match self.resumed.take() {
+            Some(run) => run,
+            None => {
+                let mut rng = solver_rng(self.seed);
+                let tour = match &self.initial_solution {
+                    Some(tour) => Route::validated(tour, tsp)?.tour,
+                    None => {
+                        let mut tour: Vec<usize> = (0..tsp.dimension).collect();
+                        tour.shuffle(&mut rng);
+                        tour
+                    }
+                };
+                let distance = tour_length(&tour, &tsp.distance_matrix);
+                self.best_route = Route::from_measured_tour(&tour, distance, tsp);
+                RunState::new(
+                    tsp,
+                    rng,
+                    TabuState {
+                        tour,
+                        distance,
+                        tabu: Vec::new(),
+                    },
+                )
+            }
+        };
+        self.run = Some(run);
+        Ok(())
+    }
+
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        let _span = self.observers.enter_step(run.iterations);
+
+        if run.iterations < self.iterations {
+            if let Some(route) = self
+                .pool
+                .as_ref()
+                .and_then(|pool| pool.better_than(self.best_route.distance))
+            {
+                run.state.tour = route.tour.clone();
+                run.state.distance = route.distance;
+                self.best_route = route;
+            }
+
+            let iteration = run.iterations;
+            let stats = self.search(tsp, &mut run.state, &mut run.rng, iteration);
+            self.history_times
+                .push(start_time.elapsed().as_millis() as u64);
+            self.history.push(self.best_route.clone());
+            self.observers.iteration(IterationInfo {
+                algorithm: "Tabu",
+                iteration,
+                best: &self.best_route,
+                elapsed_ms: start_time.elapsed().as_millis() as u64,
+                stats: &[
+                    ("current_distance", stats.current_distance as f64),
+                    ("tabu_rate", stats.tabu_rate),
+                ],
+            });
+            self.tabu_stats.push(stats);
+            if let Some(pool) = &self.pool {
+                pool.publish(&self.best_route);
+            }
+            run.iterations = iteration + 1;
+        }
+
+        let saved = checkpoint::save_if_due(
+            self.checkpointing.as_ref(),
+            &*self,
+            &mut run,
+            start_time,
+            false,
+        );
+        let step = StepResult {
+            iterations: run.iterations,
+            elapsed: start_time.elapsed(),
+            best_distance: self.best_route.distance,
+            done: run.iterations >= self.iterations || self.observers.stop_requested(),
+        };
+        self.run = Some(run);
+        saved?;
+        Ok(step)
+    }
+
+    fn finish(&mut self) -> Result<Solution> {
+        let Some(mut run) = self.run.take() else {
+            return Err(Error::NotStarted.into());
+        };
+        let start_time = run.start_time();
+        if run.iterations < self.iterations {
+            checkpoint::save_if_due(
+                self.checkpointing.as_ref(),
+                &*self,
+                &mut run,
+                start_time,
+                true,
+            )?;
+        }
+        self.run_time = start_time.elapsed().as_millis() as u64;
+
+        let solution = Solution {
+            route: self.best_route.clone(),
+            iterations: run.iterations,
+            run_time: self.run_time,
+        };
+        self.observers
+            .finish("Tabu", &solution, &self.operator_counts);
+        Ok(solution)
+    }
+
+    fn get_history(&self) -> &[Route] {
+        &self.history
+    }
+
+    fn get_history_times(&self) -> &[u64] {
+        &self.history_times
+    }
+
+    fn get_best_route(&self) -> &Route {
+        &self.best_route
+    }
+
+    fn get_run_time(&self) -> u64 {
+        self.run_time
+    }
+
+    fn add_observer(&mut self, observer: Arc<dyn Observer>) {
+        self.observers.add(observer);
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(seed);
+    }
+
+    fn get_operator_counts(&self) -> &OperatorCounts {
+        &self.operator_counts
+    }
+
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing) {
+        self.checkpointing = Some(checkpointing);
+    }
+
+    fn set_pool(&mut self, pool: SolutionPool) {
+        self.pool = Some(pool);
+    }
+
+    fn set_initial_solution(&mut self, tour: Vec<usize>) {
+        self.initial_solution = Some(tour);
+    }
+
+    fn set_archive(&mut self, archive: SolutionArchive) {
+        self.archive = Some(archive);
+    }
+
+    fn get_archive(&self) -> Option<&SolutionArchive> {
+        self.archive.as_ref()
+    }
+
+    /// Tabu search prices its candidate moves from the distance matrix
+    /// without building their tours, so it has no batches to evaluate and
+    /// this has no effect.
+    fn set_evaluator(&mut self, _evaluator: Evaluator) {}
+}
+
+impl Checkpoint for TabuSearch {
+    type State = TabuState;
+
+    fn resume(&mut self, run: RunState<TabuState>) {
+        self.resumed = Some(run);
+    }
+}
+
+impl Configurable for TabuSearch {
+    type Config = TabuConfig;
+
+    fn from_config(tsp: &TspLib, config: &TabuConfig) -> Self {
+        let mut tabu = TabuSearch::new(tsp, config.tenure, config.iterations, config.candidates);
+        tabu.seed = config.seed;
+        tabu
+    }
+}
@@ -0,0 +1,155 @@
+use std::path::PathBuf;
+#[cfg(feature = "trace")]
+use std::{fs::File, io::BufWriter};
+
+#[cfg(not(all(feature = "trace", feature = "otlp")))]
+use anyhow::bail;
+#[cfg(feature = "trace")]
+use anyhow::Context;
+use anyhow::Result;
+use tracing::level_filters::LevelFilter;
+#[cfg(feature = "trace")]
+use tracing_chrome::{ChromeLayerBuilder, FlushGuard};
+#[cfg(feature = "trace")]
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Where the solvers' `tracing` spans and events are exported to.
+#[derive(Debug, Clone)]
+pub struct TraceOptions {
+    /// A Chrome trace file, for `chrome://tracing` or Perfetto. Needs the
+    /// `trace` feature.
+    pub chrome: Option<PathBuf>,
+    /// An OTLP collector's gRPC endpoint, e.g. `http://localhost:4317`.
+    /// Needs the `otlp` feature.
+    pub otlp: Option<String>,
+    /// Most verbose level exported: `info` for runs, improvements and
+    /// operator counts, `debug` to add every step and iteration.
+    pub level: LevelFilter,
+}
+
+/// Keeps the exporters running; dropping it flushes what is left.
+pub struct Telemetry {
+    #[cfg(feature = "trace")]
+    chrome: Option<FlushGuard>,
+    #[cfg(feature = "otlp")]
+    otlp: Option<Otlp>,
+}
+
+#[cfg(feature = "otlp")]
+struct Otlp {
+    provider: opentelemetry_sdk::trace::TracerProvider,
+    /// Runs the batch exporter, which sends spans in the background.
+    _runtime: tokio::runtime::Runtime,
+}
+
+/// Installs the global subscriber exporting to the destinations of
+/// `options`. Without any, nothing is installed and tracing costs next to
+/// nothing. Fails if one needs a feature this build lacks.
+#[cfg(not(feature = "trace"))]
+pub fn init(options: &TraceOptions) -> Result<Telemetry> {
+    if options.chrome.is_some() || options.otlp.is_some() {
+        bail!("exporting traces requires a build with `--features trace` (`otlp` for OTLP)");
+    }
+    Ok(Telemetry {})
+}
+
+/// Installs the global subscriber exporting to the destinations of
+/// `options`. Without any, nothing is installed and tracing costs next to
+/// nothing. Fails if one needs a feature this build lacks.
+#[cfg(feature = "trace")]
+pub fn init(options: &TraceOptions) -> Result<Telemetry> {
+    let (chrome_layer, chrome) = match &options.chrome {
+        Some(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            let (layer, guard) = ChromeLayerBuilder::new()
+                .writer(BufWriter::new(file))
+                .include_args(true)
+                .build();
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    #[cfg(feature = "otlp")]
+    let (otlp_layer, otlp) = match &options.otlp {
+        Some(endpoint) => {
+            use opentelemetry::trace::TracerProvider;
+
+            let otlp = Otlp::connect(endpoint)?;
+            let tracer = otlp.provider.tracer("sapso");
+            (
+                Some(tracing_opentelemetry::layer().with_tracer(tracer)),
+                Some(otlp),
+            )
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otlp"))]
+    if options.otlp.is_some() {
+        bail!("OTLP export requires a build with `--features otlp`");
+    }
+
+    let exporting = chrome_layer.is_some();
+    #[cfg(feature = "otlp")]
+    let exporting = exporting || otlp_layer.is_some();
+    if exporting {
+        let subscriber = tracing_subscriber::registry()
+            .with(options.level)
+            .with(chrome_layer);
+        #[cfg(feature = "otlp")]
+        let subscriber = subscriber.with(otlp_layer);
+        subscriber
+            .try_init()
+            .context("failed to install the trace exporter")?;
+    }
+
+    Ok(Telemetry {
+        chrome,
+        #[cfg(feature = "otlp")]
+        otlp,
+    })
+}
+
+#[cfg(feature = "otlp")]
+impl Otlp {
+    fn connect(endpoint: &str) -> Result<Self> {
+        use opentelemetry::KeyValue;
+        use opentelemetry_otlp::WithExportConfig;
+
+        let runtime = tokio::runtime::Runtime::new()?;
+        let context = runtime.enter();
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .with_context(|| format!("failed to set up OTLP export to {}", endpoint))?;
+        let provider = opentelemetry_sdk::trace::TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(opentelemetry_sdk::Resource::new([KeyValue::new(
+                "service.name",
+                "sapso",
+            )]))
+            .build();
+        drop(context);
+        Ok(Otlp {
+            provider,
+            _runtime: runtime,
+        })
+    }
+}
+
+#[cfg(feature = "trace")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Some(chrome) = &self.chrome {
+            chrome.flush();
+        }
+        #[cfg(feature = "otlp")]
+        if let Some(otlp) = &self.otlp {
+            if let Err(err) = otlp.provider.shutdown() {
+                eprintln!("Warning: failed to export the last spans: {}", err);
+            }
+        }
+    }
+}
@@ -1,106 +1,552 @@
 use std::{
-    collections::HashMap,
+    borrow::Cow,
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt::Write,
     fs::{self, File},
+    hash::{Hash, Hasher},
     io::{BufRead, BufReader},
-    vec,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, OnceLock,
+    },
+    time::Duration,
 };
 
-use anyhow::Result;
-use rand::{rngs::ThreadRng, seq::SliceRandom, Rng};
+use anyhow::{bail, Context, Result};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    archive::SolutionArchive,
+    checkpoint::Checkpointing,
+    distance::{DistanceKind, DistanceMatrix, DistanceProvider},
+    diversity::Diversity,
+    error::Error,
+    fitness::Evaluator,
+    pool::SolutionPool,
+    problem::Problem,
+    progress::Observer,
+    stats::{self, OperatorCounts},
+    validate,
+};
 
 static OPTIMALS_PATH: &str = "instances/optimal_tour_lengths.txt";
 
-fn euclidean_distance(a: &City, b: &City) -> u64 {
-    let dx = a.0 - b.0;
-    let dy = a.1 - b.1;
-    let distance = (dx * dx + dy * dy).sqrt();
+/// Length of every city's candidate list, its nearest neighbours that
+/// solvers try first when extending or improving a tour.
+pub const CANDIDATES: usize = 20;
+
+/// Independent distances summed per step by the distance loops, which lets
+/// the compiler keep them in vector registers.
+const LANES: usize = 4;
+
+/// Rounded Euclidean distance from `origin` to each of `cities`, written to
+/// `row`.
+pub(crate) fn distances_from(origin: City, cities: &[City], row: &mut [u64]) {
+    for (chunk, out) in cities.chunks(LANES).zip(row.chunks_mut(LANES)) {
+        let mut distances = [0.0; LANES];
+        for (distance, city) in distances.iter_mut().zip(chunk) {
+            let dx = origin.0 - city.0;
+            let dy = origin.1 - city.1;
+            *distance = (dx * dx + dy * dy).sqrt().round();
+        }
+        for (out, distance) in out.iter_mut().zip(distances) {
+            *out = distance as u64;
+        }
+    }
+}
+
+/// Length of the closed `tour` of city indices under `matrix`.
+pub fn tour_length(tour: &[usize], matrix: &DistanceMatrix) -> u64 {
+    let (Some(&first), Some(&last)) = (tour.first(), tour.last()) else {
+        return 0;
+    };
+    let from = &tour[..tour.len() - 1];
+    let to = &tour[1..];
+    let mut sums = [0; LANES];
+    for (a, b) in from.chunks_exact(LANES).zip(to.chunks_exact(LANES)) {
+        for (sum, (&a, &b)) in sums.iter_mut().zip(a.iter().zip(b)) {
+            *sum += matrix.get(a, b);
+        }
+    }
+    let rest = from.len() / LANES * LANES;
+    let remainder: u64 = from[rest..]
+        .iter()
+        .zip(&to[rest..])
+        .map(|(&a, &b)| matrix.get(a, b))
+        .sum();
 
-    distance.round() as u64
+    sums.iter().sum::<u64>() + remainder + matrix.get(last, first)
 }
 
 pub type City = (f64, f64);
 
-#[derive(Clone)]
+/// A closed route through the cities of an instance, with its length.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Route {
-    pub cities: Vec<City>,
+    /// Indices of the instance's cities, in the order they are visited.
+    pub tour: Vec<usize>,
     pub distance: u64,
+    /// Distances of the instance the route goes through, shared with it. A
+    /// route read back from a checkpoint has none until
+    /// [`Route::measure_on`].
+    #[serde(skip)]
+    matrix: DistanceMatrix,
 }
 
 impl Route {
-    pub fn new(coords: &[City]) -> Self {
-        let cities: Vec<City> = coords.iter().map(|&(x, y)| (x, y)).collect();
-        let distance = Self::calculate_distance(&cities);
-        Route { cities, distance }
+    /// The cities of `tsp` in their order, measured by its distances.
+    pub fn through(tsp: &TspLib) -> Self {
+        Self::from_tour(&(0..tsp.dimension).collect::<Vec<_>>(), tsp)
     }
 
-    pub fn new_random(coords: &[City]) -> Self {
-        let mut cities: Vec<City> = coords.iter().map(|&(x, y)| (x, y)).collect();
-        let mut rng = rand::thread_rng();
-        cities.shuffle(&mut rng);
-        let distance = Self::calculate_distance(&cities);
-        Route { cities, distance }
+    /// The cities of `tsp` in random order, measured by its distances.
+    pub fn random_through(tsp: &TspLib, rng: &mut impl Rng) -> Self {
+        let mut tour: Vec<usize> = (0..tsp.dimension).collect();
+        tour.shuffle(rng);
+        Self::from_tour(&tour, tsp)
     }
 
-    pub fn calculate_distance(cities: &[City]) -> u64 {
-        let mut distance = euclidean_distance(&cities[cities.len() - 1], &cities[0]);
-        for i in 1..cities.len() {
-            distance += euclidean_distance(&cities[i - 1], &cities[i]);
-        }
-        distance
+    /// The `tour` of city indices of `tsp`, measured by its distances.
+    pub fn from_tour(tour: &[usize], tsp: &TspLib) -> Self {
+        Self::from_measured_tour(tour, tour_length(tour, &tsp.distance_matrix), tsp)
+    }
+
+    /// The `tour` of city indices of `tsp` once checked to visit every city
+    /// exactly once, e.g. an initial solution read from a file.
+    pub fn validated(tour: &[usize], tsp: &TspLib) -> Result<Self> {
+        let distance = validate::tour_length(tsp, tour)?;
+        Ok(Self::from_measured_tour(tour, distance, tsp))
     }
 
-    pub fn swap_random_cities(&self, rng: &mut rand::prelude::ThreadRng) -> Self {
-        let mut new_cities = self.cities.clone();
-        let i = rng.gen_range(0..new_cities.len());
-        let j = rng.gen_range(0..new_cities.len());
-        new_cities.swap(i, j);
-        let distance = Self::calculate_distance(&new_cities);
+    /// The `tour` of city indices of `tsp`, already known to be `distance`
+    /// long.
+    pub fn from_measured_tour(tour: &[usize], distance: u64, tsp: &TspLib) -> Self {
         Route {
-            cities: new_cities,
+            tour: tour.to_vec(),
             distance,
+            matrix: tsp.distance_matrix.clone(),
         }
     }
 
-    pub fn two_opt_move(&self, i: usize, j: usize) -> Self {
-        let mut new_cities = self.cities.clone();
+    /// Measures the route by the distances of `tsp`, whose cities it goes
+    /// through, e.g. after reading it back from a checkpoint.
+    pub fn measure_on(&mut self, tsp: &TspLib) {
+        self.matrix = tsp.distance_matrix.clone();
+        self.distance = tour_length(&self.tour, &self.matrix);
+    }
 
-        let (left, right) = (i.min(j), i.max(j));
-        new_cities[left..=right].reverse();
+    /// Coordinates of the route's cities in `tsp`, in order, for drawing it.
+    pub fn coordinates(&self, tsp: &TspLib) -> Vec<City> {
+        self.tour.iter().map(|&city| tsp.cities[city]).collect()
+    }
 
-        let distance = Self::calculate_distance(&new_cities);
+    /// The route through `tour` instead, measured by the same distances.
+    fn with_tour(&self, tour: Vec<usize>) -> Self {
         Route {
-            cities: new_cities,
-            distance,
+            distance: tour_length(&tour, &self.matrix),
+            tour,
+            matrix: self.matrix.clone(),
+        }
+    }
+
+    pub fn swap_random_cities(&self, rng: &mut impl Rng) -> Self {
+        let mut tour = self.tour.clone();
+        let i = rng.gen_range(0..tour.len());
+        let j = rng.gen_range(0..tour.len());
+        tour.swap(i, j);
+        self.with_tour(tour)
+    }
+
+    pub fn two_opt_move(&self, i: usize, j: usize) -> Self {
+        let mut tour = self.tour.clone();
+        let (left, right) = (i.min(j), i.max(j));
+        tour[left..=right].reverse();
+        self.with_tour(tour)
+    }
+
+    /// The route with a stretch of up to three cities moved elsewhere in the
+    /// tour, either way round.
+    pub fn or_opt_move(&self, rng: &mut impl Rng) -> Self {
+        let mut tour = self.tour.clone();
+        if tour.len() < 3 {
+            return self.with_tour(tour);
+        }
+        let length = rng.gen_range(1..=3.min(tour.len() - 2));
+        let start = rng.gen_range(0..=tour.len() - length);
+        let mut segment: Vec<usize> = tour.drain(start..start + length).collect();
+        if rng.gen::<bool>() {
+            segment.reverse();
         }
+        let at = rng.gen_range(0..=tour.len());
+        tour.splice(at..at, segment);
+        self.with_tour(tour)
     }
 
-    pub fn random_move(&self, rng: &mut ThreadRng) -> Self {
-        if rng.gen::<f64>() < 0.8 {
-            self.swap_random_cities(rng)
+    /// A random swap, 2-opt or Or-opt neighbour of the route, each drawn as
+    /// often as `weights` says, with the name of the move.
+    pub fn random_move(&self, weights: &MoveWeights, rng: &mut impl Rng) -> (Self, &'static str) {
+        let draw = rng.gen::<f64>() * weights.total();
+        if draw < weights.swap {
+            (self.swap_random_cities(rng), "swap")
+        } else if draw < weights.swap + weights.two_opt {
+            let i = rng.gen_range(0..self.tour.len());
+            let j = rng.gen_range(0..self.tour.len());
+            (self.two_opt_move(i, j), "2-opt")
         } else {
-            let i = rng.gen_range(0..self.cities.len());
-            let j = rng.gen_range(0..self.cities.len());
-            self.two_opt_move(i, j)
+            (self.or_opt_move(rng), "or-opt")
+        }
+    }
+}
+
+/// How often [`Route::random_move`] draws each of its moves, relative to
+/// each other.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MoveWeights {
+    pub swap: f64,
+    pub two_opt: f64,
+    pub or_opt: f64,
+}
+
+impl MoveWeights {
+    pub fn total(&self) -> f64 {
+        self.swap + self.two_opt + self.or_opt
+    }
+}
+
+impl Default for MoveWeights {
+    fn default() -> Self {
+        MoveWeights {
+            swap: 0.8,
+            two_opt: 0.2,
+            or_opt: 0.0,
+        }
+    }
+}
+
+/// A flag asking solvers to stop after their current iteration, keeping the
+/// best route found so far. Clones share the flag, so one can be handed to a
+/// Ctrl-C handler or another thread while the solver checks its own.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// When `solve` stops early, on top of the solver's own iteration budget. The
+/// default never stops a run early.
+#[derive(Debug, Clone, Default)]
+pub struct Termination {
+    /// Stop after the first iteration that ends past this much time.
+    pub time_limit: Option<Duration>,
+    /// Stop after this many iterations (epochs for SA, generations for GA).
+    pub max_iterations: Option<usize>,
+    /// Stop after this many iterations in a row without a shorter route.
+    pub stall_iterations: Option<usize>,
+    /// Stop as soon as the best route is no longer than this.
+    pub target_distance: Option<u64>,
+    /// Stop as soon as the best route is within this many percent of the
+    /// instance's known optimum; ignored for instances without one.
+    pub target_gap: Option<f64>,
+    /// Stop once this token is cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Termination {
+    /// Stops a run after the first iteration that ends past `limit`.
+    pub fn time_limit(limit: Duration) -> Self {
+        Termination {
+            time_limit: Some(limit),
+            ..Termination::default()
+        }
+    }
+
+    /// The termination for runs on `tsp`, with `target_gap` turned into a
+    /// target distance by its known optimum.
+    pub fn on(&self, tsp: &TspLib) -> Termination {
+        let gap_target = self
+            .target_gap
+            .and_then(|gap| stats::target_distance(tsp, gap));
+        Termination {
+            target_distance: self.target_distance.max(gap_target),
+            target_gap: None,
+            ..self.clone()
         }
     }
+
+    /// Whether a run that has completed `iterations` iterations in `elapsed`,
+    /// the last `stalled` of them without improvement, with `best_distance`
+    /// the shortest route so far, should stop. A `target_gap` counts only
+    /// once [`Termination::on`] has resolved it.
+    pub fn reached(
+        &self,
+        iterations: usize,
+        stalled: usize,
+        elapsed: Duration,
+        best_distance: u64,
+    ) -> bool {
+        self.cancellation
+            .as_ref()
+            .is_some_and(CancellationToken::is_cancelled)
+            || self.time_limit.is_some_and(|limit| elapsed >= limit)
+            || self.max_iterations.is_some_and(|max| iterations >= max)
+            || self.stall_iterations.is_some_and(|max| stalled >= max)
+            || self
+                .target_distance
+                .is_some_and(|target| best_distance <= target)
+    }
+}
+
+/// The outcome of a call to `solve`.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Solution {
+    pub route: Route,
+    /// Iterations completed before the run stopped.
+    pub iterations: usize,
+    /// Milliseconds the run took.
+    pub run_time: u64,
 }
 
-pub trait HeuristicAlgorithm {
-    fn solve(&mut self, tsp: &TspLib);
-    fn get_history(&self) -> Vec<Route>;
-    fn get_best_route(&self) -> Route;
+/// What a call to `step` left the run at.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StepResult {
+    /// Iterations completed in the run so far.
+    pub iterations: usize,
+    /// Time the run has taken so far.
+    pub elapsed: Duration,
+    /// Length of the best route found so far.
+    pub best_distance: u64,
+    /// Whether the solver's own budget is used up or an observer asked it to
+    /// stop. Once the budget is used up, further steps run no iterations.
+    pub done: bool,
+}
+
+pub trait HeuristicAlgorithm: Send {
+    /// Starts a run on `tsp`, or picks up the one a checkpoint was loaded
+    /// with, for `step` to drive.
+    fn init(&mut self, tsp: &TspLib) -> Result<()>;
+    /// Runs one iteration (an epoch for SA, a generation for GA) of the run
+    /// started by `init`, unless the solver's own budget is already used up.
+    fn step(&mut self, tsp: &TspLib) -> Result<StepResult>;
+    /// Ends the run, reports it to the observers and returns the best route
+    /// found. A run that stopped before its budget ran out is checkpointed.
+    fn finish(&mut self) -> Result<Solution>;
+
+    /// Runs the solver on `problem`, a [`TspLib`] instance or any other
+    /// [`Problem`], until its own budget runs out or `termination` is
+    /// reached, and returns the best route found.
+    fn solve(&mut self, problem: &dyn Problem, termination: &Termination) -> Result<Solution> {
+        let tsp = &*problem.instance();
+        let termination = termination.on(tsp);
+        self.init(tsp)?;
+        let (mut best_distance, mut improved_at) = (u64::MAX, 0);
+        loop {
+            let step = self.step(tsp)?;
+            if step.best_distance < best_distance {
+                (best_distance, improved_at) = (step.best_distance, step.iterations);
+            }
+            if step.done
+                || termination.reached(
+                    step.iterations,
+                    step.iterations - improved_at,
+                    step.elapsed,
+                    step.best_distance,
+                )
+            {
+                break;
+            }
+        }
+        self.finish()
+    }
+
+    fn get_history(&self) -> &[Route];
+    /// Milliseconds since the start of `solve` at which each history entry was recorded.
+    fn get_history_times(&self) -> &[u64];
+    fn get_best_route(&self) -> &Route;
     fn get_run_time(&self) -> u64;
+    /// Registers an observer told about every iteration, improvement and the
+    /// end of each run.
+    fn add_observer(&mut self, observer: Arc<dyn Observer>);
+    /// Makes `solve` reproducible by drawing all of its random numbers from a
+    /// generator seeded with `seed`.
+    fn set_seed(&mut self, seed: u64);
+    /// How often each operator was applied, accepted and improving.
+    fn get_operator_counts(&self) -> &OperatorCounts;
+    /// Diversity of the population after the last iteration, for solvers
+    /// that keep one.
+    fn get_diversity(&self) -> Option<Diversity> {
+        None
+    }
+    /// Share of the moves of the last run that were accepted, for solvers
+    /// that may reject them.
+    fn get_acceptance_rate(&self) -> Option<f64> {
+        None
+    }
+    /// Saves the run to `checkpointing.path` every `checkpointing.every`
+    /// iterations and whenever it stops early, so it can be resumed with
+    /// [`crate::checkpoint::load`].
+    fn set_checkpointing(&mut self, checkpointing: Checkpointing);
+    /// Publishes every improvement to `pool` and, before each iteration,
+    /// takes up the pool's best route if it is shorter than the solver's own.
+    fn set_pool(&mut self, pool: SolutionPool);
+    /// Starts the next run from `tour` rather than from scratch, e.g. the
+    /// result of another solver: SA and Tabu search from it, GA and PSO
+    /// put it in their population, ACO lays pheromone along it.
+    fn set_initial_solution(&mut self, tour: Vec<usize>);
+    /// Offers the tours the solver comes across to `archive`, which keeps the
+    /// best structurally distinct ones.
+    fn set_archive(&mut self, archive: SolutionArchive);
+    fn get_archive(&self) -> Option<&SolutionArchive>;
+    /// Evaluates the solver's batches of candidate tours with `evaluator`.
+    fn set_evaluator(&mut self, evaluator: Evaluator);
+}
+
+/// A solver built from a settings struct. Kept apart from `HeuristicAlgorithm`
+/// so that trait can still be used as `dyn HeuristicAlgorithm`.
+pub trait Configurable: HeuristicAlgorithm + Sized {
+    type Config: Serialize;
+
+    fn from_config(tsp: &TspLib, config: &Self::Config) -> Self;
+}
+
+/// Fails if `tsp` has too few cities to form a tour.
+pub fn check_instance(tsp: &TspLib) -> Result<()> {
+    if tsp.cities.len() < 2 {
+        return Err(Error::TooFewCities {
+            instance: tsp.name.clone(),
+            cities: tsp.cities.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// The random number generator solvers run with. It is the generator behind
+/// `StdRng`, named so its state can be saved in checkpoints.
+pub type SolverRng = ChaCha12Rng;
+
+/// The random number generator a solver runs with: seeded with `seed` if
+/// given, otherwise from system entropy.
+pub fn solver_rng(seed: Option<u64>) -> SolverRng {
+    match seed {
+        Some(seed) => SolverRng::seed_from_u64(seed),
+        None => SolverRng::from_entropy(),
+    }
+}
+
+/// Generators for `count` tasks run in parallel, seeded from `rng`, so a
+/// seeded run draws the same numbers whatever the number of threads.
+pub fn task_rngs(rng: &mut SolverRng, count: usize) -> Vec<SolverRng> {
+    (0..count)
+        .map(|_| SolverRng::seed_from_u64(rng.gen()))
+        .collect()
 }
 
-#[derive(Clone)]
+/// An instance. It serializes without its distance matrix, which is rebuilt
+/// in memory from the cities when it is read back, unless its distances are
+/// EXPLICIT.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(try_from = "Instance", into = "Instance")]
 pub struct TspLib {
     pub name: String,
     pub comment: String,
     pub dimension: usize,
+    /// Coordinates of the cities. Instances with EXPLICIT distances that come
+    /// without display coordinates get their cities laid out on a circle.
     pub cities: Vec<City>,
-    pub distance_matrix: Vec<Vec<u64>>,
+    pub distance_kind: DistanceKind,
+    pub distance_matrix: DistanceMatrix,
     pub optimal_tour: Option<Vec<usize>>,
     pub optimal_tour_length: Option<u64>,
+    /// Candidate lists, built from the distance matrix on first use and
+    /// shared by clones.
+    neighbors: Arc<OnceLock<Vec<Vec<usize>>>>,
+}
+
+/// A [`TspLib`] as it is serialized.
+#[derive(Serialize, Deserialize)]
+struct Instance {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    comment: String,
+    #[serde(default)]
+    distance_kind: DistanceKind,
+    cities: Vec<City>,
+    /// Only for EXPLICIT instances, whose distances cannot be rebuilt from
+    /// the cities.
+    #[serde(default)]
+    edge_weights: Option<Vec<Vec<u64>>>,
+    #[serde(default)]
+    optimal_tour: Option<Vec<usize>>,
+    #[serde(default)]
+    optimal_tour_length: Option<u64>,
+}
+
+impl TryFrom<Instance> for TspLib {
+    type Error = anyhow::Error;
+
+    /// Fails if an EXPLICIT instance lacks its distances or has a row of them
+    /// too short for its cities, e.g. one sent by a broken client.
+    fn try_from(instance: Instance) -> Result<Self> {
+        let n = instance.cities.len();
+        let distance_matrix = match (instance.distance_kind, &instance.edge_weights) {
+            (DistanceKind::Explicit, Some(weights)) => {
+                if weights.len() != n || weights.iter().any(|row| row.len() != n) {
+                    bail!(
+                        "edge_weights of {} must be {} rows of {} distances",
+                        instance.name,
+                        n,
+                        n
+                    );
+                }
+                DistanceMatrix::from_fn(n, |a, b| weights[a][b])
+            }
+            (DistanceKind::Explicit, None) => {
+                bail!("EXPLICIT instance {} has no edge_weights", instance.name)
+            }
+            _ => DistanceMatrix::auto(&instance.cities, instance.distance_kind),
+        };
+        Ok(TspLib {
+            name: instance.name,
+            comment: instance.comment,
+            dimension: n,
+            cities: instance.cities,
+            distance_kind: instance.distance_kind,
+            distance_matrix,
+            optimal_tour: instance.optimal_tour,
+            optimal_tour_length: instance.optimal_tour_length,
+            neighbors: Arc::default(),
+        })
+    }
+}
+
+impl From<TspLib> for Instance {
+    fn from(tsp: TspLib) -> Self {
+        Instance {
+            edge_weights: (tsp.distance_kind == DistanceKind::Explicit)
+                .then(|| tsp.distance_matrix.rows().map(Cow::into_owned).collect()),
+            name: tsp.name,
+            comment: tsp.comment,
+            distance_kind: tsp.distance_kind,
+            cities: tsp.cities,
+            optimal_tour: tsp.optimal_tour,
+            optimal_tour_length: tsp.optimal_tour_length,
+        }
+    }
 }
 
 impl TspLib {
@@ -110,11 +556,100 @@ impl TspLib {
             comment: String::new(),
             dimension: 0,
             cities: Vec::new(),
-            distance_matrix: Vec::new(),
+            distance_kind: DistanceKind::Euc2d,
+            distance_matrix: DistanceMatrix::default(),
+            optimal_tour: None,
+            optimal_tour_length: None,
+            neighbors: Arc::default(),
+        }
+    }
+
+    /// A Euclidean instance over `cities`, with no known optimum.
+    pub fn from_cities(name: &str, cities: Vec<City>) -> TspLib {
+        TspLib {
+            name: name.to_string(),
+            dimension: cities.len(),
+            distance_matrix: DistanceMatrix::auto(&cities, DistanceKind::Euc2d),
+            cities,
+            ..TspLib::new()
+        }
+    }
+
+    /// An instance with the EXPLICIT distances of `distance_matrix`, drawn
+    /// with its cities at `cities`, with no known optimum.
+    pub fn from_distances(
+        name: &str,
+        cities: Vec<City>,
+        distance_matrix: DistanceMatrix,
+    ) -> TspLib {
+        TspLib {
+            name: name.to_string(),
+            dimension: cities.len(),
+            cities,
+            distance_kind: DistanceKind::Explicit,
+            distance_matrix,
+            ..TspLib::new()
+        }
+    }
+
+    /// Whether some distance differs from the one back, as in TSPLIB's ATSP
+    /// instances. Only EXPLICIT distances can.
+    pub fn is_asymmetric(&self) -> bool {
+        self.distance_kind == DistanceKind::Explicit && !self.distance_matrix.is_symmetric()
+    }
+
+    /// Tells instances apart without comparing their distance matrices.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.name.hash(&mut hasher);
+        self.distance_kind.hash(&mut hasher);
+        for &(x, y) in &self.cities {
+            x.to_bits().hash(&mut hasher);
+            y.to_bits().hash(&mut hasher);
+        }
+        hasher.finish()
+    }
+
+    /// A random sub-instance of `cities` of the instance's cities, or a copy of
+    /// the whole instance if it has no more than that.
+    pub fn subsample(&self, cities: usize, rng: &mut impl Rng) -> TspLib {
+        if cities >= self.dimension {
+            return self.clone();
+        }
+        let mut kept = rand::seq::index::sample(rng, self.dimension, cities).into_vec();
+        kept.sort_unstable();
+
+        TspLib {
+            name: format!("{}-sub{}", self.name, cities),
+            comment: format!(
+                "{} of the {} cities of {}",
+                cities, self.dimension, self.name
+            ),
+            dimension: cities,
+            cities: kept.iter().map(|&i| self.cities[i]).collect(),
+            distance_kind: self.distance_kind,
+            distance_matrix: DistanceMatrix::from_fn(cities, |a, b| {
+                self.distance_matrix.get(kept[a], kept[b])
+            }),
             optimal_tour: None,
             optimal_tour_length: None,
+            neighbors: Arc::default(),
         }
     }
+
+    /// Every city's [`CANDIDATES`] nearest neighbours, nearest first, built
+    /// on the first call. Replacing the distance matrix afterwards leaves
+    /// them stale.
+    pub fn neighbors(&self) -> &[Vec<usize>] {
+        self.neighbors
+            .get_or_init(|| self.distance_matrix.nearest_neighbors(CANDIDATES))
+    }
+}
+
+impl Default for TspLib {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl std::fmt::Debug for TspLib {
@@ -125,8 +660,8 @@ impl std::fmt::Debug for TspLib {
         };
         write!(
             f,
-            "TspLib {{ name: {}, comment: {}, dimension: {}, optimal_tour_length: {:?} }}",
-            self.name, self.comment, self.dimension, optimal_tour_length
+            "TspLib {{ name: {}, comment: {}, dimension: {}, distance_kind: {}, optimal_tour_length: {:?} }}",
+            self.name, self.comment, self.dimension, self.distance_kind, optimal_tour_length
         )
     }
 }
@@ -147,72 +682,367 @@ pub fn get_optimal_tour_length() -> Result<HashMap<String, u64>> {
     Ok(optimal_tour_lengths)
 }
 
+fn header_value(line: &str, line_number: usize) -> Result<&str> {
+    match line.split_once(':') {
+        Some((_, value)) => Ok(value.trim()),
+        None => bail!(
+            "line {}: expected `KEY : VALUE`, got `{}`",
+            line_number,
+            line
+        ),
+    }
+}
+
+fn next_line<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    line_number: &mut usize,
+) -> Result<&'a str> {
+    *line_number += 1;
+    match lines.next() {
+        Some(line) => Ok(line),
+        None => bail!("line {}: unexpected end of file", line_number),
+    }
+}
+
 pub fn read_tsp_file(filename: &str) -> Result<TspLib> {
-    let mut tsp = TspLib::new();
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
+    read_tsp_file_with(filename, &DistanceProvider::Auto)
+}
 
-    let mut lines = reader.lines();
-    let mut line = lines.next().unwrap()?;
+/// [`read_tsp_file`], keeping the distance matrix where `provider` says.
+pub fn read_tsp_file_with(filename: &str, provider: &DistanceProvider) -> Result<TspLib> {
+    let contents =
+        fs::read_to_string(filename).with_context(|| format!("failed to open {}", filename))?;
+    let mut tsp = parse_tsp_with(&contents, provider)?;
 
-    assert!(line.contains("NAME"));
-    tsp.name = line.split(":").collect::<Vec<&str>>()[1].trim().to_string();
-    line = lines.next().unwrap()?;
+    let tour_file = format!("instances/{}.opt.tour", tsp.name);
+    if fs::exists(&tour_file)? {
+        let optimal_tour = read_tour_file(&tour_file, tsp.dimension)
+            .with_context(|| format!("failed to parse {}", tour_file))?;
+        tsp.optimal_tour = Some(optimal_tour);
+    }
 
-    while !line.contains("NODE_COORD_SECTION") {
-        if line.contains("NAME") {
-            tsp.name = line.split(":").collect::<Vec<&str>>()[1].trim().to_string();
-        } else if line.contains("COMMENT") {
-            tsp.comment = line.split(":").collect::<Vec<&str>>()[1].trim().to_string();
-        } else if line.contains("DIMENSION") {
-            tsp.dimension = line.split(":").collect::<Vec<&str>>()[1].trim().parse()?;
-        } else if line.contains("EDGE_WEIGHT_TYPE") {
-            let edge_weight_type = line.split(":").collect::<Vec<&str>>()[1].trim();
-            assert_eq!(edge_weight_type, "EUC_2D");
-        }
-        line = lines.next().unwrap()?;
+    let optimal_tour_lengths = get_optimal_tour_length()?;
+    if let Some(&length) = optimal_tour_lengths.get(&tsp.name) {
+        tsp.optimal_tour_length = Some(length);
     }
 
-    for _ in 0..tsp.dimension {
-        line = lines.next().unwrap()?;
-        let coords = line.split_whitespace().collect::<Vec<&str>>();
-        let x = coords[1].parse()?;
-        let y = coords[2].parse()?;
-        tsp.cities.push((x, y));
+    Ok(tsp)
+}
+
+/// Parses the contents of a TSPLIB `.tsp` file. Unlike `read_tsp_file` it
+/// touches no other file, so the optimal tour and its length are left unset.
+pub fn parse_tsp(contents: &str) -> Result<TspLib> {
+    parse_tsp_with(contents, &DistanceProvider::Auto)
+}
+
+/// How the `EDGE_WEIGHT_SECTION` of an EXPLICIT instance lists its matrix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EdgeWeightFormat {
+    FullMatrix,
+    UpperRow,
+    LowerRow,
+    UpperDiagRow,
+    LowerDiagRow,
+}
+
+impl EdgeWeightFormat {
+    fn parse(format: &str, line_number: usize) -> Result<Self> {
+        Ok(match format {
+            "FULL_MATRIX" => EdgeWeightFormat::FullMatrix,
+            "UPPER_ROW" => EdgeWeightFormat::UpperRow,
+            "LOWER_ROW" => EdgeWeightFormat::LowerRow,
+            "UPPER_DIAG_ROW" => EdgeWeightFormat::UpperDiagRow,
+            "LOWER_DIAG_ROW" => EdgeWeightFormat::LowerDiagRow,
+            _ => bail!(
+                "line {}: unsupported EDGE_WEIGHT_FORMAT `{}`",
+                line_number,
+                format
+            ),
+        })
+    }
+
+    /// The row and column of each weight of an `n`-city matrix, in the order
+    /// they are listed.
+    fn positions(self, n: usize) -> Vec<(usize, usize)> {
+        (0..n)
+            .flat_map(|i| {
+                let columns = match self {
+                    EdgeWeightFormat::FullMatrix => 0..n,
+                    EdgeWeightFormat::UpperRow => i + 1..n,
+                    EdgeWeightFormat::LowerRow => 0..i,
+                    EdgeWeightFormat::UpperDiagRow => i..n,
+                    EdgeWeightFormat::LowerDiagRow => 0..i + 1,
+                };
+                columns.map(move |j| (i, j))
+            })
+            .collect()
     }
+}
 
-    tsp.distance_matrix = vec![vec![0; tsp.dimension]; tsp.dimension];
-    for i in 0..tsp.dimension - 1 {
-        for j in i + 1..tsp.dimension {
-            let dist = euclidean_distance(&tsp.cities[i], &tsp.cities[j]);
-            tsp.distance_matrix[i][j] = dist;
-            tsp.distance_matrix[j][i] = dist;
+/// Reads the `dimension` lines `<id> <x> <y>` of a coordinate section.
+fn read_coordinates<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    line_number: &mut usize,
+    dimension: usize,
+) -> Result<Vec<City>> {
+    let mut cities = Vec::with_capacity(dimension);
+    for _ in 0..dimension {
+        let line = next_line(lines, line_number)?;
+        let coords = line.split_whitespace().collect::<Vec<&str>>();
+        if coords.len() < 3 {
+            bail!(
+                "line {}: expected `<id> <x> <y>`, got `{}`",
+                line_number,
+                line
+            );
         }
+        let x = coords[1]
+            .parse()
+            .with_context(|| format!("line {}: invalid x coordinate", line_number))?;
+        let y = coords[2]
+            .parse()
+            .with_context(|| format!("line {}: invalid y coordinate", line_number))?;
+        cities.push((x, y));
     }
+    Ok(cities)
+}
 
-    if fs::exists(format!("instances/{}.opt.tour", tsp.name))? {
-        let file = File::open(format!("instances/{}.opt.tour", tsp.name))?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-        while !line.contains("TOUR_SECTION") {
-            line = lines.next().unwrap()?;
+/// Reads `count` weights of an `EDGE_WEIGHT_SECTION`, however they are
+/// spread over lines.
+fn read_weights<'a>(
+    lines: &mut impl Iterator<Item = &'a str>,
+    line_number: &mut usize,
+    count: usize,
+) -> Result<Vec<u64>> {
+    let mut weights = Vec::with_capacity(count);
+    while weights.len() < count {
+        let line = next_line(lines, line_number)?;
+        for token in line.split_whitespace() {
+            let weight = token
+                .parse::<f64>()
+                .ok()
+                .filter(|weight| *weight >= 0.0)
+                .with_context(|| format!("line {}: invalid weight `{}`", line_number, token))?;
+            weights.push(weight.round() as u64);
         }
-        let mut optimal_tour = Vec::new();
-        for _ in 0..tsp.dimension {
-            line = lines.next().unwrap()?;
-            if line.contains("-1") {
-                break;
+    }
+    if weights.len() > count {
+        bail!(
+            "line {}: expected {} weights in EDGE_WEIGHT_SECTION, got {}",
+            line_number,
+            count,
+            weights.len()
+        );
+    }
+    Ok(weights)
+}
+
+/// Positions on a circle for the `n` cities of an instance without
+/// coordinates, so routes through it can be drawn and told apart.
+pub(crate) fn circle_layout(n: usize) -> Vec<City> {
+    (0..n)
+        .map(|i| {
+            let angle = std::f64::consts::TAU * i as f64 / n as f64;
+            (1000.0 * angle.cos(), 1000.0 * angle.sin())
+        })
+        .collect()
+}
+
+/// [`parse_tsp`], keeping the distance matrix where `provider` says.
+/// Matrices given in an EXPLICIT instance are always kept in memory.
+pub fn parse_tsp_with(contents: &str, provider: &DistanceProvider) -> Result<TspLib> {
+    let mut tsp = TspLib::new();
+    let mut lines = contents.lines();
+    let mut line_number = 0;
+    let line = next_line(&mut lines, &mut line_number)?;
+
+    if !line.contains("NAME") {
+        bail!("line {}: expected NAME header, got `{}`", line_number, line);
+    }
+    tsp.name = header_value(line, line_number)?.to_string();
+
+    let mut asymmetric = false;
+    let mut format = None;
+    let mut coordinates = None;
+    let mut display = None;
+    let mut weights = None;
+    while let Some(line) = lines.next() {
+        line_number += 1;
+        let key = line.split(':').next().unwrap_or_default().trim();
+        match key {
+            "NAME" => tsp.name = header_value(line, line_number)?.to_string(),
+            "COMMENT" => tsp.comment = header_value(line, line_number)?.to_string(),
+            "TYPE" => match header_value(line, line_number)? {
+                "TSP" => asymmetric = false,
+                "ATSP" => asymmetric = true,
+                other => bail!(
+                    "line {}: unsupported TYPE `{}`, expected TSP or ATSP",
+                    line_number,
+                    other
+                ),
+            },
+            "DIMENSION" => {
+                tsp.dimension = header_value(line, line_number)?
+                    .parse()
+                    .with_context(|| format!("line {}: invalid DIMENSION", line_number))?;
+            }
+            "EDGE_WEIGHT_TYPE" => {
+                tsp.distance_kind = header_value(line, line_number)?
+                    .parse()
+                    .with_context(|| format!("line {}: invalid EDGE_WEIGHT_TYPE", line_number))?;
+            }
+            "EDGE_WEIGHT_FORMAT" => {
+                format = Some(EdgeWeightFormat::parse(
+                    header_value(line, line_number)?,
+                    line_number,
+                )?);
+            }
+            "NODE_COORD_SECTION" | "DISPLAY_DATA_SECTION" | "EDGE_WEIGHT_SECTION"
+                if tsp.dimension < 2 =>
+            {
+                bail!(
+                    "line {}: DIMENSION must be declared before {} and be at least 2",
+                    line_number,
+                    key
+                );
+            }
+            "NODE_COORD_SECTION" => {
+                coordinates = Some(read_coordinates(
+                    &mut lines,
+                    &mut line_number,
+                    tsp.dimension,
+                )?);
+            }
+            "DISPLAY_DATA_SECTION" => {
+                display = Some(read_coordinates(
+                    &mut lines,
+                    &mut line_number,
+                    tsp.dimension,
+                )?);
+            }
+            "EDGE_WEIGHT_SECTION" => {
+                let Some(format) = format else {
+                    bail!(
+                        "line {}: EDGE_WEIGHT_FORMAT must be declared before EDGE_WEIGHT_SECTION",
+                        line_number
+                    );
+                };
+                let positions = format.positions(tsp.dimension);
+                let listed = read_weights(&mut lines, &mut line_number, positions.len())?;
+                let n = tsp.dimension;
+                let mut matrix = vec![0; n * n];
+                for ((i, j), weight) in positions.into_iter().zip(listed) {
+                    matrix[i * n + j] = weight;
+                    if format != EdgeWeightFormat::FullMatrix {
+                        matrix[j * n + i] = weight;
+                    }
+                }
+                weights = Some(matrix);
             }
-            let node = line.trim().parse::<usize>()?;
-            optimal_tour.push(node - 1);
+            "EOF" => break,
+            _ => {}
         }
-        tsp.optimal_tour = Some(optimal_tour);
     }
 
-    let optimal_tour_lengths = get_optimal_tour_length()?;
-    if let Some(&length) = optimal_tour_lengths.get(&tsp.name) {
-        tsp.optimal_tour_length = Some(length);
+    if asymmetric
+        && (tsp.distance_kind != DistanceKind::Explicit
+            || format != Some(EdgeWeightFormat::FullMatrix))
+    {
+        bail!(
+            "ATSP instance {} must give its distances as an EXPLICIT FULL_MATRIX",
+            tsp.name
+        );
+    }
+    if tsp.distance_kind == DistanceKind::Explicit {
+        let Some(weights) = weights else {
+            bail!("EXPLICIT instance {} has no EDGE_WEIGHT_SECTION", tsp.name);
+        };
+        let n = tsp.dimension;
+        tsp.cities = display.or(coordinates).unwrap_or_else(|| circle_layout(n));
+        tsp.distance_matrix = DistanceMatrix::from_fn(n, |a, b| weights[a * n + b]);
+    } else {
+        let Some(cities) = coordinates else {
+            bail!("instance {} has no NODE_COORD_SECTION", tsp.name);
+        };
+        tsp.cities = cities;
+        tsp.distance_matrix =
+            DistanceMatrix::provide(&tsp.cities, tsp.distance_kind, tsp.fingerprint(), provider)?;
     }
 
     Ok(tsp)
 }
+
+/// Writes `tsp` to `path` as a TSPLIB `.tsp` file with its cities'
+/// coordinates, which [`read_tsp_file`] reads back. Instances with EXPLICIT
+/// distances have no coordinates to write.
+pub fn write_tsp_file(path: &Path, tsp: &TspLib, comment: &str) -> Result<()> {
+    if tsp.distance_kind == DistanceKind::Explicit {
+        bail!(
+            "{} has EXPLICIT distances, which cannot be written",
+            tsp.name
+        );
+    }
+    let mut contents = format!(
+        "NAME : {}\nCOMMENT : {}\nTYPE : TSP\nDIMENSION : {}\nEDGE_WEIGHT_TYPE : {}\nNODE_COORD_SECTION\n",
+        tsp.name, comment, tsp.dimension, tsp.distance_kind
+    );
+    for (index, (x, y)) in tsp.cities.iter().enumerate() {
+        writeln!(contents, "{} {} {}", index + 1, x, y)?;
+    }
+    contents += "EOF\n";
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Writes `tour`, zero-based city indices of `tsp`, to `path` as a TSPLIB
+/// `.tour` file that [`read_tour_file`] and other TSPLIB tools can read.
+pub fn write_tour_file(path: &Path, tsp: &TspLib, tour: &[usize], comment: &str) -> Result<()> {
+    let name = path.file_name().map_or_else(
+        || format!("{}.tour", tsp.name),
+        |name| name.to_string_lossy().into_owned(),
+    );
+    let mut contents = format!(
+        "NAME : {}\nCOMMENT : {}\nTYPE : TOUR\nDIMENSION : {}\nTOUR_SECTION\n",
+        name,
+        comment,
+        tour.len()
+    );
+    for &city in tour {
+        writeln!(contents, "{}", city + 1)?;
+    }
+    contents += "-1\nEOF\n";
+    fs::write(path, contents).with_context(|| format!("failed to write {}", path.display()))
+}
+
+/// Reads the `TOUR_SECTION` of a TSPLIB `.tour` file as zero-based city indices.
+pub fn read_tour_file(filename: &str, dimension: usize) -> Result<Vec<usize>> {
+    let contents =
+        fs::read_to_string(filename).with_context(|| format!("failed to open {}", filename))?;
+
+    let mut lines = contents.lines();
+    let mut line_number = 0;
+    while !next_line(&mut lines, &mut line_number)?.contains("TOUR_SECTION") {}
+
+    let mut tour = Vec::new();
+    for line in lines {
+        line_number += 1;
+        for token in line.split_whitespace() {
+            if token == "-1" || token == "EOF" {
+                return Ok(tour);
+            }
+            let node = token
+                .parse::<usize>()
+                .with_context(|| format!("line {}: invalid city `{}`", line_number, token))?;
+            if node == 0 || node > dimension {
+                bail!(
+                    "line {}: city {} is outside 1..={}",
+                    line_number,
+                    node,
+                    dimension
+                );
+            }
+            tour.push(node - 1);
+        }
+    }
+
+    Ok(tour)
+}
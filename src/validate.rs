@@ -0,0 +1,121 @@
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::{
+    error::Error,
+    stats,
+    tsplib::{self, read_tour_file, read_tsp_file, write_tour_file, TspLib},
+};
+
+/// Checks that `tour` visits every city of `tsp` exactly once and returns its length.
+pub fn tour_length(tsp: &TspLib, tour: &[usize]) -> Result<u64> {
+    if tour.len() != tsp.dimension {
+        return Err(Error::InvalidTour(format!(
+            "tour visits {} cities but the instance has {}",
+            tour.len(),
+            tsp.dimension
+        ))
+        .into());
+    }
+
+    let mut visited = vec![false; tsp.dimension];
+    for &city in tour {
+        if city >= tsp.dimension {
+            return Err(Error::InvalidTour(format!(
+                "city {} does not exist in the instance",
+                city + 1
+            ))
+            .into());
+        }
+        if visited[city] {
+            return Err(
+                Error::InvalidTour(format!("city {} is visited more than once", city + 1)).into(),
+            );
+        }
+        visited[city] = true;
+    }
+
+    Ok(tsplib::tour_length(tour, &tsp.distance_matrix))
+}
+
+/// Whether `tour` visits each of `dimension` cities exactly once.
+pub fn is_permutation(tour: &[usize], dimension: usize) -> bool {
+    let mut visited = vec![false; dimension];
+    tour.len() == dimension
+        && tour
+            .iter()
+            .all(|&city| city < dimension && !std::mem::replace(&mut visited[city], true))
+}
+
+/// `tour` made a valid tour of `tsp`: later visits of a city and cities that
+/// do not exist are dropped, and each city left out is inserted where it
+/// lengthens the tour least.
+pub fn repair(tsp: &TspLib, tour: &[usize]) -> Vec<usize> {
+    let matrix = &tsp.distance_matrix;
+    let mut visited = vec![false; tsp.dimension];
+    let mut repaired: Vec<usize> = tour
+        .iter()
+        .copied()
+        .filter(|&city| city < tsp.dimension && !std::mem::replace(&mut visited[city], true))
+        .collect();
+    for city in (0..tsp.dimension).filter(|&city| !visited[city]) {
+        let position = (0..repaired.len())
+            .min_by_key(|&i| {
+                let (a, b) = (repaired[i], repaired[(i + 1) % repaired.len()]);
+                (matrix.get(a, city) + matrix.get(city, b)) as i64 - matrix.get(a, b) as i64
+            })
+            .map_or(0, |i| i + 1);
+        repaired.insert(position, city);
+    }
+    repaired
+}
+
+fn print_tour_report(tsp: &TspLib, label: &str, tour: &[usize]) -> Result<()> {
+    let length = tour_length(tsp, tour).with_context(|| format!("{} is invalid", label))?;
+    println!("{}: valid permutation of {} cities", label, tour.len());
+    println!("{}: length {}", label, length);
+    if let Some(gap) = stats::gap(length as f64, tsp) {
+        println!("{}: gap to optimum {}", label, stats::format_gap(gap));
+    }
+    if let Some(optimal_tour) = &tsp.optimal_tour {
+        let optimal = tsplib::tour_length(optimal_tour, &tsp.distance_matrix);
+        if tour != optimal_tour.as_slice() {
+            println!(
+                "{}: {:+} against the optimal tour",
+                label,
+                length as i64 - optimal as i64
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses `instance` and, if given, checks `tour` against it, printing a short
+/// report. With `repaired`, an invalid tour is repaired and written there
+/// instead of failing the check.
+pub fn run(instance: &str, tour: Option<&str>, repaired: Option<&Path>) -> Result<()> {
+    let tsp = read_tsp_file(instance).with_context(|| format!("failed to parse {}", instance))?;
+    println!("{:?}", tsp);
+
+    if let Some(optimal_tour) = &tsp.optimal_tour {
+        print_tour_report(&tsp, "Optimal tour", optimal_tour)?;
+    }
+
+    if let Some(tour_file) = tour {
+        let tour = read_tour_file(tour_file, tsp.dimension)
+            .with_context(|| format!("failed to parse {}", tour_file))?;
+        match (tour_length(&tsp, &tour), repaired) {
+            (Err(err), Some(path)) => {
+                println!("{}: {}, repairing it", tour_file, err);
+                let tour = repair(&tsp, &tour);
+                write_tour_file(path, &tsp, &tour, &format!("{} repaired", tour_file))?;
+                print_tour_report(&tsp, &path.display().to_string(), &tour)?;
+            }
+            _ => print_tour_report(&tsp, tour_file, &tour)?,
+        }
+    }
+
+    Ok(())
+}
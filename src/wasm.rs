@@ -0,0 +1,158 @@
+use std::sync::{Arc, Mutex};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    config::{self, SolverSpec},
+    progress::{IterationInfo, Observer},
+    tsplib::{self, Route, Termination, TspLib},
+};
+
+/// A TSPLIB instance loaded from the text of a `.tsp` file.
+#[wasm_bindgen]
+pub struct Instance {
+    tsp: TspLib,
+}
+
+#[wasm_bindgen]
+impl Instance {
+    #[wasm_bindgen(constructor)]
+    pub fn new(contents: &str) -> Result<Instance, JsError> {
+        let tsp = tsplib::parse_tsp(contents).map_err(to_js_error)?;
+        Ok(Instance { tsp })
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.tsp.name.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn dimension(&self) -> usize {
+        self.tsp.dimension
+    }
+
+    /// Coordinates of every city as `[x0, y0, x1, y1, ...]`.
+    pub fn coordinates(&self) -> Vec<f64> {
+        self.tsp.cities.iter().flat_map(|&(x, y)| [x, y]).collect()
+    }
+
+    /// Runs the solver described by `spec`, a JSON object such as
+    /// `{"algorithm": "sa", "temperature": 1000}`, for at most `steps`
+    /// iterations, recording every tour that improved on the best so far.
+    pub fn solve(&self, spec: &str, steps: usize, seed: u32) -> Result<Run, JsError> {
+        let spec: SolverSpec = serde_json::from_str(spec)?;
+        let improvements = Arc::new(Improvements::default());
+        let mut solver = config::build_solver(&self.tsp, &spec);
+        solver.set_seed(seed as u64);
+        solver.add_observer(improvements.clone());
+        let termination = Termination {
+            max_iterations: Some(steps),
+            ..Termination::default()
+        };
+        let solution = solver.solve(&self.tsp, &termination).map_err(to_js_error)?;
+
+        let tours = std::mem::take(&mut *improvements.tours.lock().unwrap());
+        Ok(Run {
+            tours: tours
+                .into_iter()
+                .map(|(iteration, route)| Tour {
+                    iteration,
+                    distance: route.distance,
+                    cities: self.tour(&route),
+                })
+                .collect(),
+            best: Tour {
+                iteration: solution.iterations,
+                distance: solution.route.distance,
+                cities: self.tour(&solution.route),
+            },
+            run_time: solution.run_time,
+        })
+    }
+}
+
+impl Instance {
+    fn tour(&self, route: &Route) -> Vec<u32> {
+        route.tour.iter().map(|&city| city as u32).collect()
+    }
+}
+
+/// The tours a run went through, from its first iteration to its best tour.
+#[wasm_bindgen]
+pub struct Run {
+    tours: Vec<Tour>,
+    best: Tour,
+    run_time: u64,
+}
+
+struct Tour {
+    iteration: usize,
+    distance: u64,
+    cities: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Run {
+    /// Number of intermediate tours, each shorter than the one before.
+    #[wasm_bindgen(getter)]
+    pub fn improvements(&self) -> usize {
+        self.tours.len()
+    }
+
+    /// City indices of the `k`th intermediate tour.
+    pub fn tour(&self, k: usize) -> Option<Vec<u32>> {
+        self.tours.get(k).map(|tour| tour.cities.clone())
+    }
+
+    /// Length of the `k`th intermediate tour.
+    pub fn distance(&self, k: usize) -> Option<f64> {
+        self.tours.get(k).map(|tour| tour.distance as f64)
+    }
+
+    /// Iteration at which the `k`th intermediate tour was found.
+    pub fn iteration(&self, k: usize) -> Option<usize> {
+        self.tours.get(k).map(|tour| tour.iteration)
+    }
+
+    #[wasm_bindgen(getter = bestTour)]
+    pub fn best_tour(&self) -> Vec<u32> {
+        self.best.cities.clone()
+    }
+
+    #[wasm_bindgen(getter = bestDistance)]
+    pub fn best_distance(&self) -> f64 {
+        self.best.distance as f64
+    }
+
+    /// Iterations the run completed.
+    #[wasm_bindgen(getter)]
+    pub fn iterations(&self) -> usize {
+        self.best.iteration
+    }
+
+    /// Milliseconds the run took.
+    #[wasm_bindgen(getter = runTime)]
+    pub fn run_time(&self) -> f64 {
+        self.run_time as f64
+    }
+}
+
+/// Keeps every improving route of a run along with its iteration.
+#[derive(Default)]
+struct Improvements {
+    tours: Mutex<Vec<(usize, Route)>>,
+}
+
+impl Observer for Improvements {
+    fn on_improvement(&self, info: &IterationInfo) {
+        self.tours
+            .lock()
+            .unwrap()
+            .push((info.iteration, info.best.clone()));
+    }
+}
+
+fn to_js_error(error: anyhow::Error) -> JsError {
+    JsError::new(&format!("{:#}", error))
+}